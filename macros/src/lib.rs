@@ -0,0 +1,399 @@
+// include_scene!("scene.ron"): 在编译时读取并解析场景描述文件, 展开为构建场景的代码
+// 运行时不需要解析, 也不需要读文件, 适合演示程序和嵌入式目标
+//
+// 文件格式是 RON 的一个子集:
+//
+// Scene(
+//     width: 256,
+//     height: 256,
+//     shapes: [
+//         Circle(x: 128.0, y: 128.0, r: 20.0, emissive: 2.0),
+//         Capsule(ax: 40.0, ay: 200.0, bx: 90.0, by: 200.0, r: 4.0, emissive: (1.0, 0.5, 0.1)),
+//         Subtract(Rect(cx: 64.0, cy: 64.0, sx: 20.0, sy: 10.0), Circle(x: 64.0, y: 64.0, r: 8.0)),
+//     ],
+// )
+//
+// 形状的字段可以按任意顺序书写, 省略的 emissive 和 theta 为 0
+// emissive 可以是一个数值(灰色), 也可以是 (r, g, b)
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use std::fmt::Write;
+use std::path::Path;
+
+#[proc_macro]
+pub fn include_scene(input: TokenStream) -> TokenStream {
+    let code = match expand(&input.to_string()) {
+        Ok(code) => code,
+        Err(message) => format!("compile_error!({:?})", message),
+    };
+    code.parse().unwrap()
+}
+
+fn expand(input: &str) -> Result<String, String> {
+    let input = input.trim();
+    if input.len() < 2 || !input.starts_with('"') || !input.ends_with('"') {
+        return Err("include_scene! expects a string literal path".to_string());
+    }
+    let relative = &input[1..input.len() - 1];
+
+    // 路径相对于调用者所在 crate 的根目录
+    let root = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_else(|_| ".".to_string());
+    let path = Path::new(&root).join(relative);
+    let text = std::fs::read_to_string(&path)
+        .map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+    let value = Parser::new(&text).parse_document()?;
+
+    // include_str! 让场景文件修改后重新编译
+    let mut code = format!(
+        "{{ const _: &str = include_str!({:?}); {}",
+        path.display().to_string(),
+        generate_scene(&value)?
+    );
+    code.push('}');
+    Ok(code)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Value {
+    Number(f64),
+    // 名字和字段, 字段名为 None 时是按位置书写的参数
+    Struct(String, Vec<(Option<String>, Value)>),
+    List(Vec<Value>),
+    Tuple(Vec<Value>),
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(text: &'a str) -> Parser<'a> {
+        Parser { text, position: 0 }
+    }
+
+    fn parse_document(&mut self) -> Result<Value, String> {
+        let value = self.parse_value()?;
+        self.skip_whitespace();
+        if self.position < self.text.len() {
+            return Err(self.error("unexpected trailing input"));
+        }
+        Ok(value)
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.text[self.position..]
+    }
+
+    fn error(&self, message: &str) -> String {
+        let line = self.text[..self.position].lines().count().max(1);
+        format!("scene file line {}: {}", line, message)
+    }
+
+    // 跳过空白和 // 注释
+    fn skip_whitespace(&mut self) {
+        loop {
+            let rest = self.rest();
+            let trimmed = rest.trim_start();
+            self.position += rest.len() - trimmed.len();
+            if trimmed.starts_with("//") {
+                self.position += trimmed.find('\n').unwrap_or(trimmed.len());
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.rest().starts_with(c) {
+            self.position += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected `{}`", c)))
+        }
+    }
+
+    fn identifier(&mut self) -> Option<String> {
+        self.skip_whitespace();
+        let rest = self.rest();
+        let length = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        if length == 0 || rest.starts_with(|c: char| c.is_ascii_digit()) {
+            return None;
+        }
+        self.position += length;
+        Some(rest[..length].to_string())
+    }
+
+    // 解析以 close 结尾、用逗号分隔的一组值, 开头的括号已经被读取
+    fn parse_items(&mut self, close: char) -> Result<Vec<Value>, String> {
+        let mut items = vec![];
+        while !self.eat(close) {
+            items.push(self.parse_value()?);
+            if !self.eat(',') {
+                self.expect(close)?;
+                break;
+            }
+        }
+        Ok(items)
+    }
+
+    fn parse_value(&mut self) -> Result<Value, String> {
+        self.skip_whitespace();
+        if self.eat('[') {
+            return Ok(Value::List(self.parse_items(']')?));
+        }
+        if self.eat('(') {
+            return Ok(Value::Tuple(self.parse_items(')')?));
+        }
+        if let Some(name) = self.identifier() {
+            let mut fields = vec![];
+            if self.eat('(') {
+                while !self.eat(')') {
+                    let start = self.position;
+                    let field = match self.identifier() {
+                        Some(field) if self.eat(':') => Some(field),
+                        _ => {
+                            self.position = start;
+                            None
+                        }
+                    };
+                    fields.push((field, self.parse_value()?));
+                    if !self.eat(',') {
+                        self.expect(')')?;
+                        break;
+                    }
+                }
+            }
+            return Ok(Value::Struct(name, fields));
+        }
+
+        let rest = self.rest();
+        let length = rest
+            .find(|c: char| !(c.is_ascii_digit() || "+-.eE_".contains(c)))
+            .unwrap_or(rest.len());
+        let number = rest[..length].replace('_', "");
+        match number.parse() {
+            Ok(value) if length > 0 => {
+                self.position += length;
+                Ok(Value::Number(value))
+            }
+            _ => Err(self.error("expected a value")),
+        }
+    }
+}
+
+// 取出结构体的字段, 按名字查找, 没有名字的字段按位置对应
+fn field(fields: &[(Option<String>, Value)], names: &[&str], index: usize) -> Option<Value> {
+    fields
+        .iter()
+        .find(|(name, _)| name.as_deref() == Some(names[index]))
+        .or_else(|| fields.get(index).filter(|(name, _)| name.is_none()))
+        .map(|(_, value)| value.clone())
+}
+
+// 浮点数字面量, 类型为库使用的浮点数类型(f64 或者 f32)
+fn float(value: f64) -> String {
+    format!("({:?} as ::colorful_light2d::float::Float)", value)
+}
+
+fn number(
+    fields: &[(Option<String>, Value)],
+    names: &[&str],
+    index: usize,
+) -> Result<String, String> {
+    match field(fields, names, index) {
+        Some(Value::Number(value)) => Ok(float(value)),
+        Some(_) => Err(format!("`{}` must be a number", names[index])),
+        None if names[index] == "emissive" || names[index] == "theta" => Ok(float(0.0)),
+        None => Err(format!("missing field `{}`", names[index])),
+    }
+}
+
+// 发光颜色, 单个数值会在构造形状时转换为灰色
+fn emissive(
+    fields: &[(Option<String>, Value)],
+    names: &[&str],
+    index: usize,
+) -> Result<String, String> {
+    match field(fields, names, index) {
+        Some(Value::Tuple(items)) => match items.as_slice() {
+            [Value::Number(r), Value::Number(g), Value::Number(b)] => Ok(format!(
+                "::colorful_light2d::color::Color::new({}, {}, {})",
+                float(*r),
+                float(*g),
+                float(*b)
+            )),
+            _ => Err("`emissive` must be a number or (r, g, b)".to_string()),
+        },
+        _ => number(fields, names, index),
+    }
+}
+
+fn generate_scene(value: &Value) -> Result<String, String> {
+    let fields = match value {
+        Value::Struct(name, fields) if name == "Scene" => fields,
+        _ => return Err("the scene file must contain a `Scene(...)`".to_string()),
+    };
+    let names = ["width", "height", "shapes"];
+    let size = |index: usize| match field(fields, &names, index) {
+        Some(Value::Number(value)) if value >= 0.0 && value.fract() == 0.0 => {
+            Ok(format!("{}u32", value))
+        }
+        _ => Err(format!("`{}` must be a non-negative integer", names[index])),
+    };
+
+    let mut code = format!(
+        "#[allow(unused_mut)] let mut scene = ::colorful_light2d::scene::Scene::new({}, {});",
+        size(0)?,
+        size(1)?
+    );
+    match field(fields, &names, 2) {
+        Some(Value::List(shapes)) => {
+            for shape in shapes.iter() {
+                write!(code, "scene.add_shape({});", generate_shape(shape)?).unwrap();
+            }
+        }
+        Some(_) => return Err("`shapes` must be a list".to_string()),
+        None => {}
+    }
+    code.push_str("scene");
+    Ok(code)
+}
+
+fn generate_shape(value: &Value) -> Result<String, String> {
+    let (name, fields) = match value {
+        Value::Struct(name, fields) => (name.as_str(), fields),
+        _ => return Err("expected a shape".to_string()),
+    };
+    let primitive = |names: &[&str]| -> Result<String, String> {
+        let arguments: Result<Vec<String>, String> = (0..names.len())
+            .map(|i| match names[i] {
+                "emissive" => emissive(fields, names, i),
+                _ => number(fields, names, i),
+            })
+            .collect();
+        Ok(format!(
+            "::std::boxed::Box::new(::colorful_light2d::shape::{}::new({}))",
+            name,
+            arguments?.join(", ")
+        ))
+    };
+    let operation = |function: &str| -> Result<String, String> {
+        let shapes: Vec<&Value> = fields.iter().map(|(_, value)| value).collect();
+        if shapes.len() != 2 {
+            return Err(format!("`{}` takes two shapes", name));
+        }
+        Ok(format!(
+            "::colorful_light2d::shape::Shapes::{}({}, {})",
+            function,
+            generate_shape(shapes[0])?,
+            generate_shape(shapes[1])?
+        ))
+    };
+
+    match name {
+        "Circle" => primitive(&["x", "y", "r", "emissive"]),
+        "Plane" => primitive(&["px", "py", "nx", "ny", "emissive"]),
+        "Capsule" => primitive(&["ax", "ay", "bx", "by", "r", "emissive"]),
+        "Rect" => primitive(&["cx", "cy", "theta", "sx", "sy", "emissive"]),
+        "Rhombus" => primitive(&["cx", "cy", "theta", "sx", "sy", "emissive"]),
+        "Trapezoid" => primitive(&["cx", "cy", "theta", "s1", "s2", "h", "emissive"]),
+        "Parabola" => primitive(&["cx", "cy", "theta", "f", "w", "thickness", "emissive"]),
+        "Vesica" => primitive(&["cx", "cy", "theta", "r", "d", "emissive"]),
+        "Crescent" => primitive(&["cx", "cy", "theta", "ra", "rb", "d", "emissive"]),
+        "Egg" => primitive(&["cx", "cy", "theta", "ra", "rb", "emissive"]),
+        "Hexagram" => primitive(&["cx", "cy", "theta", "r", "emissive"]),
+        "Superellipse" => primitive(&["cx", "cy", "theta", "sx", "sy", "n", "emissive"]),
+        "Triangle" => primitive(&["ax", "ay", "bx", "by", "cx", "cy", "emissive"]),
+        "Pie" => primitive(&["cx", "cy", "r", "start", "end", "emissive"]),
+        "Heart" => primitive(&["cx", "cy", "size", "emissive"]),
+        "Cross" => primitive(&["cx", "cy", "theta", "sx", "sy", "thickness", "emissive"]),
+        "Ellipse" => primitive(&["cx", "cy", "rx", "ry", "theta", "emissive"]),
+        "Ring" => primitive(&["cx", "cy", "r", "thickness", "emissive"]),
+        "Arc" => primitive(&[
+            "cx",
+            "cy",
+            "r",
+            "theta",
+            "aperture",
+            "thickness",
+            "emissive",
+        ]),
+        "Union" => operation("union"),
+        "Intersect" => operation("intersect"),
+        "Subtract" => operation("subtract"),
+        _ => Err(format!("unknown shape `{}`", name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let value = Parser::new("Scene(width: 8, // 注释\n shapes: [Circle(1.0, y: -2e1)],)")
+            .parse_document()
+            .unwrap();
+        assert_eq!(
+            value,
+            Value::Struct(
+                "Scene".to_string(),
+                vec![
+                    (Some("width".to_string()), Value::Number(8.0)),
+                    (
+                        Some("shapes".to_string()),
+                        Value::List(vec![Value::Struct(
+                            "Circle".to_string(),
+                            vec![
+                                (None, Value::Number(1.0)),
+                                (Some("y".to_string()), Value::Number(-20.0)),
+                            ]
+                        )])
+                    ),
+                ]
+            )
+        );
+        assert!(Parser::new("Scene(width: )").parse_document().is_err());
+        assert!(Parser::new("Scene() extra").parse_document().is_err());
+    }
+
+    #[test]
+    fn generate() {
+        let scene = Parser::new("Scene(width: 8, height: 4, shapes: [Circle(x: 1, y: 2, r: 3)])")
+            .parse_document()
+            .unwrap();
+        let code = generate_scene(&scene).unwrap();
+        assert!(code.contains("Scene::new(8u32, 4u32)"));
+        assert!(code.contains("Circle::new((1.0 as ::colorful_light2d::float::Float), "));
+
+        let colored =
+            Parser::new("Scene(width: 8, height: 4, shapes: [Circle(1, 2, 3, (1, 0.5, 0))])")
+                .parse_document()
+                .unwrap();
+        let code = generate_scene(&colored).unwrap();
+        assert!(code.contains("Color::new((1.0 as ::colorful_light2d::float::Float), (0.5 as"));
+        let bad = Parser::new("Scene(width: 8, height: 4, shapes: [Circle(1, 2, 3, (1, 0.5))])")
+            .parse_document()
+            .unwrap();
+        assert!(generate_scene(&bad).is_err());
+
+        let bad = Parser::new("Scene(width: 8, height: 4, shapes: [Star(1)])")
+            .parse_document()
+            .unwrap();
+        assert!(generate_scene(&bad).is_err());
+    }
+}
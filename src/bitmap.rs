@@ -0,0 +1,337 @@
+// 从位图蒙版创建形状: 把图片按亮度二值化, 对结果做有符号的欧几里得距离变换, 查询时对像素中心上的距离双线性插值
+// 可以把任意栅格图案当作光源或者遮挡物, 精度受分辨率限制: 离边缘几个像素以内误差不到半个像素,
+// 远离边缘的地方(例如形状的中轴附近)插值会把距离的尖峰抹平, 误差在一个像素左右
+
+use crate::color::Color;
+use crate::environment::decode_png;
+use crate::float::Float;
+use crate::material::Material;
+use crate::shape::{Aabb, EmissionProfile, SdfResult, Shape};
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+
+#[derive(Clone)]
+pub struct BitmapShape {
+    width: usize,
+    height: usize,
+    // 像素中心到边缘的有符号距离(以像素为单位), 按行排列
+    distances: Vec<Float>,
+    // 形状内部的像素数
+    inside: usize,
+    // 图片左上角在场景中的位置, 以及每个像素在场景中的边长
+    x: Float,
+    y: Float,
+    scale: Float,
+    emissive: Color,
+}
+
+impl BitmapShape {
+    // 加载 PNG 图片, 亮度不低于 threshold (在 [0, 1] 内) 的像素属于形状内部
+    // 默认左上角在原点, 每个像素对应场景中的一个单位, 不发光
+    pub fn from_image<P: AsRef<Path>>(path: P, threshold: Float) -> Result<BitmapShape> {
+        let data = fs::read(path)?;
+        let (width, height, pixels) = decode_png(&data)?;
+        if width == 0 || height == 0 {
+            return Err(Error::new(ErrorKind::InvalidData, "empty mask image"));
+        }
+        let mask: Vec<bool> = pixels
+            .iter()
+            .map(|color| color.luminance() >= threshold)
+            .collect();
+        Ok(BitmapShape::from_mask(
+            width as usize,
+            height as usize,
+            &mask,
+        ))
+    }
+
+    // 从按行排列的蒙版创建, true 表示形状内部, 宽和高都不能为 0
+    pub fn from_mask(width: usize, height: usize, mask: &[bool]) -> BitmapShape {
+        assert!(width > 0 && height > 0, "empty mask");
+        assert_eq!(mask.len(), width * height);
+        // 外部像素到最近的内部像素中心的距离, 以及内部像素到最近的外部像素中心的距离,
+        // 边缘在两个像素中心的中间, 所以各减去半个像素
+        let outside = distance_transform(width, height, |i| mask[i]);
+        let inside = distance_transform(width, height, |i| !mask[i]);
+        // 图片外面也是形状外部, 所以内部像素的距离不超过到图片边缘的距离, 全部是内部时也是有限的;
+        // 没有内部像素时形状是空的, 真实距离是无穷大, 用图片的对角线代替, 仍然是距离的下限
+        let diagonal = (width as Float).hypot(height as Float);
+        let distances = (0..width * height)
+            .map(|i| {
+                if mask[i] {
+                    let (u, v) = ((i % width) as Float + 0.5, (i / width) as Float + 0.5);
+                    let border = u.min(v).min(width as Float - u).min(height as Float - v);
+                    -(inside[i] - 0.5).min(border)
+                } else {
+                    (outside[i] - 0.5).min(diagonal)
+                }
+            })
+            .collect();
+        BitmapShape {
+            width,
+            height,
+            distances,
+            inside: mask.iter().filter(|&&inside| inside).count(),
+            x: 0.0,
+            y: 0.0,
+            scale: 1.0,
+            emissive: Color::BLACK,
+        }
+    }
+
+    // 把图片的左上角放在 (x, y), 每个像素在场景中的边长为 scale
+    pub fn with_placement(mut self, x: Float, y: Float, scale: Float) -> Self {
+        self.x = x;
+        self.y = y;
+        self.scale = scale;
+        self
+    }
+
+    pub fn with_emissive(mut self, emissive: impl Into<Color>) -> Self {
+        self.emissive = emissive.into();
+        self
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    // 在像素坐标 (u, v) 处对像素中心上的距离双线性插值, (u, v) 需要在像素中心围成的范围内
+    fn sample(&self, u: Float, v: Float) -> Float {
+        let (u, v) = (u - 0.5, v - 0.5);
+        let i = (u as usize).min(self.width.saturating_sub(2));
+        let j = (v as usize).min(self.height.saturating_sub(2));
+        let i1 = (i + 1).min(self.width - 1);
+        let j1 = (j + 1).min(self.height - 1);
+        let (fu, fv) = ((u - i as Float).min(1.0), (v - j as Float).min(1.0));
+        let at = |i: usize, j: usize| self.distances[j * self.width + i];
+        let top = at(i, j) * (1.0 - fu) + at(i1, j) * fu;
+        let bottom = at(i, j1) * (1.0 - fu) + at(i1, j1) * fu;
+        top * (1.0 - fv) + bottom * fv
+    }
+}
+
+impl Shape for BitmapShape {
+    // 图片外面都是形状外部: 设 q 为 (x, y) 在像素中心围成的矩形上的投影,
+    // 最近的内部点在矩形内, 所以到它的距离不小于 |p - q| 与 q 处距离的平方和的平方根
+    fn sdf(&self, x: Float, y: Float) -> SdfResult {
+        let (u, v) = ((x - self.x) / self.scale, (y - self.y) / self.scale);
+        let cu = u.clamp(0.5, self.width as Float - 0.5);
+        let cv = v.clamp(0.5, self.height as Float - 0.5);
+        let sd = self.sample(cu, cv);
+        let outside = (u - cu).hypot(v - cv);
+        let sd = if outside > 0.0 {
+            outside.hypot(sd.max(0.0))
+        } else {
+            sd
+        };
+        SdfResult {
+            sd: sd * self.scale,
+            material: Material::emissive(self.emissive),
+            profile: EmissionProfile::Uniform,
+        }
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        Some(Aabb::new(
+            self.x,
+            self.y,
+            self.x + self.width as Float * self.scale,
+            self.y + self.height as Float * self.scale,
+        ))
+    }
+
+    // 二值化后每个内部像素的面积之和
+    fn area(&self) -> Float {
+        self.inside as Float * self.scale * self.scale
+    }
+}
+
+// 每个像素中心到最近的满足 target 的像素中心的欧几里得距离, 没有这样的像素时为无穷大
+// 先按列、再按行做一维的平方距离变换 (Felzenszwalb & Huttenlocher), 总共是 O(像素数)
+fn distance_transform(width: usize, height: usize, target: impl Fn(usize) -> bool) -> Vec<Float> {
+    let mut squared: Vec<Float> = (0..width * height)
+        .map(|i| if target(i) { 0.0 } else { Float::INFINITY })
+        .collect();
+    let mut line = vec![];
+    for i in 0..width {
+        line.clear();
+        line.extend((0..height).map(|j| squared[j * width + i]));
+        for (j, value) in squared_distance_1d(&line).into_iter().enumerate() {
+            squared[j * width + i] = value;
+        }
+    }
+    for j in 0..height {
+        let row = &mut squared[j * width..(j + 1) * width];
+        let transformed = squared_distance_1d(row);
+        row.copy_from_slice(&transformed);
+    }
+    squared.into_iter().map(Float::sqrt).collect()
+}
+
+// 一维的平方距离变换: d(q) = min_p (q - p)² + f(p), 求抛物线族的下包络
+fn squared_distance_1d(f: &[Float]) -> Vec<Float> {
+    let n = f.len();
+    let mut result = vec![Float::INFINITY; n];
+    // 下包络中各段抛物线的顶点, 以及相邻两段的分界
+    let mut vertices = Vec::with_capacity(n);
+    let mut boundaries: Vec<Float> = Vec::with_capacity(n + 1);
+    for q in (0..n).filter(|&q| f[q].is_finite()) {
+        loop {
+            match vertices.last() {
+                None => {
+                    vertices.push(q);
+                    boundaries.clear();
+                    boundaries.push(Float::NEG_INFINITY);
+                    break;
+                }
+                Some(&p) => {
+                    // 以 q 和 p 为顶点的两条抛物线的交点
+                    let (qf, pf) = (q as Float, p as Float);
+                    let s = ((f[q] + qf * qf) - (f[p] + pf * pf)) / (2.0 * (qf - pf));
+                    if s <= *boundaries.last().unwrap() {
+                        vertices.pop();
+                        boundaries.pop();
+                    } else {
+                        vertices.push(q);
+                        boundaries.push(s);
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    if vertices.is_empty() {
+        return result;
+    }
+
+    let mut k = 0;
+    for (q, value) in result.iter_mut().enumerate() {
+        while k + 1 < vertices.len() && boundaries[k + 1] < q as Float {
+            k += 1;
+        }
+        let p = vertices[k];
+        let d = q as Float - p as Float;
+        *value = d * d + f[p];
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shape::Circle;
+
+    // 半径为 r 的圆盘, 圆心在图片中心
+    fn disk(size: usize, r: Float) -> Vec<bool> {
+        let center = size as Float / 2.0;
+        (0..size * size)
+            .map(|i| {
+                let (x, y) = ((i % size) as Float + 0.5, (i / size) as Float + 0.5);
+                (x - center).hypot(y - center) < r
+            })
+            .collect()
+    }
+
+    #[test]
+    fn distance_transform_matches_brute_force() {
+        let mask = [
+            false, false, true, false, false, //
+            false, false, false, false, false, //
+            false, false, false, false, true, //
+        ];
+        let distances = distance_transform(5, 3, |i| mask[i]);
+        for (i, distance) in distances.iter().enumerate() {
+            let (x, y) = ((i % 5) as Float, (i / 5) as Float);
+            let expected = (0..15)
+                .filter(|&k| mask[k])
+                .map(|k| (x - (k % 5) as Float).hypot(y - (k / 5) as Float))
+                .fold(Float::INFINITY, Float::min);
+            assert!((distance - expected).abs() < 1e-9);
+        }
+        assert!(distance_transform(2, 2, |_| false)
+            .iter()
+            .all(|d| d.is_infinite()));
+    }
+
+    #[test]
+    fn disk_close_to_circle() {
+        let shape = BitmapShape::from_mask(64, 64, &disk(64, 20.0)).with_placement(10.0, 20.0, 0.5);
+        let circle = Circle::new(26.0, 36.0, 10.0, 0.0);
+        for i in 0..50 {
+            for j in 0..50 {
+                let (x, y) = (i as Float * 1.0, 10.0 + j as Float * 1.0);
+                let (sd, expected) = (shape.sdf(x, y).sd, circle.sdf(x, y).sd);
+                // 图片里面离边缘近时误差不到半个像素, 远处不到一个多像素, 图片外面的距离是下限
+                if shape.bounds().unwrap().distance(x, y) == 0.0 {
+                    let tolerance = if expected.abs() < 3.0 { 0.25 } else { 0.6 };
+                    assert!((sd - expected).abs() < tolerance);
+                } else {
+                    assert!(sd > 0.0 && sd <= expected + 0.5);
+                }
+            }
+        }
+        assert!((shape.area() / circle.area() - 1.0).abs() < 1e-2);
+        assert_eq!(shape.bounds(), Some(Aabb::new(10.0, 20.0, 42.0, 52.0)));
+    }
+
+    #[test]
+    fn uniform_masks() {
+        // 全部是内部时, 距离是到图片边缘的距离
+        let full = BitmapShape::from_mask(10, 6, &[true; 60]);
+        assert!((full.sdf(4.5, 1.5).sd + 1.5).abs() < 1e-9);
+        // 中间的尖峰被插值抹平, 误差不到一个像素
+        assert!((full.sdf(5.0, 3.0).sd + 3.0).abs() < 1.0);
+        assert!((full.sdf(1.5, 3.0).sd + 1.5).abs() < 1e-9);
+        assert!((full.sdf(12.0, 3.0).sd - 2.0).abs() <= 0.5);
+        assert_eq!(full.area(), 60.0);
+
+        // 全部是外部时, 距离是有限的正数
+        let empty = BitmapShape::from_mask(10, 6, &[false; 60]);
+        for &(x, y) in &[(5.0, 3.0), (0.0, 0.0), (-20.0, 40.0)] {
+            let sd = empty.sdf(x, y).sd;
+            assert!(sd.is_finite() && sd > 0.0);
+        }
+        assert_eq!(empty.area(), 0.0);
+        let (gx, gy) = full.gradient(8.5, 3.0);
+        assert!(gx.is_finite() && gy.is_finite());
+    }
+
+    #[test]
+    #[should_panic(expected = "empty mask")]
+    fn empty_mask() {
+        BitmapShape::from_mask(0, 4, &[]);
+    }
+
+    #[test]
+    fn from_png() {
+        let path = std::env::temp_dir().join("colorful_light2d_bitmap_mask.png");
+        let file = fs::File::create(&path).unwrap();
+        let mut encoder = png::Encoder::new(file, 32, 32);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        let data: Vec<u8> = disk(32, 10.0)
+            .iter()
+            .map(|&inside| if inside { 200 } else { 30 })
+            .collect();
+        encoder
+            .write_header()
+            .unwrap()
+            .write_image_data(&data)
+            .unwrap();
+
+        let shape = BitmapShape::from_image(&path, 0.5)
+            .unwrap()
+            .with_emissive(2.0);
+        fs::remove_file(&path).unwrap();
+        assert_eq!((shape.width(), shape.height()), (32, 32));
+        assert!((shape.sdf(16.0, 16.0).sd + 10.0).abs() < 1.2);
+        assert!((shape.sdf(16.0, 30.0).sd - 4.0).abs() < 0.5);
+        assert!(BitmapShape::from_image("/nonexistent/mask.png", 0.5).is_err());
+    }
+}
@@ -0,0 +1,276 @@
+// RGB 颜色, 每个分量是线性的光量, 可以大于 1
+use crate::float::Float;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Sub};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Color {
+    pub r: Float,
+    pub g: Float,
+    pub b: Float,
+}
+
+impl Color {
+    pub const BLACK: Color = Color::new(0.0, 0.0, 0.0);
+    pub const WHITE: Color = Color::new(1.0, 1.0, 1.0);
+
+    pub const fn new(r: Float, g: Float, b: Float) -> Color {
+        Color { r, g, b }
+    }
+
+    // 三个分量相同的灰色
+    pub const fn gray(value: Float) -> Color {
+        Color::new(value, value, value)
+    }
+
+    // 从十六进制字符串创建颜色, 支持 "#rrggbb"、"rrggbb"、"#rgb" 和 "rgb"
+    pub fn from_hex(hex: &str) -> Option<Color> {
+        let hex = hex.trim().trim_start_matches('#');
+        if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        let parse = |s: &str| u8::from_str_radix(s, 16).ok().map(|v| v as Float / 255.0);
+        match hex.len() {
+            6 => Some(Color::new(
+                parse(&hex[0..2])?,
+                parse(&hex[2..4])?,
+                parse(&hex[4..6])?,
+            )),
+            3 => {
+                let parse_short = |i: usize| parse(&hex[i..i + 1]).map(|v| v * 17.0);
+                Some(Color::new(
+                    parse_short(0)?,
+                    parse_short(1)?,
+                    parse_short(2)?,
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    // 从 HSV 创建颜色, h 为角度(度), s 和 v 在 [0, 1] 之间
+    pub fn from_hsv(h: Float, s: Float, v: Float) -> Color {
+        let c = v * s;
+        Color::from_hue(h, c) + Color::gray(v - c)
+    }
+
+    // 从 HSL 创建颜色, h 为角度(度), s 和 l 在 [0, 1] 之间
+    pub fn from_hsl(h: Float, s: Float, l: Float) -> Color {
+        let c = (1.0 - (2.0 * l - 1.0).abs()) * s;
+        Color::from_hue(h, c) + Color::gray(l - c / 2.0)
+    }
+
+    // 色相为 h, 色度为 c, 最小分量为 0 的颜色
+    fn from_hue(h: Float, c: Float) -> Color {
+        let h = h.rem_euclid(360.0) / 60.0;
+        let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+        match h as u32 {
+            0 => Color::new(c, x, 0.0),
+            1 => Color::new(x, c, 0.0),
+            2 => Color::new(0.0, c, x),
+            3 => Color::new(0.0, x, c),
+            4 => Color::new(x, 0.0, c),
+            _ => Color::new(c, 0.0, x),
+        }
+    }
+
+    // CSS 中的基本颜色名, 不区分大小写
+    pub fn named(name: &str) -> Option<Color> {
+        let hex = match name.to_ascii_lowercase().as_str() {
+            "black" => "000000",
+            "white" => "ffffff",
+            "gray" | "grey" => "808080",
+            "silver" => "c0c0c0",
+            "red" => "ff0000",
+            "maroon" => "800000",
+            "orange" => "ffa500",
+            "yellow" => "ffff00",
+            "olive" => "808000",
+            "lime" => "00ff00",
+            "green" => "008000",
+            "cyan" | "aqua" => "00ffff",
+            "teal" => "008080",
+            "blue" => "0000ff",
+            "navy" => "000080",
+            "magenta" | "fuchsia" => "ff00ff",
+            "purple" => "800080",
+            "pink" => "ffc0cb",
+            "brown" => "a52a2a",
+            "gold" => "ffd700",
+            "violet" => "ee82ee",
+            "indigo" => "4b0082",
+            _ => return None,
+        };
+        Color::from_hex(hex)
+    }
+
+    // 只有第 index 个通道(0 为红, 1 为绿, 2 为蓝)为 1 的颜色
+    pub const fn primary(index: usize) -> Color {
+        match index {
+            0 => Color::new(1.0, 0.0, 0.0),
+            1 => Color::new(0.0, 1.0, 0.0),
+            _ => Color::new(0.0, 0.0, 1.0),
+        }
+    }
+
+    // 第 index 个通道的值
+    pub fn channel(&self, index: usize) -> Float {
+        match index {
+            0 => self.r,
+            1 => self.g,
+            _ => self.b,
+        }
+    }
+
+    // 亮度(Rec. 709 权重)
+    pub fn luminance(&self) -> Float {
+        0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
+    }
+
+    pub fn max_component(&self) -> Float {
+        self.r.max(self.g).max(self.b)
+    }
+
+    // 对每个分量分别做同样的运算
+    pub fn map(&self, f: impl Fn(Float) -> Float) -> Color {
+        Color::new(f(self.r), f(self.g), f(self.b))
+    }
+
+    pub fn is_black(&self) -> bool {
+        self.r <= 0.0 && self.g <= 0.0 && self.b <= 0.0
+    }
+
+    // 转换为 8 位的像素值, 超出 [0, 1] 的部分会被截断
+    pub fn to_rgb8(&self) -> [u8; 3] {
+        let quantize = |v: Float| (v.clamp(0.0, 1.0) * 255.0) as u8;
+        [quantize(self.r), quantize(self.g), quantize(self.b)]
+    }
+}
+
+// 单个数值表示灰色, 原来用单个数值表示发光强度的代码不需要修改
+impl From<Float> for Color {
+    fn from(value: Float) -> Color {
+        Color::gray(value)
+    }
+}
+
+impl Add for Color {
+    type Output = Color;
+
+    fn add(self, other: Color) -> Color {
+        Color::new(self.r + other.r, self.g + other.g, self.b + other.b)
+    }
+}
+
+impl AddAssign for Color {
+    fn add_assign(&mut self, other: Color) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for Color {
+    type Output = Color;
+
+    fn sub(self, other: Color) -> Color {
+        Color::new(self.r - other.r, self.g - other.g, self.b - other.b)
+    }
+}
+
+// 分量分别相乘, 例如光穿过滤色片
+impl Mul for Color {
+    type Output = Color;
+
+    fn mul(self, other: Color) -> Color {
+        Color::new(self.r * other.r, self.g * other.g, self.b * other.b)
+    }
+}
+
+impl Mul<Float> for Color {
+    type Output = Color;
+
+    fn mul(self, k: Float) -> Color {
+        Color::new(self.r * k, self.g * k, self.b * k)
+    }
+}
+
+impl Mul<Color> for Float {
+    type Output = Color;
+
+    fn mul(self, color: Color) -> Color {
+        color * self
+    }
+}
+
+impl MulAssign<Float> for Color {
+    fn mul_assign(&mut self, k: Float) {
+        *self = *self * k;
+    }
+}
+
+impl MulAssign for Color {
+    fn mul_assign(&mut self, other: Color) {
+        *self = *self * other;
+    }
+}
+
+impl Div<Float> for Color {
+    type Output = Color;
+
+    fn div(self, k: Float) -> Color {
+        Color::new(self.r / k, self.g / k, self.b / k)
+    }
+}
+
+impl DivAssign<Float> for Color {
+    fn div_assign(&mut self, k: Float) {
+        *self = *self / k;
+    }
+}
+
+impl Sum for Color {
+    fn sum<I: Iterator<Item = Color>>(iter: I) -> Color {
+        iter.fold(Color::BLACK, |sum, color| sum + color)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: Color, b: Color) {
+        assert!((a - b).r.abs() < 1e-9 && (a - b).g.abs() < 1e-9 && (a - b).b.abs() < 1e-9);
+    }
+
+    #[test]
+    fn constructors() {
+        assert_eq!(
+            Color::from_hex("#ff8000"),
+            Some(Color::new(1.0, 128.0 / 255.0, 0.0))
+        );
+        assert_eq!(Color::from_hex("0f0"), Some(Color::new(0.0, 1.0, 0.0)));
+        assert_eq!(Color::from_hex("#12345"), None);
+        assert_eq!(Color::from_hex("#gg0000"), None);
+        assert_eq!(Color::named("Red"), Some(Color::new(1.0, 0.0, 0.0)));
+        assert_eq!(Color::named("no such color"), None);
+
+        assert_close(Color::from_hsv(0.0, 1.0, 1.0), Color::new(1.0, 0.0, 0.0));
+        assert_close(Color::from_hsv(240.0, 1.0, 0.5), Color::new(0.0, 0.0, 0.5));
+        assert_close(Color::from_hsl(120.0, 1.0, 0.5), Color::new(0.0, 1.0, 0.0));
+        assert_close(Color::from_hsl(0.0, 0.0, 0.75), Color::gray(0.75));
+    }
+
+    #[test]
+    fn arithmetic() {
+        let a = Color::new(0.5, 0.25, 1.0);
+        assert_eq!(a + a, a * 2.0);
+        assert_eq!(a * Color::new(2.0, 0.0, 1.0), Color::new(1.0, 0.0, 1.0));
+        assert_eq!(2.0 * a / 2.0, a);
+        assert_eq!(a.to_rgb8(), [127, 63, 255]);
+        assert_eq!((a * 4.0).to_rgb8(), [255, 255, 255]);
+        assert_eq!(Color::from(0.5), Color::gray(0.5));
+        assert_eq!(vec![a, a].into_iter().sum::<Color>(), a * 2.0);
+        assert_eq!(a.map(|v| v * 2.0), a * 2.0);
+        let channels = (0..3).map(|i| Color::primary(i) * a.channel(i));
+        assert_eq!(channels.sum::<Color>(), a);
+    }
+}
@@ -0,0 +1,719 @@
+// 用数组(arena)保存的 CSG 树: 节点之间用下标互相引用, 而不是嵌套的 Box<dyn Shape>
+// 深的 CSG 树的节点在内存中是连续的, 整棵树可以直接 clone,
+// 每帧重新构建场景时可以 clear 之后复用已经分配的内存
+// 目前还没有提供序列化格式
+
+use crate::color::Color;
+use crate::float::Float;
+use crate::material::Material;
+use crate::shape::{
+    intersect_bounds, intersect_result, offset_bounds, smooth_intersect_result,
+    smooth_subtract_result, smooth_union_bounds, smooth_union_result, subtract_result,
+    union_all_bounds, union_all_result, union_bounds, union_result, Aabb, Arc, Bend, Bezier2,
+    Bezier3, Capsule, Circle, Crescent, Cross, Egg, Ellipse, EmissionProfile, Gear, Heart,
+    Hexagram, Mirror, Parabola, Pie, Plane, PolarRepeat, Polygon, Rect, Repeat, Rhombus, Ring,
+    Scale, SdfResult, Shape, Shear, Spline, Superellipse, Transform, Trapezoid, Triangle, Vesica,
+    Warp,
+};
+
+// 节点在树中的下标
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NodeId(usize);
+
+#[derive(Clone)]
+pub enum CsgNode {
+    Circle(Circle),
+    Plane(Plane),
+    Capsule(Capsule),
+    Rect(Rect),
+    Triangle(Triangle),
+    Ellipse(Ellipse),
+    Polygon(Polygon),
+    Ring(Ring),
+    Arc(Arc),
+    Pie(Pie),
+    Bezier2(Bezier2),
+    Bezier3(Bezier3),
+    Heart(Heart),
+    Cross(Cross),
+    Rhombus(Rhombus),
+    Trapezoid(Trapezoid),
+    Parabola(Parabola),
+    Vesica(Vesica),
+    Crescent(Crescent),
+    Superellipse(Superellipse),
+    Gear(Gear),
+    Hexagram(Hexagram),
+    Egg(Egg),
+    Spline(Spline),
+    Union(NodeId, NodeId),
+    // 多个节点的并集, 见 Shapes::union_all
+    UnionAll(Vec<NodeId>),
+    // 平滑并集, 第三个值为过渡的宽度 k, 见 Shapes::smooth_union
+    SmoothUnion(NodeId, NodeId, Float),
+    SmoothIntersect(NodeId, NodeId, Float),
+    SmoothSubtract(NodeId, NodeId, Float),
+    Intersect(NodeId, NodeId),
+    // 从第一个节点中减去第二个节点
+    Subtract(NodeId, NodeId),
+    // 补集, 见 Shapes::invert
+    Invert(NodeId),
+    // 向外扩展的距离, 见 Shapes::offset
+    Offset(NodeId, Float),
+    // 空心轮廓的半宽, 见 Shapes::shell
+    Shell(NodeId, Float),
+    // 放大、旋转和平移, 见 Transformed
+    Transformed(NodeId, Transform),
+    // 两个方向分别缩放, 见 Shapes::scale
+    Scaled(NodeId, Scale),
+    // 关于直线对称, 见 Shapes::mirror
+    Mirror(NodeId, Mirror),
+    // 无限重复, 见 Shapes::repeat
+    Repeat(NodeId, Repeat),
+    // 绕一点重复, 见 Shapes::repeat_polar
+    PolarRepeat(NodeId, PolarRepeat),
+    // 用噪声扰动, 见 Warped
+    Warped(NodeId, Warp),
+    // 错切, 见 Shapes::shear_x 和 Shapes::shear_y
+    Shear(NodeId, Shear),
+    // 沿 x 轴弯曲, 见 Shapes::bend
+    Bend(NodeId, Bend),
+    Profiled(NodeId, EmissionProfile),
+    // 指定各颜色通道的折射率
+    Refractive(NodeId, Color),
+    // 指定各颜色通道的吸收系数
+    Absorbing(NodeId, Color),
+    // 指定材质, 代替子树中各个形状的材质
+    Surface(NodeId, Material),
+}
+
+macro_rules! impl_from_primitive {
+    ($($name:ident),*) => {
+        $(
+            impl From<$name> for CsgNode {
+                fn from(shape: $name) -> CsgNode {
+                    CsgNode::$name(shape)
+                }
+            }
+        )*
+    };
+}
+
+impl_from_primitive!(
+    Circle,
+    Plane,
+    Capsule,
+    Rect,
+    Triangle,
+    Ellipse,
+    Polygon,
+    Ring,
+    Arc,
+    Pie,
+    Bezier2,
+    Bezier3,
+    Heart,
+    Cross,
+    Rhombus,
+    Trapezoid,
+    Parabola,
+    Vesica,
+    Crescent,
+    Superellipse,
+    Gear,
+    Hexagram,
+    Egg,
+    Spline
+);
+
+// 作为 Shape 使用时计算 set_root 指定的根节点的 sdf, 没有指定根节点时是空的
+// 不属于根节点的子树的节点也可以留在树中, 例如之后要复用的辅助节点
+#[derive(Clone, Default)]
+pub struct CsgTree {
+    nodes: Vec<CsgNode>,
+    root: Option<NodeId>,
+}
+
+impl CsgTree {
+    pub fn new() -> CsgTree {
+        CsgTree::default()
+    }
+
+    // 添加一个节点, 节点引用的子节点必须已经在树中
+    pub fn add<N: Into<CsgNode>>(&mut self, node: N) -> NodeId {
+        let node = node.into();
+        let exists = |id: &NodeId| id.0 < self.nodes.len();
+        let valid = match &node {
+            CsgNode::Union(a, b)
+            | CsgNode::SmoothUnion(a, b, _)
+            | CsgNode::SmoothIntersect(a, b, _)
+            | CsgNode::SmoothSubtract(a, b, _)
+            | CsgNode::Intersect(a, b)
+            | CsgNode::Subtract(a, b) => exists(a) && exists(b),
+            CsgNode::UnionAll(ids) => ids.iter().all(exists),
+            CsgNode::Invert(a)
+            | CsgNode::Offset(a, _)
+            | CsgNode::Shell(a, _)
+            | CsgNode::Transformed(a, _)
+            | CsgNode::Scaled(a, _)
+            | CsgNode::Mirror(a, _)
+            | CsgNode::Repeat(a, _)
+            | CsgNode::PolarRepeat(a, _)
+            | CsgNode::Warped(a, _)
+            | CsgNode::Shear(a, _)
+            | CsgNode::Bend(a, _)
+            | CsgNode::Profiled(a, _)
+            | CsgNode::Refractive(a, _)
+            | CsgNode::Absorbing(a, _)
+            | CsgNode::Surface(a, _) => exists(a),
+            _ => true,
+        };
+        assert!(valid, "child node is not in this tree");
+        self.nodes.push(node);
+        NodeId(self.nodes.len() - 1)
+    }
+
+    pub fn union(&mut self, a: NodeId, b: NodeId) -> NodeId {
+        self.add(CsgNode::Union(a, b))
+    }
+
+    pub fn union_all(&mut self, ids: Vec<NodeId>) -> NodeId {
+        self.add(CsgNode::UnionAll(ids))
+    }
+
+    pub fn smooth_union(&mut self, a: NodeId, b: NodeId, k: Float) -> NodeId {
+        self.add(CsgNode::SmoothUnion(a, b, k))
+    }
+
+    pub fn intersect(&mut self, a: NodeId, b: NodeId) -> NodeId {
+        self.add(CsgNode::Intersect(a, b))
+    }
+
+    pub fn subtract(&mut self, a: NodeId, b: NodeId) -> NodeId {
+        self.add(CsgNode::Subtract(a, b))
+    }
+
+    pub fn smooth_intersect(&mut self, a: NodeId, b: NodeId, k: Float) -> NodeId {
+        self.add(CsgNode::SmoothIntersect(a, b, k))
+    }
+
+    pub fn smooth_subtract(&mut self, a: NodeId, b: NodeId, k: Float) -> NodeId {
+        self.add(CsgNode::SmoothSubtract(a, b, k))
+    }
+
+    pub fn invert(&mut self, a: NodeId) -> NodeId {
+        self.add(CsgNode::Invert(a))
+    }
+
+    pub fn offset(&mut self, a: NodeId, r: Float) -> NodeId {
+        self.add(CsgNode::Offset(a, r))
+    }
+
+    pub fn shell(&mut self, a: NodeId, thickness: Float) -> NodeId {
+        self.add(CsgNode::Shell(a, thickness))
+    }
+
+    pub fn transformed(&mut self, a: NodeId, transform: Transform) -> NodeId {
+        self.add(CsgNode::Transformed(a, transform))
+    }
+
+    pub fn scale(&mut self, a: NodeId, sx: Float, sy: Float) -> NodeId {
+        self.add(CsgNode::Scaled(a, Scale::new(sx, sy)))
+    }
+
+    pub fn mirror_x(&mut self, a: NodeId, axis_x: Float) -> NodeId {
+        self.mirror(a, axis_x, 0.0, 1.0, 0.0)
+    }
+
+    pub fn mirror_y(&mut self, a: NodeId, axis_y: Float) -> NodeId {
+        self.mirror(a, 0.0, axis_y, 0.0, 1.0)
+    }
+
+    pub fn mirror(&mut self, a: NodeId, x: Float, y: Float, nx: Float, ny: Float) -> NodeId {
+        self.add(CsgNode::Mirror(a, Mirror::new(x, y, nx, ny)))
+    }
+
+    pub fn repeat(&mut self, a: NodeId, spacing_x: Float, spacing_y: Float) -> NodeId {
+        self.add(CsgNode::Repeat(a, Repeat::new(spacing_x, spacing_y)))
+    }
+
+    pub fn repeat_polar(&mut self, a: NodeId, cx: Float, cy: Float, count: usize) -> NodeId {
+        self.add(CsgNode::PolarRepeat(a, PolarRepeat::new(cx, cy, count)))
+    }
+
+    pub fn warped(&mut self, a: NodeId, amplitude: Float, frequency: Float, seed: u64) -> NodeId {
+        self.add(CsgNode::Warped(a, Warp::new(amplitude, frequency, seed)))
+    }
+
+    pub fn shear_x(&mut self, a: NodeId, k: Float) -> NodeId {
+        self.add(CsgNode::Shear(a, Shear::horizontal(k)))
+    }
+
+    pub fn shear_y(&mut self, a: NodeId, k: Float) -> NodeId {
+        self.add(CsgNode::Shear(a, Shear::vertical(k)))
+    }
+
+    pub fn bend(&mut self, a: NodeId, k: Float) -> NodeId {
+        self.add(CsgNode::Bend(a, Bend::new(k)))
+    }
+
+    pub fn profiled(&mut self, a: NodeId, profile: EmissionProfile) -> NodeId {
+        self.add(CsgNode::Profiled(a, profile))
+    }
+
+    pub fn refractive(&mut self, a: NodeId, eta: impl Into<Color>) -> NodeId {
+        self.add(CsgNode::Refractive(a, eta.into()))
+    }
+
+    pub fn absorbing(&mut self, a: NodeId, absorption: impl Into<Color>) -> NodeId {
+        self.add(CsgNode::Absorbing(a, absorption.into()))
+    }
+
+    pub fn surface(&mut self, a: NodeId, material: Material) -> NodeId {
+        self.add(CsgNode::Surface(a, material))
+    }
+
+    // 指定树的根节点
+    pub fn set_root(&mut self, id: NodeId) {
+        assert!(id.0 < self.nodes.len(), "root node is not in this tree");
+        self.root = Some(id);
+    }
+
+    pub fn root(&self) -> Option<NodeId> {
+        self.root
+    }
+
+    pub fn node(&self, id: NodeId) -> &CsgNode {
+        &self.nodes[id.0]
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    // 删除所有节点和根节点, 保留已经分配的内存
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.root = None;
+    }
+
+    // 计算以 id 为根的子树的 sdf
+    pub fn node_sdf(&self, id: NodeId, x: Float, y: Float) -> SdfResult {
+        match &self.nodes[id.0] {
+            CsgNode::Circle(shape) => shape.sdf(x, y),
+            CsgNode::Plane(shape) => shape.sdf(x, y),
+            CsgNode::Capsule(shape) => shape.sdf(x, y),
+            CsgNode::Rect(shape) => shape.sdf(x, y),
+            CsgNode::Triangle(shape) => shape.sdf(x, y),
+            CsgNode::Ellipse(shape) => shape.sdf(x, y),
+            CsgNode::Polygon(shape) => shape.sdf(x, y),
+            CsgNode::Ring(shape) => shape.sdf(x, y),
+            CsgNode::Arc(shape) => shape.sdf(x, y),
+            CsgNode::Pie(shape) => shape.sdf(x, y),
+            CsgNode::Bezier2(shape) => shape.sdf(x, y),
+            CsgNode::Bezier3(shape) => shape.sdf(x, y),
+            CsgNode::Heart(shape) => shape.sdf(x, y),
+            CsgNode::Cross(shape) => shape.sdf(x, y),
+            CsgNode::Rhombus(shape) => shape.sdf(x, y),
+            CsgNode::Trapezoid(shape) => shape.sdf(x, y),
+            CsgNode::Parabola(shape) => shape.sdf(x, y),
+            CsgNode::Vesica(shape) => shape.sdf(x, y),
+            CsgNode::Crescent(shape) => shape.sdf(x, y),
+            CsgNode::Superellipse(shape) => shape.sdf(x, y),
+            CsgNode::Gear(shape) => shape.sdf(x, y),
+            CsgNode::Hexagram(shape) => shape.sdf(x, y),
+            CsgNode::Egg(shape) => shape.sdf(x, y),
+            CsgNode::Spline(shape) => shape.sdf(x, y),
+            CsgNode::Union(a, b) => union_result(self.node_sdf(*a, x, y), self.node_sdf(*b, x, y)),
+            CsgNode::UnionAll(ids) => {
+                union_all_result(ids.iter().map(|id| self.node_sdf(*id, x, y)))
+            }
+            CsgNode::SmoothUnion(a, b, k) => {
+                smooth_union_result(self.node_sdf(*a, x, y), self.node_sdf(*b, x, y), *k)
+            }
+            CsgNode::SmoothIntersect(a, b, k) => {
+                smooth_intersect_result(self.node_sdf(*a, x, y), self.node_sdf(*b, x, y), *k)
+            }
+            CsgNode::SmoothSubtract(a, b, k) => {
+                smooth_subtract_result(self.node_sdf(*a, x, y), self.node_sdf(*b, x, y), *k)
+            }
+            CsgNode::Intersect(a, b) => {
+                intersect_result(self.node_sdf(*a, x, y), self.node_sdf(*b, x, y))
+            }
+            CsgNode::Subtract(a, b) => {
+                subtract_result(self.node_sdf(*a, x, y), self.node_sdf(*b, x, y))
+            }
+            CsgNode::Invert(a) => {
+                let mut result = self.node_sdf(*a, x, y);
+                result.sd = -result.sd;
+                result
+            }
+            CsgNode::Offset(a, r) => {
+                let mut result = self.node_sdf(*a, x, y);
+                result.sd -= *r;
+                result
+            }
+            CsgNode::Shell(a, thickness) => {
+                let mut result = self.node_sdf(*a, x, y);
+                result.sd = result.sd.abs() - *thickness;
+                result
+            }
+            CsgNode::Transformed(a, transform) => {
+                transform.sdf(x, y, |x, y| self.node_sdf(*a, x, y))
+            }
+            CsgNode::Scaled(a, scale) => scale.sdf(x, y, |x, y| self.node_sdf(*a, x, y)),
+            CsgNode::Mirror(a, mirror) => mirror.sdf(x, y, |x, y| self.node_sdf(*a, x, y)),
+            CsgNode::Repeat(a, repeat) => repeat.sdf(x, y, |x, y| self.node_sdf(*a, x, y)),
+            CsgNode::PolarRepeat(a, repeat) => repeat.sdf(x, y, |x, y| self.node_sdf(*a, x, y)),
+            CsgNode::Warped(a, warp) => warp.sdf(x, y, |x, y| self.node_sdf(*a, x, y)),
+            CsgNode::Shear(a, shear) => shear.sdf(x, y, |x, y| self.node_sdf(*a, x, y)),
+            CsgNode::Bend(a, bend) => {
+                bend.sdf(x, y, self.node_bounds(*a), |x, y| self.node_sdf(*a, x, y))
+            }
+            CsgNode::Profiled(a, profile) => {
+                let mut result = self.node_sdf(*a, x, y);
+                result.profile = profile.clone();
+                result
+            }
+            CsgNode::Refractive(a, eta) => {
+                let mut result = self.node_sdf(*a, x, y);
+                result.material.eta = *eta;
+                result
+            }
+            CsgNode::Absorbing(a, absorption) => {
+                let mut result = self.node_sdf(*a, x, y);
+                result.material.absorption = *absorption;
+                result
+            }
+            CsgNode::Surface(a, material) => {
+                let mut result = self.node_sdf(*a, x, y);
+                result.material = *material;
+                result
+            }
+        }
+    }
+
+    pub fn node_bounds(&self, id: NodeId) -> Option<Aabb> {
+        match &self.nodes[id.0] {
+            CsgNode::Circle(shape) => shape.bounds(),
+            CsgNode::Plane(shape) => shape.bounds(),
+            CsgNode::Capsule(shape) => shape.bounds(),
+            CsgNode::Rect(shape) => shape.bounds(),
+            CsgNode::Triangle(shape) => shape.bounds(),
+            CsgNode::Ellipse(shape) => shape.bounds(),
+            CsgNode::Polygon(shape) => shape.bounds(),
+            CsgNode::Ring(shape) => shape.bounds(),
+            CsgNode::Arc(shape) => shape.bounds(),
+            CsgNode::Pie(shape) => shape.bounds(),
+            CsgNode::Bezier2(shape) => shape.bounds(),
+            CsgNode::Bezier3(shape) => shape.bounds(),
+            CsgNode::Heart(shape) => shape.bounds(),
+            CsgNode::Cross(shape) => shape.bounds(),
+            CsgNode::Rhombus(shape) => shape.bounds(),
+            CsgNode::Trapezoid(shape) => shape.bounds(),
+            CsgNode::Parabola(shape) => shape.bounds(),
+            CsgNode::Vesica(shape) => shape.bounds(),
+            CsgNode::Crescent(shape) => shape.bounds(),
+            CsgNode::Superellipse(shape) => shape.bounds(),
+            CsgNode::Gear(shape) => shape.bounds(),
+            CsgNode::Hexagram(shape) => shape.bounds(),
+            CsgNode::Egg(shape) => shape.bounds(),
+            CsgNode::Spline(shape) => shape.bounds(),
+            CsgNode::Union(a, b) => union_bounds(self.node_bounds(*a), self.node_bounds(*b)),
+            CsgNode::UnionAll(ids) => union_all_bounds(ids.iter().map(|id| self.node_bounds(*id))),
+            CsgNode::SmoothUnion(a, b, k) => {
+                smooth_union_bounds(self.node_bounds(*a), self.node_bounds(*b), *k)
+            }
+            CsgNode::Intersect(a, b) | CsgNode::SmoothIntersect(a, b, _) => {
+                intersect_bounds(self.node_bounds(*a), self.node_bounds(*b))
+            }
+            CsgNode::Subtract(a, _)
+            | CsgNode::SmoothSubtract(a, _, _)
+            | CsgNode::Profiled(a, _)
+            | CsgNode::Refractive(a, _)
+            | CsgNode::Absorbing(a, _)
+            | CsgNode::Surface(a, _) => self.node_bounds(*a),
+            // 无限重复的形状没有包围盒
+            CsgNode::Invert(_) | CsgNode::Repeat(_, _) => None,
+            CsgNode::Offset(a, r) | CsgNode::Shell(a, r) => offset_bounds(self.node_bounds(*a), *r),
+            CsgNode::Transformed(a, transform) => transform.bounds(self.node_bounds(*a)),
+            CsgNode::Scaled(a, scale) => scale.bounds(self.node_bounds(*a)),
+            CsgNode::Mirror(a, mirror) => mirror.bounds(self.node_bounds(*a)),
+            CsgNode::PolarRepeat(a, repeat) => repeat.bounds(self.node_bounds(*a)),
+            CsgNode::Warped(a, warp) => warp.bounds(self.node_bounds(*a)),
+            CsgNode::Shear(a, shear) => shear.bounds(self.node_bounds(*a)),
+            CsgNode::Bend(a, bend) => bend.bounds(self.node_bounds(*a)),
+        }
+    }
+}
+
+impl Shape for CsgTree {
+    fn sdf(&self, x: Float, y: Float) -> SdfResult {
+        match self.root() {
+            Some(root) => self.node_sdf(root, x, y),
+            None => SdfResult {
+                sd: Float::MAX,
+                material: Material::default(),
+                profile: EmissionProfile::Uniform,
+            },
+        }
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        self.node_bounds(self.root()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shape::{Shapes, Transformed, Warped};
+
+    #[test]
+    fn matches_boxed_tree() {
+        let mut tree = CsgTree::new();
+        let a = tree.add(Circle::new(0.0, 0.0, 2.0, 1.0));
+        let b = tree.add(Rect::new(1.0, 0.0, 0.2, 1.0, 3.0, 2.0));
+        let c = tree.add(Capsule::new(-2.0, 2.0, 2.0, 2.0, 0.5, 3.0));
+        let ab = tree.subtract(a, b);
+        let root = tree.union(ab, c);
+        tree.set_root(root);
+
+        let boxed = Shapes::union(
+            Shapes::subtract(
+                Box::new(Circle::new(0.0, 0.0, 2.0, 1.0)),
+                Box::new(Rect::new(1.0, 0.0, 0.2, 1.0, 3.0, 2.0)),
+            ),
+            Box::new(Capsule::new(-2.0, 2.0, 2.0, 2.0, 0.5, 3.0)),
+        );
+
+        let copy = tree.clone();
+        for i in 0..100 {
+            let (x, y) = (i as Float * 0.07 - 3.5, (i * 7 % 100) as Float * 0.07 - 3.5);
+            let (r1, r2) = (copy.sdf(x, y), boxed.sdf(x, y));
+            assert_eq!(r1.sd, r2.sd);
+            assert_eq!(r1.material, r2.material);
+        }
+        assert_eq!(tree.bounds(), boxed.bounds());
+
+        // 之后添加的辅助节点不会改变根节点
+        let root = tree.root();
+        let helper = tree.add(Circle::new(5.0, 5.0, 1.0, 1.0));
+        assert_eq!(tree.root(), root);
+        assert_eq!(tree.sdf(0.0, 0.0).sd, boxed.sdf(0.0, 0.0).sd);
+        tree.set_root(helper);
+        assert_eq!(tree.sdf(5.0, 5.0).sd, -1.0);
+
+        tree.clear();
+        assert_eq!(tree.root(), None);
+        assert!(tree.is_empty());
+        assert_eq!(tree.sdf(0.0, 0.0).sd, Float::MAX);
+    }
+
+    #[test]
+    fn unary_operations() {
+        let mut tree = CsgTree::new();
+        let a = tree.add(Circle::new(0.0, 0.0, 2.0, 1.0));
+        let b = tree.invert(a);
+        let c = tree.add(Circle::new(0.0, 0.0, 2.5, 1.0));
+        let d = tree.offset(c, 0.5);
+        let e = tree.intersect(b, d);
+        let root = tree.shell(e, 0.2);
+        tree.set_root(root);
+        let boxed = Shapes::shell(
+            Shapes::intersect(
+                Shapes::invert(Box::new(Circle::new(0.0, 0.0, 2.0, 1.0))),
+                Shapes::offset(Box::new(Circle::new(0.0, 0.0, 2.5, 1.0)), 0.5),
+            ),
+            0.2,
+        );
+        for i in 0..100 {
+            let (x, y) = (i as Float * 0.08 - 4.0, (i * 7 % 100) as Float * 0.08 - 4.0);
+            assert_eq!(tree.sdf(x, y).sd, boxed.sdf(x, y).sd);
+        }
+        assert_eq!(tree.bounds(), boxed.bounds());
+        assert_eq!(tree.node_bounds(b), None);
+    }
+
+    // 在 [-range, range] 内的点上比较树和嵌套的形状的 sdf 和材质, 以及包围盒
+    fn assert_matches(tree: &CsgTree, boxed: &dyn Shape, range: Float) {
+        for i in 0..100 {
+            let x = (i as Float / 99.0 * 2.0 - 1.0) * range;
+            let y = ((i * 37 % 100) as Float / 99.0 * 2.0 - 1.0) * range;
+            let (r1, r2) = (tree.sdf(x, y), boxed.sdf(x, y));
+            assert_eq!(r1.sd, r2.sd);
+            assert_eq!(r1.material, r2.material);
+        }
+        assert_eq!(tree.bounds(), boxed.bounds());
+    }
+
+    #[test]
+    fn union_all() {
+        let mut tree = CsgTree::new();
+        let a = tree.add(Circle::new(0.0, 0.0, 2.0, 1.0));
+        let b = tree.add(Rect::new(3.0, 1.0, 0.3, 1.0, 2.0, 2.0));
+        let c = tree.add(Circle::new(-2.0, 2.0, 1.0, 3.0));
+        let root = tree.union_all(vec![a, b, c]);
+        tree.set_root(root);
+        let boxed = Shapes::union_all(vec![
+            Box::new(Circle::new(0.0, 0.0, 2.0, 1.0)),
+            Box::new(Rect::new(3.0, 1.0, 0.3, 1.0, 2.0, 2.0)),
+            Box::new(Circle::new(-2.0, 2.0, 1.0, 3.0)),
+        ]);
+        assert_matches(&tree, boxed.as_ref(), 5.0);
+
+        let mut empty = CsgTree::new();
+        let root = empty.union_all(vec![]);
+        empty.set_root(root);
+        assert_eq!(empty.sdf(0.0, 0.0).sd, Float::MAX);
+        assert_eq!(empty.bounds(), None);
+    }
+
+    #[test]
+    fn transformed() {
+        let transform = Transform::default()
+            .with_translation(1.0, -2.0)
+            .with_rotation(0.7)
+            .with_scale(1.5);
+        let mut tree = CsgTree::new();
+        let a = tree.add(Rect::new(0.5, 0.0, 0.0, 2.0, 1.0, 1.0));
+        let root = tree.transformed(a, transform);
+        tree.set_root(root);
+        let boxed = Transformed::new(Box::new(Rect::new(0.5, 0.0, 0.0, 2.0, 1.0, 1.0)))
+            .with_translation(1.0, -2.0)
+            .with_rotation(0.7)
+            .with_scale(1.5);
+        assert_matches(&tree, &boxed, 5.0);
+    }
+
+    #[test]
+    fn scaled() {
+        let mut tree = CsgTree::new();
+        let a = tree.add(Circle::new(0.5, 0.0, 1.0, 1.0));
+        let root = tree.scale(a, 3.0, 0.5);
+        tree.set_root(root);
+        let boxed = Shapes::scale(Box::new(Circle::new(0.5, 0.0, 1.0, 1.0)), 3.0, 0.5);
+        assert_matches(&tree, boxed.as_ref(), 5.0);
+    }
+
+    #[test]
+    fn mirror() {
+        let mut tree = CsgTree::new();
+        let a = tree.add(Circle::new(2.0, 1.0, 1.5, 1.0));
+        let b = tree.mirror_x(a, 0.5);
+        let root = tree.mirror(b, 0.0, 0.0, 1.0, 2.0);
+        tree.set_root(root);
+        let boxed = Shapes::mirror(
+            Shapes::mirror_x(Box::new(Circle::new(2.0, 1.0, 1.5, 1.0)), 0.5),
+            0.0,
+            0.0,
+            1.0,
+            2.0,
+        );
+        assert_matches(&tree, boxed.as_ref(), 5.0);
+
+        let mut tree = CsgTree::new();
+        let a = tree.add(Circle::new(1.0, 2.0, 1.5, 1.0));
+        let root = tree.mirror_y(a, 0.5);
+        tree.set_root(root);
+        let boxed = Shapes::mirror_y(Box::new(Circle::new(1.0, 2.0, 1.5, 1.0)), 0.5);
+        assert_matches(&tree, boxed.as_ref(), 5.0);
+    }
+
+    #[test]
+    fn repeat() {
+        let mut tree = CsgTree::new();
+        let a = tree.add(Circle::new(0.0, 0.0, 0.5, 1.0));
+        let root = tree.repeat(a, 2.0, 3.0);
+        tree.set_root(root);
+        let boxed = Shapes::repeat(Box::new(Circle::new(0.0, 0.0, 0.5, 1.0)), 2.0, 3.0);
+        assert_matches(&tree, boxed.as_ref(), 8.0);
+        assert_eq!(tree.bounds(), None);
+    }
+
+    #[test]
+    fn polar_repeat() {
+        let mut tree = CsgTree::new();
+        let a = tree.add(Rect::new(3.0, 1.0, 0.0, 0.8, 0.3, 1.0));
+        let root = tree.repeat_polar(a, 1.0, 1.0, 6);
+        tree.set_root(root);
+        let boxed = Shapes::repeat_polar(
+            Box::new(Rect::new(3.0, 1.0, 0.0, 0.8, 0.3, 1.0)),
+            1.0,
+            1.0,
+            6,
+        );
+        assert_matches(&tree, boxed.as_ref(), 5.0);
+    }
+
+    #[test]
+    fn warped() {
+        let mut tree = CsgTree::new();
+        let a = tree.add(Circle::new(0.0, 0.0, 2.0, 1.0));
+        let root = tree.warped(a, 0.3, 1.5, 7);
+        tree.set_root(root);
+        let boxed = Warped::new(Box::new(Circle::new(0.0, 0.0, 2.0, 1.0)), 0.3, 1.5, 7);
+        assert_matches(&tree, &boxed, 4.0);
+    }
+
+    #[test]
+    fn shear_and_bend() {
+        let mut tree = CsgTree::new();
+        let a = tree.add(Rect::new(0.0, 0.0, 0.0, 2.0, 1.0, 1.0));
+        let b = tree.shear_x(a, 0.5);
+        let c = tree.shear_y(b, -0.3);
+        let root = tree.bend(c, 0.2);
+        tree.set_root(root);
+        let boxed = Shapes::bend(
+            Shapes::shear_y(
+                Shapes::shear_x(Box::new(Rect::new(0.0, 0.0, 0.0, 2.0, 1.0, 1.0)), 0.5),
+                -0.3,
+            ),
+            0.2,
+        );
+        assert_matches(&tree, boxed.as_ref(), 5.0);
+    }
+
+    #[test]
+    fn smooth_operations() {
+        type Operation = fn(Box<dyn Shape>, Box<dyn Shape>, Float) -> Box<dyn Shape>;
+        type Node = fn(&mut CsgTree, NodeId, NodeId, Float) -> NodeId;
+        let operations: [(Operation, Node); 3] = [
+            (
+                |a, b, k| Shapes::smooth_union(a, b, k),
+                CsgTree::smooth_union,
+            ),
+            (
+                |a, b, k| Shapes::smooth_intersect(a, b, k),
+                CsgTree::smooth_intersect,
+            ),
+            (
+                |a, b, k| Shapes::smooth_subtract(a, b, k),
+                CsgTree::smooth_subtract,
+            ),
+        ];
+        for (boxed, node) in operations.iter() {
+            let mut tree = CsgTree::new();
+            let a = tree.add(Circle::new(0.0, 0.0, 2.0, 1.0));
+            let b = tree.add(Rect::new(3.0, 1.0, 0.3, 1.0, 2.0, 2.0));
+            let root = node(&mut tree, a, b, 1.5);
+            tree.set_root(root);
+            let boxed = boxed(
+                Box::new(Circle::new(0.0, 0.0, 2.0, 1.0)),
+                Box::new(Rect::new(3.0, 1.0, 0.3, 1.0, 2.0, 2.0)),
+                1.5,
+            );
+            for i in 0..100 {
+                let (x, y) = (i as Float * 0.08 - 3.0, (i * 7 % 100) as Float * 0.07 - 3.5);
+                let (r1, r2) = (tree.sdf(x, y), boxed.sdf(x, y));
+                assert_eq!(r1.sd, r2.sd);
+                assert_eq!(r1.material, r2.material);
+            }
+            assert_eq!(tree.bounds(), boxed.bounds());
+        }
+    }
+}
@@ -0,0 +1,160 @@
+// 去噪: 对渲染出的浮点光量做联合双边滤波, 采样数很少时也能得到干净的图片
+// 每个像素与窗口内的邻居加权平均, 权重取决于距离、颜色的差别和引导信息(AOV)的差别
+// 引导信息记录像素被哪个形状覆盖以及覆盖的比例, 形状的边缘不会被模糊
+// 比较颜色时使用 3×3 平均后的图片, 避免噪点本身让权重变得很乱
+
+use crate::color::Color;
+use crate::float::Float;
+
+// 比较相对颜色差时亮度的下限, 避免很暗的像素之间微小的差别也被当作边缘
+const MIN_LUMINANCE: Float = 1e-2;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Denoiser {
+    // 滤波窗口的半径(像素)
+    pub radius: u32,
+    // 距离的标准差(像素)
+    pub sigma_spatial: Float,
+    // 颜色差相对于亮度的标准差, 越大越平滑, 也越容易抹掉明暗交界
+    pub sigma_color: Float,
+    // 覆盖比例之差的标准差
+    pub sigma_guide: Float,
+}
+
+impl Denoiser {
+    // 窗口半径为 radius 的去噪器, 其余参数使用适合大多数场景的默认值
+    pub fn new(radius: u32) -> Denoiser {
+        Denoiser {
+            radius,
+            sigma_spatial: (radius as Float / 2.0).max(0.5),
+            sigma_color: 0.5,
+            sigma_guide: 0.2,
+        }
+    }
+}
+
+// 一个像素的引导信息: 被形状覆盖的比例, 以及覆盖它的形状(在形状外面时为 None)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Guide {
+    pub coverage: Float,
+    pub shape: Option<usize>,
+}
+
+// 对 width × height 的图片去噪, buffer 和 guide 都按行排列
+pub(crate) fn denoise(
+    buffer: &[Color],
+    guide: &[Guide],
+    width: u32,
+    height: u32,
+    denoiser: &Denoiser,
+) -> Vec<Color> {
+    let (width, height) = (width as usize, height as usize);
+    let smooth = box_blur(buffer, width, height);
+    let radius = denoiser.radius as isize;
+    let spatial = 2.0 * denoiser.sigma_spatial * denoiser.sigma_spatial;
+    let color = 2.0 * denoiser.sigma_color * denoiser.sigma_color;
+    let coverage = 2.0 * denoiser.sigma_guide * denoiser.sigma_guide;
+
+    let mut result = vec![Color::BLACK; buffer.len()];
+    for y in 0..height {
+        for x in 0..width {
+            let center = y * width + x;
+            let mut sum = Color::BLACK;
+            let mut total = 0.0;
+            for dy in -radius..=radius {
+                for dx in -radius..=radius {
+                    let (nx, ny) = (x as isize + dx, y as isize + dy);
+                    if nx < 0 || ny < 0 || nx >= width as isize || ny >= height as isize {
+                        continue;
+                    }
+                    let neighbor = ny as usize * width + nx as usize;
+                    let (a, b) = (guide[center], guide[neighbor]);
+                    if let (Some(i), Some(j)) = (a.shape, b.shape) {
+                        if i != j {
+                            continue;
+                        }
+                    }
+
+                    let scale = smooth[center]
+                        .luminance()
+                        .max(smooth[neighbor].luminance())
+                        .max(MIN_LUMINANCE);
+                    let d = (smooth[center] - smooth[neighbor]).map(|c| c / scale);
+                    let distance = (dx * dx + dy * dy) as Float;
+                    let weight = (-distance / spatial
+                        - (d.r * d.r + d.g * d.g + d.b * d.b) / color
+                        - (a.coverage - b.coverage).powi(2) / coverage)
+                        .exp();
+                    sum += buffer[neighbor] * weight;
+                    total += weight;
+                }
+            }
+            // 中心像素自身的权重总是 1, total 不会为 0
+            result[center] = sum / total;
+        }
+    }
+    result
+}
+
+// 3×3 的平均, 边缘只平均画面内的像素
+fn box_blur(buffer: &[Color], width: usize, height: usize) -> Vec<Color> {
+    let mut result = vec![Color::BLACK; buffer.len()];
+    for y in 0..height {
+        for x in 0..width {
+            let mut sum = Color::BLACK;
+            let mut count = 0.0;
+            for ny in y.saturating_sub(1)..(y + 2).min(height) {
+                for nx in x.saturating_sub(1)..(x + 2).min(width) {
+                    sum += buffer[ny * width + nx];
+                    count += 1.0;
+                }
+            }
+            result[y * width + x] = sum / count;
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::difference;
+    use crate::scene::Scene;
+    use crate::shape::Circle;
+
+    #[test]
+    fn denoise_render() {
+        let mut scene = Scene::new(32, 24);
+        scene.add_shape(Box::new(Circle::new(10.0, 12.0, 4.0, 1.0)));
+        scene.set_seed(5);
+        scene.set_sample_count(1024);
+        let reference = scene.render_radiance();
+
+        scene.set_sample_count(8);
+        let noisy = scene.render_radiance();
+        let denoised = scene.denoise(&noisy, &Denoiser::new(3));
+        let before = difference(&noisy, &reference).rmse;
+        let after = difference(&denoised, &reference).rmse;
+        assert!(after * 2.0 < before, "{} {}", after, before);
+        // 形状里面的像素不会被外面较暗的像素拉低
+        assert!(denoised[12 * 32 + 10].r > 0.99);
+    }
+
+    #[test]
+    fn shapes_are_not_mixed() {
+        // 两个相邻的形状即使颜色相近也不会互相混合
+        let buffer = [Color::gray(1.0), Color::gray(0.9)];
+        let guide = [
+            Guide {
+                coverage: 1.0,
+                shape: Some(0),
+            },
+            Guide {
+                coverage: 1.0,
+                shape: Some(1),
+            },
+        ];
+        let result = denoise(&buffer, &guide, 2, 1, &Denoiser::new(2));
+        assert_eq!(result, buffer);
+    }
+}
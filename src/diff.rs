@@ -0,0 +1,119 @@
+// 比较两个场景(或同一个场景的两组设置)的渲染结果
+// 生成带符号的差异图和统计数据, 方便检查两次修改之间光照的变化
+
+use crate::color::Color;
+use crate::float::Float;
+use crate::scene::Scene;
+
+// 差异图中颜色达到最饱和时对应的差值
+const FULL_SCALE: Float = 0.25;
+// 差值超过这个值的像素算作发生了变化
+const CHANGED_THRESHOLD: Float = 1.0 / 255.0;
+
+#[derive(Clone, Debug, PartialEq)]
+// 除 changed_ratio 以外, 都把每个像素的三个颜色分量当作三个独立的值统计
+pub struct DifferenceStats {
+    // b 减 a 的平均值, 大于 0 说明 b 整体更亮
+    pub mean: Float,
+    // 差值绝对值的平均值
+    pub mean_absolute: Float,
+    pub rmse: Float,
+    // 差值绝对值的最大值
+    pub max_absolute: Float,
+    // 峰值信噪比(以 1.0 为峰值), 两张图完全相同时为无穷大
+    pub psnr: Float,
+    // 发生了变化(任意一个分量发生了变化)的像素所占的比例
+    pub changed_ratio: Float,
+}
+
+// 比较两组渲染结果(每个像素的光量), 两者的大小必须相同
+pub fn difference(a: &[Color], b: &[Color]) -> DifferenceStats {
+    assert_eq!(a.len(), b.len(), "images must have the same size");
+    let pixel_count = a.len().max(1) as Float;
+    let count = pixel_count * 3.0;
+
+    let mut sum = 0.0;
+    let mut sum_absolute = 0.0;
+    let mut sum_squared = 0.0;
+    let mut max_absolute: Float = 0.0;
+    let mut changed = 0;
+    for (&a, &b) in a.iter().zip(b.iter()) {
+        let d = b - a;
+        let mut pixel_changed = false;
+        for &d in [d.r, d.g, d.b].iter() {
+            sum += d;
+            sum_absolute += d.abs();
+            sum_squared += d * d;
+            max_absolute = max_absolute.max(d.abs());
+            pixel_changed |= d.abs() > CHANGED_THRESHOLD;
+        }
+        if pixel_changed {
+            changed += 1;
+        }
+    }
+
+    let mse = sum_squared / count;
+    DifferenceStats {
+        mean: sum / count,
+        mean_absolute: sum_absolute / count,
+        rmse: mse.sqrt(),
+        max_absolute,
+        psnr: -10.0 * mse.log10(),
+        changed_ratio: changed as Float / pixel_count,
+    }
+}
+
+// 渲染两个场景, 把带符号的差异图保存到 path, 并返回统计数据
+// 按亮度比较, b 比 a 亮的地方为红色, 暗的地方为蓝色, 没有变化的地方为黑色
+pub fn render_difference_to_file(a: &Scene, b: &Scene, path: &str) -> DifferenceStats {
+    assert!(
+        a.width() == b.width() && a.height() == b.height(),
+        "scenes must have the same size"
+    );
+    let image_a = a.render_radiance();
+    let image_b = b.render_radiance();
+
+    let mut image = vec![0u8; image_a.len() * 3];
+    for (pixel, (va, vb)) in image.chunks_mut(3).zip(image_a.iter().zip(image_b.iter())) {
+        let d = ((vb.luminance() - va.luminance()) / FULL_SCALE).clamp(-1.0, 1.0);
+        let color = if d > 0.0 {
+            Color::new(d, 0.0, 0.0)
+        } else {
+            Color::new(0.0, 0.0, -d)
+        };
+        pixel.copy_from_slice(&color.to_rgb8());
+    }
+    a.save_to_file(&image, path);
+
+    difference(&image_a, &image_b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats() {
+        let a = [0.0, 0.5, 1.0, 0.25].map(Color::gray);
+        let stats = difference(&a, &a);
+        assert_eq!(stats.rmse, 0.0);
+        assert_eq!(stats.psnr, Float::INFINITY);
+        assert_eq!(stats.changed_ratio, 0.0);
+
+        let b = [0.0, 0.5, 0.5, 0.75].map(Color::gray);
+        let stats = difference(&a, &b);
+        assert_eq!(stats.mean, 0.0);
+        assert_eq!(stats.mean_absolute, 0.25);
+        assert_eq!(stats.max_absolute, 0.5);
+        assert_eq!(stats.changed_ratio, 0.5);
+        assert!((stats.rmse - (0.125 as Float).sqrt()).abs() < 1e-12);
+
+        // 只有一个分量发生变化的像素也算作变化了
+        let mut c = a;
+        c[0].b = 0.75;
+        let stats = difference(&a, &c);
+        assert_eq!(stats.changed_ratio, 0.25);
+        assert_eq!(stats.max_absolute, 0.75);
+        assert!((stats.mean - 0.75 / 12.0).abs() < 1e-12);
+    }
+}
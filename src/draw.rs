@@ -0,0 +1,87 @@
+// 在 RGB 图片上绘制调试用的图形
+
+use crate::color::Color;
+use crate::float::Float;
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn draw_line(
+    image: &mut [u8],
+    width: u32,
+    height: u32,
+    x0: Float,
+    y0: Float,
+    x1: Float,
+    y1: Float,
+    color: Color,
+) {
+    let length = ((x1 - x0).powi(2) + (y1 - y0).powi(2)).sqrt();
+    if length < 1.0 {
+        put_pixel(image, width, height, x0, y0, color);
+        return;
+    }
+    let ux = (x1 - x0) / length;
+    let uy = (y1 - y0) / length;
+    // 线段可能非常长(光线离开了画面), 只画画面附近的部分
+    let count = length.min(((width + height) * 2) as Float) as usize;
+    for i in 0..=count {
+        put_pixel(
+            image,
+            width,
+            height,
+            x0 + ux * i as Float,
+            y0 + uy * i as Float,
+            color,
+        );
+    }
+}
+
+pub(crate) fn put_pixel(
+    image: &mut [u8],
+    width: u32,
+    height: u32,
+    x: Float,
+    y: Float,
+    color: Color,
+) {
+    if x < 0.0 || y < 0.0 || x >= width as Float || y >= height as Float {
+        return;
+    }
+    let index = ((y as u32 * width + x as u32) * 3) as usize;
+    image[index..index + 3].copy_from_slice(&color.to_rgb8());
+}
+
+// 画一个从 (x, y) 出发, 方向为 (dx, dy), 长度为 length 的箭头
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn draw_arrow(
+    image: &mut [u8],
+    width: u32,
+    height: u32,
+    x: Float,
+    y: Float,
+    dx: Float,
+    dy: Float,
+    length: Float,
+    color: Color,
+) {
+    let ex = x + dx * length;
+    let ey = y + dy * length;
+    draw_line(image, width, height, x, y, ex, ey, color);
+
+    // 箭头的两翼, 与箭身成 150 度
+    let head = length * 0.3;
+    let (sin, cos) = (150.0 as Float).to_radians().sin_cos();
+    for &sign in [1.0, -1.0].iter() {
+        let hx = dx * cos - dy * sin * sign;
+        let hy = dx * sin * sign + dy * cos;
+        draw_line(
+            image,
+            width,
+            height,
+            ex,
+            ey,
+            ex + hx * head,
+            ey + hy * head,
+            color,
+        );
+    }
+}
@@ -0,0 +1,322 @@
+// 环境光: 没有命中任何形状的光线, 按它的方向从环境中获取光量
+// 可以从一维的角度条带图片(或者全景图中的一行)加载, 用真实拍摄的光照来照亮二维场景
+
+use crate::color::Color;
+use crate::float::consts::TAU;
+use crate::float::Float;
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+
+pub enum Environment {
+    // 没有环境光
+    None,
+    // 各个方向相同的环境光
+    Constant(Color),
+    // 角度条带: 第 i 个值对应角度 (i + 0.5) / len * 2π 附近的光量, 角度与图片坐标系一致
+    // 即 0 指向 +x, π/2 指向 +y (图片的下方)
+    Strip(Vec<Color>),
+}
+
+impl Environment {
+    // 从图片加载环境光, 支持 PNG 和 Radiance HDR (.hdr) 格式
+    // 使用图片中间的一行作为角度条带, 一维条带图片只有一行, 就是这一行
+    // PNG 的像素值按 sRGB 转换为线性的光量后映射到 [0, scale], HDR 的值本身是线性的, 直接乘以 scale
+    pub fn from_file<P: AsRef<Path>>(path: P, scale: Float) -> Result<Environment> {
+        let data = fs::read(path)?;
+        let (width, height, pixels) = if data.starts_with(b"#?") {
+            decode_hdr(&data)?
+        } else {
+            let (width, height, pixels) = decode_png(&data)?;
+            let linear = pixels.iter().map(|color| color.map(srgb_to_linear));
+            (width, height, linear.collect())
+        };
+        if width == 0 || height == 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "empty environment image",
+            ));
+        }
+
+        let row = (height / 2) as usize;
+        let strip = pixels[row * width as usize..(row + 1) * width as usize]
+            .iter()
+            .map(|&color| color * scale)
+            .collect();
+        Ok(Environment::Strip(strip))
+    }
+
+    // 从 (dx, dy) 方向获取的环境光
+    pub fn radiance(&self, dx: Float, dy: Float) -> Color {
+        match self {
+            Environment::None => Color::BLACK,
+            Environment::Constant(value) => *value,
+            Environment::Strip(values) => {
+                if values.is_empty() {
+                    return Color::BLACK;
+                }
+                // 在相邻两个值之间线性插值, 首尾相接
+                let len = values.len();
+                let t = dy.atan2(dx).rem_euclid(TAU) / TAU * len as Float - 0.5;
+                let t = t.rem_euclid(len as Float);
+                let i = (t as usize).min(len - 1);
+                let f = t - i as Float;
+                values[i] * (1.0 - f) + values[(i + 1) % len] * f
+            }
+        }
+    }
+}
+
+fn invalid_data<E>(error: E) -> Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    Error::new(ErrorKind::InvalidData, error)
+}
+
+// sRGB 编码的 [0, 1] 内的值转换为线性的光量
+fn srgb_to_linear(value: Float) -> Float {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+// 新式游程编码中一个字节最多表示的像素数: 四个分量各用 2 个字节表示最多 127 个像素
+const HDR_MAX_PIXELS_PER_BYTE: usize = 16;
+
+pub(crate) type DecodedImage = (u32, u32, Vec<Color>);
+
+// 解码 PNG 图片, 像素值映射到 [0, 1]
+pub(crate) fn decode_png(data: &[u8]) -> Result<DecodedImage> {
+    let decoder = png::Decoder::new(data);
+    let (info, mut reader) = decoder.read_info().map_err(invalid_data)?;
+    let mut buffer = vec![0; info.buffer_size()];
+    reader.next_frame(&mut buffer).map_err(invalid_data)?;
+
+    let (channels, max) = match (info.color_type, info.bit_depth) {
+        (png::ColorType::Grayscale, png::BitDepth::Eight) => (1, 255.0),
+        (png::ColorType::GrayscaleAlpha, png::BitDepth::Eight) => (2, 255.0),
+        (png::ColorType::RGB, png::BitDepth::Eight) => (3, 255.0),
+        (png::ColorType::RGBA, png::BitDepth::Eight) => (4, 255.0),
+        (color_type, bit_depth) => {
+            return Err(invalid_data(format!(
+                "unsupported png format: {:?} {:?}",
+                color_type, bit_depth
+            )))
+        }
+    };
+
+    let pixels = buffer
+        .chunks(channels)
+        .take((info.width * info.height) as usize)
+        .map(|pixel| {
+            if channels < 3 {
+                Color::gray(pixel[0] as Float / max)
+            } else {
+                Color::new(
+                    pixel[0] as Float / max,
+                    pixel[1] as Float / max,
+                    pixel[2] as Float / max,
+                )
+            }
+        })
+        .collect();
+    Ok((info.width, info.height, pixels))
+}
+
+// 解码 Radiance HDR (RGBE) 图片, 支持未压缩和新式游程编码的扫描线
+fn decode_hdr(data: &[u8]) -> Result<DecodedImage> {
+    let mut pos = 0;
+    let next_line = |pos: &mut usize| -> Result<String> {
+        let start = *pos;
+        while *pos < data.len() && data[*pos] != b'\n' {
+            *pos += 1;
+        }
+        if *pos >= data.len() {
+            return Err(invalid_data("unexpected end of hdr header"));
+        }
+        *pos += 1;
+        Ok(String::from_utf8_lossy(&data[start..*pos - 1]).into_owned())
+    };
+
+    // 文件头, 以空行结束
+    loop {
+        let line = next_line(&mut pos)?;
+        if line.is_empty() {
+            break;
+        }
+        if let Some(format) = line.strip_prefix("FORMAT=") {
+            if format != "32-bit_rle_rgbe" {
+                return Err(invalid_data(format!("unsupported hdr format: {}", format)));
+            }
+        }
+    }
+
+    // 分辨率, 只支持最常见的 "-Y height +X width"
+    let resolution = next_line(&mut pos)?;
+    let parts: Vec<&str> = resolution.split_whitespace().collect();
+    if parts.len() != 4 || parts[0] != "-Y" || parts[2] != "+X" {
+        return Err(invalid_data(format!(
+            "unsupported hdr resolution: {}",
+            resolution
+        )));
+    }
+    let height: u32 = parts[1].parse().map_err(invalid_data)?;
+    let width: u32 = parts[3].parse().map_err(invalid_data)?;
+
+    // 文件头里的分辨率不可信, 剩下的数据不够编码这么多像素时认为文件损坏, 避免分配过多的内存
+    let max_pixels = (data.len() - pos).saturating_mul(HDR_MAX_PIXELS_PER_BYTE);
+    let count = (width as usize)
+        .checked_mul(height as usize)
+        .filter(|&count| count <= max_pixels && width as usize <= max_pixels)
+        .ok_or_else(|| invalid_data(format!("hdr resolution too large: {}", resolution)))?;
+
+    let mut pixels = Vec::with_capacity(count);
+    let mut scanline = vec![[0u8; 4]; width as usize];
+    for _ in 0..height {
+        pos = read_hdr_scanline(data, pos, &mut scanline)?;
+        pixels.extend(scanline.iter().map(|&rgbe| rgbe_to_float(rgbe)));
+    }
+    Ok((width, height, pixels))
+}
+
+// 读取一行扫描线, 返回读取之后的位置
+fn read_hdr_scanline(data: &[u8], mut pos: usize, scanline: &mut [[u8; 4]]) -> Result<usize> {
+    let eof = || invalid_data("unexpected end of hdr data");
+    let width = scanline.len();
+    let header = data.get(pos..pos + 4).ok_or_else(eof)?;
+
+    let is_rle =
+        (8..0x8000).contains(&width) && header[0] == 2 && header[1] == 2 && header[2] & 0x80 == 0;
+    if !is_rle {
+        for pixel in scanline.iter_mut() {
+            let bytes = data.get(pos..pos + 4).ok_or_else(eof)?;
+            pixel.copy_from_slice(bytes);
+            pos += 4;
+        }
+        return Ok(pos);
+    }
+
+    if ((header[2] as usize) << 8 | header[3] as usize) != width {
+        return Err(invalid_data("hdr scanline width mismatch"));
+    }
+    pos += 4;
+
+    // 新式游程编码: 四个分量分别编码
+    for channel in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let count = *data.get(pos).ok_or_else(eof)? as usize;
+            pos += 1;
+            if count > 128 {
+                let count = count - 128;
+                let value = *data.get(pos).ok_or_else(eof)?;
+                pos += 1;
+                if x + count > width {
+                    return Err(invalid_data("hdr run overflows scanline"));
+                }
+                for pixel in scanline[x..x + count].iter_mut() {
+                    pixel[channel] = value;
+                }
+                x += count;
+            } else {
+                if count == 0 || x + count > width {
+                    return Err(invalid_data("bad hdr run length"));
+                }
+                let values = data.get(pos..pos + count).ok_or_else(eof)?;
+                for (pixel, &value) in scanline[x..x + count].iter_mut().zip(values) {
+                    pixel[channel] = value;
+                }
+                pos += count;
+                x += count;
+            }
+        }
+    }
+    Ok(pos)
+}
+
+fn rgbe_to_float([r, g, b, e]: [u8; 4]) -> Color {
+    if e == 0 {
+        return Color::BLACK;
+    }
+    let f = (2 as Float).powi(e as i32 - 136);
+    Color::new(
+        (r as Float + 0.5) * f,
+        (g as Float + 0.5) * f,
+        (b as Float + 0.5) * f,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_rle_hdr() {
+        let mut data = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 8\n".to_vec();
+        data.extend_from_slice(&[2, 2, 0, 8]);
+        // r, g, b 都是前 4 个为 128, 后 4 个为 0..4, 指数全部为 129
+        for _ in 0..3 {
+            data.extend_from_slice(&[128 + 4, 128, 4, 0, 1, 2, 3]);
+        }
+        data.extend_from_slice(&[128 + 8, 129]);
+
+        let (width, height, pixels) = decode_hdr(&data).unwrap();
+        assert_eq!((width, height), (8, 1));
+        assert!((pixels[0].r - 1.0).abs() < 0.01);
+        assert!((pixels[7].b - 3.5 / 128.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn oversized_hdr() {
+        for resolution in [
+            "-Y 4294967295 +X 4294967295",
+            "-Y 0 +X 4294967295",
+            "-Y 65536 +X 65536",
+        ] {
+            let mut data = b"#?RADIANCE\n\n".to_vec();
+            data.extend_from_slice(resolution.as_bytes());
+            data.extend_from_slice(b"\n\x02\x02\x00\x08");
+            let error = decode_hdr(&data).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::InvalidData);
+            assert!(error.to_string().contains("too large"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn png_is_linearized() {
+        let path = std::env::temp_dir().join("colorful_light2d_environment.png");
+        let file = fs::File::create(&path).unwrap();
+        let mut encoder = png::Encoder::new(file, 3, 1);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .write_header()
+            .unwrap()
+            .write_image_data(&[0, 128, 255])
+            .unwrap();
+
+        let environment = Environment::from_file(&path, 2.0).unwrap();
+        fs::remove_file(&path).unwrap();
+        let strip = match environment {
+            Environment::Strip(strip) => strip,
+            _ => panic!("expected a strip"),
+        };
+        assert_eq!(strip[0], Color::BLACK);
+        // sRGB 的中灰 128 大约是线性的 0.216
+        assert!((strip[1].r - 2.0 * 0.2158605).abs() < 1e-5);
+        assert!((strip[2].g - 2.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn strip_radiance() {
+        let red = Color::new(1.0, 0.0, 0.0);
+        let environment = Environment::Strip(vec![red, Color::BLACK, Color::BLACK, Color::BLACK]);
+        assert_eq!(environment.radiance(1.0, 1.0), red);
+        assert_eq!(environment.radiance(-1.0, -1.0), Color::BLACK);
+        assert!((environment.radiance(1.0, 0.0).r - 0.5).abs() < 1e-9);
+        assert_eq!(environment.radiance(1.0, 0.0).g, 0.0);
+    }
+}
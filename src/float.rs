@@ -0,0 +1,14 @@
+// 渲染计算使用的浮点数类型: 默认为 f64, 开启 f32 特性后为 f32
+// 在 WASM 或者嵌入式设备上 f32 通常更快, 代价是精度较低
+// 库中所有的坐标、距离和光量都使用这个类型
+
+#[cfg(not(feature = "f32"))]
+pub type Float = f64;
+#[cfg(feature = "f32")]
+pub type Float = f32;
+
+// 与 Float 对应的数学常数
+#[cfg(feature = "f32")]
+pub use std::f32::consts;
+#[cfg(not(feature = "f32"))]
+pub use std::f64::consts;
@@ -0,0 +1,110 @@
+use crate::color::Color;
+use crate::draw::{draw_line, put_pixel};
+use crate::float::Float;
+
+// 单像素光线检查器, 用于调试
+// 记录某个像素每次采样的光线方向、步进过程、命中点和返回的光量
+
+// 一次步进: 步进时所在的位置, 以及该位置的 sd
+pub struct MarchStep {
+    pub x: Float,
+    pub y: Float,
+    pub sd: Float,
+}
+
+// 光线的一段, 从 (ox, oy) 出发, 沿 (dx, dy) 方向步进
+// 光线发生反射/折射时会产生新的一段
+pub struct RaySegment {
+    pub ox: Float,
+    pub oy: Float,
+    pub dx: Float,
+    pub dy: Float,
+    // 这一段之前已经发生的反射/折射次数, 最初的光线为 0
+    pub depth: usize,
+    pub steps: Vec<MarchStep>,
+    // 命中点, 没有命中任何形状时为 None
+    pub hit: Option<(Float, Float)>,
+}
+
+impl RaySegment {
+    pub fn new(ox: Float, oy: Float, dx: Float, dy: Float) -> RaySegment {
+        RaySegment {
+            ox,
+            oy,
+            dx,
+            dy,
+            depth: 0,
+            steps: vec![],
+            hit: None,
+        }
+    }
+
+    // 这一段光线的终点: 命中点或者最后一次步进的位置
+    pub fn end(&self) -> (Float, Float) {
+        match (self.hit, self.steps.last()) {
+            (Some(hit), _) => hit,
+            (None, Some(step)) => (step.x, step.y),
+            (None, None) => (self.ox, self.oy),
+        }
+    }
+}
+
+// 一次采样的记录
+pub struct SampleRecord {
+    // 采样方向
+    pub dx: Float,
+    pub dy: Float,
+    // 光线依次经过的各段
+    pub segments: Vec<RaySegment>,
+    // 这次采样返回的光量
+    pub radiance: Color,
+}
+
+// 一个像素的检查结果
+pub struct PixelInspection {
+    pub x: u32,
+    pub y: u32,
+    pub samples: Vec<SampleRecord>,
+    // 最终写入图片的像素值
+    pub value: [u8; 3],
+}
+
+const HIT_COLOR: Color = Color::new(0.0, 1.0, 0.0);
+const MISS_COLOR: Color = Color::new(1.0, 0.0, 0.0);
+const STEP_COLOR: Color = Color::new(1.0, 1.0, 0.0);
+
+impl PixelInspection {
+    // 命中了发光形状的采样数
+    pub fn hit_count(&self) -> usize {
+        self.samples
+            .iter()
+            .filter(|sample| sample.segments.iter().any(|s| s.hit.is_some()))
+            .count()
+    }
+
+    // 把光线画在 RGB 图片上: 命中的光线为绿色, 未命中的为红色, 步进点为黄色
+    pub fn draw_overlay(&self, image: &mut [u8], width: u32, height: u32) {
+        for sample in self.samples.iter() {
+            for segment in sample.segments.iter() {
+                let color = if segment.hit.is_some() {
+                    HIT_COLOR
+                } else {
+                    MISS_COLOR
+                };
+                let (ex, ey) = segment.end();
+                draw_line(image, width, height, segment.ox, segment.oy, ex, ey, color);
+                for step in segment.steps.iter() {
+                    put_pixel(image, width, height, step.x, step.y, STEP_COLOR);
+                }
+            }
+        }
+    }
+}
+
+// SDF 梯度的可视化方式
+pub enum GradientView {
+    // 用色相表示梯度方向, 亮度表示梯度长度(正确的 SDF 梯度长度为 1)
+    DirectionMap,
+    // 每隔 spacing 个像素画一个表示梯度方向的箭头
+    Arrows { spacing: u32 },
+}
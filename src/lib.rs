@@ -0,0 +1,26 @@
+// 让 include_scene! 展开的代码在本 crate 内部也能通过 ::colorful_light2d 访问
+extern crate self as colorful_light2d;
+
+pub mod bitmap;
+pub mod color;
+pub mod csg;
+pub mod denoise;
+pub mod diff;
+mod draw;
+pub mod environment;
+pub mod float;
+pub mod inspect;
+pub mod material;
+#[cfg(feature = "plotters")]
+pub mod plot;
+mod resample;
+mod rng;
+pub mod scene;
+pub mod scenes;
+pub mod settings;
+pub mod shape;
+pub mod svg;
+#[cfg(feature = "text")]
+pub mod text;
+
+pub use colorful_light2d_macros::include_scene;
@@ -1,6 +1,3 @@
-mod scene;
-mod shape;
-
 fn main() {
     println!("hello world")
 }
@@ -0,0 +1,86 @@
+// 材质: 形状的光学性质, 与形状的几何分开, 同一个几何形状可以搭配不同的材质
+use crate::color::Color;
+use crate::float::Float;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Material {
+    // 自发光的颜色和强度
+    pub emissive: Color,
+
+    // 不透明表面镜面反射的光所占的比例, 其余的光被吸收; 透明表面的反射率由菲涅尔项决定
+    pub reflectivity: Float,
+
+    // 每个颜色通道的折射率, 大于 0 时形状是透明的(像玻璃), 光线在表面发生折射; 为 0 时不透明
+    // 各通道的折射率不同时发生色散, 三个通道分别折射
+    pub eta: Color,
+
+    // 透明形状每个颜色通道的吸收系数, 光线在形状里面走过距离 d 后光量衰减为 exp(-absorption * d)
+    // 各通道的吸收系数不同时, 透过的光会被染色(比如吸收红光和绿光的蓝色玻璃)
+    pub absorption: Color,
+}
+
+impl Material {
+    // 只发光的不透明材质
+    pub fn emissive(emissive: impl Into<Color>) -> Material {
+        Material {
+            emissive: emissive.into(),
+            ..Material::default()
+        }
+    }
+
+    // 反射 reflectivity 比例的光的镜子
+    pub fn mirror(reflectivity: Float) -> Material {
+        Material {
+            reflectivity,
+            ..Material::default()
+        }
+    }
+
+    // 折射率为 eta 的透明材质, 例如玻璃约为 1.5
+    pub fn glass(eta: Float) -> Material {
+        Material {
+            eta: Color::gray(eta),
+            ..Material::default()
+        }
+    }
+
+    // 红、绿、蓝三个通道的折射率不同的透明材质, 白光穿过时分散成彩虹
+    pub fn dispersive(eta_r: Float, eta_g: Float, eta_b: Float) -> Material {
+        Material {
+            eta: Color::new(eta_r, eta_g, eta_b),
+            ..Material::default()
+        }
+    }
+
+    pub fn is_transparent(&self) -> bool {
+        self.eta.max_component() > 0.0
+    }
+
+    pub fn is_dispersive(&self) -> bool {
+        self.eta.r != self.eta.g || self.eta.g != self.eta.b
+    }
+
+    // 光线在表面上会被反射或者折射, 而不是全部被吸收
+    pub fn is_specular(&self) -> bool {
+        self.is_transparent() || self.reflectivity > 0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn constructors() {
+        let light = Material::emissive(2.0);
+        assert_eq!(light.emissive, Color::gray(2.0));
+        assert!(!light.is_specular());
+
+        assert!(Material::mirror(0.9).is_specular());
+        assert!(!Material::mirror(0.9).is_transparent());
+
+        let glass = Material::glass(1.5);
+        assert!(glass.is_transparent() && !glass.is_dispersive());
+        assert!(Material::dispersive(1.4, 1.5, 1.6).is_dispersive());
+    }
+}
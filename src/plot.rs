@@ -0,0 +1,124 @@
+// 用 plotters 把渲染的统计数据画成图表, 需要开启 plotters 特性
+// 没有开启字体相关的特性, 所以图表中没有文字, 只有曲线和色块
+
+use crate::float::Float;
+use crate::scene::{save_png, TileTiming};
+use plotters::prelude::*;
+use std::error::Error;
+
+const BACKGROUND: RGBColor = RGBColor(255, 255, 255);
+const FOREGROUND: RGBColor = RGBColor(40, 90, 200);
+const MARGIN: u32 = 10;
+
+// 在 width x height 的画布上绘图, 然后保存为 PNG
+fn save_chart<F>(width: u32, height: u32, path: &str, draw: F) -> Result<(), Box<dyn Error>>
+where
+    F: FnOnce(&DrawingArea<BitMapBackend, plotters::coord::Shift>) -> Result<(), Box<dyn Error>>,
+{
+    let mut buffer = vec![0u8; width as usize * height as usize * 3];
+    {
+        let root = BitMapBackend::with_buffer(&mut buffer, (width, height)).into_drawing_area();
+        root.fill(&BACKGROUND)?;
+        draw(&root)?;
+        root.present()?;
+    }
+    save_png(&buffer, width, height, path);
+    Ok(())
+}
+
+// 收敛曲线: 横轴为渲染的遍数, 纵轴为误差(见 Scene::convergence)
+pub fn plot_convergence(
+    errors: &[Float],
+    width: u32,
+    height: u32,
+    path: &str,
+) -> Result<(), Box<dyn Error>> {
+    save_chart(width, height, path, |root| {
+        let max = errors
+            .iter()
+            .cloned()
+            .fold(0.0, Float::max)
+            .max(Float::MIN_POSITIVE);
+        let mut chart = ChartBuilder::on(root)
+            .margin(MARGIN)
+            .build_cartesian_2d(0.0..errors.len().max(2) as Float - 1.0, 0.0..max)?;
+        chart.draw_series(LineSeries::new(
+            errors.iter().enumerate().map(|(i, &e)| (i as Float, e)),
+            &FOREGROUND,
+        ))?;
+        Ok(())
+    })
+}
+
+// 直方图(见 Scene::radiance_histogram)
+pub fn plot_histogram(
+    histogram: &[usize],
+    width: u32,
+    height: u32,
+    path: &str,
+) -> Result<(), Box<dyn Error>> {
+    save_chart(width, height, path, |root| {
+        let max = histogram.iter().cloned().max().unwrap_or(0).max(1);
+        let mut chart = ChartBuilder::on(root)
+            .margin(MARGIN)
+            .build_cartesian_2d(0..histogram.len(), 0..max)?;
+        chart.draw_series(
+            histogram
+                .iter()
+                .enumerate()
+                .map(|(i, &count)| Rectangle::new([(i, 0), (i + 1, count)], FOREGROUND.filled())),
+        )?;
+        Ok(())
+    })
+}
+
+// 区块渲染时间的热力图, 每个区块按画面中的位置绘制, 越亮表示越慢(见 Scene::tile_timings)
+pub fn plot_tile_timings(
+    timings: &[TileTiming],
+    width: u32,
+    height: u32,
+    path: &str,
+) -> Result<(), Box<dyn Error>> {
+    save_chart(width, height, path, |root| {
+        let max = timings.iter().map(|t| t.duration).max().unwrap_or_default();
+        let right = timings.iter().map(|t| t.x + t.width).max().unwrap_or(1);
+        let bottom = timings.iter().map(|t| t.y + t.height).max().unwrap_or(1);
+        let mut chart = ChartBuilder::on(root)
+            .margin(MARGIN)
+            .build_cartesian_2d(0..right, bottom..0)?;
+        chart.draw_series(timings.iter().map(|t| {
+            let ratio = if max.is_zero() {
+                0.0
+            } else {
+                t.duration.as_secs_f64() / max.as_secs_f64()
+            };
+            let value = (ratio * 255.0) as u8;
+            let color = RGBColor(value, value / 2, 255 - value);
+            Rectangle::new(
+                [(t.x, t.y), (t.x + t.width, t.y + t.height)],
+                color.filled(),
+            )
+        }))?;
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::Scene;
+    use crate::shape::Circle;
+
+    #[test]
+    fn plot() {
+        let mut scene = Scene::new(16, 16);
+        scene.add_shape(Box::new(Circle::new(8.0, 8.0, 3.0, 1.0)));
+        let path = std::env::temp_dir().join("colorful-light2d-plot-test.png");
+        let path = path.to_str().unwrap();
+
+        plot_convergence(&scene.convergence(3, None), 64, 48, path).unwrap();
+        plot_histogram(&scene.radiance_histogram(8, 1.0), 64, 48, path).unwrap();
+        plot_tile_timings(&scene.tile_timings(8), 64, 48, path).unwrap();
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,92 @@
+// 缩小浮点 RGB 图片, 使用可分离的三角形(tent)滤波器
+// 滤波器的半径随缩小的比例变大, 避免缩小后出现摩尔纹和锯齿
+
+use crate::color::Color;
+use crate::float::Float;
+
+pub(crate) fn downsample(
+    buffer: &[Color],
+    width: u32,
+    height: u32,
+    new_width: u32,
+    new_height: u32,
+) -> Vec<Color> {
+    if width == new_width && height == new_height {
+        return buffer.to_vec();
+    }
+
+    // 先缩小每一行, 再缩小每一列
+    let (width, height) = (width as usize, height as usize);
+    let (new_width, new_height) = (new_width as usize, new_height as usize);
+    let mut rows = vec![Color::BLACK; new_width * height];
+    for y in 0..height {
+        let src = &buffer[y * width..(y + 1) * width];
+        resample_line(src, &mut rows[y * new_width..(y + 1) * new_width]);
+    }
+
+    let mut result = vec![Color::BLACK; new_width * new_height];
+    for x in 0..new_width {
+        let column: Vec<Color> = (0..height).map(|y| rows[y * new_width + x]).collect();
+        let mut target = vec![Color::BLACK; new_height];
+        resample_line(&column, &mut target);
+        for (y, value) in target.into_iter().enumerate() {
+            result[y * new_width + x] = value;
+        }
+    }
+
+    result
+}
+
+// 把 src 中的一行重新采样为 dst.len() 个值
+fn resample_line(src: &[Color], dst: &mut [Color]) {
+    let len = src.len();
+    let count = dst.len();
+    let scale = len as Float / count as Float;
+    let radius = scale.max(1.0);
+
+    for (i, target) in dst.iter_mut().enumerate() {
+        // 目标像素的中心在原图中的位置
+        let center = (i as Float + 0.5) * scale;
+        let start = ((center - radius).floor().max(0.0)) as usize;
+        let end = ((center + radius).ceil() as usize).min(len);
+
+        let mut sum = Color::BLACK;
+        let mut weight_sum = 0.0;
+        for (j, &value) in src.iter().enumerate().take(end).skip(start) {
+            let weight = 1.0 - ((j as Float + 0.5 - center) / radius).abs();
+            if weight <= 0.0 {
+                continue;
+            }
+            sum += value * weight;
+            weight_sum += weight;
+        }
+        *target = if weight_sum > 0.0 {
+            sum / weight_sum
+        } else {
+            Color::BLACK
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn downsample_preserves_average() {
+        let orange = Color::new(1.0, 0.75, 0.0);
+        let constant = vec![orange; 16 * 8];
+        for value in downsample(&constant, 16, 8, 5, 3) {
+            let d = value - orange;
+            assert!(d.r.abs() < 1e-12 && d.g.abs() < 1e-12 && d.b == 0.0);
+        }
+
+        // 棋盘格缩小后接近灰色
+        let checker: Vec<Color> = (0..64)
+            .map(|i| Color::gray(((i % 8 + i / 8) % 2) as Float))
+            .collect();
+        for value in downsample(&checker, 8, 8, 2, 2) {
+            assert!((value.g - 0.5).abs() < 0.05);
+        }
+    }
+}
@@ -0,0 +1,195 @@
+// 渲染使用的随机数生成器
+// 确定性模式下使用基于计数器的生成器: 输出只取决于种子、数据流编号和计数器,
+// 与线程数、渲染顺序和平台无关
+
+use crate::float::consts::FRAC_PI_2;
+use crate::float::Float;
+use rand::rngs::ThreadRng;
+use rand::RngCore;
+
+// 基于计数器的随机数生成器, 每个数据流(例如每个像素)使用一个独立的 key
+pub struct CounterRng {
+    key: u64,
+    counter: u64,
+}
+
+impl CounterRng {
+    pub fn new(seed: u64, stream: u64) -> CounterRng {
+        CounterRng {
+            key: mix(mix(seed) ^ stream),
+            counter: 0,
+        }
+    }
+}
+
+impl RngCore for CounterRng {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.counter = self.counter.wrapping_add(1);
+        mix(self
+            .key
+            .wrapping_add(self.counter.wrapping_mul(0x9e37_79b9_7f4a_7c15)))
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+// SplitMix64 的混合函数
+fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+// 把若干个整数混合为 [0, 1) 上均匀分布的数, 相同的输入总是得到相同的结果
+// 用在不方便传递随机数生成器、结果又需要可以复现的地方
+pub fn hash_unit(values: &[u64]) -> Float {
+    let hash = values.iter().fold(0u64, |hash, &value| {
+        mix(hash.wrapping_add(value).wrapping_add(0x9e37_79b9_7f4a_7c15))
+    });
+    // 只取 24 位, 使用 f32 时也能精确表示, 不会舍入为 1.0
+    (hash >> 40) as Float / (1u64 << 24) as Float
+}
+
+// 场景使用的随机数生成器, 没有设置种子时使用线程本地的生成器
+pub enum SceneRng {
+    Thread(ThreadRng),
+    Counter(CounterRng),
+}
+
+impl SceneRng {
+    pub fn new(seed: Option<u64>, stream: u64) -> SceneRng {
+        match seed {
+            Some(seed) => SceneRng::Counter(CounterRng::new(seed, stream)),
+            None => SceneRng::Thread(rand::thread_rng()),
+        }
+    }
+}
+
+impl RngCore for SceneRng {
+    fn next_u32(&mut self) -> u32 {
+        match self {
+            SceneRng::Thread(rng) => rng.next_u32(),
+            SceneRng::Counter(rng) => rng.next_u32(),
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        match self {
+            SceneRng::Thread(rng) => rng.next_u64(),
+            SceneRng::Counter(rng) => rng.next_u64(),
+        }
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        match self {
+            SceneRng::Thread(rng) => rng.fill_bytes(dest),
+            SceneRng::Counter(rng) => rng.fill_bytes(dest),
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        match self {
+            SceneRng::Thread(rng) => rng.try_fill_bytes(dest),
+            SceneRng::Counter(rng) => rng.try_fill_bytes(dest),
+        }
+    }
+}
+
+// sin(r) / r 和 cos(r) 关于 r² 的泰勒展开系数
+const SIN_COEFFICIENTS: [Float; 8] = [
+    1.0,
+    -1.0 / 6.0,
+    1.0 / 120.0,
+    -1.0 / 5040.0,
+    1.0 / 362_880.0,
+    -1.0 / 39_916_800.0,
+    1.0 / 6_227_020_800.0,
+    -1.0 / 1_307_674_368_000.0,
+];
+const COS_COEFFICIENTS: [Float; 8] = [
+    1.0,
+    -1.0 / 2.0,
+    1.0 / 24.0,
+    -1.0 / 720.0,
+    1.0 / 40_320.0,
+    -1.0 / 3_628_800.0,
+    1.0 / 479_001_600.0,
+    -1.0 / 87_178_291_200.0,
+];
+
+fn horner(coefficients: &[Float], x: Float) -> Float {
+    coefficients.iter().rev().fold(0.0, |sum, &c| sum * x + c)
+}
+
+// 只用加法和乘法计算的 sin 和 cos, 在所有平台上结果完全相同
+// 标准库的 sin/cos 依赖平台的数学库, 不同平台的结果可能有最后几位的差别
+pub fn portable_sin_cos(x: Float) -> (Float, Float) {
+    let q = (x / FRAC_PI_2).round();
+    let r = x - q * FRAC_PI_2;
+    let r2 = r * r;
+
+    // 在 [-π/4, π/4] 上的泰勒展开
+    let s = r * horner(&SIN_COEFFICIENTS, r2);
+    let c = horner(&COS_COEFFICIENTS, r2);
+
+    match (q as i64).rem_euclid(4) {
+        0 => (s, c),
+        1 => (c, -s),
+        2 => (-s, -c),
+        _ => (-c, s),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn counter_rng_is_reproducible() {
+        let mut a = CounterRng::new(42, 7);
+        let mut b = CounterRng::new(42, 7);
+        let mut c = CounterRng::new(42, 8);
+        for _ in 0..100 {
+            let value: Float = a.gen_range(0.0..1.0);
+            assert_eq!(value, b.gen_range(0.0..1.0));
+            assert_ne!(value, c.gen_range(0.0..1.0));
+        }
+    }
+
+    #[test]
+    fn hash_unit_is_uniform() {
+        let values: Vec<Float> = (0..10000).map(|i| hash_unit(&[7, i])).collect();
+        assert!(values.iter().all(|&value| (0.0..1.0).contains(&value)));
+        let mean = values.iter().sum::<Float>() / values.len() as Float;
+        assert!((mean - 0.5).abs() < 0.01);
+        assert_eq!(hash_unit(&[7, 3]), values[3]);
+        assert_ne!(hash_unit(&[3, 7]), values[3]);
+    }
+
+    #[test]
+    fn portable_sin_cos_accuracy() {
+        // f32 的精度只有大约 7 位有效数字
+        let tolerance = if cfg!(feature = "f32") { 1e-5 } else { 1e-13 };
+        for i in -1000..1000 {
+            let x = i as Float * 0.0137;
+            let (sin, cos) = portable_sin_cos(x);
+            assert!((sin - x.sin()).abs() < tolerance);
+            assert!((cos - x.cos()).abs() < tolerance);
+        }
+    }
+}
@@ -1,144 +1,1649 @@
-use crate::shape::{SdfResult, Shape};
-use rand::Rng;
+use crate::color::Color;
+use crate::denoise::{denoise, Denoiser, Guide};
+use crate::draw::draw_arrow;
+use crate::environment::Environment;
+use crate::float::consts::TAU;
+use crate::float::Float;
+use crate::inspect::{GradientView, MarchStep, PixelInspection, RaySegment, SampleRecord};
+use crate::material::Material;
+use crate::resample::downsample;
+use crate::rng::{portable_sin_cos, SceneRng};
+use crate::settings::{Preset, RenderSettings};
+use crate::shape::{Aabb, EmissionProfile, SdfResult, Shape};
 use std::fs;
 use std::fs::File;
-use std::io::BufWriter;
+use std::io::{BufWriter, Write};
+use std::sync::OnceLock;
+use std::thread;
 
-const TWO_PI: f64 = 6.28318530718;
-const EPSILON: f64 = 1e-6;
+mod adaptive;
+mod analysis;
+mod builder;
+mod bvh;
+mod gradient_domain;
+mod guiding;
+mod incremental;
+mod next_event;
+mod photon;
+mod probe;
+mod progressive;
+mod refraction;
+mod roulette;
+mod sampling;
+mod sdf_grid;
+mod stats;
+mod tiles;
+mod wavefront;
+
+pub use adaptive::AdaptiveSampling;
+pub use analysis::SceneAnalysis;
+pub use builder::SceneBuilder;
+use bvh::Bvh;
+use guiding::GuidingField;
+pub use incremental::IncrementalRender;
+use next_event::Light;
+pub use probe::RadianceProbe;
+pub use progressive::ProgressiveEvent;
+pub use refraction::Fresnel;
+use refraction::{absorbance, dispersion, Ray, SURFACE_BIAS};
+use sampling::PixelSampler;
+pub use sampling::SamplingStrategy;
+use sdf_grid::SdfGrid;
+pub use stats::TileTiming;
+pub use tiles::{Tile, TileEvent};
+
+const EPSILON: Float = 1e-6;
+// 可见性查询时最多步进的次数
+const VISIBILITY_MAX_STEP: usize = 1024;
+// 穿过滤色片边界时额外前进的距离, 保证能越过边界
+const FILTER_CROSS_STEP: Float = 1e-4;
+// 计算滤色片透射率时最多步进的次数
+const FILTER_MAX_STEP: usize = 256;
+// 黄金分割比的倒数, 用来生成每一帧的旋转角度(使用 f32 时多余的精度会被舍去)
+#[allow(clippy::excessive_precision)]
+const GOLDEN_RATIO_CONJUGATE: Float = 0.618_033_988_749_894_9;
+
+// 光线带回的光量随距离的衰减方式
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Attenuation {
+    // 不衰减, 无论多远都带回完整的自发光强度(风格化的效果)
+    None,
+    // 按距离的倒数衰减(二维中的 1/r), 参数为开始衰减的参考距离
+    // 距离小于参考距离时不衰减
+    InverseDistance(Float),
+}
+
+impl Attenuation {
+    // 光线走过 distance 后剩下的比例
+    pub fn factor(&self, distance: Float) -> Float {
+        match *self {
+            Attenuation::None => 1.0,
+            Attenuation::InverseDistance(reference) => reference / distance.max(reference),
+        }
+    }
+}
+
+// 场景中形状的句柄, 由 Scene::add_shape 返回
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ShapeHandle(usize);
+
+// 光线命中形状之后的一次着色和分叉, 见 Scene::bounce
+struct Bounce {
+    // 命中的形状自身带回的光量
+    radiance: Color,
+    // 这一段在透明形状里面时, 带回的光被吸收后剩下的比例
+    absorbance: Color,
+    // 次级光线带回的光在到达这一段的起点之前穿过的滤色片的透射率
+    transmittance: Color,
+    // 按颜色通道分组的次级光线, 没有发生色散时只有第一组
+    groups: [Option<BounceGroup>; 3],
+}
+
+// 同一个颜色通道的反射和折射光线
+struct BounceGroup {
+    // 光线携带的颜色通道, 以及带回的光要保留的颜色
+    channel: Option<usize>,
+    mask: Color,
+    // 先反射后折射, 每条光线的权重(已经乘以轮盘赌的系数)和它带回的光最终占像素值的比例
+    branches: [Option<(Ray, Float, Float)>; 2],
+}
 
 pub struct Scene {
     width: u32,
     height: u32,
     shapes: Vec<Box<dyn Shape>>,
-    sample_count: u8,
+    // 每个形状的包围盒, 与 shapes 一一对应
+    bounds: Vec<Option<Aabb>>,
+    // 所有形状都支持解析求交时, 光线直接前进到最近的边, 不再逐步步进
+    analytic: bool,
+    // 形状的包围体层次, 第一次查询时建立, 形状改变后清空
+    bvh: OnceLock<Bvh>,
+    // 距离场网格缓存的格子边长, 为 None 时不使用缓存
+    sdf_grid_cell: Option<Float>,
+    // 烘焙好的距离场网格, 第一次步进时烘焙, 形状改变后清空
+    sdf_grid: OnceLock<SdfGrid>,
+    // 是否对光源进行显式采样
+    next_event: bool,
+    // 显式采样使用的发光形状, 第一次采样时建立, 形状改变后清空
+    lights: OnceLock<Vec<Light>>,
+    // 滤色片: 不发光也不遮挡光线, 光线每穿过一次就乘以它的透射率
+    filters: Vec<(Box<dyn Shape>, Color)>,
+    sample_count: u32,
     max_step: usize,
+    // 光线最多反射/折射的次数
+    max_depth: usize,
+    // 光线离形状的距离小于 epsilon 时认为命中了形状
+    epsilon: Float,
+    // 光子映射使用的光子数, 为 0 时不进行光子映射
+    photon_count: usize,
+    // 收集光子时的半径
+    photon_radius: Float,
+    // 是否开启路径引导
+    path_guiding: bool,
+    // 是否使用梯度域渲染
+    gradient_domain: bool,
+    // 光线的最大追踪距离, 为 None 时使用画面对角线的长度
+    max_distance: Option<Float>,
+    attenuation: Attenuation,
+    // 没有命中任何形状的光线从环境中获取的光
+    environment: Environment,
+    // 确定性模式使用的种子, 为 None 时不开启确定性模式
+    seed: Option<u64>,
+    // 动画的帧号
+    frame: u64,
+    // 上一次增量渲染之后被修改过的区域
+    dirty: Option<Aabb>,
+    // 形状的修改能影响到的距离, 为 None 时认为能影响整个画面
+    influence_radius: Option<Float>,
+    // 透明表面反射率的计算方式
+    fresnel: Fresnel,
+    // 反射/折射超过这个次数之后进行俄罗斯轮盘赌, 以及每条光线存活的概率
+    roulette_depth: usize,
+    roulette_survival: Float,
+    // 渲染使用的线程数, 为 0 时使用所有的 CPU 核心
+    threads: usize,
+    // 像素内的采样模式
+    sampling: SamplingStrategy,
+    // 自适应采样的设置, 为 None 时每个像素都使用 sample_count 次采样
+    adaptive: Option<AdaptiveSampling>,
+    // 渲染时区块的边长
+    tile_size: u32,
+    // 输出图片前使用的去噪器, 为 None 时不去噪
+    denoiser: Option<Denoiser>,
+    // 输出图片时的曝光补偿(EV)
+    exposure: Float,
 }
 
 impl Scene {
+    // 质量相关的设置使用 RenderSettings::default
     pub fn new(width: u32, height: u32) -> Scene {
+        let settings = RenderSettings::default();
         Scene {
             width,
             height,
-            sample_count: 64,
+            sample_count: settings.sample_count,
             shapes: vec![],
-            max_step: 10,
+            bounds: vec![],
+            analytic: true,
+            bvh: OnceLock::new(),
+            sdf_grid_cell: None,
+            sdf_grid: OnceLock::new(),
+            next_event: false,
+            lights: OnceLock::new(),
+            filters: vec![],
+            max_step: settings.max_step,
+            max_depth: settings.max_depth,
+            epsilon: settings.epsilon,
+            photon_count: settings.photon_count,
+            photon_radius: settings.photon_radius,
+            path_guiding: settings.path_guiding,
+            gradient_domain: settings.gradient_domain,
+            max_distance: None,
+            attenuation: Attenuation::None,
+            environment: Environment::None,
+            seed: None,
+            frame: 0,
+            dirty: None,
+            influence_radius: None,
+            fresnel: Fresnel::Schlick,
+            roulette_depth: settings.roulette_depth,
+            roulette_survival: settings.roulette_survival,
+            threads: 1,
+            sampling: SamplingStrategy::Uniform,
+            adaptive: None,
+            tile_size: 32,
+            denoiser: settings.denoiser,
+            exposure: settings.exposure,
+        }
+    }
+
+    // 设置每个像素的采样数, 默认为 64
+    // 离线渲染高质量的图片时可以使用上千的采样数
+    // 采样数不超过 65536 (一批光线的上限) 时渲染的内存占用与采样数无关;
+    // 一个像素的光线总是在同一批里, 所以采样数更大时每批只有一个像素, 内存占用随采样数增长
+    // 采样数至少为 1, 传入 0 时按 1 处理
+    pub fn set_sample_count(&mut self, sample_count: u32) {
+        self.sample_count = sample_count.max(1);
+        self.invalidate();
+    }
+
+    // 设置每条光线最多步进的次数, 默认为 10
+    // 场景中有很小的形状, 或者光线需要贴着形状的边走很远时需要设置得更大
+    pub fn set_max_step(&mut self, max_step: usize) {
+        self.max_step = max_step;
+        self.invalidate();
+    }
+
+    // 设置命中形状的距离阈值, 默认为 1e-6
+    pub fn set_epsilon(&mut self, epsilon: Float) {
+        self.epsilon = epsilon;
+        self.invalidate();
+    }
+
+    // 设置光量随距离的衰减方式, 默认不衰减
+    pub fn set_attenuation(&mut self, attenuation: Attenuation) {
+        self.attenuation = attenuation;
+        self.invalidate();
+    }
+
+    // 开启梯度域渲染: 除了每个像素的光量, 还用相同的光线方向估计相邻像素之间的差,
+    // 再通过求解 screened Poisson 方程重建图片
+    // 相同采样数下, 柔和的光照会明显更平滑(开启后不使用路径引导)
+    pub fn set_gradient_domain(&mut self, enabled: bool) {
+        self.gradient_domain = enabled;
+        self.invalidate();
+    }
+
+    // 设置光线的最大追踪距离, 光线走过这么远仍没有命中任何形状时, 认为它没有带回任何光
+    // 默认是画面对角线的长度, 形状位于画面之外(例如画面外的光源)时需要设置得更大
+    // 传入 None 恢复默认值
+    pub fn set_max_distance(&mut self, distance: Option<Float>) {
+        self.max_distance = distance;
+        self.invalidate();
+    }
+
+    // 设置光线最多反射/折射的次数, 默认为 8
+    // 达到这个次数之后再命中镜面或者透明的形状时不再分出新的光线, 只带回形状自身发出的光
+    // 透镜、镜面相互多次反射的场景需要更大的值
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
+        self.invalidate();
+    }
+
+    // 光线的最大追踪距离
+    pub fn max_distance(&self) -> Float {
+        self.max_distance
+            .unwrap_or_else(|| (self.width as Float).hypot(self.height as Float))
+    }
+
+    // 开启光子映射, 用来渲染透镜等产生的焦散
+    // count 为 0 时关闭, radius 为收集光子时的半径(像素)
+    pub fn set_photon_mapping(&mut self, count: usize, radius: Float) {
+        self.photon_count = count;
+        self.photon_radius = radius;
+        self.invalidate();
+    }
+
+    // 开启路径引导: 渲染前先学习每个区域的入射光方向分布, 再按这个分布采样光线方向
+    // 适合光只能穿过狭窄缝隙到达的场景
+    pub fn set_path_guiding(&mut self, enabled: bool) {
+        self.path_guiding = enabled;
+        self.invalidate();
+    }
+
+    // 设置渲染使用的线程数, 默认为 1, 只在调用者的线程上渲染; 为 0 时使用所有的 CPU 核心
+    // 渲染的结果与线程数无关
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads;
+    }
+
+    fn thread_count(&self) -> usize {
+        match self.threads {
+            0 => thread::available_parallelism().map_or(1, |n| n.get()),
+            threads => threads,
         }
     }
 
-    pub fn add_shape(&mut self, shape: Box<dyn Shape>) {
+    // 一次性使用一组渲染设置
+    pub fn apply_settings(&mut self, settings: &RenderSettings) {
+        self.sample_count = settings.sample_count.max(1);
+        self.max_step = settings.max_step;
+        self.max_depth = settings.max_depth;
+        self.epsilon = settings.epsilon;
+        self.path_guiding = settings.path_guiding;
+        self.gradient_domain = settings.gradient_domain;
+        self.photon_count = settings.photon_count;
+        self.photon_radius = settings.photon_radius;
+        self.set_russian_roulette(settings.roulette_depth, settings.roulette_survival);
+        self.denoiser = settings.denoiser;
+        self.exposure = settings.exposure;
+        self.invalidate();
+    }
+
+    // 使用预设的渲染设置
+    pub fn use_preset(&mut self, preset: Preset) {
+        self.apply_settings(&preset.settings());
+    }
+
+    // 当前的渲染设置, 可以保存下来作为用户自己的预设
+    pub fn settings(&self) -> RenderSettings {
+        RenderSettings {
+            sample_count: self.sample_count,
+            max_step: self.max_step,
+            max_depth: self.max_depth,
+            epsilon: self.epsilon,
+            path_guiding: self.path_guiding,
+            gradient_domain: self.gradient_domain,
+            photon_count: self.photon_count,
+            photon_radius: self.photon_radius,
+            roulette_depth: self.roulette_depth,
+            roulette_survival: self.roulette_survival,
+            denoiser: self.denoiser,
+            exposure: self.exposure,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    // 添加一个形状, 返回的句柄可以用来之后替换这个形状
+    // 形状的包围盒会被记为需要重新渲染的区域, 见 update_incremental
+    pub fn add_shape(&mut self, shape: Box<dyn Shape>) -> ShapeHandle {
+        let bounds = shape.bounds();
+        self.mark_dirty(bounds);
+        self.bounds.push(bounds);
+        self.analytic &= shape.supports_raycast();
         self.shapes.push(shape);
+        self.bvh.take();
+        self.sdf_grid.take();
+        self.lights.take();
+        ShapeHandle(self.shapes.len() - 1)
+    }
+
+    pub fn shape(&self, handle: ShapeHandle) -> &dyn Shape {
+        self.shapes[handle.0].as_ref()
+    }
+
+    // 替换句柄对应的形状(例如移动了形状或者修改了发光强度), 返回原来的形状
+    // 新旧形状的包围盒会被记为需要重新渲染的区域, 见 update_incremental
+    pub fn replace_shape(&mut self, handle: ShapeHandle, shape: Box<dyn Shape>) -> Box<dyn Shape> {
+        let bounds = shape.bounds();
+        let old = std::mem::replace(&mut self.shapes[handle.0], shape);
+        let old_bounds = std::mem::replace(&mut self.bounds[handle.0], bounds);
+        self.analytic = self.shapes.iter().all(|shape| shape.supports_raycast());
+        self.bvh.take();
+        self.sdf_grid.take();
+        self.lights.take();
+        self.mark_dirty(old_bounds.zip(bounds).map(|(a, b)| a.union(&b)));
+        old
+    }
+
+    // 把 region 记为需要重新渲染的区域, None 表示整个画面(例如无界的形状)
+    fn mark_dirty(&mut self, region: Option<Aabb>) {
+        let region =
+            region.unwrap_or_else(|| Aabb::new(Float::MIN, Float::MIN, Float::MAX, Float::MAX));
+        self.dirty = Some(match self.dirty {
+            Some(dirty) => dirty.union(&region),
+            None => region,
+        });
+    }
+
+    // 修改了影响整个画面的设置(例如环境光或者采样数), 下次 update_incremental 时重新渲染整个画面
+    fn invalidate(&mut self) {
+        self.mark_dirty(None);
+    }
+
+    // 设置环境光, 光线走过最大追踪距离仍没有命中任何形状时, 按方向从环境中获取光量
+    pub fn set_environment(&mut self, environment: Environment) {
+        self.environment = environment;
+        self.invalidate();
+    }
+
+    // 开启严格的确定性模式, 传入 None 关闭
+    // 开启后使用固定的种子和基于计数器的随机数生成器, 每个像素的随机数只取决于种子和像素坐标,
+    // 光线方向使用只有加法和乘法的 sin/cos 计算, 保证不同平台、不同线程数下的输出完全相同
+    // (形状自身的 SDF 如果用到了三角函数, 仍然依赖平台的数学库)
+    pub fn set_deterministic(&mut self, seed: Option<u64>) {
+        self.seed = seed;
+        self.invalidate();
+    }
+
+    // 使用种子 seed 开启确定性模式, 相同的种子在任何线程数下都渲染出逐字节相同的图片
+    pub fn set_seed(&mut self, seed: u64) {
+        self.set_deterministic(Some(seed));
+    }
+
+    // 设置动画的帧号, 默认为 0
+    // 每一帧的光线方向整体旋转一个按黄金分割数列变化的角度, 相邻帧的噪点互不相关,
+    // 多帧叠加时采样方向在圆周上分布得很均匀
+    // 确定性模式下每一帧的输出仍然只取决于种子和帧号, 不会出现固定在画面上的噪点
+    pub fn set_frame(&mut self, frame: u64) {
+        self.frame = frame;
+        self.invalidate();
+    }
+
+    // 添加一个滤色片(例如彩色玻璃), 它既不发光也不遮挡光线
+    // 穿过它的光线最终带回的光量的每个颜色通道乘以 transmittance 的对应通道, 例如红色的滤色片只让红光通过
+    pub fn add_filter(&mut self, shape: Box<dyn Shape>, transmittance: impl Into<Color>) {
+        self.filters.push((shape, transmittance.into()));
+        self.invalidate();
+    }
+
+    // 设置输出图片(render_to_file 等)前使用的去噪器, 传入 None 关闭, 默认关闭
+    // render_streamed_to_file 逐行写入时不会去噪
+    pub fn set_denoiser(&mut self, denoiser: Option<Denoiser>) {
+        self.denoiser = denoiser;
+    }
+
+    // 设置输出图片时的曝光补偿(EV), 每 +1 EV 光量乘以 2, 默认为 0
+    // 在去噪之后、转换为像素值之前调整, 不影响 render_tiles 等返回的光量
+    pub fn set_exposure(&mut self, ev: Float) {
+        self.exposure = ev;
+    }
+
+    // 对这个场景渲染出的光量(例如 render_tiles 或者 render_progressive 的结果)去噪, 按行排列
+    // 使用场景的几何作为引导, 不会模糊形状的边缘
+    pub fn denoise(&self, radiance: &[Color], denoiser: &Denoiser) -> Vec<Color> {
+        let mut guide = Vec::with_capacity(radiance.len());
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let (x, y) = (x as Float, y as Float);
+                let shape = match self.nearest_shape(x, y) {
+                    Some((index, result)) if result.sd < 0.0 => Some(index),
+                    _ => None,
+                };
+                guide.push(Guide {
+                    coverage: self.coverage(x, y),
+                    shape,
+                });
+            }
+        }
+        denoise(radiance, &guide, self.width, self.height, denoiser)
     }
 
     pub fn render_to_file(&self, path: &str) {
+        let image = self.render();
+        self.save_to_file(&image, path);
+    }
+
+    // 只渲染一次, 输出多个尺寸的图片(例如原图、预览图和缩略图)
+    // outputs 中每一项为 (长边的像素数, 文件路径), 保持宽高比, 不会放大
+    // 缩小时先在浮点光量上做滤波, 再转换为像素值
+    pub fn render_to_files(&self, outputs: &[(u32, &str)]) {
+        let buffer = self.render_output();
+        let long_edge = self.width.max(self.height);
+
+        for &(size, path) in outputs.iter() {
+            let size = size.clamp(1, long_edge);
+            let width = ((self.width as u64 * size as u64) / long_edge as u64).max(1) as u32;
+            let height = ((self.height as u64 * size as u64) / long_edge as u64).max(1) as u32;
+            let resized = downsample(&buffer, self.width, self.height, width, height);
+
+            let mut image = vec![0u8; resized.len() * 3];
+            for (pixel, value) in image.chunks_mut(3).zip(resized.iter()) {
+                pixel.copy_from_slice(&value.to_rgb8());
+            }
+            save_png(&image, width, height, path);
+        }
+    }
+
+    // 只渲染一次, 输出多个曝光的图片, 用于 HDR 合成或者不重新渲染就挑选合适的曝光
+    // outputs 中每一项为 (曝光补偿(EV), 文件路径), 每 +1 EV 光量乘以 2, 在场景的曝光之上再补偿
+    pub fn render_exposures_to_files(&self, outputs: &[(Float, &str)]) {
+        let buffer = self.render_output();
+
+        for &(ev, path) in outputs.iter() {
+            let scale = ev.exp2();
+            let mut image = vec![0u8; buffer.len() * 3];
+            for (pixel, &value) in image.chunks_mut(3).zip(buffer.iter()) {
+                pixel.copy_from_slice(&(value * scale).to_rgb8());
+            }
+            self.save_to_file(&image, path);
+        }
+    }
+
+    // 边渲染边写入 PNG: 每渲染完一行就把这一行压缩后写成一个 IDAT 块
+    // 渲染很慢时可以随时查看已经完成的部分, 渲染被中断时已经写入的行也不会丢失
+    // 每一行按线程数分段同时渲染, 结果与 render_tiles 相同
+    // 梯度域渲染需要整张图片才能重建, 开启时会在渲染完成后一次性写入
+    pub fn render_streamed_to_file(&self, path: &str) {
+        fs::remove_file(path).unwrap_or_default();
+        // 不使用 BufWriter, 保证每一行都立刻写到文件中
+        let file = File::create(path).unwrap();
+        let mut encoder = png::Encoder::new(file, self.width, self.height);
+        encoder.set_color(png::ColorType::RGB);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        let mut stream = writer.stream_writer();
+
+        if self.gradient_domain {
+            stream.write_all(&self.render()).unwrap();
+        } else {
+            let guiding_field = self.build_guiding_field();
+            let photon_map = self.build_photon_map();
+            let (guide, photon_map) = (guiding_field.as_ref(), photon_map.as_ref());
+            let width = self.width as usize;
+            let threads = self.thread_count().clamp(1, width);
+            // 每个线程渲染一行中连续的 chunk 个像素
+            let chunk = width.div_ceil(threads);
+            let scale = self.exposure.exp2();
+            let mut row = vec![0u8; width * 3];
+            for y in 0..self.height {
+                let y = y as Float;
+                let render_part = |start: usize, part: &mut [u8]| {
+                    for (i, pixel) in part.chunks_mut(3).enumerate() {
+                        let x = (start + i) as Float;
+                        let mut value = self.sample_pixel(x, y, guide);
+                        if let Some(photon_map) = photon_map {
+                            value += photon_map.gather(x, y);
+                        }
+                        pixel.copy_from_slice(&(value * scale).to_rgb8());
+                    }
+                };
+                if threads <= 1 {
+                    render_part(0, &mut row);
+                } else {
+                    thread::scope(|scope| {
+                        for (i, part) in row.chunks_mut(chunk * 3).enumerate() {
+                            let render_part = &render_part;
+                            scope.spawn(move || render_part(i * chunk, part));
+                        }
+                    });
+                }
+                stream.write_all(&row).unwrap();
+                stream.flush().unwrap();
+            }
+        }
+
+        stream.finish().unwrap();
+    }
+
+    // 查询光能否从 p 点沿直线到达 q 点, 返回线段上每个颜色通道的透射率
+    // 使用与渲染相同的几何, 被不透明的形状遮挡时返回黑色, 否则返回经过的滤色片的透射率
+    // 透明形状不遮挡光线, 线段在透明形状里面的部分按它的吸收系数衰减(Beer–Lambert 定律),
+    // 不考虑折射造成的偏折和表面的反射
+    // p 或 q 在不透明形状内部时视为被遮挡
+    pub fn visible(&self, p: (Float, Float), q: (Float, Float)) -> Color {
+        let (ux, uy) = (q.0 - p.0, q.1 - p.1);
+        let length = (ux * ux + uy * uy).sqrt();
+        if length < EPSILON {
+            let result = self.sdf(p.0, p.1);
+            return if result.sd < self.epsilon && !result.material.is_transparent() {
+                Color::BLACK
+            } else {
+                Color::WHITE
+            };
+        }
+        let (dx, dy) = (ux / length, uy / length);
+        let offset = SURFACE_BIAS.max(self.epsilon * 4.0);
+
+        let mut transmittance = self.filter_transmittance(p.0, p.1, dx, dy, length);
+        let mut distance: Float = 0.0;
+        // 在透明形状里面时为 -1.0, 同时记录进入的位置和形状的吸收系数
+        let mut side: Float = 1.0;
+        let mut entry: Float = 0.0;
+        let mut absorption = Color::BLACK;
+        for _ in 0..VISIBILITY_MAX_STEP {
+            let px = p.0 + dx * distance.min(length);
+            let py = p.1 + dy * distance.min(length);
+            let result = self.march_sdf(px, py);
+            if distance == 0.0 && result.sd < 0.0 && result.material.is_transparent() {
+                // 起点在透明形状里面
+                side = -1.0;
+                absorption = result.material.absorption;
+            }
+            let sd = result.sd * side;
+            if distance >= length {
+                if sd < self.epsilon && side > 0.0 && !result.material.is_transparent() {
+                    return Color::BLACK;
+                }
+                if side < 0.0 {
+                    transmittance *= absorption.map(|a| (-a * (length - entry)).exp());
+                }
+                return transmittance;
+            }
+            if sd < self.epsilon {
+                if side > 0.0 {
+                    if !result.material.is_transparent() {
+                        return Color::BLACK;
+                    }
+                    // 进入透明形状
+                    entry = distance;
+                    absorption = result.material.absorption;
+                } else {
+                    // 离开透明形状
+                    transmittance *= absorption.map(|a| (-a * (distance - entry)).exp());
+                }
+                side = -side;
+                distance += offset;
+                continue;
+            }
+            distance += sd;
+        }
+
+        // 步数用完仍未到达 q, 说明光线贴着形状表面前进, 视为被遮挡
+        Color::BLACK
+    }
+
+    // 检查某个像素的采样过程, 记录每条光线的方向、步进过程、命中点和返回的光量
+    // 用来调试 "为什么这个像素是黑的" 这类问题
+    pub fn inspect_pixel(&self, x: u32, y: u32) -> PixelInspection {
+        let mut samples = vec![];
+        let (fx, fy) = (x as Float, y as Float);
+        let mut value = self.sample_with(fx, fy, None, Some(&mut samples));
+        if let Some(photon_map) = self.build_photon_map() {
+            value += photon_map.gather(fx, fy);
+        }
+        PixelInspection {
+            x,
+            y,
+            samples,
+            value: value.to_rgb8(),
+        }
+    }
+
+    // 计算 p0 到 p1 的线段上等间隔的 n 个点接收到的光量(包含两个端点), 不需要渲染整张图片
+    // 可以用来画光照的剖面图, 例如两盏灯下桌面上的亮度分布
+    pub fn sample_line(&self, p0: (Float, Float), p1: (Float, Float), n: usize) -> Vec<Color> {
+        let photon_map = self.build_photon_map();
+        (0..n)
+            .map(|i| {
+                let t = if n > 1 {
+                    i as Float / (n - 1) as Float
+                } else {
+                    0.0
+                };
+                let x = p0.0 + (p1.0 - p0.0) * t;
+                let y = p0.1 + (p1.1 - p0.1) * t;
+                match &photon_map {
+                    Some(photon_map) => self.sample(x, y, None) + photon_map.gather(x, y),
+                    None => self.sample(x, y, None),
+                }
+            })
+            .collect()
+    }
+
+    // 渲染场景, 并把检查结果中的光线叠加在图片上
+    pub fn render_inspection_to_file(&self, inspection: &PixelInspection, path: &str) {
+        let mut image = self.render();
+        inspection.draw_overlay(&mut image, self.width, self.height);
+        self.save_to_file(&image, path);
+    }
+
+    // 渲染场景 SDF 梯度的可视化图片
+    // 可以在反射/折射出问题之前, 发现 CSG 运算产生的错误法线
+    pub fn render_gradient_to_file(&self, view: GradientView, path: &str) {
         let mut image = vec![0u8; self.width as usize * self.height as usize * 3];
 
+        match view {
+            GradientView::DirectionMap => {
+                for x in 0..self.width {
+                    for y in 0..self.height {
+                        let index = ((y * self.width + x) * 3) as usize;
+                        let (gx, gy) = self.gradient(x as Float, y as Float);
+                        let length = (gx * gx + gy * gy).sqrt();
+                        let color =
+                            Color::from_hsv(gy.atan2(gx).to_degrees(), 1.0, length.min(1.0));
+                        image[index..index + 3].copy_from_slice(&color.to_rgb8());
+                    }
+                }
+            }
+            GradientView::Arrows { spacing } => {
+                // 背景是形状轮廓: 形状内部为灰色
+                for x in 0..self.width {
+                    for y in 0..self.height {
+                        let index = ((y * self.width + x) * 3) as usize;
+                        if self.sdf(x as Float, y as Float).sd < 0.0 {
+                            image[index..index + 3].copy_from_slice(&Color::gray(0.25).to_rgb8());
+                        }
+                    }
+                }
+
+                let spacing = spacing.max(2);
+                for x in (spacing / 2..self.width).step_by(spacing as usize) {
+                    for y in (spacing / 2..self.height).step_by(spacing as usize) {
+                        let (gx, gy) = self.gradient(x as Float, y as Float);
+                        let length = (gx * gx + gy * gy).sqrt();
+                        if length < EPSILON {
+                            continue;
+                        }
+                        let color = Color::from_hsv(gy.atan2(gx).to_degrees(), 1.0, 1.0);
+                        draw_arrow(
+                            &mut image,
+                            self.width,
+                            self.height,
+                            x as Float,
+                            y as Float,
+                            gx / length,
+                            gy / length,
+                            spacing as Float * 0.8,
+                            color,
+                        );
+                    }
+                }
+            }
+        }
+
+        self.save_to_file(&image, path);
+    }
+
+    // 渲染覆盖率遮罩: 每个像素被形状覆盖的比例, 与光照无关
+    // 可以在合成时作为边缘清晰且抗锯齿的遮罩使用
+    pub fn render_coverage_to_file(&self, path: &str) {
+        let mut image = vec![0u8; self.width as usize * self.height as usize * 3];
         for x in 0..self.width {
             for y in 0..self.height {
                 let index = ((y * self.width + x) * 3) as usize;
-                let value = self.sample(x as f64, y as f64);
-                image[index] = value;
-                image[index + 1] = value;
-                image[index + 2] = value;
+                let value = Color::gray(self.coverage(x as Float, y as Float));
+                image[index..index + 3].copy_from_slice(&value.to_rgb8());
             }
         }
-
         self.save_to_file(&image, path);
     }
 
+    // (x, y) 处的像素被形状覆盖的比例
+    // 在一个像素宽的范围内对 SDF 做 smoothstep, 得到抗锯齿的边缘
+    pub fn coverage(&self, x: Float, y: Float) -> Float {
+        let t = (0.5 - self.sdf(x, y).sd).clamp(0.0, 1.0);
+        t * t * (3.0 - 2.0 * t)
+    }
+
+    pub(crate) fn render(&self) -> Vec<u8> {
+        let mut image = vec![0u8; self.width as usize * self.height as usize * 3];
+        let buffer = self.render_output();
+
+        for (pixel, value) in image.chunks_mut(3).zip(buffer.iter()) {
+            pixel.copy_from_slice(&value.to_rgb8());
+        }
+
+        image
+    }
+
+    // 渲染出每个像素的光量, 按行排列
+    pub(crate) fn render_radiance(&self) -> Vec<Color> {
+        self.render_tiles(|_| true).unwrap()
+    }
+
+    // 输出图片使用的光量: 设置了去噪器时先去噪, 再按曝光补偿调整
+    fn render_output(&self) -> Vec<Color> {
+        let buffer = self.render_radiance();
+        let mut buffer = match &self.denoiser {
+            Some(denoiser) => self.denoise(&buffer, denoiser),
+            None => buffer,
+        };
+        if self.exposure != 0.0 {
+            let scale = self.exposure.exp2();
+            for value in buffer.iter_mut() {
+                *value *= scale;
+            }
+        }
+        buffer
+    }
+
     // 对图片中的某个点进行采样
     // 也就是计算有多少光经过了这个点
-    fn sample(&self, x: f64, y: f64) -> u8 {
-        let mut rng = rand::thread_rng();
+    // guide 不为空时, 按路径引导学习到的分布采样光线方向
+    fn sample(&self, x: Float, y: Float, guide: Option<&GuidingField>) -> Color {
+        self.sample_with(x, y, guide, None)
+    }
 
-        let mut sum: f64 = 0.0;
+    // records 不为空时, 记录每次采样的详细过程
+    fn sample_with(
+        &self,
+        x: Float,
+        y: Float,
+        guide: Option<&GuidingField>,
+        mut records: Option<&mut Vec<SampleRecord>>,
+    ) -> Color {
+        let mut rng = SceneRng::new(self.seed, pixel_stream(x, y));
+        let frame_offset = self.frame_offset();
+        let sampler = PixelSampler::new(self.sampling, self.sample_count, x, y, &mut rng);
+
+        let mut sum = Color::BLACK;
         for i in 0..self.sample_count {
-            let degree = TWO_PI * (i as f64 + rng.gen_range(0.0..1.0)) / self.sample_count as f64;
-            sum += self.trace(x, y, degree.cos(), degree.sin());
+            let (u, ox, oy) = sampler.sample(i, &mut rng);
+            let u = (u + frame_offset).fract();
+            let (degree, weight) = match guide {
+                Some(guide) => guide.sample(x, y, u),
+                None => (TAU * u, 1.0),
+            };
+            let (dx, dy) = self.direction(degree);
+            let (x, y) = (x + ox, y + oy);
+            let direct = self.sample_direct(x, y, i, self.sample_count, &mut rng);
+            match records.as_mut() {
+                Some(records) => {
+                    let mut segments = vec![];
+                    let radiance =
+                        self.trace_pixel(x, y, dx, dy, Some(&mut segments)) * weight + direct;
+                    sum += radiance;
+                    records.push(SampleRecord {
+                        dx,
+                        dy,
+                        segments,
+                        radiance,
+                    });
+                }
+                None => sum += self.trace_pixel(x, y, dx, dy, None) * weight + direct,
+            }
         }
 
-        let mut sum = sum / self.sample_count as f64 * 255.0;
-        if sum >= 255.0 {
-            sum = 255.0;
-        }
-        return sum as u8;
+        sum / self.sample_count as Float
+    }
+
+    // 当前帧所有光线方向整体旋转的比例(以圆周为 1)
+    fn frame_offset(&self) -> Float {
+        (self.frame as Float * GOLDEN_RATIO_CONJUGATE).fract()
+    }
+
+    // 角度对应的单位方向向量, 确定性模式下使用与平台无关的 sin/cos
+    fn direction(&self, degree: Float) -> (Float, Float) {
+        let (sin, cos) = if self.seed.is_some() {
+            portable_sin_cos(degree)
+        } else {
+            degree.sin_cos()
+        };
+        (cos, sin)
     }
 
     // 获取 (x, y) 点从 (dx, dy) 方向获取的光量
-    fn trace(&self, x: f64, y: f64, dx: f64, dy: f64) -> f64 {
-        let max_distance = ((self.width.pow(2) + self.width.pow(2)) as f64).sqrt();
+    // segments 不为空时, 把光线经过的每一段记录下来
+    fn trace(
+        &self,
+        x: Float,
+        y: Float,
+        dx: Float,
+        dy: Float,
+        segments: Option<&mut Vec<RaySegment>>,
+    ) -> Color {
+        self.trace_ray(
+            (x, y, dx, dy, 1.0),
+            None,
+            0,
+            0.0,
+            1.0,
+            false,
+            true,
+            segments,
+        )
+    }
+
+    // 像素采样的光线, 开启对光源的显式采样时直接命中光源带回的光乘以 MIS 权重
+    // 开启光子映射时经过反射/折射到达光源的光由光子图负责, 不再由路径追踪带回
+    fn trace_pixel(
+        &self,
+        x: Float,
+        y: Float,
+        dx: Float,
+        dy: Float,
+        segments: Option<&mut Vec<RaySegment>>,
+    ) -> Color {
+        self.trace_ray(
+            (x, y, dx, dy, 1.0),
+            None,
+            0,
+            0.0,
+            1.0,
+            self.next_event,
+            self.photon_count == 0,
+            segments,
+        )
+    }
+
+    // 追踪一段光路, depth 为之前已经发生的反射/折射次数, traveled 为之前各段光路的总长度
+    // throughput 为这段光路带回的光最终占像素值的比例, 贡献太小的分支按比例进行轮盘赌
+    // 光线命中透明的形状时分成反射和折射两条光线, 分别继续追踪, 最多 max_depth 次
+    // channel 为光线经过色散之后只携带的颜色通道, None 表示所有通道
+    // next_event 为 true 时命中的形状自身发出的光乘以显式采样的 MIS 权重, 只用于像素采样的光线
+    // caustics 为 false 时反射/折射之后命中的形状不带回自身发出的光, 这部分由光子图负责, 避免重复计算
+    #[allow(clippy::too_many_arguments)]
+    fn trace_ray(
+        &self,
+        ray: Ray,
+        channel: Option<usize>,
+        depth: usize,
+        traveled: Float,
+        throughput: Float,
+        next_event: bool,
+        caustics: bool,
+        mut segments: Option<&mut Vec<RaySegment>>,
+    ) -> Color {
+        let max_distance = self.max_distance();
+        let (x, y, dx, dy, mut side) = ray;
+        let mut segment = segments.as_deref_mut().map(|segments| {
+            let mut segment = RaySegment::new(x, y, dx, dy);
+            segment.depth = depth;
+            segments.push(segment);
+            segments.last_mut().unwrap()
+        });
 
-        let mut distance: f64 = 0.0;
+        let mut distance: Float = 0.0;
         for _ in 0..self.max_step {
-            let result = self.sdf(x + (dx * distance), y + (dy * distance));
-            if result.sd < EPSILON {
-                return result.emissive;
+            let px = x + (dx * distance);
+            let py = y + (dy * distance);
+            let result = self.march_sdf(px, py);
+            if let Some(segment) = segment.as_mut() {
+                segment.steps.push(MarchStep {
+                    x: px,
+                    y: py,
+                    sd: result.sd,
+                });
+            }
+            if depth == 0 && distance == 0.0 && result.sd < 0.0 && result.material.is_transparent()
+            {
+                // 起点在透明形状里面
+                side = -1.0;
             }
-            distance += result.sd;
+            // 在透明形状里面时 sdf 取反, 向形状的边界前进
+            let sd = result.sd * side;
+            if sd < self.epsilon {
+                if let Some(segment) = segment.as_mut() {
+                    segment.hit = Some((px, py));
+                }
+                let bounce = self.bounce(
+                    &result,
+                    (x, y, dx, dy, side),
+                    distance,
+                    channel,
+                    depth,
+                    traveled,
+                    throughput,
+                    next_event,
+                    caustics,
+                );
+                let mut radiance = bounce.radiance;
+                for group in bounce.groups.iter().flatten() {
+                    let mut light = Color::BLACK;
+                    for &(ray, weight, throughput) in group.branches.iter().flatten() {
+                        light += self.trace_ray(
+                            ray,
+                            group.channel,
+                            depth + 1,
+                            traveled + distance,
+                            throughput,
+                            false,
+                            caustics,
+                            segments.as_deref_mut(),
+                        ) * weight;
+                    }
+                    radiance += light * group.mask * bounce.transmittance;
+                }
+                // 这一段在透明形状里面时, 带回的光按走过的距离被吸收
+                return radiance * bounce.absorbance;
+            }
+            distance += self.march_step(px, py, dx, dy, sd);
             if distance >= max_distance {
-                break;
+                return self.shade_miss(x, y, dx, dy);
             }
         }
-        return 0.0;
+        Color::BLACK
     }
 
-    fn sdf(&self, x: f64, y: f64) -> SdfResult {
-        let mut result = SdfResult {
-            sd: f64::MAX,
-            emissive: 0.0,
+    // 光线 ray 走过 distance 后命中了 result: 计算命中的形状自身带回的光, 并分出反射/折射的次级光线
+    // 递归的 trace_ray 与按批追踪的 march_rays 共用, 其余参数与 trace_ray 相同
+    #[allow(clippy::too_many_arguments)]
+    fn bounce(
+        &self,
+        result: &SdfResult,
+        ray: Ray,
+        distance: Float,
+        channel: Option<usize>,
+        depth: usize,
+        traveled: Float,
+        throughput: Float,
+        next_event: bool,
+        caustics: bool,
+    ) -> Bounce {
+        let (x, y, dx, dy, side) = ray;
+        let mut radiance = if depth == 0 || caustics {
+            self.shade_hit(result, x, y, dx, dy, distance, traveled)
+        } else {
+            Color::BLACK
         };
+        if next_event {
+            radiance *= self.direct_weight(x, y, dx, dy, distance);
+        }
+        let mut bounce = Bounce {
+            radiance,
+            absorbance: absorbance(result, side, distance),
+            transmittance: Color::WHITE,
+            groups: [None, None, None],
+        };
+        if !result.material.is_specular() || depth >= self.max_depth {
+            return bounce;
+        }
+
+        // 次级光线带回的光还要穿过这一段上的滤色片, 按透射率最大的通道估计贡献
+        let (px, py) = (x + dx * distance, y + dy * distance);
+        bounce.transmittance = self.filter_transmittance(x, y, dx, dy, distance);
+        let throughput = throughput * bounce.transmittance.max_component();
+        let groups = bounce.groups.iter_mut();
+        for (group, (channel, mask)) in groups.zip(dispersion(&result.material, channel)) {
+            let split = match self.split(&result.material, channel, (px, py, dx, dy, side)) {
+                Some(split) => split,
+                None => continue,
+            };
+            let mut branches = [None, None];
+            for (branch, (ray, weight)) in branches.iter_mut().zip(split.branches()) {
+                if let Some(factor) = self.roulette(&ray, channel, depth + 1, throughput * weight) {
+                    let weight = weight * factor;
+                    *branch = Some((ray, weight, throughput * weight));
+                }
+            }
+            *group = Some(BounceGroup {
+                channel,
+                mask,
+                branches,
+            });
+        }
+        bounce
+    }
+
+    // 从 (x, y) 出发沿 (dx, dy) 方向的光线走过 distance 后命中了形状, 计算它带回的光量
+    // traveled 为这段光路之前已经走过的距离(折射之前的各段), 用来计算衰减
+    #[allow(clippy::too_many_arguments)]
+    fn shade_hit(
+        &self,
+        result: &SdfResult,
+        x: Float,
+        y: Float,
+        dx: Float,
+        dy: Float,
+        distance: Float,
+        traveled: Float,
+    ) -> Color {
+        let px = x + (dx * distance);
+        let py = y + (dy * distance);
+        self.emission(result, px, py, dx, dy)
+            * self.attenuation.factor(traveled + distance)
+            * self.filter_transmittance(x, y, dx, dy, distance)
+    }
+
+    // 光线走过最大追踪距离仍没有命中任何形状, 从环境中获取光量
+    fn shade_miss(&self, x: Float, y: Float, dx: Float, dy: Float) -> Color {
+        self.environment.radiance(dx, dy)
+            * self.filter_transmittance(x, y, dx, dy, self.max_distance())
+    }
+
+    fn sdf(&self, x: Float, y: Float) -> SdfResult {
+        match self.nearest_shape(x, y) {
+            Some((_, result)) => result,
+            None => SdfResult {
+                sd: Float::MAX,
+                material: Material::default(),
+                profile: EmissionProfile::Uniform,
+            },
+        }
+    }
+
+    // 光线在 (px, py) 处离最近的形状 sd 远时, 沿 (dx, dy) 可以安全前进的距离
+    // 所有形状都支持解析求交时直接前进到最近的边, 下一步在边上命中; 否则前进 sd
+    // 取两者中较大的, 避免数值误差让光线停在刚离开的边上
+    fn march_step(&self, px: Float, py: Float, dx: Float, dy: Float, sd: Float) -> Float {
+        if !self.analytic {
+            return sd;
+        }
+        let mut t = Float::INFINITY;
         for shape in self.shapes.iter() {
-            result = Scene::union_sd(shape.sdf(x, y), result);
+            if let Some(distance) = shape.raycast(px, py, dx, dy) {
+                t = t.min(distance);
+            }
         }
+        t.max(sd)
+    }
 
-        return result;
+    // 光线步进时使用的 sdf: 开启距离场网格缓存时, 离形状足够远的地方使用网格插值得到的距离,
+    // 此时没有对应的形状, 材质为默认值
+    fn march_sdf(&self, x: Float, y: Float) -> SdfResult {
+        if let Some(cell) = self.sdf_grid_cell {
+            let grid = self.sdf_grid.get_or_init(|| SdfGrid::bake(self, cell));
+            if let Some(sd) = grid.distance(x, y) {
+                return SdfResult {
+                    sd,
+                    material: Material::default(),
+                    profile: EmissionProfile::Uniform,
+                };
+            }
+        }
+        self.sdf(x, y)
     }
 
-    // 对两个形状做并集
-    // 此时 sd 的结果应该是两个形状当中 sd 比较小的那个
-    fn union_sd(result_a: SdfResult, result_b: SdfResult) -> SdfResult {
-        return if result_a.sd < result_b.sd {
-            result_a
-        } else {
-            result_b
-        };
+    // 离 (x, y) 最近的形状的下标和它的 sdf, 通过包围体层次跳过远处的形状
+    fn nearest_shape(&self, x: Float, y: Float) -> Option<(usize, SdfResult)> {
+        let bvh = self.bvh.get_or_init(|| Bvh::build(&self.bounds));
+        bvh.nearest(&self.shapes, x, y)
     }
 
-    fn save_to_file(&self, image: &Vec<u8>, path: &str) {
-        fs::remove_file(path).unwrap_or_default();
-        let file = File::create(path).unwrap();
-        let ref mut w = BufWriter::new(file);
+    // 光线沿 (dx, dy) 方向在 (x, y) 处命中形状时, 形状朝光线来向发出的光量
+    fn emission(&self, result: &SdfResult, x: Float, y: Float, dx: Float, dy: Float) -> Color {
+        if let EmissionProfile::Uniform = result.profile {
+            return result.material.emissive;
+        }
 
-        let mut encoder = png::Encoder::new(w, self.width as u32, self.height as u32);
-        encoder.set_color(png::ColorType::RGB);
-        encoder.set_depth(png::BitDepth::Eight);
-        let mut writer = encoder.write_header().unwrap();
+        let (nx, ny) = self.gradient(x, y);
+        let length = (nx * nx + ny * ny).sqrt();
+        if length < EPSILON {
+            return result.material.emissive;
+        }
+        let cos_theta = -(nx * dx + ny * dy) / length;
+        result.material.emissive * result.profile.evaluate(cos_theta)
+    }
 
-        writer.write_image_data(image.as_slice()).unwrap();
+    // 从 (x, y) 沿 (dx, dy) 方向前进 length 的过程中, 经过的滤色片的每个颜色通道的总透射率
+    // 每进入一次滤色片乘一次它的透射率, 起点在滤色片内部也算经过一次
+    fn filter_transmittance(
+        &self,
+        x: Float,
+        y: Float,
+        dx: Float,
+        dy: Float,
+        length: Float,
+    ) -> Color {
+        let mut transmittance = Color::WHITE;
+        for (filter, factor) in self.filters.iter() {
+            let mut inside = false;
+            let mut distance: Float = 0.0;
+            for _ in 0..FILTER_MAX_STEP {
+                if distance > length {
+                    break;
+                }
+                let sd = filter.sdf(x + dx * distance, y + dy * distance).sd;
+                if sd < 0.0 && !inside {
+                    transmittance *= *factor;
+                }
+                inside = sd < 0.0;
+                distance += sd.abs() + FILTER_CROSS_STEP;
+            }
+        }
+
+        transmittance
     }
+
+    // 场景 SDF 在 (x, y) 处的梯度, 也就是离 (x, y) 最近的形状的梯度
+    // 与 sdf 中的并集一样, 距离相同时取先加入的形状
+    fn gradient(&self, x: Float, y: Float) -> (Float, Float) {
+        match self.nearest_shape(x, y) {
+            Some((index, _)) => self.shapes[index].gradient(x, y),
+            None => (0.0, 0.0),
+        }
+    }
+
+    pub(crate) fn save_to_file(&self, image: &[u8], path: &str) {
+        save_png(image, self.width, self.height, path);
+    }
+}
+
+// 把 RGB 图片保存为 PNG 文件
+pub(crate) fn save_png(image: &[u8], width: u32, height: u32, path: &str) {
+    fs::remove_file(path).unwrap_or_default();
+    let file = File::create(path).unwrap();
+    let w = &mut BufWriter::new(file);
+
+    let mut encoder = png::Encoder::new(w, width, height);
+    encoder.set_color(png::ColorType::RGB);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().unwrap();
+
+    writer.write_image_data(image).unwrap();
+}
+
+// 像素对应的随机数据流编号
+fn pixel_stream(x: Float, y: Float) -> u64 {
+    (y as u64) << 32 | x as u64
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::shape::{Rect, Triangle};
+    use crate::shape::{Circle, Plane, Shapes, Triangle};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    // 读取测试输出的 PNG 文件, 读取后删除
+    fn read_png(path: &str) -> Vec<u8> {
+        let decoder = png::Decoder::new(File::open(path).unwrap());
+        let (info, mut reader) = decoder.read_info().unwrap();
+        let mut image = vec![0; info.buffer_size()];
+        reader.next_frame(&mut image).unwrap();
+        fs::remove_file(path).unwrap();
+        image
+    }
 
     #[test]
     fn basic() {
-        let width: f64 = 512.0;
-        let height: f64 = 384.0;
+        let width: Float = 512.0;
+        let height: Float = 384.0;
         let mut scene = Scene::new(width as u32, height as u32);
-        scene.add_shape(
-            Box::new(Triangle::new(
-                width * 0.5,
-                height * 0.2,
-                width * 0.8,
-                height * 0.8,
-                width * 0.3,
-                height* 0.6,
-                1.0
-            ))
-        );
+        scene.add_shape(Box::new(Triangle::new(
+            width * 0.5,
+            height * 0.2,
+            width * 0.8,
+            height * 0.8,
+            width * 0.3,
+            height * 0.6,
+            1.0,
+        )));
         scene.render_to_file("./image.png");
     }
+
+    #[test]
+    fn colored_emission() {
+        let mut scene = Scene::new(32, 16);
+        let (red, azure) = (Color::new(1.0, 0.0, 0.0), Color::new(0.0, 0.5, 1.0));
+        scene.add_shape(Box::new(Circle::new(8.0, 8.0, 4.0, red)));
+        scene.add_shape(Box::new(Circle::new(24.0, 8.0, 4.0, azure)));
+
+        let path = std::env::temp_dir().join("colorful-light2d-color-test.png");
+        let path = path.to_str().unwrap();
+        scene.render_to_file(path);
+        let image = read_png(path);
+        let pixel = |x: usize, y: usize| &image[(y * 32 + x) * 3..(y * 32 + x) * 3 + 3];
+        assert_eq!(pixel(8, 8), &[255, 0, 0]);
+        assert_eq!(pixel(24, 8), &[0, 127, 255]);
+        // 两个光源之间同时接收到两种颜色的光
+        let between = pixel(16, 8);
+        assert!(between[0] > 0 && between[1] > 0 && between[2] > 0);
+    }
+
+    #[test]
+    fn inspect_pixel() {
+        let mut scene = Scene::new(64, 64);
+        scene.add_shape(Box::new(Circle::new(32.0, 32.0, 8.0, 1.0)));
+
+        // 圆内的像素, 每条光线第一步就命中
+        let inspection = scene.inspect_pixel(32, 32);
+        assert_eq!(inspection.samples.len(), 64);
+        assert_eq!(inspection.value, [255, 255, 255]);
+        for sample in inspection.samples.iter() {
+            assert_eq!(sample.segments.len(), 1);
+            assert_eq!(sample.segments[0].steps.len(), 1);
+            assert!(sample.segments[0].hit.is_some());
+            assert_eq!(sample.radiance, Color::WHITE);
+        }
+
+        // 圆外的像素, 只有朝向圆的光线会命中
+        let inspection = scene.inspect_pixel(2, 32);
+        assert!(inspection
+            .samples
+            .iter()
+            .any(|s| s.segments[0].hit.is_none()));
+        assert!(inspection
+            .samples
+            .iter()
+            .filter(|s| s.segments[0].hit.is_some())
+            .all(|s| s.dx > 0.0));
+    }
+
+    #[test]
+    fn max_distance() {
+        let mut scene = Scene::new(30, 40);
+        assert_eq!(scene.max_distance(), 50.0);
+        scene.set_max_distance(Some(1000.0));
+        assert_eq!(scene.max_distance(), 1000.0);
+        scene.set_max_distance(None);
+        assert_eq!(scene.max_distance(), 50.0);
+    }
+
+    #[test]
+    fn attenuation() {
+        assert_eq!(Attenuation::None.factor(100.0), 1.0);
+        assert_eq!(Attenuation::InverseDistance(10.0).factor(5.0), 1.0);
+        assert_eq!(Attenuation::InverseDistance(10.0).factor(40.0), 0.25);
+
+        let mut scene = Scene::new(64, 64);
+        scene.add_shape(Box::new(Circle::new(8.0, 32.0, 2.0, 1.0)));
+        scene.set_attenuation(Attenuation::InverseDistance(4.0));
+        assert!((scene.trace(50.0, 32.0, -1.0, 0.0, None).g - 0.1).abs() < 1e-6);
+    }
+
+    #[test]
+    fn emission_profile() {
+        let mut scene = Scene::new(64, 64);
+        scene.add_shape(Shapes::profiled(
+            Box::new(Circle::new(32.0, 32.0, 8.0, 1.0)),
+            EmissionProfile::CosineLobe(2.0),
+        ));
+
+        // 正对法线方向时为完整的强度, 斜着看时按 cos²θ 衰减
+        assert!((scene.trace(50.0, 32.0, -1.0, 0.0, None).r - 1.0).abs() < 1e-3);
+        let (sin, cos) = (0.5 as Float).sin_cos();
+        let radiance = scene.trace(32.0 + 8.0 * sin, 60.0, 0.0, -1.0, None).r;
+        assert!((radiance - cos * cos).abs() < 1e-3);
+
+        let curve = EmissionProfile::Curve(vec![1.0, 0.5, 0.0].into());
+        assert_eq!(curve.evaluate(1.0), 1.0);
+        assert!((curve.evaluate((crate::float::consts::PI / 4.0).cos()) - 0.5).abs() < 1e-9);
+        assert_eq!(curve.evaluate(0.0), 0.0);
+    }
+
+    #[test]
+    fn filter() {
+        let mut scene = Scene::new(64, 64);
+        scene.add_shape(Box::new(Circle::new(8.0, 32.0, 4.0, 1.0)));
+        scene.add_filter(Box::new(Circle::new(24.0, 32.0, 4.0, 0.0)), 0.5);
+        scene.add_filter(Box::new(Circle::new(40.0, 32.0, 4.0, 0.0)), 0.5);
+
+        // 穿过两个滤色片
+        assert!((scene.trace(60.0, 32.0, -1.0, 0.0, None).r - 0.25).abs() < 1e-9);
+        // 起点在滤色片里面
+        assert!((scene.trace(24.0, 32.0, -1.0, 0.0, None).r - 0.5).abs() < 1e-9);
+        // 滤色片不遮挡光线
+        assert_eq!(scene.trace(40.0, 20.0, -1.0, 0.0, None), Color::BLACK);
+        assert!((scene.visible((60.0, 32.0), (14.0, 32.0)).g - 0.25).abs() < 1e-9);
+    }
+
+    #[test]
+    fn colored_filter() {
+        // 白光穿过红色的滤色片后只剩红光, 再穿过黄色的只剩红光
+        let mut scene = Scene::new(64, 64);
+        scene.add_shape(Box::new(Circle::new(8.0, 32.0, 4.0, 1.0)));
+        scene.add_filter(
+            Box::new(Circle::new(24.0, 32.0, 4.0, 0.0)),
+            Color::new(1.0, 0.0, 0.0),
+        );
+        assert_eq!(
+            scene.trace(60.0, 32.0, -1.0, 0.0, None),
+            Color::new(1.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            scene.visible((60.0, 32.0), (14.0, 32.0)),
+            Color::new(1.0, 0.0, 0.0)
+        );
+        scene.add_filter(
+            Box::new(Circle::new(40.0, 32.0, 4.0, 0.0)),
+            Color::new(0.8, 0.6, 0.0),
+        );
+        let color = scene.trace(60.0, 32.0, -1.0, 0.0, None);
+        assert!((color.r - 0.8).abs() < 1e-9 && color.g == 0.0 && color.b == 0.0);
+
+        // 光源被红色的滤色片包住时, 渲染出的图片只有红色
+        let mut scene = Scene::new(64, 64);
+        scene.add_shape(Box::new(Circle::new(32.0, 32.0, 4.0, 1.0)));
+        scene.add_filter(
+            Box::new(Circle::new(32.0, 32.0, 12.0, 0.0)),
+            Color::new(1.0, 0.0, 0.0),
+        );
+        scene.set_deterministic(Some(1));
+        scene.set_sample_count(16);
+        let image = scene.render_radiance();
+        let outside = (0..64 * 64)
+            .filter(|i| (i % 64).max(i / 64) > 48)
+            .fold(Color::BLACK, |sum, i| sum + image[i]);
+        assert!(outside.r > 0.0 && outside.g == 0.0 && outside.b == 0.0);
+    }
+
+    #[test]
+    fn environment() {
+        let mut scene = Scene::new(64, 64);
+        scene.add_shape(Box::new(Circle::new(8.0, 32.0, 4.0, 0.0)));
+        scene.set_environment(Environment::Constant(Color::new(0.5, 0.25, 0.0)));
+
+        assert_eq!(
+            scene.trace(32.0, 32.0, 1.0, 0.0, None),
+            Color::new(0.5, 0.25, 0.0)
+        );
+        assert_eq!(scene.trace(32.0, 32.0, -1.0, 0.0, None), Color::BLACK);
+    }
+
+    #[test]
+    fn deterministic() {
+        let mut scene = Scene::new(32, 32);
+        scene.add_shape(Box::new(Circle::new(16.0, 16.0, 4.0, 1.0)));
+        scene.set_path_guiding(true);
+        scene.set_deterministic(Some(1));
+
+        let image = scene.render();
+        assert!(image == scene.render());
+        scene.set_deterministic(Some(2));
+        assert!(image != scene.render());
+
+        // 与线程数无关
+        scene.set_seed(1);
+        scene.set_threads(3);
+        assert!(image == scene.render());
+    }
+
+    #[test]
+    fn render_streamed() {
+        let mut scene = Scene::new(24, 16);
+        scene.add_shape(Box::new(Circle::new(12.0, 8.0, 4.0, 1.0)));
+        scene.set_deterministic(Some(1));
+
+        let path = std::env::temp_dir().join("colorful-light2d-streamed-test.png");
+        let path = path.to_str().unwrap();
+        scene.render_streamed_to_file(path);
+        assert!(read_png(path) == scene.render());
+
+        // 自适应采样和多线程渲染的结果也与按区块渲染相同
+        scene.set_adaptive(Some(AdaptiveSampling::new(0.02, 8, 128)));
+        scene.set_threads(3);
+        scene.render_streamed_to_file(path);
+        assert!(read_png(path) == scene.render());
+    }
+
+    #[test]
+    fn frame() {
+        let mut scene = Scene::new(32, 32);
+        scene.add_shape(Box::new(Circle::new(16.0, 16.0, 4.0, 1.0)));
+        scene.set_deterministic(Some(1));
+
+        let image = scene.render();
+        scene.set_frame(1);
+        let next = scene.render();
+        assert!(image != next);
+        scene.set_frame(0);
+        assert!(image == scene.render());
+        scene.set_frame(1);
+        assert!(next == scene.render());
+    }
+
+    #[test]
+    fn render_exposures() {
+        let mut scene = Scene::new(16, 16);
+        scene.add_shape(Box::new(Circle::new(8.0, 8.0, 2.0, 0.5)));
+
+        let dir = std::env::temp_dir();
+        let dark = dir.join("colorful-light2d-exposure-dark-test.png");
+        let bright = dir.join("colorful-light2d-exposure-bright-test.png");
+        let (dark, bright) = (dark.to_str().unwrap(), bright.to_str().unwrap());
+        scene.render_exposures_to_files(&[(-1.0, dark), (1.0, bright)]);
+
+        // 光源内部的光量为 0.5
+        let center = (8 * 16 + 8) * 3;
+        assert_eq!(read_png(dark)[center], 63);
+        assert_eq!(read_png(bright)[center], 255);
+    }
+
+    #[test]
+    fn sample_line() {
+        let mut scene = Scene::new(64, 64);
+        scene.add_shape(Box::new(Circle::new(32.0, 32.0, 4.0, 1.0)));
+        scene.set_deterministic(Some(1));
+
+        let profile = scene.sample_line((0.0, 32.0), (32.0, 32.0), 5);
+        assert_eq!(profile.len(), 5);
+        // 终点在光源内部, 越靠近光源越亮
+        assert_eq!(profile[4], Color::WHITE);
+        assert!(profile[0].r < profile[2].r && profile[2].r < profile[3].r);
+        assert_eq!(profile[1], scene.sample(8.0, 32.0, None));
+        assert!(scene.sample_line((0.0, 0.0), (1.0, 1.0), 0).is_empty());
+    }
+
+    #[test]
+    fn composed_shapes() {
+        // 圆环: 大圆减去小圆
+        let mut scene = Scene::new(64, 64);
+        scene.add_shape(Shapes::subtract(
+            Box::new(Circle::new(32.0, 32.0, 16.0, 1.0)),
+            Box::new(Circle::new(32.0, 32.0, 8.0, 1.0)),
+        ));
+
+        assert_eq!(scene.sample(44.0, 32.0, None), Color::WHITE);
+        // 洞里的点能从所有方向看到圆环
+        assert_eq!(scene.sample(32.0, 32.0, None), Color::WHITE);
+        assert!(scene.sdf(32.0, 32.0).sd > 0.0);
+    }
+
+    #[test]
+    fn coverage() {
+        let mut scene = Scene::new(64, 64);
+        scene.add_shape(Box::new(Circle::new(32.0, 32.0, 8.0, 0.0)));
+
+        assert_eq!(scene.coverage(32.0, 32.0), 1.0);
+        assert_eq!(scene.coverage(32.0, 10.0), 0.0);
+        assert!((scene.coverage(40.0, 32.0) - 0.5).abs() < 1e-9);
+        assert!(scene.coverage(39.8, 32.0) > 0.5);
+    }
+
+    #[test]
+    fn visible() {
+        let mut scene = Scene::new(64, 64);
+        scene.add_shape(Box::new(Circle::new(32.0, 32.0, 8.0, 1.0)));
+
+        assert_eq!(scene.visible((10.0, 32.0), (54.0, 32.0)), Color::BLACK);
+        assert_eq!(scene.visible((10.0, 10.0), (54.0, 10.0)), Color::WHITE);
+        assert_eq!(scene.visible((10.0, 32.0), (20.0, 32.0)), Color::WHITE);
+        assert_eq!(scene.visible((32.0, 32.0), (10.0, 10.0)), Color::BLACK);
+    }
+
+    #[test]
+    fn visible_through_glass() {
+        // 没有吸收的玻璃不遮挡光线
+        let mut scene = Scene::new(64, 64);
+        scene.add_shape(Shapes::refractive(
+            Box::new(Circle::new(32.0, 32.0, 8.0, 0.0)),
+            1.5,
+        ));
+        let transmittance = scene.visible((10.0, 32.0), (54.0, 32.0));
+        assert!((transmittance.r - 1.0).abs() < 1e-9);
+        assert_eq!(transmittance.r, transmittance.b);
+
+        // 有吸收的玻璃按光线在里面走过的距离衰减, 这里走过的距离是直径 16
+        let mut scene = Scene::new(64, 64);
+        scene.add_shape(Shapes::absorbing(
+            Shapes::refractive(Box::new(Circle::new(32.0, 32.0, 8.0, 0.0)), 1.5),
+            Color::new(0.0, 0.05, 0.1),
+        ));
+        let tolerance = 1e-3;
+        let transmittance = scene.visible((10.0, 32.0), (54.0, 32.0));
+        assert!((transmittance.r - 1.0).abs() < tolerance);
+        assert!((transmittance.g - (-0.8 as Float).exp()).abs() < tolerance);
+        assert!((transmittance.b - (-1.6 as Float).exp()).abs() < tolerance);
+        // 端点在玻璃里面时只计算里面的一段
+        let transmittance = scene.visible((32.0, 32.0), (54.0, 32.0));
+        assert!((transmittance.b - (-0.8 as Float).exp()).abs() < tolerance);
+        let transmittance = scene.visible((10.0, 32.0), (32.0, 32.0));
+        assert!((transmittance.b - (-0.8 as Float).exp()).abs() < tolerance);
+
+        // 玻璃后面的不透明形状仍然遮挡光线
+        scene.add_shape(Box::new(Circle::new(48.0, 32.0, 4.0, 0.0)));
+        assert_eq!(scene.visible((10.0, 32.0), (54.0, 32.0)), Color::BLACK);
+    }
+
+    #[test]
+    fn gradient() {
+        let mut scene = Scene::new(64, 64);
+        scene.add_shape(Box::new(Circle::new(32.0, 32.0, 8.0, 1.0)));
+
+        // 圆的梯度指向圆心的反方向, 长度为 1
+        let (gx, gy) = scene.gradient(40.0, 32.0);
+        assert!((gx - 1.0).abs() < 1e-6);
+        assert!(gy.abs() < 1e-6);
+        let (gx, gy) = scene.gradient(32.0, 20.0);
+        assert!(gx.abs() < 1e-6);
+        assert!((gy + 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn culling() {
+        // 记录 sdf 被调用的次数
+        struct Counted(Circle, Arc<AtomicUsize>);
+        impl Shape for Counted {
+            fn sdf(&self, x: Float, y: Float) -> SdfResult {
+                self.1.fetch_add(1, Ordering::Relaxed);
+                self.0.sdf(x, y)
+            }
+            fn bounds(&self) -> Option<Aabb> {
+                self.0.bounds()
+            }
+        }
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut scene = Scene::new(64, 64);
+        scene.add_shape(Box::new(Circle::new(8.0, 8.0, 4.0, 1.0)));
+        let far = Circle::new(56.0, 56.0, 4.0, 2.0);
+        scene.add_shape(Box::new(Counted(far, calls.clone())));
+
+        // 远处的形状被跳过
+        assert_eq!(scene.sdf(10.0, 8.0).sd, -2.0);
+        assert_eq!(calls.load(Ordering::Relaxed), 0);
+        let result = scene.sdf(50.0, 56.0);
+        assert_eq!(result.sd, 2.0);
+        assert_eq!(result.material.emissive, Color::gray(2.0));
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn analytic() {
+        // 只有圆和平面时光线直接前进到边上, 步进次数很少也能命中远处的形状
+        let mut scene = Scene::new(64, 64);
+        scene.add_shape(Box::new(Circle::new(60.0, 32.0, 2.0, 1.0)));
+        scene.add_shape(Box::new(Plane::new(0.0, 80.0, 0.0, -1.0, 0.5)));
+        scene.set_max_step(3);
+        assert_eq!(scene.trace(4.0, 32.0, 1.0, 0.0, None), Color::gray(1.0));
+        assert_eq!(scene.trace(4.0, 32.0, 0.0, 1.0, None), Color::gray(0.5));
+
+        // 不支持解析求交的形状让整个场景回到光线步进, 经过它旁边时步长很小, 用完了步进次数
+        scene.add_shape(Shapes::union(
+            Box::new(Circle::new(20.0, 34.5, 2.0, 0.0)),
+            Box::new(Circle::new(4.0, 4.0, 1.0, 0.0)),
+        ));
+        assert_eq!(scene.trace(4.0, 32.0, 1.0, 0.0, None), Color::BLACK);
+    }
+
+    #[test]
+    fn zero_sample_count() {
+        let mut scene = Scene::new(8, 8);
+        scene.add_shape(Box::new(Circle::new(4.0, 4.0, 2.0, 1.0)));
+        scene.set_sample_count(0);
+        assert_eq!(scene.settings().sample_count, 1);
+        assert!(scene.render_radiance().iter().all(|c| c.r.is_finite()));
+        scene.set_gradient_domain(true);
+        assert!(scene.render_radiance().iter().all(|c| c.r.is_finite()));
+
+        scene.apply_settings(&RenderSettings {
+            sample_count: 0,
+            ..RenderSettings::default()
+        });
+        assert_eq!(scene.settings().sample_count, 1);
+    }
+
+    #[test]
+    fn preset() {
+        let mut scene = Scene::new(16, 16);
+        assert_eq!(scene.settings(), RenderSettings::default());
+        scene.use_preset(Preset::Draft);
+        assert_eq!(scene.settings(), Preset::Draft.settings());
+        assert_eq!(scene.sample_count, 16);
+        assert_eq!(scene.denoiser, Some(Denoiser::new(3)));
+
+        // 设置中的去噪器和曝光在输出图片时生效
+        scene.add_shape(Box::new(Circle::new(8.0, 8.0, 2.0, 0.25)));
+        scene.set_deterministic(Some(1));
+        let denoiser = Denoiser::new(3);
+        let denoised = scene.denoise(&scene.render_radiance(), &denoiser);
+        assert!(scene.render_output() == denoised);
+        let settings = RenderSettings {
+            exposure: 1.0,
+            ..scene.settings()
+        };
+        scene.apply_settings(&settings);
+        assert_eq!(scene.settings(), settings);
+        let brighter: Vec<Color> = denoised.iter().map(|&value| value * 2.0).collect();
+        assert!(scene.render_output() == brighter);
+    }
 }
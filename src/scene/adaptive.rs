@@ -0,0 +1,192 @@
+// 自适应采样: 每个像素先取 min_samples 次采样, 根据亮度的方差估计平均值的相对误差,
+// 误差大于阈值的像素再追加一轮同样多的采样, 直到误差足够小或者用完 max_samples
+// 平坦的区域很快停止, 采样集中在半影、焦散等噪点明显的地方
+
+use super::guiding::GuidingField;
+use super::sampling::PixelSampler;
+use super::tiles::Tile;
+use super::{pixel_stream, Scene, GOLDEN_RATIO_CONJUGATE};
+use crate::color::Color;
+use crate::float::consts::TAU;
+use crate::float::Float;
+use crate::rng::SceneRng;
+
+// 计算相对误差时亮度的下限, 避免很暗的像素为了很小的绝对误差用完所有的采样
+const MIN_LUMINANCE: Float = 1e-3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AdaptiveSampling {
+    // 平均值的标准误差与亮度之比小于 threshold 时停止采样
+    pub threshold: Float,
+    pub min_samples: u32,
+    pub max_samples: u32,
+}
+
+impl AdaptiveSampling {
+    pub fn new(threshold: Float, min_samples: u32, max_samples: u32) -> AdaptiveSampling {
+        let min_samples = min_samples.max(1);
+        AdaptiveSampling {
+            threshold,
+            min_samples,
+            max_samples: max_samples.max(min_samples),
+        }
+    }
+}
+
+impl Scene {
+    // 开启自适应采样, 开启后忽略 sample_count; 传入 None 关闭
+    // 梯度域渲染不使用自适应采样
+    pub fn set_adaptive(&mut self, adaptive: Option<AdaptiveSampling>) {
+        self.adaptive = adaptive;
+        self.invalidate();
+    }
+
+    // 按自适应采样渲染出每个像素的光量, 以及每个像素最终使用的采样数, 都按行排列
+    // 没有开启自适应采样时, 每个像素的采样数都是 sample_count
+    pub fn render_adaptive(&self) -> (Vec<Color>, Vec<u32>) {
+        let mut samples = vec![self.sample_count; self.width as usize * self.height as usize];
+        let radiance = self
+            .render_tiles(|event| {
+                if let Some(counts) = event.samples {
+                    let tile = event.tile;
+                    for (row, values) in counts.chunks(tile.width as usize).enumerate() {
+                        let start = (tile.y as usize + row) * self.width as usize + tile.x as usize;
+                        samples[start..start + values.len()].copy_from_slice(values);
+                    }
+                }
+                true
+            })
+            .unwrap();
+        (radiance, samples)
+    }
+
+    // 按自适应采样渲染一个区块, 每个像素的光量和采样数按行写入 pixels 和 samples
+    pub(super) fn render_tile_adaptive(
+        &self,
+        adaptive: &AdaptiveSampling,
+        tile: &Tile,
+        pixels: &mut [Color],
+        samples: &mut [u32],
+        guide: Option<&GuidingField>,
+    ) {
+        let coords = tile.pixels();
+        for ((value, count), (x, y)) in pixels.iter_mut().zip(samples.iter_mut()).zip(coords) {
+            let (radiance, n) = self.sample_adaptive(adaptive, x as Float, y as Float, guide);
+            *value = radiance;
+            *count = n;
+        }
+    }
+
+    // 计算一个像素的光量: 开启自适应采样时按自适应采样, 否则使用固定的 sample_count
+    // 不按区块渲染的地方(逐行写入、增量渲染)都通过它采样, 保证结果与 render_tiles 相同
+    pub(super) fn sample_pixel(&self, x: Float, y: Float, guide: Option<&GuidingField>) -> Color {
+        match self.adaptive.as_ref() {
+            Some(adaptive) => self.sample_adaptive(adaptive, x, y, guide).0,
+            None => self.sample(x, y, guide),
+        }
+    }
+
+    fn sample_adaptive(
+        &self,
+        adaptive: &AdaptiveSampling,
+        x: Float,
+        y: Float,
+        guide: Option<&GuidingField>,
+    ) -> (Color, u32) {
+        let mut rng = SceneRng::new(self.seed, pixel_stream(x, y));
+        let mut sum = Color::BLACK;
+        let mut sum_luminance = 0.0;
+        let mut sum_squared = 0.0;
+        let mut count = 0;
+        let mut pass = 0;
+        // 字段是公开的, 不一定经过 new 的检查, 与 new 一样保证每轮至少有一次采样
+        let min_samples = adaptive.min_samples.max(1);
+        let max_samples = adaptive.max_samples.max(min_samples);
+
+        while count < max_samples {
+            // 每一轮的角度整体旋转一个按黄金分割数列变化的比例, 与之前各轮的采样错开
+            let n = min_samples.min(max_samples - count);
+            let rotation = self.frame_offset() + pass as Float * GOLDEN_RATIO_CONJUGATE;
+            let sampler = PixelSampler::new(self.sampling, n, x, y, &mut rng);
+            for i in 0..n {
+                let (u, ox, oy) = sampler.sample(i, &mut rng);
+                let u = (u + rotation).fract();
+                let (degree, weight) = match guide {
+                    Some(guide) => guide.sample(x, y, u),
+                    None => (TAU * u, 1.0),
+                };
+                let (dx, dy) = self.direction(degree);
+                let (x, y) = (x + ox, y + oy);
+                let direct = self.sample_direct(x, y, i, n, &mut rng);
+                let radiance = self.trace_pixel(x, y, dx, dy, None) * weight + direct;
+                let luminance = radiance.luminance();
+                sum += radiance;
+                sum_luminance += luminance;
+                sum_squared += luminance * luminance;
+            }
+            count += n;
+            pass += 1;
+
+            let mean = sum_luminance / count as Float;
+            let variance = (sum_squared / count as Float - mean * mean).max(0.0);
+            let error = (variance / count as Float).sqrt();
+            if error <= adaptive.threshold * mean.max(MIN_LUMINANCE) {
+                break;
+            }
+        }
+
+        (sum / count as Float, count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shape::Circle;
+
+    #[test]
+    fn adaptive() {
+        let mut scene = Scene::new(32, 16);
+        scene.add_shape(Box::new(Circle::new(8.0, 8.0, 3.0, 1.0)));
+        scene.set_seed(6);
+        scene.set_adaptive(Some(AdaptiveSampling::new(0.02, 16, 256)));
+        let (image, samples) = scene.render_adaptive();
+
+        // 形状里面没有噪点, 只用最少的采样; 远处的像素噪点多, 采样更多
+        assert_eq!(samples[8 * 32 + 8], 16);
+        assert_eq!(image[8 * 32 + 8], Color::WHITE);
+        assert!(samples[8 * 32 + 28] > 16);
+        assert!(samples.iter().all(|&n| (16..=256).contains(&n)));
+        assert_eq!(scene.render_radiance(), image);
+
+        scene.set_adaptive(None);
+        let (_, samples) = scene.render_adaptive();
+        assert!(samples.iter().all(|&n| n == 64));
+    }
+
+    #[test]
+    fn zero_min_samples() {
+        let mut scene = Scene::new(8, 8);
+        scene.add_shape(Box::new(Circle::new(4.0, 4.0, 2.0, 1.0)));
+        scene.set_seed(6);
+        scene.set_adaptive(Some(AdaptiveSampling {
+            threshold: 0.0,
+            min_samples: 0,
+            max_samples: 8,
+        }));
+        let (image, samples) = scene.render_adaptive();
+        // 每轮只有一次采样, 形状里面没有噪点, 一次就停止
+        assert!(samples.iter().all(|&n| (1..=8).contains(&n)));
+        assert_eq!(samples[4 * 8 + 4], 1);
+        assert!(image.iter().all(|color| color.r.is_finite()));
+
+        scene.set_adaptive(Some(AdaptiveSampling {
+            threshold: 0.0,
+            min_samples: 0,
+            max_samples: 0,
+        }));
+        let (image, samples) = scene.render_adaptive();
+        assert!(samples.iter().all(|&n| n == 1));
+        assert!(image.iter().all(|color| color.r.is_finite()));
+    }
+}
@@ -0,0 +1,176 @@
+// 场景分析: 检查场景中最小的形状、最窄的缝隙和发光强度的动态范围,
+// 据此推荐 epsilon、最大步进次数和采样数
+// 固定的 max_step = 10 在有小形状的场景中会悄悄截断光线
+
+use super::Scene;
+use crate::float::Float;
+
+// 判断两侧梯度方向相反时, 梯度沿坐标轴的分量至少要达到的值
+const RIDGE_COS: Float = 0.7;
+// 推荐的采样数的范围
+const MIN_SAMPLE_COUNT: Float = 32.0;
+const MAX_SAMPLE_COUNT: Float = 4096.0;
+// 推荐的最大步进次数的范围
+const MIN_MAX_STEP: usize = 16;
+const MAX_MAX_STEP: usize = 512;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SceneAnalysis {
+    // 最细的形状的宽度(像素), 场景中没有形状时为 None
+    pub min_feature: Option<Float>,
+    // 形状之间最窄的缝隙的宽度(像素), 没有缝隙时为 None
+    pub min_gap: Option<Float>,
+    // 发光强度(颜色分量中的最大值)的范围 (最小值, 最大值), 只统计大于 0 的发光强度, 没有发光形状时为 None
+    pub emissive_range: Option<(Float, Float)>,
+
+    // 推荐的参数
+    pub epsilon: Float,
+    pub max_step: usize,
+    pub sample_count: u32,
+}
+
+impl Scene {
+    // 分析场景并给出推荐的参数, 不修改场景
+    pub fn analyze(&self) -> SceneAnalysis {
+        let (width, height) = (self.width as usize, self.height as usize);
+        let mut field = vec![0.0; width * height];
+        let mut min_emissive = Float::MAX;
+        let mut max_emissive: Float = 0.0;
+        for y in 0..height {
+            for x in 0..width {
+                let result = self.sdf(x as Float, y as Float);
+                field[y * width + x] = result.sd;
+                let emissive = result.material.emissive.max_component();
+                if result.sd < 0.0 && emissive > 0.0 {
+                    min_emissive = min_emissive.min(emissive);
+                    max_emissive = max_emissive.max(emissive);
+                }
+            }
+        }
+
+        // 沿 x 或 y 方向, 两侧相邻像素的梯度方向相反时, 当前像素位于中轴线上
+        // 此时两侧像素到各自最近边界的距离加上它们之间的距离, 就是这里的宽度
+        // 只看梯度方向相反的情况, 可以排除形状拐角处的中轴线
+        let mut min_feature = Float::MAX;
+        let mut min_gap = Float::MAX;
+        for y in 1..height.saturating_sub(1) {
+            for x in 1..width.saturating_sub(1) {
+                let index = y * width + x;
+                let inside = field[index] < 0.0;
+                for &(offset, axis_x) in [(1, true), (width, false)].iter() {
+                    let (a, b) = (index - offset, index + offset);
+                    if (field[a] < 0.0) != inside || (field[b] < 0.0) != inside {
+                        continue;
+                    }
+                    let component = |i: usize| {
+                        let (gx, gy) = self.gradient((i % width) as Float, (i / width) as Float);
+                        let length = (gx * gx + gy * gy).sqrt();
+                        if length == 0.0 {
+                            return 0.0;
+                        }
+                        if axis_x {
+                            gx / length
+                        } else {
+                            gy / length
+                        }
+                    };
+                    // 内部的梯度指向外侧的边界, 外部的梯度背离最近的边界
+                    let sign = if inside { 1.0 } else { -1.0 };
+                    if component(a) * sign > -RIDGE_COS || component(b) * sign < RIDGE_COS {
+                        continue;
+                    }
+                    let size = field[a].abs() + field[b].abs() + 2.0;
+                    if inside {
+                        min_feature = min_feature.min(size);
+                    } else {
+                        min_gap = min_gap.min(size);
+                    }
+                }
+            }
+        }
+
+        let min_feature = if min_feature < Float::MAX {
+            Some(min_feature)
+        } else {
+            None
+        };
+        let min_gap = if min_gap < Float::MAX {
+            Some(min_gap)
+        } else {
+            None
+        };
+        let emissive_range = if max_emissive > 0.0 {
+            Some((min_emissive, max_emissive))
+        } else {
+            None
+        };
+
+        // 最小尺寸至少按一个像素算, 更小的细节在画面上也看不出来
+        let min_size = match (min_feature, min_gap) {
+            (Some(a), Some(b)) => a.min(b),
+            (Some(a), None) | (None, Some(a)) => a,
+            (None, None) => self.max_distance(),
+        }
+        .max(1.0);
+
+        let epsilon = (min_size * 1e-3).clamp(1e-6, 1e-2);
+        let max_step = (16.0 + 8.0 * (self.max_distance() / min_size).log2()).ceil() as usize;
+        let max_step = max_step.clamp(MIN_MAX_STEP, MAX_MAX_STEP);
+
+        // 动态范围越大, 小光源带来的噪点越明显, 需要更多的采样
+        let dynamic_range = emissive_range.map(|(min, max)| max / min).unwrap_or(1.0);
+        let mut sample_count = MIN_SAMPLE_COUNT * dynamic_range.sqrt();
+        if min_size < 4.0 {
+            sample_count *= 2.0;
+        }
+        let sample_count = sample_count.min(MAX_SAMPLE_COUNT) as u32;
+
+        SceneAnalysis {
+            min_feature,
+            min_gap,
+            emissive_range,
+            epsilon,
+            max_step,
+            sample_count,
+        }
+    }
+
+    // 分析场景并使用推荐的参数
+    pub fn auto_tune(&mut self) -> SceneAnalysis {
+        let analysis = self.analyze();
+        self.set_epsilon(analysis.epsilon);
+        self.set_max_step(analysis.max_step);
+        self.set_sample_count(analysis.sample_count);
+        analysis
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shape::{Circle, Rect};
+
+    #[test]
+    fn analyze() {
+        let mut scene = Scene::new(64, 64);
+        // 两个矩形之间有一条 4 像素宽的缝隙, 较细的矩形宽 6 像素
+        scene.add_shape(Box::new(Rect::new(20.0, 32.0, 0.0, 10.0, 20.0, 4.0)));
+        scene.add_shape(Box::new(Rect::new(37.0, 32.0, 0.0, 3.0, 20.0, 0.0)));
+        scene.add_shape(Box::new(Circle::new(56.0, 8.0, 4.0, 1.0)));
+
+        let analysis = scene.auto_tune();
+        assert!((analysis.min_feature.unwrap() - 6.0).abs() <= 1.0);
+        assert!((analysis.min_gap.unwrap() - 4.0).abs() <= 1.0);
+        assert_eq!(analysis.emissive_range, Some((1.0, 4.0)));
+        assert!(analysis.max_step > 10);
+        assert_eq!(analysis.sample_count, 64);
+        assert_eq!(scene.max_step, analysis.max_step);
+    }
+
+    #[test]
+    fn analyze_empty_scene() {
+        let analysis = Scene::new(16, 16).analyze();
+        assert_eq!(analysis.min_feature, None);
+        assert_eq!(analysis.emissive_range, None);
+    }
+}
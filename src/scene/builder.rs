@@ -0,0 +1,111 @@
+// 用链式调用创建场景, 一次性配置质量相关的设置, 例如
+// Scene::builder(512, 384).samples(256).max_steps(64).build()
+
+use super::{AdaptiveSampling, Scene};
+use crate::color::Color;
+use crate::environment::Environment;
+use crate::float::Float;
+
+pub struct SceneBuilder {
+    scene: Scene,
+}
+
+impl Scene {
+    pub fn builder(width: u32, height: u32) -> SceneBuilder {
+        SceneBuilder {
+            scene: Scene::new(width, height),
+        }
+    }
+}
+
+impl SceneBuilder {
+    // 每个像素的采样数
+    pub fn samples(mut self, sample_count: u32) -> SceneBuilder {
+        self.scene.set_sample_count(sample_count);
+        self
+    }
+
+    // 每条光线最多步进的次数
+    pub fn max_steps(mut self, max_step: usize) -> SceneBuilder {
+        self.scene.set_max_step(max_step);
+        self
+    }
+
+    // 光线最多反射/折射的次数
+    pub fn max_depth(mut self, max_depth: usize) -> SceneBuilder {
+        self.scene.set_max_depth(max_depth);
+        self
+    }
+
+    // 光线离形状的距离小于 epsilon 时认为命中了形状
+    pub fn epsilon(mut self, epsilon: Float) -> SceneBuilder {
+        self.scene.set_epsilon(epsilon);
+        self
+    }
+
+    // 没有命中任何形状的光线带回的光量, 即各个方向相同的环境光
+    pub fn background(mut self, level: impl Into<Color>) -> SceneBuilder {
+        self.scene
+            .set_environment(Environment::Constant(level.into()));
+        self
+    }
+
+    // 渲染使用的线程数, 为 0 时使用所有的 CPU 核心
+    pub fn threads(mut self, threads: usize) -> SceneBuilder {
+        self.scene.set_threads(threads);
+        self
+    }
+
+    // 开启自适应采样: 每个像素至少 min_samples、至多 max_samples 次采样,
+    // 平均值的相对误差小于 threshold 时停止
+    pub fn adaptive(
+        mut self,
+        threshold: Float,
+        min_samples: u32,
+        max_samples: u32,
+    ) -> SceneBuilder {
+        let adaptive = AdaptiveSampling::new(threshold, min_samples, max_samples);
+        self.scene.set_adaptive(Some(adaptive));
+        self
+    }
+
+    // 开启确定性模式, 相同的种子渲染出相同的图片
+    pub fn seed(mut self, seed: u64) -> SceneBuilder {
+        self.scene.set_seed(seed);
+        self
+    }
+
+    pub fn build(self) -> Scene {
+        self.scene
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builder() {
+        let scene = Scene::builder(32, 16)
+            .samples(8)
+            .max_steps(64)
+            .max_depth(4)
+            .epsilon(1e-4)
+            .background(0.5)
+            .seed(3)
+            .adaptive(0.05, 16, 8)
+            .build();
+        assert_eq!((scene.width(), scene.height()), (32, 16));
+        let settings = scene.settings();
+        assert_eq!(settings.sample_count, 8);
+        assert_eq!(settings.max_step, 64);
+        assert_eq!(settings.max_depth, 4);
+        assert_eq!(settings.epsilon, 1e-4);
+        assert_eq!(scene.seed, Some(3));
+        // 最多的采样数不少于最少的采样数
+        assert_eq!(scene.adaptive, Some(AdaptiveSampling::new(0.05, 16, 16)));
+
+        // 空场景中每条光线都带回背景的光
+        assert_eq!(scene.sample(3.0, 5.0, None), Color::gray(0.5));
+    }
+}
@@ -0,0 +1,190 @@
+// 包围体层次(BVH): 按包围盒把形状组织成一棵二叉树, 查询最近的形状时先访问离得近的子树,
+// 跳过包围盒比当前最近的距离还远的子树, 形状很多时每次步进只需要计算附近几个形状的 sdf
+// 在第一次查询时(也就是渲染开始时)建立, 添加或者替换形状之后重新建立
+
+use crate::float::Float;
+use crate::shape::{Aabb, SdfResult, Shape};
+
+// 遍历时栈的容量, 足够容纳按中位数划分的树
+const STACK_SIZE: usize = 64;
+
+enum NodeKind {
+    // 叶子节点只包含一个形状, 值为形状的下标
+    Leaf(usize),
+    // 内部节点的左子节点紧跟在它后面, 值为右子节点的下标
+    Interior(usize),
+}
+
+struct Node {
+    bounds: Aabb,
+    kind: NodeKind,
+}
+
+pub(super) struct Bvh {
+    nodes: Vec<Node>,
+    // 没有包围盒的形状(例如 Plane), 每次查询都要计算
+    unbounded: Vec<usize>,
+}
+
+impl Bvh {
+    // bounds 为每个形状的包围盒
+    pub(super) fn build(bounds: &[Option<Aabb>]) -> Bvh {
+        let mut indices = vec![];
+        let mut unbounded = vec![];
+        for (index, aabb) in bounds.iter().enumerate() {
+            match aabb {
+                Some(_) => indices.push(index),
+                None => unbounded.push(index),
+            }
+        }
+
+        let mut bvh = Bvh {
+            nodes: Vec::with_capacity(indices.len() * 2),
+            unbounded,
+        };
+        if !indices.is_empty() {
+            bvh.build_node(bounds, &mut indices);
+        }
+        bvh
+    }
+
+    fn build_node(&mut self, bounds: &[Option<Aabb>], indices: &mut [usize]) -> usize {
+        let aabb = |index: usize| bounds[index].unwrap();
+        let node = self.nodes.len();
+        if let [index] = *indices {
+            self.nodes.push(Node {
+                bounds: aabb(index),
+                kind: NodeKind::Leaf(index),
+            });
+            return node;
+        }
+
+        let union = indices[1..]
+            .iter()
+            .fold(aabb(indices[0]), |union, &index| union.union(&aabb(index)));
+        self.nodes.push(Node {
+            bounds: union,
+            kind: NodeKind::Interior(0),
+        });
+
+        // 沿包围盒较长的一边, 按形状包围盒的中心从中间分成两半
+        let center = |index: usize| {
+            let b = aabb(index);
+            if union.width() >= union.height() {
+                b.min_x + b.max_x
+            } else {
+                b.min_y + b.max_y
+            }
+        };
+        indices.sort_by(|&a, &b| center(a).total_cmp(&center(b)));
+        let (left, right) = indices.split_at_mut(indices.len() / 2);
+        self.build_node(bounds, left);
+        let right = self.build_node(bounds, right);
+        self.nodes[node].kind = NodeKind::Interior(right);
+        node
+    }
+
+    // 离 (x, y) 最近(sd 最小)的形状的下标和它的 sdf, 距离相同时取下标较小的形状
+    pub(super) fn nearest(
+        &self,
+        shapes: &[Box<dyn Shape>],
+        x: Float,
+        y: Float,
+    ) -> Option<(usize, SdfResult)> {
+        let mut nearest: Option<(usize, SdfResult)> = None;
+        let visit = |index: usize, nearest: &mut Option<(usize, SdfResult)>| {
+            let result = shapes[index].sdf(x, y);
+            let closer = match nearest {
+                Some((best, best_result)) => (result.sd, index) < (best_result.sd, *best),
+                None => result.sd < Float::MAX,
+            };
+            if closer {
+                *nearest = Some((index, result));
+            }
+        };
+
+        for &index in self.unbounded.iter() {
+            visit(index, &mut nearest);
+        }
+        if self.nodes.is_empty() {
+            return nearest;
+        }
+
+        let mut stack = [0; STACK_SIZE];
+        let mut len = 1;
+        while len > 0 {
+            len -= 1;
+            let node = &self.nodes[stack[len]];
+            let sd = nearest.as_ref().map_or(Float::MAX, |(_, result)| result.sd);
+            // 在包围盒外面时, 到包围盒的距离是其中形状的 sd 的下限, 比当前最近的距离还远时不可能更近
+            // 在包围盒里面时距离为 0, 其中的形状的 sd 可能是任意的负数, 不能跳过
+            if node.bounds.distance(x, y) > sd.max(0.0) {
+                continue;
+            }
+            match node.kind {
+                NodeKind::Leaf(index) => visit(index, &mut nearest),
+                NodeKind::Interior(right) => {
+                    let left = stack[len] + 1;
+                    let near_left = self.nodes[left].bounds.distance(x, y)
+                        <= self.nodes[right].bounds.distance(x, y);
+                    // 后入栈的先访问, 先访问离得近的子节点
+                    let (first, second) = if near_left {
+                        (left, right)
+                    } else {
+                        (right, left)
+                    };
+                    stack[len] = second;
+                    stack[len + 1] = first;
+                    len += 2;
+                }
+            }
+        }
+        nearest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::CounterRng;
+    use crate::shape::{Circle, Plane, Rect};
+    use rand::Rng;
+
+    #[test]
+    fn nearest() {
+        let mut rng = CounterRng::new(1, 0);
+        let mut shapes: Vec<Box<dyn Shape>> = vec![];
+        for _ in 0..200 {
+            let x = rng.gen_range(0.0..100.0);
+            let y = rng.gen_range(0.0..100.0);
+            let r = rng.gen_range(0.5..3.0);
+            if rng.gen_range(0.0..1.0) < 0.5 {
+                shapes.push(Box::new(Circle::new(x, y, r, 1.0)));
+            } else {
+                shapes.push(Box::new(Rect::new(x, y, r, r, 1.0, 1.0)));
+            }
+        }
+        // 重复的形状距离相同, 应当取下标较小的那个
+        shapes.push(Box::new(Circle::new(50.0, 50.0, 2.0, 1.0)));
+        shapes.push(Box::new(Circle::new(50.0, 50.0, 2.0, 1.0)));
+        shapes.push(Box::new(Plane::new(0.0, -20.0, 0.0, 1.0, 1.0)));
+        let bounds: Vec<_> = shapes.iter().map(|shape| shape.bounds()).collect();
+        let bvh = Bvh::build(&bounds);
+
+        for i in 0..400 {
+            let x = (i % 20) as Float * 5.3 - 3.0;
+            let y = (i / 20) as Float * 5.3 - 3.0;
+            let (index, result) = bvh.nearest(&shapes, x, y).unwrap();
+            let mut expected = 0;
+            for (j, shape) in shapes.iter().enumerate() {
+                if shape.sdf(x, y).sd < shapes[expected].sdf(x, y).sd {
+                    expected = j;
+                }
+            }
+            assert_eq!(index, expected);
+            assert_eq!(result.sd, shapes[expected].sdf(x, y).sd);
+        }
+        assert_eq!(bvh.nearest(&shapes, 50.0, 50.0).unwrap().0, 200);
+        assert!(Bvh::build(&[]).nearest(&[], 0.0, 0.0).is_none());
+    }
+}
@@ -0,0 +1,151 @@
+// 梯度域渲染: 估计每个像素的光量(primal)和相邻像素之间的梯度, 再通过 screened Poisson 重建
+// 相邻像素使用相同的光线方向, 所以梯度的估计方差很小, 重建后的图片比直接采样更平滑
+
+use super::{pixel_stream, Scene};
+use crate::color::Color;
+use crate::float::consts::TAU;
+use crate::float::Float;
+use crate::rng::SceneRng;
+use rand::Rng;
+
+// primal 在重建时的权重, 越小越依赖梯度
+const PRIMAL_WEIGHT: Float = 0.2;
+// 重建时 Gauss-Seidel 迭代的次数
+const ITERATIONS: usize = 200;
+
+impl Scene {
+    pub(super) fn render_gradient_domain(&self) -> Vec<Color> {
+        let (width, height) = (self.width as usize, self.height as usize);
+        let mut primal = vec![Color::BLACK; width * height];
+        // gx[i] 是右边的像素减去当前像素, gy[i] 是下边的像素减去当前像素
+        let mut gx = vec![Color::BLACK; width * height];
+        let mut gy = vec![Color::BLACK; width * height];
+        let frame_offset = self.frame_offset();
+        // 与像素采样相同, 开启光子映射时反射/折射之后到达光源的光由光子图负责
+        let caustics = self.photon_count == 0;
+        let trace = |x, y, dx, dy| {
+            self.trace_ray(
+                (x, y, dx, dy, 1.0),
+                None,
+                0,
+                0.0,
+                1.0,
+                false,
+                caustics,
+                None,
+            )
+        };
+
+        for y in 0..height {
+            for x in 0..width {
+                let index = y * width + x;
+                let (fx, fy) = (x as Float, y as Float);
+                let mut rng = SceneRng::new(self.seed, pixel_stream(fx, fy));
+
+                for i in 0..self.sample_count {
+                    let u = (i as Float + rng.gen_range(0.0..1.0)) / self.sample_count as Float;
+                    let u = (u + frame_offset).fract();
+                    let (dx, dy) = self.direction(TAU * u);
+                    let center = trace(fx, fy, dx, dy);
+                    primal[index] += center;
+                    if x + 1 < width {
+                        gx[index] += trace(fx + 1.0, fy, dx, dy) - center;
+                    }
+                    if y + 1 < height {
+                        gy[index] += trace(fx, fy + 1.0, dx, dy) - center;
+                    }
+                }
+
+                primal[index] /= self.sample_count as Float;
+                gx[index] /= self.sample_count as Float;
+                gy[index] /= self.sample_count as Float;
+            }
+        }
+
+        reconstruct(width, height, &primal, &gx, &gy)
+    }
+}
+
+// 求解 screened Poisson 方程: 最小化
+// α² Σ (I - primal)² + Σ (I(x+1) - I(x) - gx)² + Σ (I(y+1) - I(y) - gy)²
+// 三个颜色分量互不影响, 同时求解
+fn reconstruct(
+    width: usize,
+    height: usize,
+    primal: &[Color],
+    gx: &[Color],
+    gy: &[Color],
+) -> Vec<Color> {
+    let alpha2 = PRIMAL_WEIGHT * PRIMAL_WEIGHT;
+    let mut image = primal.to_vec();
+
+    for _ in 0..ITERATIONS {
+        for y in 0..height {
+            for x in 0..width {
+                let index = y * width + x;
+                let mut sum = alpha2 * primal[index];
+                let mut weight = alpha2;
+                if x > 0 {
+                    sum += image[index - 1] + gx[index - 1];
+                    weight += 1.0;
+                }
+                if x + 1 < width {
+                    sum += image[index + 1] - gx[index];
+                    weight += 1.0;
+                }
+                if y > 0 {
+                    sum += image[index - width] + gy[index - width];
+                    weight += 1.0;
+                }
+                if y + 1 < height {
+                    sum += image[index + width] - gy[index];
+                    weight += 1.0;
+                }
+                image[index] = sum / weight;
+            }
+        }
+    }
+
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reconstruct_from_exact_gradients() {
+        let (width, height) = (8, 6);
+        let expected: Vec<Color> = (0..width * height)
+            .map(|i| {
+                let (x, y) = ((i % width) as Float, (i / width) as Float);
+                Color::new((x * 0.3).sin() + y * 0.1, x * 0.05, 1.0 - y * 0.1)
+            })
+            .collect();
+        let mut gx = vec![Color::BLACK; width * height];
+        let mut gy = vec![Color::BLACK; width * height];
+        for y in 0..height {
+            for x in 0..width {
+                let index = y * width + x;
+                if x + 1 < width {
+                    gx[index] = expected[index + 1] - expected[index];
+                }
+                if y + 1 < height {
+                    gy[index] = expected[index + width] - expected[index];
+                }
+            }
+        }
+
+        // primal 上的噪声会被准确的梯度抹平
+        let primal: Vec<Color> = expected
+            .iter()
+            .enumerate()
+            .map(|(i, &v)| v + Color::gray(if i % 2 == 0 { 0.2 } else { -0.2 }))
+            .collect();
+        let image = reconstruct(width, height, &primal, &gx, &gy);
+        for (&a, &b) in image.iter().zip(expected.iter()) {
+            let d = a - b;
+            assert!(d.r.abs() < 0.05 && d.g.abs() < 0.05 && d.b.abs() < 0.05);
+        }
+    }
+}
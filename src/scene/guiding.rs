@@ -0,0 +1,142 @@
+// 路径引导: 把画面分成若干区域, 每个区域学习一个入射光方向的直方图,
+// 渲染时按直方图对光线方向做重要性采样
+// 在光只能穿过狭窄缝隙到达像素的场景中, 可以显著减少噪点
+
+use super::Scene;
+use crate::float::consts::TAU;
+use crate::float::Float;
+use crate::rng::SceneRng;
+use rand::Rng;
+
+// 每个区域的大小(像素)
+const TILE_SIZE: u32 = 16;
+// 方向直方图的格数
+const BIN_COUNT: usize = 64;
+// 每个区域用来学习的光线数
+const TRAINING_RAYS: usize = 512;
+// 确定性模式下学习使用的随机数据流编号
+const GUIDING_STREAM: u64 = 1 << 63;
+// 混合进去的均匀分布的比例, 保证每个方向都有机会被采样到
+const UNIFORM_RATIO: Float = 0.2;
+
+pub struct GuidingField {
+    tiles_x: u32,
+    tiles_y: u32,
+    // 每个区域的直方图, 学习时记录光量, 学习完成后转换为累积分布
+    histograms: Vec<[Float; BIN_COUNT]>,
+}
+
+impl GuidingField {
+    fn new(width: u32, height: u32) -> GuidingField {
+        let tiles_x = width.div_ceil(TILE_SIZE);
+        let tiles_y = height.div_ceil(TILE_SIZE);
+        GuidingField {
+            tiles_x,
+            tiles_y,
+            histograms: vec![[0.0; BIN_COUNT]; (tiles_x * tiles_y) as usize],
+        }
+    }
+
+    fn tile_index(&self, x: Float, y: Float) -> usize {
+        let tx = ((x.max(0.0) as u32) / TILE_SIZE).min(self.tiles_x - 1);
+        let ty = ((y.max(0.0) as u32) / TILE_SIZE).min(self.tiles_y - 1);
+        (ty * self.tiles_x + tx) as usize
+    }
+
+    // 记录 (x, y) 处从 degree 方向得到的光量
+    fn record(&mut self, x: Float, y: Float, degree: Float, radiance: Float) {
+        let bin = ((degree.rem_euclid(TAU) / TAU * BIN_COUNT as Float) as usize).min(BIN_COUNT - 1);
+        let index = self.tile_index(x, y);
+        self.histograms[index][bin] += radiance;
+    }
+
+    // 把学习到的直方图和均匀分布混合, 并转换为累积分布
+    fn finish(&mut self) {
+        for histogram in self.histograms.iter_mut() {
+            let total: Float = histogram.iter().sum();
+            let mut cdf = 0.0;
+            for value in histogram.iter_mut() {
+                let weight = if total > 0.0 {
+                    (1.0 - UNIFORM_RATIO) * *value / total + UNIFORM_RATIO / BIN_COUNT as Float
+                } else {
+                    1.0 / BIN_COUNT as Float
+                };
+                cdf += weight;
+                *value = cdf;
+            }
+        }
+    }
+
+    // 根据 [0, 1) 之间的 u 采样一个方向
+    // 返回方向的角度, 以及这个方向相对于均匀采样的权重 1 / (2π * pdf)
+    pub fn sample(&self, x: Float, y: Float, u: Float) -> (Float, Float) {
+        let cdf = &self.histograms[self.tile_index(x, y)];
+        let bin = cdf.iter().position(|&c| u < c).unwrap_or(BIN_COUNT - 1);
+        let start = if bin == 0 { 0.0 } else { cdf[bin - 1] };
+        let weight = cdf[bin] - start;
+
+        let t = ((u - start) / weight).clamp(0.0, 1.0);
+        let degree = (bin as Float + t) * TAU / BIN_COUNT as Float;
+        (degree, 1.0 / (weight * BIN_COUNT as Float))
+    }
+}
+
+impl Scene {
+    // 学习路径引导使用的方向分布, 没有开启路径引导时返回 None
+    pub(super) fn build_guiding_field(&self) -> Option<GuidingField> {
+        if !self.path_guiding {
+            return None;
+        }
+
+        let mut field = GuidingField::new(self.width, self.height);
+        for ty in 0..field.tiles_y {
+            for tx in 0..field.tiles_x {
+                let stream = GUIDING_STREAM | (ty * field.tiles_x + tx) as u64;
+                let mut rng = SceneRng::new(self.seed, stream);
+                for i in 0..TRAINING_RAYS {
+                    let x = ((tx * TILE_SIZE) as Float + rng.gen_range(0.0..TILE_SIZE as Float))
+                        .min(self.width as Float);
+                    let y = ((ty * TILE_SIZE) as Float + rng.gen_range(0.0..TILE_SIZE as Float))
+                        .min(self.height as Float);
+                    let degree =
+                        TAU * (i as Float + rng.gen_range(0.0..1.0)) / TRAINING_RAYS as Float;
+                    let (dx, dy) = self.direction(degree);
+                    let radiance = self.trace(x, y, dx, dy, None);
+                    field.record(x, y, degree, radiance.luminance());
+                }
+            }
+        }
+        field.finish();
+
+        Some(field)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample_weights() {
+        let mut field = GuidingField::new(16, 16);
+        field.record(0.0, 0.0, 1.0, 10.0);
+        field.finish();
+
+        // 大部分方向落在记录过光量的格子里
+        let count = 1000;
+        let mut hits = 0;
+        let mut weight_sum = 0.0;
+        for i in 0..count {
+            let (degree, weight) = field.sample(0.0, 0.0, (i as Float + 0.5) / count as Float);
+            let bin = (degree / TAU * BIN_COUNT as Float) as usize;
+            if bin == (1.0 / TAU * BIN_COUNT as Float) as usize {
+                hits += 1;
+            }
+            weight_sum += weight;
+        }
+        assert!(hits as Float > count as Float * (1.0 - UNIFORM_RATIO) * 0.99);
+
+        // 权重的平均值为 1, 所以引导后的估计是无偏的
+        assert!((weight_sum / count as Float - 1.0).abs() < 1e-2);
+    }
+}
@@ -0,0 +1,163 @@
+// 增量渲染: 交互式编辑时, 修改形状后只重新渲染受影响的区域
+// 受影响的区域是新旧形状的包围盒, 再向外扩大形状能影响到的距离
+// 光线不衰减时, 一个光源或者遮挡物能影响整个画面, 所以需要使用者指定影响距离
+
+use super::{save_png, Scene};
+use crate::color::Color;
+use crate::float::Float;
+
+// 增量渲染的结果, 保存每个像素的光量
+pub struct IncrementalRender {
+    width: u32,
+    height: u32,
+    buffer: Vec<Color>,
+}
+
+impl IncrementalRender {
+    // 每个像素的光量, 按行排列
+    pub fn radiance(&self) -> &[Color] {
+        &self.buffer
+    }
+
+    pub fn save_to_file(&self, path: &str) {
+        let mut image = vec![0u8; self.buffer.len() * 3];
+        for (pixel, value) in image.chunks_mut(3).zip(self.buffer.iter()) {
+            pixel.copy_from_slice(&value.to_rgb8());
+        }
+        save_png(&image, self.width, self.height, path);
+    }
+}
+
+impl Scene {
+    // 设置形状的修改能影响到的距离(像素), 传入 None 时认为能影响整个画面(默认)
+    // 例如光线按距离衰减并且远处的光可以忽略时, 可以设置为光衰减到可以忽略的距离
+    pub fn set_influence_radius(&mut self, radius: Option<Float>) {
+        self.influence_radius = radius;
+    }
+
+    // 完整地渲染一次, 之后可以用 update_incremental 只更新被修改过的区域
+    pub fn render_incremental(&mut self) -> IncrementalRender {
+        self.dirty = None;
+        IncrementalRender {
+            width: self.width,
+            height: self.height,
+            buffer: self.render_radiance(),
+        }
+    }
+
+    // 重新渲染上次更新之后被 add_shape 或 replace_shape 修改过的区域, 返回重新渲染的像素数
+    // 修改了环境光、采样数等影响整个画面的设置之后, 重新渲染整个画面
+    // 梯度域渲染和光子映射需要整张图片, 开启时总是重新渲染整个画面
+    pub fn update_incremental(&mut self, render: &mut IncrementalRender) -> usize {
+        let dirty = match self.dirty.take() {
+            Some(dirty) => dirty,
+            None => return 0,
+        };
+        let radius = match self.influence_radius {
+            Some(radius)
+                if !self.gradient_domain
+                    && self.photon_count == 0
+                    && render.width == self.width
+                    && render.height == self.height =>
+            {
+                radius
+            }
+            _ => {
+                *render = self.render_incremental();
+                return render.buffer.len();
+            }
+        };
+
+        let (width, height) = (self.width as Float, self.height as Float);
+        let x0 = (dirty.min_x - radius).ceil().clamp(0.0, width) as u32;
+        let y0 = (dirty.min_y - radius).ceil().clamp(0.0, height) as u32;
+        let x1 = ((dirty.max_x + radius).floor() + 1.0).clamp(0.0, width) as u32;
+        let y1 = ((dirty.max_y + radius).floor() + 1.0).clamp(0.0, height) as u32;
+        if x0 >= x1 || y0 >= y1 {
+            return 0;
+        }
+
+        let guiding_field = self.build_guiding_field();
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let index = (y * self.width + x) as usize;
+                render.buffer[index] =
+                    self.sample_pixel(x as Float, y as Float, guiding_field.as_ref());
+            }
+        }
+        ((x1 - x0) * (y1 - y0)) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::Environment;
+    use crate::scene::AdaptiveSampling;
+    use crate::shape::Circle;
+
+    #[test]
+    fn update_incremental() {
+        let mut scene = Scene::new(32, 32);
+        scene.add_shape(Box::new(Circle::new(8.0, 8.0, 3.0, 1.0)));
+        let light = scene.add_shape(Box::new(Circle::new(24.0, 24.0, 2.0, 1.0)));
+        scene.set_deterministic(Some(1));
+        scene.set_influence_radius(Some(4.0));
+
+        let mut render = scene.render_incremental();
+        assert_eq!(scene.update_incremental(&mut render), 0);
+
+        scene.replace_shape(light, Box::new(Circle::new(25.0, 24.0, 2.0, 1.0)));
+        // 包围盒 [22, 27] x [22, 26] 向外扩大 4 像素, 超出画面的部分被截掉
+        assert_eq!(scene.update_incremental(&mut render), 14 * 13);
+        // 影响距离足够大时与完整渲染的结果相同
+        scene.set_influence_radius(Some(64.0));
+        scene.replace_shape(light, Box::new(Circle::new(20.0, 20.0, 2.0, 1.0)));
+        assert_eq!(scene.update_incremental(&mut render), 32 * 32);
+        assert!(render.radiance() == scene.render_radiance().as_slice());
+
+        // 开启自适应采样时, 更新的像素与完整渲染一样使用自适应采样
+        scene.set_adaptive(Some(AdaptiveSampling::new(0.02, 8, 128)));
+        let mut render = scene.render_incremental();
+        scene.replace_shape(light, Box::new(Circle::new(22.0, 20.0, 2.0, 1.0)));
+        assert_eq!(scene.update_incremental(&mut render), 32 * 32);
+        assert!(render.radiance() == scene.render_radiance().as_slice());
+    }
+
+    #[test]
+    fn add_shape_and_settings() {
+        let mut scene = Scene::new(32, 32);
+        scene.add_shape(Box::new(Circle::new(8.0, 8.0, 3.0, 1.0)));
+        scene.set_deterministic(Some(1));
+        scene.set_influence_radius(Some(4.0));
+
+        let mut render = scene.render_incremental();
+        let before = render.radiance().to_vec();
+        scene.add_shape(Box::new(Circle::new(24.0, 24.0, 2.0, 1.0)));
+        // 包围盒 [22, 26] x [22, 26] 向外扩大 4 像素
+        assert_eq!(scene.update_incremental(&mut render), 13 * 13);
+        assert!(render.radiance()[24 * 32 + 24].r > before[24 * 32 + 24].r);
+
+        // 环境光影响整个画面
+        scene.set_environment(Environment::Constant(Color::new(0.5, 0.5, 0.5)));
+        assert_eq!(scene.update_incremental(&mut render), 32 * 32);
+        assert!(render.radiance() == scene.render_radiance().as_slice());
+        assert_eq!(scene.update_incremental(&mut render), 0);
+    }
+
+    #[test]
+    fn auto_tune() {
+        let mut scene = Scene::new(32, 32);
+        scene.add_shape(Box::new(Circle::new(16.0, 16.0, 3.0, 1.0)));
+        scene.set_deterministic(Some(1));
+        scene.set_influence_radius(Some(4.0));
+
+        let mut render = scene.render_incremental();
+        let before = render.radiance().to_vec();
+        // 自动调整的参数影响整个画面
+        scene.auto_tune();
+        assert_eq!(scene.update_incremental(&mut render), 32 * 32);
+        assert!(render.radiance() != before.as_slice());
+        assert!(render.radiance() == scene.render_radiance().as_slice());
+    }
+}
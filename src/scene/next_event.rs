@@ -0,0 +1,252 @@
+// 对光源的显式采样(next-event estimation): 均匀地选取方向时, 小光源只被很少的光线命中, 噪点很多
+// 开启后每次采样除了沿选取的方向追踪, 还在随机选中的发光形状的边界上取一点, 检查能否直接看到它
+// 两种采样都能得到直接光照, 按多重重要性采样(MIS)的平衡启发式加权合并, 结果仍然是无偏的
+
+use super::Scene;
+use crate::color::Color;
+use crate::float::consts::TAU;
+use crate::float::Float;
+use crate::rng::SceneRng;
+use crate::shape::{Aabb, SdfResult};
+use rand::Rng;
+
+// 确定性模式下采样光源边界使用的随机数据流编号
+const LIGHT_STREAM: u64 = 1 << 61;
+// 采样一个光源的边界时在包围盒内取点的次数
+const LIGHT_TRIES: usize = 1 << 16;
+// 在光源的包围盒外留出的余量, 保证边界附近的带完全在取点范围内
+const LIGHT_MARGIN: Float = 1.0;
+// 光线命中的位置离光源上的点的距离之差在这个范围内时, 认为光源上的点可见
+const VISIBILITY_TOLERANCE: Float = 0.5;
+
+// 一个发光的形状, 以及它边界上的点(和外法线)与周长
+pub(super) struct Light {
+    index: usize,
+    points: Vec<(Float, Float, Float, Float)>,
+    perimeter: Float,
+}
+
+impl Scene {
+    // 开启对光源的显式采样, 默认关闭
+    // 只对像素的采样生效, 梯度域渲染、路径引导的学习和辐射探针仍然只按方向采样
+    // 无界的发光形状(例如半平面)不参与显式采样
+    pub fn set_next_event(&mut self, enabled: bool) {
+        self.next_event = enabled;
+        self.invalidate();
+    }
+
+    // 从 (x, y) 对光源采样一次, 返回它带回的光量(已经乘以 MIS 权重)
+    // 与方向一样按采样的序号 i 分层: 把所有光源的边界依次连起来等分为 count 份, 第 i 次在第 i 份中随机选点
+    // 没有开启显式采样时返回黑色, 也不消耗随机数
+    pub(super) fn sample_direct(
+        &self,
+        x: Float,
+        y: Float,
+        i: u32,
+        count: u32,
+        rng: &mut SceneRng,
+    ) -> Color {
+        if !self.next_event {
+            return Color::BLACK;
+        }
+        let lights = self.lights();
+        if lights.is_empty() {
+            return Color::BLACK;
+        }
+        let u = (i as Float + rng.gen_range(0.0..1.0)) / count as Float * lights.len() as Float;
+        let light = &lights[(u as usize).min(lights.len() - 1)];
+        let v = u.fract() * light.points.len() as Float;
+        let (lx, ly, nx, ny) = light.points[(v as usize).min(light.points.len() - 1)];
+
+        // 背对 (x, y) 的边界一定被光源自己挡住
+        let (ux, uy) = (lx - x, ly - y);
+        let length = (ux * ux + uy * uy).sqrt();
+        if length < self.epsilon || ux * nx + uy * ny >= 0.0 {
+            return Color::BLACK;
+        }
+        let (dx, dy) = (ux / length, uy / length);
+        let (distance, result) = match self.first_hit(x, y, dx, dy, length) {
+            Some(hit) => hit,
+            None => return Color::BLACK,
+        };
+        // 先命中了别的形状, 或者先命中了这个光源的另一段边界
+        let (px, py) = (x + dx * distance, y + dy * distance);
+        let visible = (distance - length).abs() <= VISIBILITY_TOLERANCE
+            && matches!(self.nearest_shape(px, py), Some((index, _)) if index == light.index);
+        if !visible {
+            return Color::BLACK;
+        }
+
+        // 按光源采样的概率密度 p 得到的估计为 L / (2π p), 乘以权重 p / (1 / 2π + p)
+        let pdf = self.light_pdf(light, x, y, dx, dy, distance);
+        self.shade_hit(&result, x, y, dx, dy, distance, 0.0) / (1.0 + TAU * pdf)
+    }
+
+    // 像素采样的光线从 (x, y) 沿 (dx, dy) 前进 distance 后直接命中形状时, 带回的光的 MIS 权重
+    // 方向均匀采样的概率密度为 1 / 2π, 命中的不是光源时光源采样不可能选中这个方向, 权重为 1
+    pub(super) fn direct_weight(
+        &self,
+        x: Float,
+        y: Float,
+        dx: Float,
+        dy: Float,
+        distance: Float,
+    ) -> Float {
+        let (px, py) = (x + dx * distance, y + dy * distance);
+        let index = match self.nearest_shape(px, py) {
+            Some((index, _)) => index,
+            None => return 1.0,
+        };
+        match self.lights().iter().find(|light| light.index == index) {
+            Some(light) => 1.0 / (1.0 + TAU * self.light_pdf(light, x, y, dx, dy, distance)),
+            None => 1.0,
+        }
+    }
+
+    // 光源采样选中从 (x, y) 沿 (dx, dy) 距离 distance 处的边界点的概率密度(相对于方向的角度)
+    // 边界上长度为 dl 的一段在 distance 处对应的角度为 dl·|cos| / distance
+    fn light_pdf(
+        &self,
+        light: &Light,
+        x: Float,
+        y: Float,
+        dx: Float,
+        dy: Float,
+        distance: Float,
+    ) -> Float {
+        let (px, py) = (x + dx * distance, y + dy * distance);
+        let (nx, ny) = self.shapes[light.index].gradient(px, py);
+        let length = (nx * nx + ny * ny).sqrt();
+        if length == 0.0 {
+            return 0.0;
+        }
+        let cos = (nx * dx + ny * dy).abs() / length;
+        let count = self.lights().len() as Float;
+        distance / (cos * count * light.perimeter)
+    }
+
+    // 从 (x, y) 沿 (dx, dy) 步进, 返回第一次命中形状时的距离和 sdf
+    // 起点在形状里面, 或者走过 length 之后仍然没有命中时返回 None
+    fn first_hit(
+        &self,
+        x: Float,
+        y: Float,
+        dx: Float,
+        dy: Float,
+        length: Float,
+    ) -> Option<(Float, SdfResult)> {
+        let mut distance: Float = 0.0;
+        for _ in 0..self.max_step {
+            let (px, py) = (x + dx * distance, y + dy * distance);
+            let result = self.march_sdf(px, py);
+            if result.sd < self.epsilon {
+                return if distance > 0.0 {
+                    Some((distance, result))
+                } else {
+                    None
+                };
+            }
+            distance += self.march_step(px, py, dx, dy, result.sd);
+            if distance > length + VISIBILITY_TOLERANCE {
+                return None;
+            }
+        }
+        None
+    }
+
+    // 场景中边界上有发光的有界形状, 第一次使用时采样它们的边界, 形状改变后清空
+    fn lights(&self) -> &[Light] {
+        self.lights.get_or_init(|| {
+            let mut lights = vec![];
+            for (index, shape) in self.shapes.iter().enumerate() {
+                let bounds = match self.bounds[index] {
+                    Some(bounds) => bounds,
+                    None => continue,
+                };
+                let region = Aabb::new(
+                    bounds.min_x - LIGHT_MARGIN,
+                    bounds.min_y - LIGHT_MARGIN,
+                    bounds.max_x + LIGHT_MARGIN,
+                    bounds.max_y + LIGHT_MARGIN,
+                );
+                let mut rng = SceneRng::new(self.seed, LIGHT_STREAM | index as u64);
+                let (mut points, perimeter) =
+                    self.sample_boundary(shape.as_ref(), &region, LIGHT_TRIES, &mut rng);
+                // 按绕包围盒中心的角度排序, 分层选点时每一份对应边界上相邻的一段
+                let (cx, cy) = (
+                    (bounds.min_x + bounds.max_x) / 2.0,
+                    (bounds.min_y + bounds.max_y) / 2.0,
+                );
+                points.sort_by(|a, b| {
+                    pseudo_angle(a.0 - cx, a.1 - cy).total_cmp(&pseudo_angle(b.0 - cx, b.1 - cy))
+                });
+                let emissive = points
+                    .iter()
+                    .any(|&(x, y, _, _)| !shape.sdf(x, y).material.emissive.is_black());
+                if emissive && perimeter > 0.0 {
+                    lights.push(Light {
+                        index,
+                        points,
+                        perimeter,
+                    });
+                }
+            }
+            lights
+        })
+    }
+}
+
+// 随角度单调增加的 [0, 4) 上的值, 用来按角度排序, 不依赖平台的 atan2
+fn pseudo_angle(dx: Float, dy: Float) -> Float {
+    let p = dx / (dx.abs() + dy.abs()).max(Float::MIN_POSITIVE);
+    if dy < 0.0 {
+        3.0 + p
+    } else {
+        1.0 - p
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::difference;
+    use crate::shape::{Circle, Rect};
+
+    #[test]
+    fn next_event() {
+        let mut scene = Scene::new(24, 16);
+        scene.add_shape(Box::new(Circle::new(4.0, 4.0, 1.0, 4.0)));
+        scene.set_seed(9);
+        scene.set_sample_count(16);
+
+        // 圆形光源在距离 d 处张开的角度为 2·asin(r / d)
+        let reference: Vec<Color> = (0..24 * 16)
+            .map(|i| {
+                let (x, y) = ((i % 24) as Float - 4.0, (i / 24) as Float - 4.0);
+                let d = (x * x + y * y).sqrt();
+                match d <= 1.0 {
+                    true => Color::gray(4.0),
+                    false => Color::gray(4.0 * 2.0 * (1.0 / d).asin() / TAU),
+                }
+            })
+            .collect();
+        let uniform = difference(&scene.render_radiance(), &reference).rmse;
+        scene.set_next_event(true);
+        let image = scene.render_radiance();
+        let direct = difference(&image, &reference).rmse;
+        assert!(direct * 4.0 < uniform, "{} {}", direct, uniform);
+        // 无偏: 整张图片的平均亮度与参考相同
+        let mean = |image: &[Color]| image.iter().map(Color::luminance).sum::<Float>();
+        assert!((mean(&image) / mean(&reference) - 1.0).abs() < 0.02);
+
+        // 被遮挡的像素照不到光, 逐像素采样与按区块批量追踪的结果相同
+        scene.add_shape(Box::new(Rect::new(12.0, 8.0, 0.0, 1.0, 4.0, 0.0)));
+        scene.set_max_step(64);
+        let image = scene.render_radiance();
+        assert_eq!(image[12 * 24 + 20], Color::BLACK);
+        for (i, value) in image.iter().enumerate() {
+            let (x, y) = ((i % 24) as Float, (i / 24) as Float);
+            assert_eq!(*value, scene.sample(x, y, None));
+        }
+    }
+}
@@ -0,0 +1,397 @@
+// 光子映射: 从发光形状发射光子, 记录经过反射/折射后的光子路径, 用来渲染焦散
+// 纯路径追踪很难收敛到透镜产生的焦散, 光子映射可以直接把这部分光 "送到" 像素上
+
+use super::{absorbance, Ray, Scene};
+use crate::color::Color;
+use crate::float::consts::TAU;
+use crate::float::Float;
+use crate::rng::SceneRng;
+use crate::shape::{Aabb, Shape};
+use rand::Rng;
+
+// 光子路径沿途沉积时的步长
+const DEPOSIT_STEP: Float = 0.5;
+// 确定性模式下发射光子使用的随机数据流编号
+const PHOTON_STREAM: u64 = 1 << 62;
+// 采样发光形状边界时使用的带宽, 也是在形状的包围盒外留出的余量
+const BOUNDARY_BAND: Float = 1.0;
+// 采样一个形状的边界时在取点范围内取点的次数
+const BOUNDARY_TRIES: usize = 1 << 14;
+// 取点范围内没有找到边界附近的点时, 改为沿梯度投影的起点数, 以及每个起点最多投影的次数
+const PROJECT_TRIES: usize = 1024;
+const PROJECT_STEPS: usize = 8;
+
+// 二维光子图, 每个像素一个格子, 记录经过该格子的光子路径长度乘以能量(track-length 估计)
+pub struct PhotonMap {
+    width: u32,
+    height: u32,
+    grid: Vec<Color>,
+    // 收集光子时的半径
+    radius: Float,
+}
+
+impl PhotonMap {
+    fn new(width: u32, height: u32, radius: Float) -> PhotonMap {
+        PhotonMap {
+            width,
+            height,
+            grid: vec![Color::BLACK; width as usize * height as usize],
+            radius,
+        }
+    }
+
+    // 把一段光子路径沉积到经过的格子里
+    fn deposit(&mut self, ax: Float, ay: Float, bx: Float, by: Float, power: Color) {
+        let length = ((bx - ax).powi(2) + (by - ay).powi(2)).sqrt();
+        let count = (length / DEPOSIT_STEP).ceil() as usize;
+        if count == 0 {
+            return;
+        }
+        let step = length / count as Float;
+        for i in 0..count {
+            let t = (i as Float + 0.5) / count as Float;
+            let x = ax + (bx - ax) * t;
+            let y = ay + (by - ay) * t;
+            if x < 0.0 || y < 0.0 || x >= self.width as Float || y >= self.height as Float {
+                continue;
+            }
+            let index = (y as u32 * self.width + x as u32) as usize;
+            self.grid[index] += power * step;
+        }
+    }
+
+    // 收集 (x, y) 附近的光子, 返回该点的光量(与 Scene::sample 的结果同一量纲)
+    pub fn gather(&self, x: Float, y: Float) -> Color {
+        let r = self.radius.max(0.5);
+        let min_x = (x - r).floor().max(0.0) as u32;
+        let min_y = (y - r).floor().max(0.0) as u32;
+        let max_x = ((x + r).ceil() as u32).min(self.width);
+        let max_y = ((y + r).ceil() as u32).min(self.height);
+
+        let mut sum = Color::BLACK;
+        let mut area = 0.0;
+        for cy in min_y..max_y {
+            for cx in min_x..max_x {
+                let ux = cx as Float + 0.5 - x;
+                let uy = cy as Float + 0.5 - y;
+                if ux * ux + uy * uy > r * r {
+                    continue;
+                }
+                sum += self.grid[(cy * self.width + cx) as usize];
+                area += 1.0;
+            }
+        }
+        if area == 0.0 {
+            return Color::BLACK;
+        }
+
+        // 格子里记录的是能量乘以长度, 除以面积得到通量密度, 再除以 2π 得到平均辐射亮度
+        sum / area / TAU
+    }
+}
+
+impl Scene {
+    // 发射光子并建立光子图, photon_count 为 0 时不建立
+    pub(super) fn build_photon_map(&self) -> Option<PhotonMap> {
+        if self.photon_count == 0 {
+            return None;
+        }
+
+        // 先采样每个形状的边界, 只保留边界上有发光的形状, 光子按估计的发光功率分配给它们
+        // 每个光子的能量除以它所在的形状分到的光子数, 分配的比例只影响噪点, 不影响期望
+        let mut emitters = vec![];
+        for (index, shape) in self.shapes.iter().enumerate() {
+            let mut rng = SceneRng::new(self.seed, PHOTON_STREAM | index as u64);
+            let region = self.boundary_region(index);
+            let (points, perimeter) =
+                match self.sample_boundary(shape.as_ref(), &region, BOUNDARY_TRIES, &mut rng) {
+                    (points, _) if points.is_empty() => {
+                        self.project_boundary(shape.as_ref(), &region, &mut rng)
+                    }
+                    sampled => sampled,
+                };
+            let emitted = points
+                .iter()
+                .map(|&(x, y, _, _)| shape.sdf(x, y).material.emissive.luminance())
+                .sum::<Float>()
+                / points.len().max(1) as Float
+                * perimeter;
+            if emitted > 0.0 {
+                emitters.push((shape, rng, points, perimeter, emitted));
+            }
+        }
+
+        let mut map = PhotonMap::new(self.width, self.height, self.photon_radius);
+        let total: Float = emitters.iter().map(|emitter| emitter.4).sum();
+        for (shape, mut rng, points, perimeter, emitted) in emitters {
+            // 辐射亮度为 L 的朗伯边界, 单位长度发出的能量为 2L
+            let count = ((self.photon_count as Float * emitted / total).round() as usize).max(1);
+            for _ in 0..count {
+                let (px, py, nx, ny) = points[rng.gen_range(0..points.len())];
+                let result = shape.sdf(px, py);
+                if result.material.emissive.is_black() {
+                    continue;
+                }
+
+                // 按余弦分布在法线附近选取发射方向, 再按自发光的角度分布调整能量
+                let sin: Float = rng.gen_range(-1.0..1.0);
+                let cos = (1.0 - sin * sin).sqrt();
+                let power = result.material.emissive
+                    * (2.0 * result.profile.evaluate(cos) * perimeter / count as Float);
+                let dx = nx * cos - ny * sin;
+                let dy = nx * sin + ny * cos;
+                let ray = (
+                    px + nx * self.epsilon * 2.0,
+                    py + ny * self.epsilon * 2.0,
+                    dx,
+                    dy,
+                    1.0,
+                );
+                self.trace_photon(&mut map, ray, power, &mut rng);
+            }
+        }
+
+        Some(map)
+    }
+
+    // 采样第 index 个形状的边界时的取点范围: 有界的形状取包围盒并留出余量, 无界的形状取整个画面
+    fn boundary_region(&self, index: usize) -> Aabb {
+        match self.bounds[index] {
+            Some(bounds) => Aabb::new(
+                bounds.min_x - BOUNDARY_BAND,
+                bounds.min_y - BOUNDARY_BAND,
+                bounds.max_x + BOUNDARY_BAND,
+                bounds.max_y + BOUNDARY_BAND,
+            ),
+            None => Aabb::new(0.0, 0.0, self.width as Float, self.height as Float),
+        }
+    }
+
+    // 在 region 范围内随机取点, 沿梯度投影到形状的边界上, 用于 sample_boundary 找不到边界附近的点时
+    // (例如无界的形状的边界在画面之外); 周长按投影得到的点的包围盒估计, 只是粗略的近似
+    fn project_boundary(
+        &self,
+        shape: &dyn Shape,
+        region: &Aabb,
+        rng: &mut impl Rng,
+    ) -> (Vec<(Float, Float, Float, Float)>, Float) {
+        let mut points = vec![];
+        for _ in 0..PROJECT_TRIES {
+            let mut x = rng.gen_range(region.min_x..region.max_x);
+            let mut y = rng.gen_range(region.min_y..region.max_y);
+            for _ in 0..PROJECT_STEPS {
+                let sd = shape.sdf(x, y).sd;
+                let (gx, gy) = shape.gradient(x, y);
+                let length = (gx * gx + gy * gy).sqrt();
+                if length == 0.0 || !sd.is_finite() {
+                    break;
+                }
+                let nx = gx / length;
+                let ny = gy / length;
+                if sd.abs() <= BOUNDARY_BAND * 0.5 {
+                    points.push((x - nx * sd, y - ny * sd, nx, ny));
+                    break;
+                }
+                x -= nx * sd;
+                y -= ny * sd;
+            }
+        }
+
+        let bounds = points
+            .iter()
+            .fold(None, |bounds: Option<Aabb>, &(x, y, _, _)| {
+                let point = Aabb::new(x, y, x, y);
+                Some(bounds.map_or(point, |bounds| bounds.union(&point)))
+            });
+        let perimeter = bounds.map_or(0.0, |bounds| 2.0 * (bounds.width() + bounds.height()));
+        (points, perimeter)
+    }
+
+    // 在 region 范围内随机取 tries 个点, 保留落在形状边界附近的点, 并投影到边界上
+    // 返回边界上的点(以及外法线)和估计的周长, region 需要包含整个边界
+    pub(super) fn sample_boundary(
+        &self,
+        shape: &dyn Shape,
+        region: &Aabb,
+        tries: usize,
+        rng: &mut impl Rng,
+    ) -> (Vec<(Float, Float, Float, Float)>, Float) {
+        let mut points = vec![];
+        for _ in 0..tries {
+            let x = rng.gen_range(region.min_x..region.max_x);
+            let y = rng.gen_range(region.min_y..region.max_y);
+            let sd = shape.sdf(x, y).sd;
+            if sd.abs() > BOUNDARY_BAND * 0.5 {
+                continue;
+            }
+            let (gx, gy) = shape.gradient(x, y);
+            let length = (gx * gx + gy * gy).sqrt();
+            if length == 0.0 {
+                continue;
+            }
+            let nx = gx / length;
+            let ny = gy / length;
+            points.push((x - nx * sd, y - ny * sd, nx, ny));
+        }
+
+        let area = region.width() * region.height();
+        let perimeter = points.len() as Float / tries as Float * area / BOUNDARY_BAND;
+        (points, perimeter)
+    }
+
+    // 追踪一个光子, 光子命中透明形状或者镜面时按反射率随机选择反射或者折射, 然后继续追踪, 最多 max_depth 次
+    // 只有反射/折射过的光子路径才会被沉积, 直接光照由路径追踪负责
+    fn trace_photon(&self, map: &mut PhotonMap, ray: Ray, power: Color, rng: &mut impl Rng) {
+        let max_distance = self.max_distance();
+        let mut ray = ray;
+        let mut power = power;
+        let mut channel = None;
+
+        for depth in 0..=self.max_depth {
+            let (x, y, dx, dy, side) = ray;
+            let mut hit = None;
+            let mut distance: Float = 0.0;
+            for _ in 0..self.max_step {
+                let result = self.march_sdf(x + dx * distance, y + dy * distance);
+                let sd = result.sd * side;
+                if sd < self.epsilon {
+                    hit = Some(result);
+                    break;
+                }
+                distance += sd;
+                if distance >= max_distance {
+                    break;
+                }
+            }
+
+            if depth > 0 {
+                map.deposit(x, y, x + dx * distance, y + dy * distance, power);
+            }
+
+            let result = match hit {
+                Some(result) if result.material.is_specular() => result,
+                _ => break,
+            };
+            // 色散的表面上随机选择一个颜色通道, 之后光子只携带这个通道的能量
+            if channel.is_none() && result.material.is_dispersive() {
+                let c = rng.gen_range(0..3);
+                power *= Color::primary(c) * 3.0;
+                channel = Some(c);
+            }
+            let (px, py) = (x + dx * distance, y + dy * distance);
+            let split = match self.split(&result.material, channel, (px, py, dx, dy, side)) {
+                Some(split) => split,
+                None => break,
+            };
+            // 选中每条光线的概率等于它的权重, 所以光子的能量不需要调整
+            // 不透明的镜面上没有选中反射的光子被吸收
+            power *= self.filter_transmittance(x, y, dx, dy, distance);
+            power *= absorbance(&result, side, distance);
+            ray = if rng.gen_range(0.0..1.0) < split.reflectance {
+                split.reflected
+            } else {
+                match split.refracted {
+                    Some(refracted) => refracted,
+                    None => break,
+                }
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::ShapeHandle;
+    use crate::shape::{Circle, Rect, Shapes};
+
+    #[test]
+    fn caustic() {
+        let mut scene = Scene::new(64, 32);
+        scene.add_shape(Box::new(Rect::new(2.0, 16.0, 0.0, 1.0, 12.0, 1.0)));
+        scene.add_shape(Box::new(Circle::new(24.0, 16.0, 8.0, 0.0)));
+        scene.set_photon_mapping(20000, 2.0);
+        scene.set_deterministic(Some(1));
+        scene.max_step = 64;
+
+        // 不透明的形状吸收光子, 只有直接光照, 光子图是空的
+        let map = scene.build_photon_map().unwrap();
+        assert_eq!(map.gather(44.0, 16.0), Color::BLACK);
+
+        // 透镜把光子汇聚到后面
+        scene.replace_shape(
+            ShapeHandle(1),
+            Shapes::refractive(Box::new(Circle::new(24.0, 16.0, 8.0, 0.0)), 1.5),
+        );
+        let map = scene.build_photon_map().unwrap();
+        assert!(map.gather(38.0, 16.0).r > 0.0);
+    }
+
+    #[test]
+    fn caustic_energy() {
+        // 透镜后面区域的平均亮度: 光子映射只负责经过透镜的光, 开启前后收敛到相同的值
+        let brightness = |photon_count, sample_count| {
+            let mut scene = Scene::new(64, 32);
+            scene.add_shape(Box::new(Rect::new(2.0, 16.0, 0.0, 1.0, 12.0, 1.0)));
+            scene.add_shape(Shapes::refractive(
+                Box::new(Circle::new(24.0, 16.0, 8.0, 0.0)),
+                1.5,
+            ));
+            scene.set_photon_mapping(photon_count, 2.0);
+            scene.set_sample_count(sample_count);
+            scene.set_deterministic(Some(1));
+            scene.max_step = 64;
+            let image = scene.render_radiance();
+            let mut sum = 0.0;
+            for y in 8..24 {
+                for x in 36..60 {
+                    sum += image[y * 64 + x].g;
+                }
+            }
+            sum / (16.0 * 24.0)
+        };
+
+        let path_traced = brightness(0, 64);
+        let photon_mapped = brightness(100000, 32);
+        assert!((photon_mapped / path_traced - 1.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn small_emitter() {
+        // 很小的光源和很多不发光的形状: 光子只分配给光源, 并且在光源的包围盒附近采样边界
+        let mut scene = Scene::new(256, 256);
+        scene.add_shape(Box::new(Circle::new(8.0, 128.0, 0.5, 4.0)));
+        scene.add_shape(Shapes::refractive(
+            Box::new(Circle::new(32.0, 128.0, 8.0, 0.0)),
+            1.5,
+        ));
+        for i in 0..8 {
+            let x = 64.0 + i as Float * 24.0;
+            scene.add_shape(Box::new(Rect::new(x, 32.0, 0.0, 4.0, 4.0, 0.0)));
+        }
+        scene.set_photon_mapping(1000, 2.0);
+        scene.set_deterministic(Some(1));
+        scene.max_step = 64;
+
+        let map = scene.build_photon_map().unwrap();
+        assert!(map.grid.iter().any(|power| !power.is_black()));
+        assert!(map.gather(48.0, 128.0).r > 0.0);
+    }
+
+    #[test]
+    fn boundary_perimeter() {
+        let scene = Scene::new(64, 64);
+        let circle = Circle::new(32.0, 32.0, 16.0, 1.0);
+        let region = Aabb::new(0.0, 0.0, 64.0, 64.0);
+
+        let (points, perimeter) =
+            scene.sample_boundary(&circle, &region, 20000, &mut rand::thread_rng());
+        assert!((perimeter - TAU * 16.0).abs() < TAU * 16.0 * 0.15);
+        let tolerance = if cfg!(feature = "f32") { 1e-4 } else { 1e-6 };
+        for (x, y, nx, ny) in points {
+            assert!(circle.sdf(x, y).sd.abs() < tolerance);
+            assert!(((x - 32.0) / 16.0 - nx).abs() < 1e-3);
+            assert!(((y - 32.0) / 16.0 - ny).abs() < 1e-3);
+        }
+    }
+}
@@ -0,0 +1,169 @@
+// 辐射分布探针: 记录某个点从各个方向接收到的光量(极坐标直方图)
+// 可以用来分析场景的光照, 也可以用来检查重要性采样的分布是否合理
+
+use super::{pixel_stream, save_png, Scene};
+use crate::color::Color;
+use crate::draw::draw_line;
+use crate::float::consts::TAU;
+use crate::float::Float;
+use crate::rng::SceneRng;
+use rand::Rng;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+
+// 确定性模式下探针使用的随机数据流编号
+const PROBE_STREAM: u64 = 3 << 62;
+// 极坐标图中画网格圆的数量
+const PLOT_RINGS: usize = 4;
+// 用多少段线段近似一个圆
+const PLOT_CIRCLE_SEGMENTS: usize = 64;
+const PLOT_GRID_COLOR: Color = Color::new(0.25, 0.25, 0.25);
+const PLOT_CURVE_COLOR: Color = Color::new(1.0, 0.8, 0.2);
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RadianceProbe {
+    pub x: Float,
+    pub y: Float,
+    // 第 i 格为从 [i, i + 1) * 2π / bins.len() 方向射来的平均光量
+    // 角度与光线方向相同: 0 为 +x 方向, 沿顺时针(画面坐标 y 轴向下)增大
+    pub bins: Vec<Color>,
+}
+
+impl RadianceProbe {
+    // 第 i 格中心的角度
+    pub fn angle(&self, i: usize) -> Float {
+        (i as Float + 0.5) * TAU / self.bins.len() as Float
+    }
+
+    // 所有方向的平均光量, 也就是渲染时这个点的像素值
+    pub fn mean(&self) -> Color {
+        self.bins.iter().cloned().sum::<Color>() / self.bins.len() as Float
+    }
+
+    // 导出为 CSV, 每行为 "角度(弧度),r,g,b"
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("angle,r,g,b\n");
+        for (i, value) in self.bins.iter().enumerate() {
+            writeln!(csv, "{},{},{},{}", self.angle(i), value.r, value.g, value.b).unwrap();
+        }
+        csv
+    }
+
+    pub fn save_csv(&self, path: &str) -> io::Result<()> {
+        fs::write(path, self.to_csv())
+    }
+
+    // 保存为 size x size 的极坐标图, 曲线到中心的距离与该方向光量的亮度成正比
+    // 光量最大的方向刚好碰到最外面的网格圆
+    pub fn save_plot(&self, size: u32, path: &str) {
+        let mut image = vec![0u8; size as usize * size as usize * 3];
+        let center = size as Float / 2.0;
+        let radius = center * 0.9;
+
+        // 网格圆和坐标轴
+        let circle = |r: Float, image: &mut Vec<u8>| {
+            for i in 0..PLOT_CIRCLE_SEGMENTS {
+                let a0 = TAU * i as Float / PLOT_CIRCLE_SEGMENTS as Float;
+                let a1 = TAU * (i + 1) as Float / PLOT_CIRCLE_SEGMENTS as Float;
+                draw_line(
+                    image,
+                    size,
+                    size,
+                    center + r * a0.cos(),
+                    center + r * a0.sin(),
+                    center + r * a1.cos(),
+                    center + r * a1.sin(),
+                    PLOT_GRID_COLOR,
+                );
+            }
+        };
+        for ring in 1..=PLOT_RINGS {
+            circle(radius * ring as Float / PLOT_RINGS as Float, &mut image);
+        }
+        let (left, right) = (center - radius, center + radius);
+        draw_line(
+            &mut image,
+            size,
+            size,
+            left,
+            center,
+            right,
+            center,
+            PLOT_GRID_COLOR,
+        );
+        draw_line(
+            &mut image,
+            size,
+            size,
+            center,
+            left,
+            center,
+            right,
+            PLOT_GRID_COLOR,
+        );
+
+        let max = self.bins.iter().map(Color::luminance).fold(0.0, Float::max);
+        if max > 0.0 {
+            let point = |i: usize| {
+                let r = radius * self.bins[i % self.bins.len()].luminance() / max;
+                let angle = self.angle(i);
+                (center + r * angle.cos(), center + r * angle.sin())
+            };
+            for i in 0..self.bins.len() {
+                let (x0, y0) = point(i);
+                let (x1, y1) = point(i + 1);
+                draw_line(&mut image, size, size, x0, y0, x1, y1, PLOT_CURVE_COLOR);
+            }
+        }
+
+        save_png(&image, size, size, path);
+    }
+}
+
+impl Scene {
+    // 在 (x, y) 处放置一个探针, 把方向分成 bins 格, 每格发出 rays_per_bin 条光线
+    // 每格内的光线方向是分层随机的
+    pub fn probe(&self, x: Float, y: Float, bins: usize, rays_per_bin: usize) -> RadianceProbe {
+        let bins = bins.max(1);
+        let rays_per_bin = rays_per_bin.max(1);
+        let mut rng = SceneRng::new(self.seed, PROBE_STREAM | pixel_stream(x, y));
+
+        let values = (0..bins)
+            .map(|bin| {
+                let mut sum = Color::BLACK;
+                for i in 0..rays_per_bin {
+                    let u = (i as Float + rng.gen_range(0.0..1.0)) / rays_per_bin as Float;
+                    let degree = (bin as Float + u) * TAU / bins as Float;
+                    let (dx, dy) = self.direction(degree);
+                    sum += self.trace(x, y, dx, dy, None);
+                }
+                sum / rays_per_bin as Float
+            })
+            .collect();
+
+        RadianceProbe { x, y, bins: values }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shape::Circle;
+
+    #[test]
+    fn probe() {
+        let mut scene = Scene::new(64, 64);
+        // 光源在探针的 +x 方向
+        scene.add_shape(Box::new(Circle::new(48.0, 32.0, 8.0, 1.0)));
+
+        let probe = scene.probe(16.0, 32.0, 8, 32);
+        assert_eq!(probe.bins.len(), 8);
+        // 第 0 格和第 7 格紧挨着 +x 方向
+        assert!(probe.bins[0].r > 0.0 && probe.bins[7].r > 0.0);
+        assert_eq!(probe.bins[3], Color::BLACK);
+        assert_eq!(probe.bins[4], Color::BLACK);
+        assert!(probe.to_csv().starts_with("angle,r,g,b\n"));
+        assert_eq!(probe.to_csv().lines().count(), 9);
+    }
+}
@@ -0,0 +1,103 @@
+// 渐进式渲染: 分多遍渲染, 每一遍使用不同的帧号(见 set_frame), 把结果逐遍平均
+// 每一遍之后都可以预览当前的平均结果, 很长的渲染可以在效果足够好时提前停止,
+// 停止时已经完成的各遍仍然是一张可用的图片
+
+use super::Scene;
+use crate::color::Color;
+use crate::float::Float;
+use std::time::{Duration, Instant};
+
+// 渐进式渲染的一次通知
+pub struct ProgressiveEvent<'a> {
+    // 已经完成的遍数, 以及总遍数
+    pub pass: u32,
+    pub passes: u32,
+    // 目前为止各遍平均后每个像素的光量, 按行排列
+    pub radiance: &'a [Color],
+}
+
+impl Scene {
+    // 渲染 passes 遍, 返回各遍平均后每个像素的光量, 按行排列
+    // interval 为 None 时每完成一遍调用一次 on_pass, 否则距离上次通知至少经过 interval 才调用,
+    // 最后一遍完成时总会调用; on_pass 返回 false 时停止渲染, 返回已经完成的各遍的平均结果
+    // 每一遍使用 sample_count 次采样, 相当于总共使用 passes × sample_count 次采样
+    pub fn render_progressive<F>(
+        &mut self,
+        passes: u32,
+        interval: Option<Duration>,
+        mut on_pass: F,
+    ) -> Vec<Color>
+    where
+        F: FnMut(&ProgressiveEvent) -> bool,
+    {
+        let frame = self.frame;
+        let mut sum = vec![Color::BLACK; self.width as usize * self.height as usize];
+        let mut average = sum.clone();
+        let mut notified = Instant::now();
+
+        for pass in 1..=passes {
+            self.frame = frame + (pass - 1) as u64;
+            for (sum, value) in sum.iter_mut().zip(self.render_radiance()) {
+                *sum += value;
+            }
+            for (average, &sum) in average.iter_mut().zip(sum.iter()) {
+                *average = sum / pass as Float;
+            }
+
+            let due = match interval {
+                Some(interval) => pass == passes || notified.elapsed() >= interval,
+                None => true,
+            };
+            if due {
+                notified = Instant::now();
+                let event = ProgressiveEvent {
+                    pass,
+                    passes,
+                    radiance: &average,
+                };
+                if !on_pass(&event) {
+                    break;
+                }
+            }
+        }
+
+        self.frame = frame;
+        average
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shape::Circle;
+
+    #[test]
+    fn render_progressive() {
+        let mut scene = Scene::new(20, 12);
+        scene.add_shape(Box::new(Circle::new(10.0, 6.0, 3.0, 1.0)));
+        scene.set_seed(3);
+        let first = scene.render_radiance();
+
+        // 第一遍与普通渲染相同, 提前停止时返回最后一次通知的结果
+        let mut previews = vec![];
+        let image = scene.render_progressive(4, None, |event| {
+            assert_eq!(event.passes, 4);
+            previews.push(event.radiance.to_vec());
+            event.pass < 2
+        });
+        assert_eq!(previews.len(), 2);
+        assert_eq!(previews[0], first);
+        assert_eq!(image, previews[1]);
+        assert_ne!(image, first);
+
+        // 间隔很长时只在最后一遍完成时通知, 渲染之后帧号不变
+        let mut passes = vec![];
+        let image = scene.render_progressive(3, Some(Duration::from_secs(3600)), |event| {
+            passes.push(event.pass);
+            true
+        });
+        assert_eq!(passes, [3]);
+        assert_eq!(image.len(), 20 * 12);
+        assert_eq!(scene.render_radiance(), first);
+    }
+}
@@ -0,0 +1,353 @@
+// 折射: 光线命中透明的形状(折射率 eta 大于 0)时, 一部分光被反射, 其余的按 Snell 定律折射进入形状
+// 在形状内部时 sdf 取反, 光线向形状的边界前进; 入射角太大时发生全反射, 光线留在同一侧
+// 反射和折射的比例由菲涅尔项决定, 可以用 Scene::set_fresnel 切换计算方式
+// 各颜色通道的折射率不同时, 白光在表面上分成三条只带一个通道的光线, 分别折射(色散)
+// 不透明的表面按材质的 reflectivity 做镜面反射, 其余的光被吸收
+
+use super::{Scene, EPSILON};
+use crate::color::Color;
+use crate::float::Float;
+use crate::material::Material;
+use crate::shape::SdfResult;
+
+// 折射后光线的起点离开表面的距离, 保证新的光线不会在起点立刻命中同一个表面
+// 在形状内部步进时, 每一步的距离是到最近边界的距离, 刚离开表面时步长很小,
+// 这个距离越大, 离开表面需要的步进次数越少
+pub(super) const SURFACE_BIAS: Float = 1e-4;
+
+// 光线的起点、方向, 以及光线在形状外面(1.0)还是透明形状里面(-1.0)
+pub(super) type Ray = (Float, Float, Float, Float, Float);
+
+// 透明表面的反射率(被反射的光所占的比例)的计算方式
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Fresnel {
+    // 固定的反射率, 与入射角无关, 为 0 时只折射(全反射除外)
+    Constant(Float),
+    // Schlick 近似
+    Schlick,
+    // 完整的菲涅尔方程(非偏振光)
+    Exact,
+}
+
+impl Fresnel {
+    // 从折射率为 n1 的一侧以入射角余弦 cos_i 射向折射率为 n2 的一侧, 折射角余弦为 cos_t 时的反射率
+    pub fn reflectance(&self, n1: Float, n2: Float, cos_i: Float, cos_t: Float) -> Float {
+        match *self {
+            Fresnel::Constant(reflectance) => reflectance.clamp(0.0, 1.0),
+            Fresnel::Schlick => {
+                let r0 = ((n1 - n2) / (n1 + n2)).powi(2);
+                // 从光密介质射向光疏介质时使用折射角
+                let cos = if n1 > n2 { cos_t } else { cos_i };
+                r0 + (1.0 - r0) * (1.0 - cos).powi(5)
+            }
+            Fresnel::Exact => {
+                let rs = (n1 * cos_i - n2 * cos_t) / (n1 * cos_i + n2 * cos_t);
+                let rp = (n2 * cos_i - n1 * cos_t) / (n2 * cos_i + n1 * cos_t);
+                (rs * rs + rp * rp) / 2.0
+            }
+        }
+    }
+}
+
+// 光线在表面上分成的反射光线和折射光线
+pub(super) struct Split {
+    pub reflected: Ray,
+    // 被反射的光所占的比例, 全反射时为 1
+    pub reflectance: Float,
+    // 全反射或者表面不透明时为 None
+    pub refracted: Option<Ray>,
+}
+
+// 光线在形状里面(side 为 -1.0)走过 distance 后每个颜色通道剩下的比例(Beer–Lambert 定律)
+// 命中的是光线所在的透明形状的内表面, 它的吸收系数就是这一段光路的吸收系数
+pub(super) fn absorbance(result: &SdfResult, side: Float, distance: Float) -> Color {
+    let absorption = result.material.absorption;
+    if side < 0.0 && !absorption.is_black() {
+        absorption.map(|a| (-a * distance).exp())
+    } else {
+        Color::WHITE
+    }
+}
+
+// 光线在表面上按颜色通道分开: 返回每条光线的通道以及它带回的光要保留的颜色
+// 只有包含所有通道的光线遇到色散的表面时才会分成三条
+pub(super) fn dispersion(
+    material: &Material,
+    channel: Option<usize>,
+) -> impl Iterator<Item = (Option<usize>, Color)> {
+    let disperse = channel.is_none() && material.is_dispersive();
+    let whole = if disperse {
+        None
+    } else {
+        Some((channel, Color::WHITE))
+    };
+    let channels = (0..3)
+        .filter(move |_| disperse)
+        .map(|c| (Some(c), Color::primary(c)));
+    whole.into_iter().chain(channels)
+}
+
+impl Split {
+    // 两条光线和各自的权重, 先反射后折射, 权重为 0 的光线不会出现
+    pub fn branches(&self) -> impl Iterator<Item = (Ray, Float)> {
+        let reflected = Some((self.reflected, self.reflectance));
+        let refracted = self.refracted.map(|ray| (ray, 1.0 - self.reflectance));
+        reflected
+            .into_iter()
+            .chain(refracted)
+            .filter(|&(_, weight)| weight > 0.0)
+    }
+}
+
+impl Scene {
+    // 设置透明表面反射率的计算方式, 默认使用 Schlick 近似
+    pub fn set_fresnel(&mut self, fresnel: Fresnel) {
+        self.fresnel = fresnel;
+        self.invalidate();
+    }
+
+    // 光线沿 (dx, dy) 方向在 (px, py) 处命中了材质为 material 的表面, side 为光线所在的一侧
+    // channel 为光线携带的颜色通道, 决定透明表面的折射率, None 表示所有通道(此时各通道的折射率相同)
+    // 返回反射和折射之后的光线, 无法计算表面的法线时返回 None
+    pub(super) fn split(
+        &self,
+        material: &Material,
+        channel: Option<usize>,
+        hit: Ray,
+    ) -> Option<Split> {
+        let (px, py, dx, dy, side) = hit;
+        let (gx, gy) = self.gradient(px, py);
+        let length = (gx * gx + gy * gy).sqrt();
+        if length < EPSILON {
+            return None;
+        }
+        // 朝向光线来向的法线
+        let nx = gx / length * side;
+        let ny = gy / length * side;
+        let cos_i = -(dx * nx + dy * ny);
+        let offset = SURFACE_BIAS.max(self.epsilon * 4.0);
+
+        let rx = dx + 2.0 * cos_i * nx;
+        let ry = dy + 2.0 * cos_i * ny;
+        let reflected = (px + nx * offset, py + ny * offset, rx, ry, side);
+        if !material.is_transparent() {
+            return Some(Split {
+                reflected,
+                reflectance: material.reflectivity.clamp(0.0, 1.0),
+                refracted: None,
+            });
+        }
+
+        // 入射一侧与出射一侧的折射率
+        let eta = material.eta.channel(channel.unwrap_or(1));
+        let (n1, n2) = if side > 0.0 { (1.0, eta) } else { (eta, 1.0) };
+        let ratio = n1 / n2;
+        let k = 1.0 - ratio * ratio * (1.0 - cos_i * cos_i);
+        if k < 0.0 {
+            // 全反射
+            return Some(Split {
+                reflected,
+                reflectance: 1.0,
+                refracted: None,
+            });
+        }
+
+        let cos_t = k.sqrt();
+        let c = ratio * cos_i - cos_t;
+        let tx = ratio * dx + c * nx;
+        let ty = ratio * dy + c * ny;
+        Some(Split {
+            reflected,
+            reflectance: self.fresnel.reflectance(n1, n2, cos_i, cos_t),
+            refracted: Some((px - nx * offset, py - ny * offset, tx, ty, -side)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shape::{Circle, Plane, Rect, Shapes, Surface};
+
+    #[test]
+    fn reflectance() {
+        let tolerance = if cfg!(feature = "f32") { 1e-6 } else { 1e-9 };
+        for fresnel in [Fresnel::Schlick, Fresnel::Exact].iter() {
+            // 垂直入射时玻璃反射 4% 的光, 两个方向相同
+            assert!((fresnel.reflectance(1.0, 1.5, 1.0, 1.0) - 0.04).abs() < tolerance);
+            assert!((fresnel.reflectance(1.5, 1.0, 1.0, 1.0) - 0.04).abs() < tolerance);
+            // 掠射时几乎全部反射
+            assert!(
+                fresnel.reflectance(1.0, 1.5, 0.0, (1.0 - 1.0 / (2.25 as Float)).sqrt()) > 0.99
+            );
+        }
+        assert_eq!(Fresnel::Constant(0.3).reflectance(1.0, 1.5, 0.5, 0.8), 0.3);
+    }
+
+    #[test]
+    fn snell() {
+        // 玻璃占据 x > 32 的半平面
+        let mut scene = Scene::new(64, 64);
+        scene.add_shape(Shapes::refractive(
+            Box::new(Plane::new(32.0, 0.0, -1.0, 0.0, 0.0)),
+            1.5,
+        ));
+        let glass = Material::glass(1.5);
+
+        // 从空气射入玻璃, sin(θt) = sin(θi) / 1.5, 反射光线沿法线对称
+        let (sin, cos): (Float, Float) = (0.6, 0.8);
+        let split = scene
+            .split(&glass, None, (32.0, 32.0, cos, sin, 1.0))
+            .unwrap();
+        let (x, _, tx, ty, side) = split.refracted.unwrap();
+        assert!(x > 32.0);
+        assert_eq!(side, -1.0);
+        assert!((ty - sin / 1.5).abs() < 1e-6);
+        assert!((tx * tx + ty * ty - 1.0).abs() < 1e-9);
+        let (x, _, rx, ry, side) = split.reflected;
+        assert!(x < 32.0);
+        assert_eq!(side, 1.0);
+        assert!((rx + cos).abs() < 1e-6 && (ry - sin).abs() < 1e-6);
+        assert!(split.reflectance > 0.04 && split.reflectance < 0.1);
+
+        // 从玻璃射向空气, 折射角变大
+        let split = scene
+            .split(&glass, None, (32.0, 32.0, -cos, sin, -1.0))
+            .unwrap();
+        let (x, _, _, ty, side) = split.refracted.unwrap();
+        assert!(x < 32.0);
+        assert_eq!(side, 1.0);
+        assert!((ty - sin * 1.5).abs() < 1e-6);
+
+        // 入射角大于临界角(sin(θ) > 1 / 1.5)时全反射, 光线留在玻璃里面
+        let split = scene
+            .split(&glass, None, (32.0, 32.0, -sin, cos, -1.0))
+            .unwrap();
+        assert!(split.refracted.is_none());
+        assert_eq!(split.reflectance, 1.0);
+        let (x, _, rx, ry, side) = split.reflected;
+        assert!(x > 32.0);
+        assert_eq!(side, -1.0);
+        assert!((rx - sin).abs() < 1e-6 && (ry - cos).abs() < 1e-6);
+    }
+
+    #[test]
+    fn mirror() {
+        let mut scene = Scene::new(64, 64);
+        // 刚离开镜面时步长很小, 需要更多的步进次数
+        scene.max_step = 64;
+        scene.add_shape(Box::new(Rect::new(4.0, 32.0, 0.0, 2.0, 8.0, 1.0)));
+        scene.add_shape(Box::new(Surface::new(
+            Box::new(Plane::new(48.0, 0.0, -1.0, 0.0, 0.0)),
+            Material::mirror(0.5),
+        )));
+
+        // 垂直射向镜子的光线被反射回来, 看到一半亮度的光源
+        let radiance = scene.trace(32.0, 32.0, 1.0, 0.0, None);
+        assert_eq!(radiance, Color::gray(0.5));
+        // 光路分为射向镜子和被反射的两段
+        let mut segments = vec![];
+        scene.trace(32.0, 20.0, 1.0, 0.0, Some(&mut segments));
+        assert_eq!(segments.len(), 2);
+        assert_eq!((segments[0].depth, segments[1].depth), (0, 1));
+
+        // 不允许反射时镜子只挡住光线
+        scene.set_max_depth(0);
+        assert_eq!(scene.trace(32.0, 32.0, 1.0, 0.0, None), Color::BLACK);
+        assert_eq!(scene.depth_histogram(), [64 * 64 * 64]);
+    }
+
+    #[test]
+    fn trace_through_glass() {
+        let mut scene = Scene::new(64, 64);
+        // 刚进入形状时步长很小, 需要更多的步进次数
+        scene.max_step = 64;
+        scene.set_fresnel(Fresnel::Constant(0.0));
+        scene.add_shape(Box::new(Rect::new(4.0, 32.0, 0.0, 2.0, 8.0, 1.0)));
+        scene.add_shape(Shapes::refractive(
+            Box::new(Circle::new(32.0, 32.0, 8.0, 0.0)),
+            1.5,
+        ));
+
+        // 沿着透镜的光轴穿过透镜, 从圆心出发的光线也能穿出去
+        assert_eq!(scene.trace(60.0, 32.0, -1.0, 0.0, None), Color::WHITE);
+        assert_eq!(scene.trace(32.0, 32.0, -1.0, 0.0, None), Color::WHITE);
+        // 不透明时被挡住
+        let mut opaque = Scene::new(64, 64);
+        opaque.add_shape(Box::new(Rect::new(4.0, 32.0, 0.0, 2.0, 8.0, 1.0)));
+        opaque.add_shape(Box::new(Circle::new(32.0, 32.0, 8.0, 0.0)));
+        assert_eq!(opaque.trace(60.0, 32.0, -1.0, 0.0, None), Color::BLACK);
+
+        // 偏离光轴的平行光线被透镜汇聚, 穿过透镜后向光轴偏折
+        let mut segments = vec![];
+        scene.trace(60.0, 28.0, -1.0, 0.0, Some(&mut segments));
+        assert_eq!(segments.len(), 3);
+        assert!(segments[1].dy > 0.0 && segments[2].dy > 0.0);
+
+        // 考虑菲涅尔反射时, 进出透镜各损失约 4% 的光
+        scene.set_fresnel(Fresnel::Exact);
+        let radiance = scene.trace(60.0, 32.0, -1.0, 0.0, None);
+        assert!((radiance.r - 0.96 * 0.96).abs() < 0.01);
+        // 被透镜反射的光线也会被追踪
+        let mut segments = vec![];
+        scene.trace(60.0, 28.0, -1.0, 0.0, Some(&mut segments));
+        assert!(segments.len() > 3);
+    }
+
+    #[test]
+    fn absorption() {
+        let mut scene = Scene::new(64, 64);
+        scene.max_step = 64;
+        scene.set_fresnel(Fresnel::Constant(0.0));
+        scene.add_shape(Box::new(Rect::new(4.0, 32.0, 0.0, 2.0, 8.0, 1.0)));
+        scene.add_shape(Shapes::absorbing(
+            Shapes::refractive(Box::new(Circle::new(32.0, 32.0, 8.0, 0.0)), 1.5),
+            0.05,
+        ));
+
+        // 沿光轴在形状里面走过整条直径
+        let radiance = scene.trace(60.0, 32.0, -1.0, 0.0, None);
+        assert!((radiance.r - (-0.05 * (16.0 as Float)).exp()).abs() < 1e-3);
+        // 从圆心出发只走过半径
+        let radiance = scene.trace(32.0, 32.0, -1.0, 0.0, None);
+        assert!((radiance.r - (-0.05 * (8.0 as Float)).exp()).abs() < 1e-3);
+
+        // 吸收红光和绿光的蓝色玻璃, 透过的白光变成蓝色
+        let mut scene = Scene::new(64, 64);
+        scene.max_step = 64;
+        scene.set_fresnel(Fresnel::Constant(0.0));
+        scene.add_shape(Box::new(Rect::new(4.0, 32.0, 0.0, 2.0, 8.0, 1.0)));
+        scene.add_shape(Shapes::absorbing(
+            Shapes::refractive(Box::new(Circle::new(32.0, 32.0, 8.0, 0.0)), 1.5),
+            Color::new(0.2, 0.1, 0.0),
+        ));
+        let radiance = scene.trace(60.0, 32.0, -1.0, 0.0, None);
+        assert!(radiance.r < radiance.g && radiance.g < radiance.b);
+        assert!((radiance.g - (-0.1 * (16.0 as Float)).exp()).abs() < 1e-3);
+        assert_eq!(radiance.b, 1.0);
+    }
+
+    #[test]
+    fn dispersion() {
+        let mut scene = Scene::new(64, 64);
+        scene.max_step = 64;
+        scene.set_fresnel(Fresnel::Constant(0.0));
+        scene.add_shape(Box::new(Rect::new(4.0, 32.0, 0.0, 2.0, 8.0, 1.0)));
+        scene.add_shape(Shapes::dispersive(
+            Box::new(Circle::new(32.0, 32.0, 8.0, 0.0)),
+            1.4,
+            1.5,
+            1.6,
+        ));
+
+        // 沿光轴穿过时三个通道不发生偏折, 合起来还是白光
+        assert_eq!(scene.trace(60.0, 32.0, -1.0, 0.0, None), Color::WHITE);
+
+        // 偏离光轴时分成红、绿、蓝三条光线, 折射率越大偏折越多
+        let mut segments = vec![];
+        scene.trace(60.0, 28.0, -1.0, 0.0, Some(&mut segments));
+        assert_eq!(segments.len(), 7);
+        assert!(segments[1].dy < segments[3].dy && segments[3].dy < segments[5].dy);
+        assert!(segments[2].dy < segments[4].dy && segments[4].dy < segments[6].dy);
+    }
+}
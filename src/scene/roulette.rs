@@ -0,0 +1,121 @@
+// 俄罗斯轮盘赌: 反射/折射的次数超过 roulette_depth 之后, 分出的每条光线只以 survival 的概率继续追踪,
+// 存活的光线带回的光除以 survival, 期望不变, 很深的光路以一定的概率提前结束
+// 对像素的贡献小于 MIN_THROUGHPUT 的分支也以正比于贡献的概率继续追踪, 代替直接舍弃, 结果仍然是无偏的
+// 是否存活只取决于光线本身和种子, 逐像素追踪与按区块批量追踪得到完全相同的结果
+
+use super::{Ray, Scene};
+use crate::float::Float;
+use crate::rng::hash_unit;
+
+// 存活概率的下限, 太小时存活的光线权重太大, 噪点很多
+const MIN_SURVIVAL: Float = 0.01;
+// 反射/折射产生的分支对像素的贡献小于这个比例时进行轮盘赌, 存活后的贡献恰好为这个比例
+const MIN_THROUGHPUT: Float = 1e-3;
+
+impl Scene {
+    // 反射/折射超过 start_depth 次之后, 每条分出的光线以 survival 的概率继续追踪
+    // 默认 start_depth 为 usize::MAX, 也就是不论 set_max_depth 设置为多少都不进行轮盘赌
+    pub fn set_russian_roulette(&mut self, start_depth: usize, survival: Float) {
+        self.roulette_depth = start_depth;
+        self.roulette_survival = survival.clamp(MIN_SURVIVAL, 1.0);
+        self.invalidate();
+    }
+
+    // 第 depth 次反射/折射分出的光线 ray 是否继续追踪, 继续时返回它的权重需要乘以的系数
+    // throughput 为这条光线带回的光最终占像素值的比例
+    pub(super) fn roulette(
+        &self,
+        ray: &Ray,
+        channel: Option<usize>,
+        depth: usize,
+        throughput: Float,
+    ) -> Option<Float> {
+        let mut survival: Float = 1.0;
+        if depth > self.roulette_depth {
+            survival = self.roulette_survival;
+        }
+        if throughput < MIN_THROUGHPUT {
+            survival = survival.min(throughput / MIN_THROUGHPUT);
+        }
+        if survival >= 1.0 {
+            return Some(1.0);
+        }
+        if survival <= 0.0 {
+            return None;
+        }
+        let (x, y, dx, dy, _) = *ray;
+        // 使用 f32 时 to_bits 返回 u32
+        #[allow(clippy::useless_conversion)]
+        let bits = |value: Float| u64::from(value.to_bits());
+        let channel = channel.map_or(3, |c| c as u64);
+        let values = [
+            self.seed.unwrap_or(0),
+            bits(x),
+            bits(y),
+            bits(dx),
+            bits(dy),
+            channel,
+        ];
+        if hash_unit(&values) < survival {
+            Some(1.0 / survival)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::shape::{Circle, Shapes};
+
+    #[test]
+    fn russian_roulette() {
+        let mut scene = Scene::new(32, 16);
+        scene.add_shape(Box::new(Circle::new(4.0, 8.0, 2.0, 1.0)));
+        scene.add_shape(Shapes::refractive(
+            Box::new(Circle::new(16.0, 8.0, 5.0, 0.0)),
+            1.5,
+        ));
+        scene.set_seed(2);
+        scene.set_max_step(64);
+        let expected = scene.render_radiance();
+
+        // 从第一次反射/折射开始轮盘赌, 每个像素的结果有噪点, 但整张图片的平均亮度不变
+        scene.set_russian_roulette(0, 0.5);
+        let image = scene.render_radiance();
+        assert_ne!(image, expected);
+        let mean = |image: &[Color]| image.iter().map(Color::luminance).sum::<Float>();
+        assert!((mean(&image) / mean(&expected) - 1.0).abs() < 0.05);
+        for (i, value) in image.iter().enumerate() {
+            let (x, y) = ((i % 32) as Float, (i / 32) as Float);
+            assert_eq!(*value, scene.sample(x, y, None));
+        }
+
+        // 存活概率为 1 时与不进行轮盘赌相同
+        scene.set_russian_roulette(0, 1.0);
+        assert_eq!(scene.render_radiance(), expected);
+    }
+
+    #[test]
+    fn default_and_low_throughput() {
+        let mut scene = Scene::new(32, 16);
+        scene.set_seed(3);
+        let ray = |i: usize| (i as Float, 0.5, 0.6, 0.8, 1.0);
+
+        // 默认不进行轮盘赌, 与最多反射/折射次数无关
+        scene.set_max_depth(32);
+        assert_eq!(scene.roulette(&ray(0), None, 20, 1.0), Some(1.0));
+
+        // 贡献很小的分支只有少数存活, 但存活后的权重补偿了被舍弃的部分, 期望不变
+        let count = 100000;
+        let throughput = MIN_THROUGHPUT / 20.0;
+        let factors: Vec<Float> = (0..count)
+            .filter_map(|i| scene.roulette(&ray(i), None, 1, throughput))
+            .collect();
+        assert!(factors.len() < count / 10);
+        let mean = factors.iter().sum::<Float>() / count as Float;
+        assert!((mean - 1.0).abs() < 0.05, "{}", mean);
+    }
+}
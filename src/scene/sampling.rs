@@ -0,0 +1,253 @@
+// 像素内的采样模式: 每次采样需要光线方向的角度和光线起点在像素内的位置,
+// 不同的策略决定这些值在 sample_count 次采样中怎样分布
+
+use super::Scene;
+use crate::float::Float;
+use crate::rng::SceneRng;
+use rand::Rng;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SamplingStrategy {
+    // 把圆周等分为 sample_count 份, 每份随机取一个角度, 光线都从像素的左上角出发
+    Uniform,
+    // 角度同样分层抖动, 起点在整个像素内随机抖动, 形状的边缘被平滑
+    Jittered,
+    // 把像素分成 n×n 个子像素, 第 i 次采样从第 i % (n×n) 个子像素内的随机位置出发, 角度分层
+    // 采样数为 n×n 的整数倍时每个子像素的采样数相同
+    Grid(u32),
+    // 角度同样分层抖动, 起点在像素内按接近正方形的网格分层抖动,
+    // 角度与起点所在的格子随机配对, 形状的边缘也会被平滑
+    Stratified,
+    // 低差异序列(拟蒙特卡洛): 角度和起点分别取以 2、3、5 为底的 Halton 序列,
+    // 每个像素整体随机平移一次(Cranley-Patterson 旋转), 相同采样数下噪点比随机抖动更少
+    Halton,
+    // 蓝噪声抖动: 角度分层, 每个像素的平移量取自像素坐标上的 R2 序列, 相邻像素的误差互相抵消,
+    // 剩下的噪点是高频的蓝噪声, 采样数很少时看起来比白噪声舒服得多; 起点在像素内同样按 R2 序列分布
+    // 不使用随机数, 输出与种子无关
+    BlueNoise,
+}
+
+// R2 序列的两个增量: 1/g 和 1/g², g 为塑性数(x³ = x + 1 的实根)
+#[allow(clippy::excessive_precision)]
+const R2_ALPHA: (Float, Float) = (0.754_877_666_246_692_7, 0.569_840_290_998_053_3);
+
+// 一个像素的采样点
+pub(super) struct PixelSampler {
+    strategy: SamplingStrategy,
+    count: u32,
+    // 分层采样时起点网格的列数, 以及每次采样使用的格子
+    columns: u32,
+    cells: Vec<u32>,
+    // Halton 序列每一维的随机平移, 蓝噪声抖动时为角度和起点的平移
+    rotation: [Float; 3],
+}
+
+impl PixelSampler {
+    // (x, y) 为像素坐标
+    pub(super) fn new(
+        strategy: SamplingStrategy,
+        count: u32,
+        x: Float,
+        y: Float,
+        rng: &mut SceneRng,
+    ) -> PixelSampler {
+        let mut sampler = PixelSampler {
+            strategy,
+            count,
+            columns: 1,
+            cells: vec![],
+            rotation: [0.0; 3],
+        };
+        if strategy == SamplingStrategy::Halton {
+            for value in sampler.rotation.iter_mut() {
+                *value = rng.gen_range(0.0..1.0);
+            }
+        } else if strategy == SamplingStrategy::BlueNoise {
+            let offset = (x * R2_ALPHA.0 + y * R2_ALPHA.1).fract();
+            sampler.rotation = [
+                offset,
+                (offset + R2_ALPHA.0).fract(),
+                (offset + R2_ALPHA.1).fract(),
+            ];
+        } else if strategy == SamplingStrategy::Stratified {
+            let columns = (count as Float).sqrt().ceil().max(1.0) as u32;
+            let rows = count.div_ceil(columns);
+            // 打乱所有的格子, 前 count 个分给各次采样
+            let mut cells: Vec<u32> = (0..columns * rows).collect();
+            for i in (1..cells.len()).rev() {
+                let j = rng.gen_range(0..=i as u32) as usize;
+                cells.swap(i, j);
+            }
+            sampler.columns = columns;
+            sampler.cells = cells;
+        }
+        sampler
+    }
+
+    // 第 i 次采样的 (角度, 起点的 x 偏移, 起点的 y 偏移), 角度以圆周为 1, 偏移在 [0, 1) 内
+    pub(super) fn sample(&self, i: u32, rng: &mut SceneRng) -> (Float, Float, Float) {
+        match self.strategy {
+            SamplingStrategy::Uniform => (self.stratum(i, rng), 0.0, 0.0),
+            SamplingStrategy::Jittered => {
+                let u = self.stratum(i, rng);
+                (u, rng.gen_range(0.0..1.0), rng.gen_range(0.0..1.0))
+            }
+            SamplingStrategy::Grid(n) => {
+                let u = self.stratum(i, rng);
+                let n = n.max(1);
+                let cell = i % (n * n);
+                let ox = ((cell % n) as Float + rng.gen_range(0.0..1.0)) / n as Float;
+                let oy = ((cell / n) as Float + rng.gen_range(0.0..1.0)) / n as Float;
+                (u, ox, oy)
+            }
+            SamplingStrategy::Stratified => {
+                let u = self.stratum(i, rng);
+                let cell = self.cells[i as usize];
+                let rows = self.cells.len() as u32 / self.columns;
+                let ox = ((cell % self.columns) as Float + rng.gen_range(0.0..1.0))
+                    / self.columns as Float;
+                let oy = ((cell / self.columns) as Float + rng.gen_range(0.0..1.0)) / rows as Float;
+                (u, ox, oy)
+            }
+            SamplingStrategy::Halton => {
+                let [ru, rx, ry] = self.rotation;
+                (
+                    (radical_inverse(2, i) + ru).fract(),
+                    (radical_inverse(3, i) + rx).fract(),
+                    (radical_inverse(5, i) + ry).fract(),
+                )
+            }
+            SamplingStrategy::BlueNoise => {
+                let [ru, rx, ry] = self.rotation;
+                let i = i as Float;
+                (
+                    (i + ru) / self.count as Float,
+                    (rx + i * R2_ALPHA.0).fract(),
+                    (ry + i * R2_ALPHA.1).fract(),
+                )
+            }
+        }
+    }
+
+    // 圆周等分为 count 份, 在第 i 份内随机取一个角度
+    fn stratum(&self, i: u32, rng: &mut SceneRng) -> Float {
+        (i as Float + rng.gen_range(0.0..1.0)) / self.count as Float
+    }
+}
+
+// 把 index 的 base 进制表示按小数点镜像, 得到 [0, 1) 内的 Halton 序列的第 index 项
+fn radical_inverse(base: u32, mut index: u32) -> Float {
+    let mut result = 0.0;
+    let mut scale = 1.0 / base as Float;
+    while index > 0 {
+        result += (index % base) as Float * scale;
+        index /= base;
+        scale /= base as Float;
+    }
+    result
+}
+
+impl Scene {
+    // 设置像素内的采样模式, 默认为 SamplingStrategy::Uniform
+    // 梯度域渲染要求相邻像素使用相同的光线, 总是使用 Uniform
+    pub fn set_sampling(&mut self, strategy: SamplingStrategy) {
+        self.sampling = strategy;
+        self.invalidate();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shape::Rect;
+
+    #[test]
+    fn stratified() {
+        let mut rng = SceneRng::new(Some(1), 0);
+        let sampler = PixelSampler::new(SamplingStrategy::Stratified, 16, 0.0, 0.0, &mut rng);
+        let mut cells = [0; 16];
+        for i in 0..16 {
+            let (u, ox, oy) = sampler.sample(i, &mut rng);
+            // 角度落在第 i 个区间, 每个格子恰好一个起点
+            assert!(u >= i as Float / 16.0 && u < (i + 1) as Float / 16.0);
+            cells[(oy * 4.0) as usize * 4 + (ox * 4.0) as usize] += 1;
+        }
+        assert!(cells.iter().all(|&count| count == 1));
+
+        let sampler = PixelSampler::new(SamplingStrategy::Uniform, 4, 0.0, 0.0, &mut rng);
+        assert_eq!(sampler.sample(3, &mut rng).1, 0.0);
+    }
+
+    #[test]
+    fn halton() {
+        assert_eq!(radical_inverse(2, 6), 0.375);
+        assert!((radical_inverse(3, 5) - 7.0 / 9.0).abs() < 1e-6);
+
+        // 采样数为 2 的幂时, 角度在每个区间内恰好一个
+        let mut rng = SceneRng::new(Some(1), 0);
+        let sampler = PixelSampler::new(SamplingStrategy::Halton, 8, 0.0, 0.0, &mut rng);
+        let mut strata = [0; 8];
+        for i in 0..8 {
+            let (u, ox, oy) = sampler.sample(i, &mut rng);
+            let u = (u - sampler.rotation[0]).rem_euclid(1.0);
+            strata[(u * 8.0) as usize] += 1;
+            assert!((0.0..1.0).contains(&ox) && (0.0..1.0).contains(&oy));
+        }
+        assert!(strata.iter().all(|&count| count == 1));
+    }
+
+    #[test]
+    fn blue_noise() {
+        // 相邻像素的角度平移量相差很大, 每个像素的角度仍然分层
+        let mut rng = SceneRng::new(None, 0);
+        let offset = |x: Float, y: Float, rng: &mut SceneRng| {
+            PixelSampler::new(SamplingStrategy::BlueNoise, 4, x, y, rng).rotation[0]
+        };
+        let center = offset(5.0, 5.0, &mut rng);
+        for &(x, y) in [(4.0, 5.0), (6.0, 5.0), (5.0, 4.0), (5.0, 6.0)].iter() {
+            let distance = (offset(x, y, &mut rng) - center).abs();
+            assert!(distance.min(1.0 - distance) > 0.2);
+        }
+
+        let sampler = PixelSampler::new(SamplingStrategy::BlueNoise, 4, 5.0, 5.0, &mut rng);
+        for i in 0..4 {
+            let (u, ox, oy) = sampler.sample(i, &mut rng);
+            assert_eq!((u * 4.0) as u32, i);
+            assert!((0.0..1.0).contains(&ox) && (0.0..1.0).contains(&oy));
+        }
+    }
+
+    #[test]
+    fn grid() {
+        let mut rng = SceneRng::new(Some(2), 0);
+        let sampler = PixelSampler::new(SamplingStrategy::Grid(3), 18, 0.0, 0.0, &mut rng);
+        let mut cells = [0; 9];
+        for i in 0..18 {
+            let (_, ox, oy) = sampler.sample(i, &mut rng);
+            cells[(oy * 3.0) as usize * 3 + (ox * 3.0) as usize] += 1;
+        }
+        assert!(cells.iter().all(|&count| count == 2));
+    }
+
+    #[test]
+    fn smooth_edges() {
+        // 竖直的边穿过像素中间时, 起点在像素内抖动的策略得到介于两侧之间的值
+        let mut scene = Scene::new(8, 8);
+        scene.add_shape(Box::new(Rect::new(0.0, 4.0, 0.0, 3.5, 8.0, 1.0)));
+        scene.set_seed(4);
+        scene.set_sample_count(64);
+        scene.set_max_step(64);
+        for &strategy in [
+            SamplingStrategy::Jittered,
+            SamplingStrategy::Grid(4),
+            SamplingStrategy::Stratified,
+        ]
+        .iter()
+        {
+            scene.set_sampling(strategy);
+            let image = scene.render_radiance();
+            let edge = image[4 * 8 + 3].r;
+            assert!(edge > 0.6 && edge < 0.9, "{:?} {}", strategy, edge);
+        }
+    }
+}
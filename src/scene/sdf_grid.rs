@@ -0,0 +1,127 @@
+// 距离场网格缓存: 渲染前把整个场景的 sdf 烘焙到覆盖画面的网格上, 步进时对网格做双线性插值,
+// 代替计算所有形状的 sdf; 形状很多或者 CSG 节点很多的静态场景中可以大幅减少每次步进的开销
+// 插值只是近似值, 离形状很近、在形状里面或者在网格外面时仍然计算精确的 sdf,
+// 所以命中点、材质和透明形状里面的步进都与不使用缓存时一样精确
+
+use super::Scene;
+use crate::float::consts::SQRT_2;
+use crate::float::Float;
+
+pub(super) struct SdfGrid {
+    // 格子的边长(像素)
+    cell: Float,
+    columns: usize,
+    rows: usize,
+    // 网格顶点 (i * cell, j * cell) 处的 sd, 按行排列
+    values: Vec<Float>,
+}
+
+impl SdfGrid {
+    pub(super) fn bake(scene: &Scene, cell: Float) -> SdfGrid {
+        let columns = (scene.width as Float / cell).ceil() as usize + 1;
+        let rows = (scene.height as Float / cell).ceil() as usize + 1;
+        let mut values = Vec::with_capacity(columns * rows);
+        for j in 0..rows {
+            for i in 0..columns {
+                values.push(scene.sdf(i as Float * cell, j as Float * cell).sd);
+            }
+        }
+        SdfGrid {
+            cell,
+            columns,
+            rows,
+            values,
+        }
+    }
+
+    // (x, y) 处到最近的形状的距离的下限, 需要计算精确的 sdf 时返回 None
+    pub(super) fn distance(&self, x: Float, y: Float) -> Option<Float> {
+        let (u, v) = (x / self.cell, y / self.cell);
+        if !(u >= 0.0 && v >= 0.0) {
+            return None;
+        }
+        let (i, j) = (u as usize, v as usize);
+        if i + 1 >= self.columns || j + 1 >= self.rows {
+            return None;
+        }
+        let (fu, fv) = (u - i as Float, v - j as Float);
+        let index = j * self.columns + i;
+        let top = self.values[index] * (1.0 - fu) + self.values[index + 1] * fu;
+        let bottom = self.values[index + self.columns] * (1.0 - fu)
+            + self.values[index + self.columns + 1] * fu;
+        let sd = top * (1.0 - fv) + bottom * fv;
+
+        // sdf 的梯度长度不超过 1, 顶点离 (x, y) 不超过一个格子的对角线,
+        // 所以插值与精确值相差不超过对角线的长度, 减去它得到下限
+        let error = self.cell * SQRT_2;
+        if sd > 2.0 * error {
+            Some(sd - error)
+        } else {
+            None
+        }
+    }
+}
+
+impl Scene {
+    // 开启距离场网格缓存, cell 为格子的边长(像素), 例如 1.0 为每个像素一个格子, 2.0 为一半的分辨率
+    // 传入 None 关闭; 格子越小越精确, 烘焙越慢, 占用的内存也越多
+    pub fn set_sdf_grid(&mut self, cell: Option<Float>) {
+        self.sdf_grid_cell = cell.filter(|&cell| cell > 0.0);
+        self.sdf_grid.take();
+        self.invalidate();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shape::{Circle, Rect, Shapes};
+
+    #[test]
+    fn lower_bound() {
+        let mut scene = Scene::new(48, 32);
+        scene.add_shape(Box::new(Circle::new(12.0, 16.0, 5.0, 1.0)));
+        scene.add_shape(Shapes::subtract(
+            Box::new(Rect::new(34.0, 14.0, 0.4, 6.0, 4.0, 1.0)),
+            Box::new(Circle::new(36.0, 12.0, 3.0, 0.0)),
+        ));
+        let grid = SdfGrid::bake(&scene, 2.0);
+
+        let mut cached = 0;
+        for j in 0..200 {
+            for i in 0..300 {
+                let (x, y) = (i as Float * 0.17, j as Float * 0.17);
+                if let Some(sd) = grid.distance(x, y) {
+                    assert!(sd > 0.0 && sd <= scene.sdf(x, y).sd);
+                    cached += 1;
+                }
+            }
+        }
+        assert!(cached > 0);
+        // 在形状里面或者在网格外面时需要计算精确的 sdf
+        assert_eq!(grid.distance(12.0, 16.0), None);
+        assert_eq!(grid.distance(-1.0, 16.0), None);
+        assert_eq!(grid.distance(47.0, 33.0), None);
+    }
+
+    #[test]
+    fn render() {
+        let mut scene = Scene::new(48, 32);
+        scene.add_shape(Box::new(Circle::new(12.0, 16.0, 5.0, 1.0)));
+        scene.add_shape(Box::new(Rect::new(34.0, 14.0, 0.4, 6.0, 4.0, 0.5)));
+        scene.set_deterministic(Some(2));
+        scene.set_sample_count(16);
+        scene.set_max_step(64);
+        let expected = scene.render_radiance();
+
+        // 命中仍由精确的 sdf 判断, 只有贴着形状边缘的少数光线可能不同
+        scene.set_sdf_grid(Some(1.0));
+        let image = scene.render_radiance();
+        let different = image
+            .iter()
+            .zip(expected.iter())
+            .filter(|(&a, &b)| (a - b).map(Float::abs).max_component() > 1e-3)
+            .count();
+        assert!(different < image.len() / 50);
+    }
+}
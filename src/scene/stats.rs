@@ -0,0 +1,132 @@
+// 渲染的统计数据: 收敛曲线、光量直方图、光路的反射/折射次数和每个区块的渲染时间
+// 用来调查渲染的质量和性能, 开启 plotters 特性后可以用 crate::plot 画成图表
+
+use super::Scene;
+use crate::color::Color;
+use crate::diff::difference;
+use crate::float::Float;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+// 一个区块的渲染时间
+#[derive(Clone, Debug, PartialEq)]
+pub struct TileTiming {
+    // 区块左上角的像素坐标和区块的大小
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub duration: Duration,
+}
+
+impl Scene {
+    // 渲染 passes 遍, 每一遍使用不同的帧号(见 set_frame), 把结果逐遍平均
+    // reference 不为空时, 返回每一遍平均后的图片与 reference 之间的 RMSE
+    // 否则返回每一遍与上一遍平均的结果之间的 RMSE, 比 passes 少一项
+    pub fn convergence(&mut self, passes: usize, reference: Option<&[Color]>) -> Vec<Float> {
+        let frame = self.frame;
+        let mut sum = vec![Color::BLACK; self.width as usize * self.height as usize];
+        let mut previous: Option<Vec<Color>> = None;
+        let mut errors = vec![];
+
+        for pass in 0..passes {
+            self.frame = frame + pass as u64;
+            for (sum, value) in sum.iter_mut().zip(self.render_radiance()) {
+                *sum += value;
+            }
+            let average: Vec<Color> = sum.iter().map(|&sum| sum / (pass + 1) as Float).collect();
+            match (reference, &previous) {
+                (Some(reference), _) => errors.push(difference(&average, reference).rmse),
+                (None, Some(previous)) => errors.push(difference(&average, previous).rmse),
+                (None, None) => {}
+            }
+            previous = Some(average);
+        }
+
+        self.frame = frame;
+        errors
+    }
+
+    // 渲染每个像素的光量, 统计亮度落在 [0, max] 上 bins 个等宽区间内的像素数
+    // 大于 max 的亮度计入最后一个区间
+    pub fn radiance_histogram(&self, bins: usize, max: Float) -> Vec<usize> {
+        let mut histogram = vec![0; bins.max(1)];
+        let last = histogram.len() - 1;
+        for value in self.render_radiance() {
+            let bin = (value.luminance().max(0.0) / max * histogram.len() as Float) as usize;
+            histogram[bin.min(last)] += 1;
+        }
+        histogram
+    }
+
+    // 统计每次采样的光路实际达到的反射/折射次数, 第 i 项为最深达到 i 次的采样数, 共 max_depth + 1 项
+    // 最后一个不为 0 的下标就是场景实际用到的深度; 最后一项不为 0 时可能有光路被 max_depth 截断
+    pub fn depth_histogram(&self) -> Vec<usize> {
+        let mut histogram = vec![0; self.max_depth + 1];
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let mut records = vec![];
+                self.sample_with(x as Float, y as Float, None, Some(&mut records));
+                for record in records {
+                    let depth = record.segments.iter().map(|s| s.depth).max().unwrap_or(0);
+                    histogram[depth.min(self.max_depth)] += 1;
+                }
+            }
+        }
+        histogram
+    }
+
+    // 按 tile_size 大小的区块渲染整张图片, 记录每个区块花费的时间
+    // 只统计逐像素采样的时间, 不包括路径引导的学习
+    pub fn tile_timings(&self, tile_size: u32) -> Vec<TileTiming> {
+        let guiding_field = self.build_guiding_field();
+        let mut timings = vec![];
+
+        for tile in self.split_tiles(tile_size) {
+            let start = Instant::now();
+            for (x, y) in tile.pixels() {
+                black_box(self.sample(x as Float, y as Float, guiding_field.as_ref()));
+            }
+            timings.push(TileTiming {
+                x: tile.x,
+                y: tile.y,
+                width: tile.width,
+                height: tile.height,
+                duration: start.elapsed(),
+            });
+        }
+
+        timings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shape::Circle;
+
+    #[test]
+    fn stats() {
+        let mut scene = Scene::new(20, 12);
+        scene.add_shape(Box::new(Circle::new(10.0, 6.0, 3.0, 1.0)));
+        scene.set_deterministic(Some(1));
+
+        let reference = scene.render_radiance();
+        let errors = scene.convergence(3, Some(&reference));
+        assert_eq!(errors.len(), 3);
+        assert_eq!(errors[0], 0.0);
+        assert_eq!(scene.convergence(3, None).len(), 2);
+
+        let histogram = scene.radiance_histogram(4, 1.0);
+        assert_eq!(histogram.iter().sum::<usize>(), 20 * 12);
+
+        // 没有镜面和透明的形状, 光路都不会反射/折射
+        let depths = scene.depth_histogram();
+        assert_eq!(depths.len(), 9);
+        assert_eq!(depths[0], 20 * 12 * 64);
+
+        let timings = scene.tile_timings(8);
+        assert_eq!(timings.len(), 3 * 2);
+        assert_eq!((timings[2].width, timings[5].height), (4, 4));
+    }
+}
@@ -0,0 +1,254 @@
+// 按区块(tile)调度渲染: 画面被分成 tile_size × tile_size 的区块, 每个区块作为一批光线追踪
+// 多个线程依次领取还没有渲染的区块, 每完成一个区块就通知调用者,
+// 调用者可以借此显示进度, 或者在区块之间取消渲染
+// 每个像素的结果与区块的大小、线程数和完成的顺序无关
+
+use super::photon::PhotonMap;
+use super::wavefront::RayBatch;
+use super::Scene;
+use crate::color::Color;
+use crate::float::Float;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+// 画面上的一个矩形区块, (x, y) 为左上角的像素坐标
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Tile {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Tile {
+    pub fn pixel_count(&self) -> usize {
+        self.width as usize * self.height as usize
+    }
+
+    // 区块内的像素坐标, 按行排列
+    pub fn pixels(&self) -> impl Iterator<Item = (u32, u32)> {
+        let tile = *self;
+        (tile.y..tile.y + tile.height)
+            .flat_map(move |y| (tile.x..tile.x + tile.width).map(move |x| (x, y)))
+    }
+}
+
+// 一个区块渲染完成的事件
+pub struct TileEvent<'a> {
+    pub tile: Tile,
+    // 包括这个区块在内已经完成的区块数, 以及区块的总数
+    pub completed: usize,
+    pub total: usize,
+    // 区块内每个像素的光量, 按行排列
+    pub radiance: &'a [Color],
+    // 开启自适应采样时, 区块内每个像素使用的采样数, 按行排列
+    pub samples: Option<&'a [u32]>,
+}
+
+impl Scene {
+    // 设置渲染时区块的边长(像素), 默认为 32
+    pub fn set_tile_size(&mut self, size: u32) {
+        self.tile_size = size.max(1);
+    }
+
+    // 渲染时使用的区块, 从左到右、从上到下排列
+    pub fn tiles(&self) -> Vec<Tile> {
+        self.split_tiles(self.tile_size)
+    }
+
+    pub(super) fn split_tiles(&self, size: u32) -> Vec<Tile> {
+        let size = size.max(1);
+        let mut tiles = vec![];
+        for y in (0..self.height).step_by(size as usize) {
+            for x in (0..self.width).step_by(size as usize) {
+                tiles.push(Tile {
+                    x,
+                    y,
+                    width: size.min(self.width - x),
+                    height: size.min(self.height - y),
+                });
+            }
+        }
+        tiles
+    }
+
+    // 按区块渲染出每个像素的光量, 按行排列
+    // 每完成一个区块调用一次 on_tile(多个线程渲染时也不会同时调用), 返回 false 时取消渲染,
+    // 已经开始的区块仍会完成, 但不会再通知; 取消时返回 None
+    // 梯度域渲染需要整张图片才能重建, 开启时整张图片作为一个区块
+    pub fn render_tiles<F>(&self, mut on_tile: F) -> Option<Vec<Color>>
+    where
+        F: FnMut(&TileEvent) -> bool + Send,
+    {
+        let photon_map = self.build_photon_map();
+        if self.gradient_domain {
+            let mut buffer = self.render_gradient_domain();
+            let tile = Tile {
+                x: 0,
+                y: 0,
+                width: self.width,
+                height: self.height,
+            };
+            add_photons(&mut buffer, &tile, photon_map.as_ref());
+            let event = TileEvent {
+                tile,
+                completed: 1,
+                total: 1,
+                radiance: &buffer,
+                samples: None,
+            };
+            return if on_tile(&event) { Some(buffer) } else { None };
+        }
+
+        let guiding_field = self.build_guiding_field();
+        let guide = guiding_field.as_ref();
+        let photon_map = photon_map.as_ref();
+        let tiles = self.tiles();
+        let total = tiles.len();
+        let queue = Mutex::new(tiles.into_iter());
+        let cancelled = AtomicBool::new(false);
+        // 把区块写回整张图片和通知调用者都在锁内进行
+        let buffer = vec![Color::BLACK; self.width as usize * self.height as usize];
+        let output = Mutex::new((buffer, 0, on_tile));
+
+        let worker = || {
+            let mut batch = RayBatch::default();
+            let mut pixels = vec![];
+            let mut samples = vec![];
+            while !cancelled.load(Ordering::Relaxed) {
+                let next = queue.lock().unwrap().next();
+                let tile = match next {
+                    Some(tile) => tile,
+                    None => break,
+                };
+                pixels.clear();
+                pixels.resize(tile.pixel_count(), Color::BLACK);
+                match self.adaptive.as_ref() {
+                    Some(adaptive) => {
+                        samples.resize(tile.pixel_count(), 0);
+                        self.render_tile_adaptive(
+                            adaptive,
+                            &tile,
+                            &mut pixels,
+                            &mut samples,
+                            guide,
+                        );
+                    }
+                    None => self.render_tile(&mut batch, &tile, &mut pixels, guide),
+                }
+                add_photons(&mut pixels, &tile, photon_map);
+
+                let mut output = output.lock().unwrap();
+                let (buffer, completed, on_tile) = &mut *output;
+                if cancelled.load(Ordering::Relaxed) {
+                    break;
+                }
+                for (row, values) in pixels.chunks(tile.width as usize).enumerate() {
+                    let start = (tile.y as usize + row) * self.width as usize + tile.x as usize;
+                    buffer[start..start + values.len()].copy_from_slice(values);
+                }
+                *completed += 1;
+                let event = TileEvent {
+                    tile,
+                    completed: *completed,
+                    total,
+                    radiance: &pixels,
+                    samples: self.adaptive.map(|_| &samples[..]),
+                };
+                if !on_tile(&event) {
+                    cancelled.store(true, Ordering::Relaxed);
+                }
+            }
+        };
+
+        let threads = self.thread_count().min(total);
+        if threads <= 1 {
+            worker();
+        } else {
+            thread::scope(|scope| {
+                for _ in 0..threads {
+                    scope.spawn(worker);
+                }
+            });
+        }
+
+        if cancelled.into_inner() {
+            return None;
+        }
+        Some(output.into_inner().unwrap().0)
+    }
+}
+
+// 把光子图收集到的光加到区块内的每个像素上
+fn add_photons(pixels: &mut [Color], tile: &Tile, photon_map: Option<&PhotonMap>) {
+    if let Some(photon_map) = photon_map {
+        for (value, (x, y)) in pixels.iter_mut().zip(tile.pixels()) {
+            *value += photon_map.gather(x as Float, y as Float);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shape::{Circle, Rect};
+
+    #[test]
+    fn tiles() {
+        let mut scene = Scene::new(70, 40);
+        let tiles = scene.tiles();
+        assert_eq!(tiles.len(), 6);
+        assert_eq!(
+            tiles[5],
+            Tile {
+                x: 64,
+                y: 32,
+                width: 6,
+                height: 8
+            }
+        );
+        let count: usize = tiles.iter().map(Tile::pixel_count).sum();
+        assert_eq!(count, 70 * 40);
+
+        scene.set_tile_size(16);
+        assert_eq!(scene.tiles().len(), 15);
+        let pixels: Vec<_> = scene.tiles()[14].pixels().collect();
+        assert_eq!(pixels.len(), 6 * 8);
+        assert_eq!(pixels[0], (64, 32));
+        assert_eq!(pixels[6], (64, 33));
+    }
+
+    #[test]
+    fn render_tiles() {
+        let mut scene = Scene::new(40, 24);
+        scene.add_shape(Box::new(Circle::new(12.0, 12.0, 4.0, 1.0)));
+        scene.add_shape(Box::new(Rect::new(30.0, 8.0, 0.3, 4.0, 2.0, 0.0)));
+        scene.set_photon_mapping(2000, 2.0);
+        scene.set_deterministic(Some(7));
+        scene.set_tile_size(16);
+        let expected = scene.render_tiles(|_| true).unwrap();
+
+        // 区块的大小和线程数不影响结果, 每个区块通知一次
+        scene.set_tile_size(8);
+        scene.set_threads(3);
+        let mut events = vec![];
+        let image = scene.render_tiles(|event| {
+            assert_eq!(event.radiance.len(), event.tile.pixel_count());
+            events.push((event.completed, event.total));
+            true
+        });
+        assert_eq!(image.unwrap(), expected);
+        assert_eq!(events.len(), 15);
+        assert!(events.iter().enumerate().all(|(i, &e)| e == (i + 1, 15)));
+
+        // 取消之后不再通知
+        let mut count = 0;
+        let image = scene.render_tiles(|_| {
+            count += 1;
+            false
+        });
+        assert!(image.is_none());
+        assert_eq!(count, 1);
+    }
+}
@@ -0,0 +1,359 @@
+// 波前(wavefront)式的批量追踪: 不再逐个像素地完整追踪每条光线,
+// 而是把一批光线按阶段处理: 生成 -> 步进 -> 着色 -> 产生次级光线
+// 光线的状态按字段分开存储(SoA), 每个阶段都是对连续数组的简单循环,
+// 缓存更友好, 以后也方便改成 SIMD 或者映射到 GPU
+// 命中透明形状时分出的反射和折射光线作为新的光线追加到同一批里, 最后自底向上汇总
+// 每个区块(见 tiles.rs)的光线作为一批, 结果与逐像素的 sample 完全相同
+
+use super::guiding::GuidingField;
+use super::sampling::PixelSampler;
+use super::tiles::Tile;
+use super::{pixel_stream, Ray, Scene};
+use crate::color::Color;
+use crate::float::consts::TAU;
+use crate::float::Float;
+use crate::rng::SceneRng;
+use crate::shape::SdfResult;
+
+// 没有对应的次级光线
+const NO_CHILD: usize = usize::MAX;
+// 一批最初的光线的数量上限, 采样数很大时区块内的像素分成几批追踪, 限制内存占用
+// 一个像素的所有光线总是在同一批里
+const MAX_BATCH_RAYS: usize = 1 << 16;
+
+// 一批光线, 第 i 条光线属于区块内的第 i / sample_count 个像素
+#[derive(Default)]
+pub(super) struct RayBatch {
+    ox: Vec<Float>,
+    oy: Vec<Float>,
+    dx: Vec<Float>,
+    dy: Vec<Float>,
+    // 光线的权重: 最初的光线为路径引导的权重, 次级光线为反射或折射的比例
+    weight: Vec<Float>,
+    // 在当前这一段光路上已经走过的距离和步进次数
+    distance: Vec<Float>,
+    steps: Vec<usize>,
+    // 光线在形状外面(1.0)还是透明形状里面(-1.0)
+    side: Vec<Float>,
+    // 经过色散之后只携带的颜色通道, None 表示所有通道
+    channel: Vec<Option<usize>>,
+    // 已经分叉的次数, 之前各段光路的总长度和从最初的光线到这里累积的权重
+    depth: Vec<usize>,
+    traveled: Vec<Float>,
+    throughput: Vec<Float>,
+    // 命中透明形状后分出的反射和折射光线, 发生色散时每个颜色通道各一组
+    children: Vec<[[usize; 2]; 3]>,
+    // 光线在透明形状里面时每个颜色通道被吸收后剩下的比例
+    absorbance: Vec<Color>,
+    // 次级光线带回的光在到达这条光线的起点之前穿过的滤色片的透射率
+    transmittance: Vec<Color>,
+    // 光线自己带回的光量, 汇总之后为包含次级光线并乘以权重的光量
+    radiance: Vec<Color>,
+    // 最初的光线对应的那次采样中对光源显式采样带回的光量
+    direct: Vec<Color>,
+}
+
+impl RayBatch {
+    fn clear(&mut self) {
+        self.ox.clear();
+        self.oy.clear();
+        self.dx.clear();
+        self.dy.clear();
+        self.weight.clear();
+        self.distance.clear();
+        self.steps.clear();
+        self.side.clear();
+        self.channel.clear();
+        self.depth.clear();
+        self.traveled.clear();
+        self.throughput.clear();
+        self.children.clear();
+        self.absorbance.clear();
+        self.transmittance.clear();
+        self.radiance.clear();
+        self.direct.clear();
+    }
+
+    fn push(&mut self, x: Float, y: Float, dx: Float, dy: Float, weight: Float, direct: Color) {
+        self.push_ray((x, y, dx, dy, 1.0), None, weight, 0, 0.0, 1.0);
+        self.direct.push(direct);
+    }
+
+    // 追加一条光线, 返回它的下标
+    fn push_ray(
+        &mut self,
+        ray: Ray,
+        channel: Option<usize>,
+        weight: Float,
+        depth: usize,
+        traveled: Float,
+        throughput: Float,
+    ) -> usize {
+        let (x, y, dx, dy, side) = ray;
+        self.ox.push(x);
+        self.oy.push(y);
+        self.dx.push(dx);
+        self.dy.push(dy);
+        self.weight.push(weight);
+        self.distance.push(0.0);
+        self.steps.push(0);
+        self.side.push(side);
+        self.channel.push(channel);
+        self.depth.push(depth);
+        self.traveled.push(traveled);
+        self.throughput.push(throughput);
+        self.children.push([[NO_CHILD; 2]; 3]);
+        self.absorbance.push(Color::WHITE);
+        self.transmittance.push(Color::WHITE);
+        self.radiance.push(Color::BLACK);
+        self.len() - 1
+    }
+
+    fn len(&self) -> usize {
+        self.ox.len()
+    }
+}
+
+impl Scene {
+    // 渲染一个区块, 区块内每个像素的光量按行写入 pixels
+    pub(super) fn render_tile(
+        &self,
+        batch: &mut RayBatch,
+        tile: &Tile,
+        pixels: &mut [Color],
+        guide: Option<&GuidingField>,
+    ) {
+        let samples = (self.sample_count as usize).max(1);
+        let batch_pixels = (MAX_BATCH_RAYS / samples).max(1);
+        let coords: Vec<_> = tile.pixels().collect();
+        for (pixels, coords) in pixels
+            .chunks_mut(batch_pixels)
+            .zip(coords.chunks(batch_pixels))
+        {
+            batch.clear();
+            self.generate_rays(batch, coords, guide);
+            self.march_rays(batch);
+
+            // 按采样的顺序累加, 保证与逐像素的 sample 得到完全相同的结果
+            // 最初的光线排在批的最前面, 后面的次级光线已经汇总到它们里面了
+            let primary = batch
+                .radiance
+                .chunks(samples)
+                .zip(batch.direct.chunks(samples));
+            for (value, (radiance, direct)) in pixels.iter_mut().zip(primary) {
+                let mut sum = Color::BLACK;
+                for (&r, &direct) in radiance.iter().zip(direct) {
+                    sum += r + direct;
+                }
+                *value = sum / self.sample_count as Float;
+            }
+        }
+    }
+
+    // 生成阶段: 为每个像素按顺序生成 sample_count 条光线
+    // 与 sample_with 使用相同的随机数和方向
+    fn generate_rays(
+        &self,
+        batch: &mut RayBatch,
+        pixels: &[(u32, u32)],
+        guide: Option<&GuidingField>,
+    ) {
+        let frame_offset = self.frame_offset();
+        for &(x, y) in pixels {
+            let (x, y) = (x as Float, y as Float);
+            let mut rng = SceneRng::new(self.seed, pixel_stream(x, y));
+            let sampler = PixelSampler::new(self.sampling, self.sample_count, x, y, &mut rng);
+            for i in 0..self.sample_count {
+                let (u, ox, oy) = sampler.sample(i, &mut rng);
+                let u = (u + frame_offset).fract();
+                let (degree, weight) = match guide {
+                    Some(guide) => guide.sample(x, y, u),
+                    None => (TAU * u, 1.0),
+                };
+                let (dx, dy) = self.direction(degree);
+                let (x, y) = (x + ox, y + oy);
+                let direct = self.sample_direct(x, y, i, self.sample_count, &mut rng);
+                batch.push(x, y, dx, dy, weight, direct);
+            }
+        }
+    }
+
+    // 步进阶段和着色阶段交替进行, 直到所有光线都命中了不透明的形状、离开了场景或者用完了步进次数
+    // 命中透明形状的光线分出反射和折射两条次级光线, 追加到批的末尾继续步进
+    fn march_rays(&self, batch: &mut RayBatch) {
+        let max_distance = self.max_distance();
+        let mut active: Vec<usize> = (0..batch.len()).collect();
+        let mut next = Vec::with_capacity(active.len());
+        let mut hits: Vec<(usize, SdfResult)> = vec![];
+        let mut misses = vec![];
+
+        while !active.is_empty() {
+            // 步进: 计算所有活动光线当前位置的 sdf, 把光线分为命中、离开和继续步进三类
+            for &i in active.iter() {
+                if batch.steps[i] == self.max_step {
+                    continue;
+                }
+                let px = batch.ox[i] + (batch.dx[i] * batch.distance[i]);
+                let py = batch.oy[i] + (batch.dy[i] * batch.distance[i]);
+                let result = self.march_sdf(px, py);
+                if batch.depth[i] == 0
+                    && batch.distance[i] == 0.0
+                    && result.sd < 0.0
+                    && result.material.is_transparent()
+                {
+                    batch.side[i] = -1.0;
+                }
+                let sd = result.sd * batch.side[i];
+                if sd < self.epsilon {
+                    hits.push((i, result));
+                    continue;
+                }
+                batch.distance[i] += self.march_step(px, py, batch.dx[i], batch.dy[i], sd);
+                batch.steps[i] += 1;
+                if batch.distance[i] >= max_distance {
+                    misses.push(i);
+                } else {
+                    next.push(i);
+                }
+            }
+
+            // 着色
+            for (i, result) in hits.drain(..) {
+                let ray = (
+                    batch.ox[i],
+                    batch.oy[i],
+                    batch.dx[i],
+                    batch.dy[i],
+                    batch.side[i],
+                );
+                let depth = batch.depth[i];
+                // 与 trace_pixel 相同, 开启光子映射时反射/折射之后命中的形状发出的光由光子图负责
+                let bounce = self.bounce(
+                    &result,
+                    ray,
+                    batch.distance[i],
+                    batch.channel[i],
+                    depth,
+                    batch.traveled[i],
+                    batch.throughput[i],
+                    self.next_event && depth == 0,
+                    self.photon_count == 0,
+                );
+                batch.radiance[i] = bounce.radiance;
+                batch.absorbance[i] = bounce.absorbance;
+                batch.transmittance[i] = bounce.transmittance;
+
+                // 产生次级光线: 反射和折射的光线都从命中的位置出发
+                let traveled = batch.traveled[i] + batch.distance[i];
+                for (g, group) in bounce.groups.iter().enumerate() {
+                    let group = match group {
+                        Some(group) => group,
+                        None => continue,
+                    };
+                    for (slot, branch) in group.branches.iter().enumerate() {
+                        if let Some((ray, weight, throughput)) = *branch {
+                            let child = batch.push_ray(
+                                ray,
+                                group.channel,
+                                weight,
+                                depth + 1,
+                                traveled,
+                                throughput,
+                            );
+                            batch.children[i][g][slot] = child;
+                            next.push(child);
+                        }
+                    }
+                }
+            }
+            for i in misses.drain(..) {
+                let (x, y, dx, dy) = (batch.ox[i], batch.oy[i], batch.dx[i], batch.dy[i]);
+                batch.radiance[i] = self.shade_miss(x, y, dx, dy);
+            }
+
+            std::mem::swap(&mut active, &mut next);
+            next.clear();
+        }
+
+        // 汇总: 次级光线总是排在产生它的光线后面, 倒序处理时它们已经汇总好了
+        // 按颜色通道、反射、折射的顺序累加, 与递归的 trace_ray 保持相同的运算顺序
+        for i in (0..batch.len()).rev() {
+            let mut radiance = batch.radiance[i];
+            for group in batch.children[i] {
+                let children = group.iter().filter(|&&child| child != NO_CHILD);
+                let mut light = Color::BLACK;
+                let mut mask = Color::WHITE;
+                for &child in children {
+                    light += batch.radiance[child];
+                    // 包含所有通道的光线分成了只带一个通道的光线, 只保留那个通道的光
+                    if let (None, Some(c)) = (batch.channel[i], batch.channel[child]) {
+                        mask = Color::primary(c);
+                    }
+                }
+                radiance += light * mask * batch.transmittance[i];
+            }
+            batch.radiance[i] = radiance * batch.absorbance[i] * batch.weight[i];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::Environment;
+    use crate::material::Material;
+    use crate::shape::{Circle, EmissionProfile, Rect, Shapes, Surface};
+
+    #[test]
+    fn matches_per_pixel_sampling() {
+        let mut scene = Scene::new(40, 24);
+        scene.add_shape(Box::new(Circle::new(10.0, 12.0, 4.0, 1.0)));
+        scene.add_shape(Shapes::profiled(
+            Box::new(Rect::new(30.0, 8.0, 0.3, 4.0, 2.0, 2.0)),
+            EmissionProfile::CosineLobe(2.0),
+        ));
+        scene.add_filter(Box::new(Circle::new(20.0, 16.0, 5.0, 0.0)), 0.5);
+        scene.add_shape(Shapes::absorbing(
+            Shapes::refractive(Box::new(Circle::new(20.0, 6.0, 4.0, 0.0)), 1.5),
+            Color::new(0.1, 0.05, 0.0),
+        ));
+        scene.add_shape(Shapes::dispersive(
+            Box::new(Circle::new(32.0, 18.0, 3.0, 0.0)),
+            1.4,
+            1.5,
+            1.6,
+        ));
+        scene.add_shape(Box::new(Surface::new(
+            Box::new(Rect::new(4.0, 20.0, 0.0, 3.0, 1.0, 0.0)),
+            Material::mirror(0.8),
+        )));
+        scene.set_environment(Environment::Constant(Color::gray(0.1)));
+        scene.set_path_guiding(true);
+        scene.set_deterministic(Some(3));
+        scene.max_step = 32;
+
+        let guiding_field = scene.build_guiding_field();
+        let image = scene.render_radiance();
+        for y in 0..24 {
+            for x in 0..40 {
+                let expected = scene.sample(x as Float, y as Float, guiding_field.as_ref());
+                assert_eq!(image[y * 40 + x], expected);
+            }
+        }
+    }
+
+    #[test]
+    fn large_sample_count() {
+        // 采样数超过一批的上限时每个像素单独成批, 结果不变
+        let mut scene = Scene::new(3, 2);
+        scene.add_shape(Box::new(Circle::new(1.0, 4.0, 1.5, 1.0)));
+        scene.set_deterministic(Some(5));
+        scene.set_sample_count(MAX_BATCH_RAYS as u32 + 100);
+
+        let image = scene.render_radiance();
+        for (i, value) in image.iter().enumerate() {
+            let (x, y) = ((i % 3) as Float, (i / 3) as Float);
+            assert_eq!(*value, scene.sample(x, y, None));
+        }
+    }
+}
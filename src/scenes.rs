@@ -0,0 +1,346 @@
+// 内置的标准场景, 基准测试、测试和新用户都可以从同样可复现的场景开始
+// 所有场景的坐标都按画面大小缩放
+
+use crate::color::Color;
+use crate::float::consts::TAU;
+use crate::float::Float;
+use crate::rng::CounterRng;
+use crate::scene::Scene;
+use crate::shape::{Capsule, Circle, Rect, Shape, Shapes, Triangle};
+use rand::Rng;
+
+// 玻璃的折射率
+const GLASS_ETA: Float = 1.5;
+// 棱镜对红光和蓝光的折射率与 GLASS_ETA 的差, 比真实的玻璃大一些, 让色散更明显
+const PRISM_DISPERSION: Float = 0.03;
+// 放置一个形状时最多尝试的次数, 超过后放弃这个形状
+const PLACEMENT_TRIES: usize = 100;
+
+// 画面中央的一个圆形光源
+pub fn single_emitter(width: u32, height: u32) -> Scene {
+    let (w, h) = (width as Float, height as Float);
+    let mut scene = Scene::new(width, height);
+    scene.add_shape(Box::new(Circle::new(w * 0.5, h * 0.5, w.min(h) * 0.1, 2.0)));
+    scene
+}
+
+// 展示并集、交集和差集的场景
+pub fn csg_showcase(width: u32, height: u32) -> Scene {
+    let (w, h) = (width as Float, height as Float);
+    let r = w.min(h) * 0.12;
+    let mut scene = Scene::new(width, height);
+
+    scene.add_shape(Shapes::union(
+        Box::new(Circle::new(w * 0.2, h * 0.5, r, 1.0)),
+        Box::new(Circle::new(w * 0.2 + r, h * 0.5, r, 1.0)),
+    ));
+    scene.add_shape(Shapes::intersect(
+        Box::new(Circle::new(w * 0.5 - r * 0.5, h * 0.5, r, 1.0)),
+        Box::new(Circle::new(w * 0.5 + r * 0.5, h * 0.5, r, 1.0)),
+    ));
+    scene.add_shape(Shapes::subtract(
+        Box::new(Circle::new(w * 0.8 - r * 0.5, h * 0.5, r, 1.0)),
+        Box::new(Circle::new(w * 0.8 + r * 0.5, h * 0.5, r, 1.0)),
+    ));
+    scene
+}
+
+// 光源照射透镜和三棱镜
+pub fn lens_and_prism(width: u32, height: u32) -> Scene {
+    let (w, h) = (width as Float, height as Float);
+    let r = w.min(h);
+    let mut scene = Scene::new(width, height);
+
+    scene.add_shape(Box::new(Capsule::new(
+        w * 0.05,
+        h * 0.3,
+        w * 0.05,
+        h * 0.7,
+        r * 0.02,
+        3.0,
+    )));
+    // 玻璃做的双凸透镜(两个圆的交集)和三棱镜
+    scene.add_shape(Shapes::refractive(
+        Shapes::intersect(
+            Box::new(Circle::new(w * 0.35 - r * 0.3, h * 0.5, r * 0.35, 0.0)),
+            Box::new(Circle::new(w * 0.35 + r * 0.3, h * 0.5, r * 0.35, 0.0)),
+        ),
+        GLASS_ETA,
+    ));
+    scene.add_shape(Shapes::dispersive(
+        Box::new(Triangle::new(
+            w * 0.7,
+            h * 0.3,
+            w * 0.82,
+            h * 0.65,
+            w * 0.58,
+            h * 0.65,
+            0.0,
+        )),
+        GLASS_ETA - PRISM_DISPERSION,
+        GLASS_ETA,
+        GLASS_ETA + PRISM_DISPERSION,
+    ));
+    scene
+}
+
+// 类似 Cornell box 的房间: 四面墙, 天花板上的灯, 房间里的两个盒子
+pub fn cornell_box(width: u32, height: u32) -> Scene {
+    let (w, h) = (width as Float, height as Float);
+    let wall = w.min(h) * 0.02;
+    let mut scene = Scene::new(width, height);
+
+    scene.add_shape(Box::new(Rect::new(w * 0.5, wall, 0.0, w * 0.5, wall, 0.0)));
+    scene.add_shape(Box::new(Rect::new(
+        w * 0.5,
+        h - wall,
+        0.0,
+        w * 0.5,
+        wall,
+        0.0,
+    )));
+    scene.add_shape(Box::new(Rect::new(wall, h * 0.5, 0.0, wall, h * 0.5, 0.0)));
+    scene.add_shape(Box::new(Rect::new(
+        w - wall,
+        h * 0.5,
+        0.0,
+        wall,
+        h * 0.5,
+        0.0,
+    )));
+    scene.add_shape(Box::new(Rect::new(
+        w * 0.5,
+        wall * 2.5,
+        0.0,
+        w * 0.15,
+        wall * 0.5,
+        4.0,
+    )));
+    scene.add_shape(Box::new(Rect::new(
+        w * 0.35,
+        h * 0.75,
+        0.3,
+        w * 0.08,
+        h * 0.15,
+        0.0,
+    )));
+    scene.add_shape(Box::new(Rect::new(
+        w * 0.65,
+        h * 0.82,
+        -0.3,
+        w * 0.08,
+        h * 0.08,
+        0.0,
+    )));
+    scene
+}
+
+// 大量小光源组成的网格, 用来测试形状很多时的性能, 相邻光源的色相相差黄金角
+pub fn many_lights(width: u32, height: u32, count: u32) -> Scene {
+    let (w, h) = (width as Float, height as Float);
+    let columns = (count as Float).sqrt().ceil().max(1.0) as u32;
+    let rows = count.div_ceil(columns);
+    let r = (w / columns as Float).min(h / rows as Float) * 0.2;
+    let mut scene = Scene::new(width, height);
+
+    for i in 0..count {
+        let column = i % columns;
+        let row = i / columns;
+        let x = w * (column as Float + 0.5) / columns as Float;
+        let y = h * (row as Float + 0.5) / rows as Float;
+        let emissive = Color::from_hsv(i as Float * 137.5, 0.6, 0.5 + (i % 4) as Float * 0.5);
+        scene.add_shape(Box::new(Circle::new(x, y, r, emissive)));
+    }
+    scene
+}
+
+// 随机场景生成器, 同样的种子和参数总是生成同样的场景
+// 可以用来做生成艺术, 也可以用来对渲染器做大规模的压力测试
+pub struct SceneGenerator {
+    seed: u64,
+    shape_count: usize,
+    // 形状大小(外接圆半径)相对于画面短边的范围
+    min_size: Float,
+    max_size: Float,
+    // 发光形状所占的比例, 至少会有一个发光形状
+    emitter_ratio: Float,
+    // 发光强度的上限, 发光形状的强度在 [0.5, max_emissive] 之间
+    max_emissive: Float,
+    // 形状之间至少保持的间隔(相对于画面短边)
+    spacing: Float,
+}
+
+impl SceneGenerator {
+    pub fn new(seed: u64) -> SceneGenerator {
+        SceneGenerator {
+            seed,
+            shape_count: 8,
+            min_size: 0.03,
+            max_size: 0.12,
+            emitter_ratio: 0.3,
+            max_emissive: 2.0,
+            spacing: 0.02,
+        }
+    }
+
+    pub fn set_shape_count(&mut self, count: usize) {
+        self.shape_count = count;
+    }
+
+    pub fn set_size_range(&mut self, min_size: Float, max_size: Float) {
+        self.min_size = min_size;
+        self.max_size = max_size.max(min_size);
+    }
+
+    pub fn set_emitter_ratio(&mut self, ratio: Float) {
+        self.emitter_ratio = ratio.clamp(0.0, 1.0);
+    }
+
+    pub fn set_max_emissive(&mut self, emissive: Float) {
+        self.max_emissive = emissive.max(0.5);
+    }
+
+    pub fn set_spacing(&mut self, spacing: Float) {
+        self.spacing = spacing;
+    }
+
+    // 生成场景: 形状完整地落在画面内, 并且互相之间不重叠
+    // 画面放不下所有形状时, 生成的形状会少于 shape_count
+    pub fn generate(&self, width: u32, height: u32) -> Scene {
+        let mut rng = CounterRng::new(self.seed, 0);
+        let (w, h) = (width as Float, height as Float);
+        let unit = w.min(h);
+        let mut scene = Scene::new(width, height);
+
+        // 已经放置的形状的外接圆
+        let mut placed: Vec<(Float, Float, Float)> = vec![];
+        for i in 0..self.shape_count {
+            let size = unit * rng.gen_range(self.min_size..=self.max_size);
+            let spacing = unit * self.spacing;
+            let position = (0..PLACEMENT_TRIES)
+                .map(|_| {
+                    (
+                        rng.gen_range(size..(w - size).max(size + 1.0)),
+                        rng.gen_range(size..(h - size).max(size + 1.0)),
+                    )
+                })
+                .find(|&(x, y)| {
+                    placed.iter().all(|&(px, py, pr)| {
+                        ((x - px).powi(2) + (y - py).powi(2)).sqrt() > size + pr + spacing
+                    })
+                });
+            let (x, y) = match position {
+                Some(position) => position,
+                None => continue,
+            };
+            placed.push((x, y, size));
+
+            let emissive = if i == 0 || rng.gen_range(0.0..1.0) < self.emitter_ratio {
+                rng.gen_range(0.5..=self.max_emissive)
+            } else {
+                0.0
+            };
+            scene.add_shape(random_shape(&mut rng, x, y, size, emissive));
+        }
+
+        scene
+    }
+}
+
+// 在以 (x, y) 为圆心, size 为半径的圆内随机生成一个形状
+fn random_shape(
+    rng: &mut impl Rng,
+    x: Float,
+    y: Float,
+    size: Float,
+    emissive: Float,
+) -> Box<dyn Shape> {
+    let theta = rng.gen_range(0.0..TAU);
+    match rng.gen_range(0..4) {
+        0 => Box::new(Circle::new(x, y, size, emissive)),
+        1 => {
+            // 半对角线不超过 size
+            let angle = rng.gen_range(0.2..1.37 as Float);
+            Box::new(Rect::new(
+                x,
+                y,
+                theta,
+                size * angle.cos(),
+                size * angle.sin(),
+                emissive,
+            ))
+        }
+        2 => {
+            let r = size * rng.gen_range(0.2..0.5);
+            let (sin, cos) = theta.sin_cos();
+            let l = size - r;
+            Box::new(Capsule::new(
+                x - cos * l,
+                y - sin * l,
+                x + cos * l,
+                y + sin * l,
+                r,
+                emissive,
+            ))
+        }
+        _ => {
+            // 顶点按 Triangle 要求的顺序排列
+            let vertex = |i: Float| {
+                let angle = theta + TAU * i / 3.0;
+                (x + size * angle.cos(), y + size * angle.sin())
+            };
+            let (a, b, c) = (vertex(0.0), vertex(1.0), vertex(2.0));
+            Box::new(Triangle::new(a.0, a.1, b.0, b.1, c.0, c.1, emissive))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scenes_render() {
+        let scenes = [
+            single_emitter(32, 24),
+            csg_showcase(32, 24),
+            lens_and_prism(32, 24),
+            cornell_box(32, 24),
+            many_lights(32, 24, 10),
+        ];
+        for scene in scenes.iter() {
+            assert!(scene.render().iter().any(|&value| value > 0));
+        }
+    }
+
+    #[test]
+    fn include_scene() {
+        let mut scene = crate::include_scene!("scenes/demo.ron");
+        assert_eq!((scene.width(), scene.height()), (64, 48));
+
+        let orange = Color::new(1.0, 0.6, 0.2);
+        let mut expected = Scene::new(64, 48);
+        expected.add_shape(Box::new(Circle::new(16.0, 24.0, 6.0, 2.0)));
+        expected.add_shape(Shapes::subtract(
+            Box::new(Rect::new(44.0, 24.0, 0.3, 8.0, 6.0, orange)),
+            Box::new(Circle::new(44.0, 24.0, 3.0, 0.0)),
+        ));
+        expected.add_shape(Box::new(Capsule::new(8.0, 40.0, 56.0, 40.0, 1.5, 0.0)));
+        scene.set_deterministic(Some(0));
+        expected.set_deterministic(Some(0));
+        assert!(scene.render() == expected.render());
+    }
+
+    #[test]
+    fn generator_is_reproducible() {
+        let render = |seed| {
+            let mut scene = SceneGenerator::new(seed).generate(32, 24);
+            scene.set_deterministic(Some(0));
+            scene.render()
+        };
+        let image = render(1);
+        assert!(image.iter().any(|&value| value > 0));
+        assert!(image == render(1));
+        assert!(image != render(2));
+    }
+}
@@ -0,0 +1,261 @@
+// 渲染设置和预设
+// 预设把采样数、步进次数等质量相关的设置以及输出图片时的去噪和曝光打包在一起, 在质量和速度之间切换只需要一次调用
+
+use crate::denoise::Denoiser;
+use crate::float::Float;
+use std::fmt::Display;
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+use std::str::FromStr;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RenderSettings {
+    // 每个像素的采样数
+    pub sample_count: u32,
+    // 每条光线最多步进的次数
+    pub max_step: usize,
+    // 光线最多反射/折射的次数
+    pub max_depth: usize,
+    // 光线离形状的距离小于 epsilon 时认为命中了形状
+    pub epsilon: Float,
+    pub path_guiding: bool,
+    pub gradient_domain: bool,
+    // 光子映射使用的光子数, 为 0 时不进行光子映射
+    pub photon_count: usize,
+    pub photon_radius: Float,
+    // 反射/折射超过 roulette_depth 次之后, 每条光线以 roulette_survival 的概率继续追踪
+    // 默认为 usize::MAX, 不进行轮盘赌
+    pub roulette_depth: usize,
+    pub roulette_survival: Float,
+    // 输出图片前使用的去噪器, 为 None 时不去噪
+    pub denoiser: Option<Denoiser>,
+    // 输出图片时的曝光补偿(EV)
+    pub exposure: Float,
+}
+
+impl Default for RenderSettings {
+    // Scene::new 创建的场景使用这里的设置
+    fn default() -> RenderSettings {
+        RenderSettings {
+            sample_count: 64,
+            max_step: 10,
+            max_depth: 8,
+            epsilon: 1e-6,
+            path_guiding: false,
+            gradient_domain: false,
+            photon_count: 0,
+            photon_radius: 2.0,
+            roulette_depth: usize::MAX,
+            roulette_survival: 0.5,
+            denoiser: None,
+            exposure: 0.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Preset {
+    // 快速预览
+    Draft,
+    // 一般的最终输出
+    Production,
+    // 用于展示的高质量输出, 速度很慢
+    Showcase,
+}
+
+impl Preset {
+    pub fn settings(&self) -> RenderSettings {
+        match self {
+            Preset::Draft => RenderSettings {
+                sample_count: 16,
+                max_step: 16,
+                epsilon: 1e-3,
+                // 采样数很少, 用较大的窗口去噪
+                denoiser: Some(Denoiser::new(3)),
+                ..RenderSettings::default()
+            },
+            Preset::Production => RenderSettings {
+                sample_count: 128,
+                max_step: 64,
+                epsilon: 1e-5,
+                path_guiding: true,
+                denoiser: Some(Denoiser::new(1)),
+                ..RenderSettings::default()
+            },
+            Preset::Showcase => RenderSettings {
+                sample_count: 1024,
+                max_step: 256,
+                max_depth: 16,
+                epsilon: 1e-6,
+                path_guiding: true,
+                photon_count: 1_000_000,
+                photon_radius: 1.5,
+                ..RenderSettings::default()
+            },
+        }
+    }
+}
+
+impl RenderSettings {
+    // 把设置保存为文本文件, 每行一项 "key = value"
+    // 去噪器保存为 "radius sigma_spatial sigma_color sigma_guide", 不去噪时为 "none"
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let text = format!(
+            "sample_count = {}\n\
+             max_step = {}\n\
+             max_depth = {}\n\
+             epsilon = {}\n\
+             path_guiding = {}\n\
+             gradient_domain = {}\n\
+             photon_count = {}\n\
+             photon_radius = {}\n\
+             roulette_depth = {}\n\
+             roulette_survival = {}\n\
+             denoiser = {}\n\
+             exposure = {}\n",
+            self.sample_count,
+            self.max_step,
+            self.max_depth,
+            self.epsilon,
+            self.path_guiding,
+            self.gradient_domain,
+            self.photon_count,
+            self.photon_radius,
+            self.roulette_depth,
+            self.roulette_survival,
+            match &self.denoiser {
+                Some(d) => format!(
+                    "{} {} {} {}",
+                    d.radius, d.sigma_spatial, d.sigma_color, d.sigma_guide
+                ),
+                None => "none".to_string(),
+            },
+            self.exposure,
+        );
+        fs::write(path, text)
+    }
+
+    // 从 save 保存的文件读取设置, 文件中没有的项使用默认值, 以 # 开头的行是注释
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<RenderSettings> {
+        RenderSettings::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse(text: &str) -> Result<RenderSettings> {
+        let mut settings = RenderSettings::default();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = match line.find('=') {
+                Some(index) => (line[..index].trim(), line[index + 1..].trim()),
+                None => return Err(invalid_data(format!("expected `key = value`: {}", line))),
+            };
+            match key {
+                "sample_count" => settings.sample_count = parse_value(key, value)?,
+                "max_step" => settings.max_step = parse_value(key, value)?,
+                "max_depth" => settings.max_depth = parse_value(key, value)?,
+                "epsilon" => settings.epsilon = parse_value(key, value)?,
+                "path_guiding" => settings.path_guiding = parse_value(key, value)?,
+                "gradient_domain" => settings.gradient_domain = parse_value(key, value)?,
+                "photon_count" => settings.photon_count = parse_value(key, value)?,
+                "photon_radius" => settings.photon_radius = parse_value(key, value)?,
+                "roulette_depth" => settings.roulette_depth = parse_value(key, value)?,
+                "roulette_survival" => settings.roulette_survival = parse_value(key, value)?,
+                "denoiser" => settings.denoiser = parse_denoiser(value)?,
+                "exposure" => settings.exposure = parse_value(key, value)?,
+                _ => return Err(invalid_data(format!("unknown setting: {}", key))),
+            }
+        }
+        Ok(settings)
+    }
+}
+
+fn parse_value<T>(key: &str, value: &str) -> Result<T>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    value
+        .parse()
+        .map_err(|e| invalid_data(format!("invalid value for {}: {}", key, e)))
+}
+
+// "none" 表示不去噪, 只有半径时其余参数使用默认值
+fn parse_denoiser(value: &str) -> Result<Option<Denoiser>> {
+    if value == "none" {
+        return Ok(None);
+    }
+    let values: Vec<&str> = value.split_whitespace().collect();
+    match values[..] {
+        [radius] => Ok(Some(Denoiser::new(parse_value("denoiser", radius)?))),
+        [radius, sigma_spatial, sigma_color, sigma_guide] => Ok(Some(Denoiser {
+            radius: parse_value("denoiser", radius)?,
+            sigma_spatial: parse_value("denoiser", sigma_spatial)?,
+            sigma_color: parse_value("denoiser", sigma_color)?,
+            sigma_guide: parse_value("denoiser", sigma_guide)?,
+        })),
+        _ => Err(invalid_data(format!(
+            "invalid value for denoiser: {}",
+            value
+        ))),
+    }
+}
+
+fn invalid_data(message: String) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_and_load() {
+        let path = std::env::temp_dir().join("colorful-light2d-settings-test.txt");
+        let settings = Preset::Showcase.settings();
+        settings.save(&path).unwrap();
+        assert_eq!(RenderSettings::load(&path).unwrap(), settings);
+        let settings = RenderSettings {
+            exposure: -1.5,
+            ..Preset::Draft.settings()
+        };
+        settings.save(&path).unwrap();
+        assert_eq!(RenderSettings::load(&path).unwrap(), settings);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn parse() {
+        let settings = RenderSettings::parse("# draft\nsample_count = 8\n\nmax_step=20\n").unwrap();
+        assert_eq!(settings.sample_count, 8);
+        assert_eq!(settings.max_step, 20);
+        assert_eq!(settings.max_depth, RenderSettings::default().max_depth);
+        assert_eq!(settings.epsilon, RenderSettings::default().epsilon);
+
+        assert_eq!(
+            RenderSettings::parse("sample_count = 4096")
+                .unwrap()
+                .sample_count,
+            4096
+        );
+        assert!(RenderSettings::parse("sample_count = -1").is_err());
+        let settings =
+            RenderSettings::parse("roulette_depth = 2\nroulette_survival = 0.8").unwrap();
+        assert_eq!(
+            (settings.roulette_depth, settings.roulette_survival),
+            (2, 0.8)
+        );
+        let settings = RenderSettings::parse("denoiser = 2\nexposure = 1").unwrap();
+        assert_eq!(settings.denoiser, Some(Denoiser::new(2)));
+        assert_eq!(settings.exposure, 1.0);
+        assert_eq!(
+            RenderSettings::parse("denoiser = none").unwrap().denoiser,
+            None
+        );
+        assert!(RenderSettings::parse("denoiser = 2 1.0").is_err());
+        assert!(RenderSettings::parse("samples = 8").is_err());
+        assert!(RenderSettings::parse("sample_count").is_err());
+    }
+}
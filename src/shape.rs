@@ -1,297 +1,5595 @@
+use crate::color::Color;
+use crate::float::consts::{FRAC_1_SQRT_2, FRAC_PI_2, PI, SQRT_2, TAU};
+use crate::float::Float;
+use crate::material::Material;
+use crate::rng::hash_unit;
+
+// 估计面积等数值时, 包围盒的长边被分成的格数
+const MEASURE_GRID: usize = 512;
+// 用中心差分计算梯度时的步长
+const GRADIENT_DELTA: Float = 1e-3;
+// 计算椭圆上最近点时迭代的次数
+const ELLIPSE_ITERATIONS: usize = 4;
+// 用二次贝塞尔曲线近似三次贝塞尔曲线时允许的最大偏差
+const FLATTEN_TOLERANCE: Float = 1e-3;
+// 细分三次贝塞尔曲线的最大层数, 最多得到 2^FLATTEN_DEPTH 段
+const FLATTEN_DEPTH: usize = 10;
+// 计算超椭圆上最近点时, 先在每一段边界上等间隔取点的个数, 以及之后牛顿迭代的次数
+const SUPERELLIPSE_SAMPLES: usize = 16;
+const SUPERELLIPSE_ITERATIONS: usize = 4;
+// 值噪声的两个分量对坐标的雅可比矩阵的范数上限(坐标以格子为单位):
+// 每个偏导数不超过格点值之差 2 乘以五次插值函数导数的最大值 15/8, 四个偏导数的平方和开根号
+const VALUE_NOISE_LIPSCHITZ: Float = 7.5;
 
 pub struct SdfResult {
     // 带符号距离 signed distance
-    pub sd: f64,
+    pub sd: Float,
+
+    // 离 (x, y) 最近的表面的材质
+    pub material: Material,
 
-    // 自发光强度
-    pub emissive: f64,
+    // 自发光的角度分布
+    pub profile: EmissionProfile,
 }
 
-pub trait Shape {
-    fn sdf(&self, x: f64, y: f64) -> SdfResult;
+// 自发光的角度分布, 根据出射方向与表面法线的夹角计算发光强度的比例
+#[derive(Clone)]
+pub enum EmissionProfile {
+    // 各个方向相同, 像漫射的面板
+    Uniform,
+    // cos(θ)^n, n 越大光越集中在法线方向, 像 LED
+    CosineLobe(Float),
+    // 自定义曲线: θ 从 0 (法线方向) 到 π/2 (掠射方向) 等间隔取值, 中间线性插值
+    Curve(std::sync::Arc<[Float]>),
 }
 
-pub struct UnionShape {
-    shape1: Box<dyn Shape>,
-    shape2: Box<dyn Shape>,
+impl EmissionProfile {
+    // 计算出射方向与法线夹角的余弦为 cos_theta 时, 发光强度的比例
+    pub fn evaluate(&self, cos_theta: Float) -> Float {
+        let cos_theta = cos_theta.clamp(0.0, 1.0);
+        match self {
+            EmissionProfile::Uniform => 1.0,
+            EmissionProfile::CosineLobe(exponent) => cos_theta.powf(*exponent),
+            EmissionProfile::Curve(values) => match values.len() {
+                0 => 0.0,
+                1 => values[0],
+                len => {
+                    let t = cos_theta.acos() / FRAC_PI_2 * (len - 1) as Float;
+                    let i = (t as usize).min(len - 2);
+                    let f = t - i as Float;
+                    values[i] * (1.0 - f) + values[i + 1] * f
+                }
+            },
+        }
+    }
 }
 
-impl Shape for UnionShape {
-    fn sdf(&self, x: f64, y: f64) -> SdfResult {
-        let result1 = self.shape1.sdf(x, y);
-        let result2 = self.shape2.sdf(x, y);
+// 多边形和路径的填充规则, 决定自相交的轮廓和嵌套的轮廓中哪些区域属于形状内部
+// 只影响 sdf 的符号, 距离总是到最近的边的距离
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FillRule {
+    // 环绕数不为 0 的区域是内部
+    NonZero,
+    // 环绕数为奇数的区域是内部, SVG 中用来表示带洞的路径
+    EvenOdd,
+}
 
-        return if result1.sd < result2.sd {
-            result1
-        } else {
-            result2
-        };
+impl FillRule {
+    pub fn is_inside(&self, winding: i32) -> bool {
+        match self {
+            FillRule::NonZero => winding != 0,
+            FillRule::EvenOdd => winding % 2 != 0,
+        }
+    }
+
+    // 判断点是否在由若干条闭合轮廓组成的区域内, 每条轮廓的最后一个点会连回第一个点
+    pub fn contains(&self, contours: &[Vec<(Float, Float)>], x: Float, y: Float) -> bool {
+        let winding = contours
+            .iter()
+            .map(|points| winding_number(points, x, y))
+            .sum();
+        self.is_inside(winding)
     }
 }
 
-pub struct IntersectShape {
-    shape1: Box<dyn Shape>,
-    shape2: Box<dyn Shape>,
+// 闭合轮廓绕点 (x, y) 的环绕数, 轮廓方向相反时符号相反
+pub fn winding_number(points: &[(Float, Float)], x: Float, y: Float) -> i32 {
+    let mut winding = 0;
+    for (i, &(ax, ay)) in points.iter().enumerate() {
+        let (bx, by) = points[(i + 1) % points.len()];
+        // 点在边 ab 的哪一侧
+        let side = (bx - ax) * (y - ay) - (by - ay) * (x - ax);
+        if ay <= y {
+            if by > y && side > 0.0 {
+                winding += 1;
+            }
+        } else if by <= y && side < 0.0 {
+            winding -= 1;
+        }
+    }
+    winding
 }
 
-impl Shape for IntersectShape {
-    fn sdf(&self, x: f64, y: f64) -> SdfResult {
-        let mut result1 = self.shape1.sdf(x, y);
-        let mut result2 = self.shape2.sdf(x, y);
+// 轴对齐的包围盒
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min_x: Float,
+    pub min_y: Float,
+    pub max_x: Float,
+    pub max_y: Float,
+}
 
-        return if result1.sd > result2.sd {
-            result2.sd = result1.sd;
-            result2
-        } else {
-            result1.sd = result2.sd;
-            result1
-        };
+impl Aabb {
+    pub fn new(min_x: Float, min_y: Float, max_x: Float, max_y: Float) -> Aabb {
+        Aabb {
+            min_x,
+            min_y,
+            max_x,
+            max_y,
+        }
+    }
+
+    // 以 (x, y) 为中心, 半宽 hx, 半高 hy 的包围盒
+    pub fn around(x: Float, y: Float, hx: Float, hy: Float) -> Aabb {
+        Aabb::new(x - hx, y - hy, x + hx, y + hy)
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb::new(
+            self.min_x.min(other.min_x),
+            self.min_y.min(other.min_y),
+            self.max_x.max(other.max_x),
+            self.max_y.max(other.max_y),
+        )
+    }
+
+    // 两个包围盒不相交时, 返回的包围盒宽或高为负
+    pub fn intersection(&self, other: &Aabb) -> Aabb {
+        Aabb::new(
+            self.min_x.max(other.min_x),
+            self.min_y.max(other.min_y),
+            self.max_x.min(other.max_x),
+            self.max_y.min(other.max_y),
+        )
+    }
+
+    pub fn width(&self) -> Float {
+        self.max_x - self.min_x
+    }
+
+    pub fn height(&self) -> Float {
+        self.max_y - self.min_y
+    }
+
+    // (x, y) 到包围盒的距离, 在包围盒内为 0
+    // 形状在包围盒内, 所以这是到形状的距离的下限
+    pub fn distance(&self, x: Float, y: Float) -> Float {
+        let dx = (self.min_x - x).max(x - self.max_x).max(0.0);
+        let dy = (self.min_y - y).max(y - self.max_y).max(0.0);
+        (dx * dx + dy * dy).sqrt()
     }
 }
 
-pub struct SubtractShape {
-    shape1: Box<dyn Shape>,
-    shape2: Box<dyn Shape>,
+// 形状会在多个渲染线程之间共享
+pub trait Shape: Send + Sync {
+    fn sdf(&self, x: Float, y: Float) -> SdfResult;
+
+    // 包围整个形状的包围盒, 无界的形状(例如 Plane)返回 None
+    fn bounds(&self) -> Option<Aabb> {
+        None
+    }
+
+    // 面积, 无界的形状为无穷大
+    // 默认在包围盒内的网格上对 sdf 采样来估计, 基本形状会给出精确值
+    fn area(&self) -> Float {
+        match self.bounds() {
+            Some(bounds) => estimate_measure(self, &bounds).0,
+            None => Float::INFINITY,
+        }
+    }
+
+    // 周长, 无界的形状为无穷大
+    fn perimeter(&self) -> Float {
+        match self.bounds() {
+            Some(bounds) => estimate_measure(self, &bounds).1,
+            None => Float::INFINITY,
+        }
+    }
+
+    // 形心, 无界的形状或者面积为 0 的形状返回 None
+    fn centroid(&self) -> Option<(Float, Float)> {
+        estimate_measure(self, &self.bounds()?).2
+    }
+
+    // sdf 在 (x, y) 处的梯度, 在形状的边上就是外法线方向
+    // 默认用中心差分估计, 基本形状会给出精确值
+    fn gradient(&self, x: Float, y: Float) -> (Float, Float) {
+        let gx = self.sdf(x + GRADIENT_DELTA, y).sd - self.sdf(x - GRADIENT_DELTA, y).sd;
+        let gy = self.sdf(x, y + GRADIENT_DELTA).sd - self.sdf(x, y - GRADIENT_DELTA).sd;
+        (gx / (2.0 * GRADIENT_DELTA), gy / (2.0 * GRADIENT_DELTA))
+    }
+
+    // 是否支持解析求交, 支持时 raycast 总是返回 Some, 不相交时为无穷大
+    // 默认不支持, 实现了 raycast 的形状需要同时覆盖这个方法
+    fn supports_raycast(&self) -> bool {
+        false
+    }
+
+    // 从 (x, y) 沿单位方向 (dx, dy) 前进, 第一次穿过形状的边时走过的距离(从外面进入或者从里面离开),
+    // 不相交时为无穷大; 不支持解析求交的形状返回 None, 此时只能用光线步进
+    fn raycast(&self, _x: Float, _y: Float, _dx: Float, _dy: Float) -> Option<Float> {
+        None
+    }
 }
 
-impl Shape for SubtractShape {
-    fn sdf(&self, x: f64, y: f64) -> SdfResult {
-        let mut result1 = self.shape1.sdf(x, y);
-        let result2 = self.shape2.sdf(x, y);
-        let sd = if result1.sd > -result2.sd {
-            result1.sd
-        } else {
-            -result2.sd
-        };
-        result1.sd = sd;
+// 在包围盒内的网格上对 sdf 采样, 估计面积、周长和形心
+// 每个格子按 sdf 线性地计算被覆盖的比例, 所以形状的边不需要落在格子边上
+// 周长由 |sd| < h 的带状区域的面积除以带宽 2h 得到
+fn estimate_measure<S: Shape + ?Sized>(
+    shape: &S,
+    bounds: &Aabb,
+) -> (Float, Float, Option<(Float, Float)>) {
+    if bounds.width() <= 0.0 || bounds.height() <= 0.0 {
+        return (0.0, 0.0, None);
+    }
 
-        return result1;
+    let h = bounds.width().max(bounds.height()) / MEASURE_GRID as Float;
+    let columns = (bounds.width() / h).ceil() as usize + 2;
+    let rows = (bounds.height() / h).ceil() as usize + 2;
+    let (mut area, mut band, mut sum_x, mut sum_y) = (0.0, 0.0, 0.0, 0.0);
+    for row in 0..rows {
+        for column in 0..columns {
+            let x = bounds.min_x + (column as Float - 0.5) * h;
+            let y = bounds.min_y + (row as Float - 0.5) * h;
+            let sd = shape.sdf(x, y).sd;
+            let coverage = (0.5 - sd / h).clamp(0.0, 1.0);
+            area += coverage;
+            sum_x += coverage * x;
+            sum_y += coverage * y;
+            if sd.abs() < h {
+                band += 1.0;
+            }
+        }
     }
+
+    let centroid = if area > 0.0 {
+        Some((sum_x / area, sum_y / area))
+    } else {
+        None
+    };
+    (area * h * h, band * h / 2.0, centroid)
 }
 
-pub struct Shapes;
+// 并集、交集和差集对两个 sdf 结果的运算, CSG 组合形状和 crate::csg 共用
 
-impl Shapes {
-    pub fn union(shape1: Box<dyn Shape>, shape2: Box<dyn Shape>) -> Box<UnionShape> {
-        Box::new(UnionShape { shape1, shape2 })
+pub(crate) fn union_result(result1: SdfResult, result2: SdfResult) -> SdfResult {
+    if result1.sd < result2.sd {
+        result1
+    } else {
+        result2
     }
+}
 
-    pub fn intersect(shape1: Box<dyn Shape>, shape2: Box<dyn Shape>) -> Box<IntersectShape> {
-        Box::new(IntersectShape { shape1, shape2 })
+pub(crate) fn intersect_result(mut result1: SdfResult, mut result2: SdfResult) -> SdfResult {
+    if result1.sd > result2.sd {
+        result2.sd = result1.sd;
+        result2
+    } else {
+        result1.sd = result2.sd;
+        result1
     }
+}
 
-    pub fn subtract(shape1: Box<dyn Shape>, shape2: Box<dyn Shape>) -> Box<SubtractShape> {
-        Box::new(SubtractShape { shape1, shape2 })
+pub(crate) fn subtract_result(mut result1: SdfResult, result2: SdfResult) -> SdfResult {
+    let sd = if result1.sd > -result2.sd {
+        result1.sd
+    } else {
+        -result2.sd
+    };
+    result1.sd = sd;
+
+    result1
+}
+
+pub(crate) fn union_bounds(bounds1: Option<Aabb>, bounds2: Option<Aabb>) -> Option<Aabb> {
+    Some(bounds1?.union(&bounds2?))
+}
+
+// 多项式平滑最小值: 两个距离相差不到 k 时平滑过渡, 接缝处的距离比两者都小, 形状像融在一起
+// h 为第一个形状的权重, 自发光按同样的权重混合, 材质的其他性质和发光分布取权重大的一个
+pub(crate) fn smooth_union_result(result1: SdfResult, result2: SdfResult, k: Float) -> SdfResult {
+    if k <= 0.0 {
+        return union_result(result1, result2);
     }
+    let h = (0.5 + 0.5 * (result2.sd - result1.sd) / k).clamp(0.0, 1.0);
+    let sd = result2.sd * (1.0 - h) + result1.sd * h - k * h * (1.0 - h);
+    let emissive = result1.material.emissive * h + result2.material.emissive * (1.0 - h);
+    let mut result = if h >= 0.5 { result1 } else { result2 };
+    result.sd = sd;
+    result.material.emissive = emissive;
+    result
 }
 
-pub struct Circle {
-    ox: f64,
-    oy: f64,
-    r: f64,
-    emissive: f64,
+// 平滑最大值: 与 intersect_result 一样材质取离得近的形状, 自发光按权重混合, k 趋于 0 时与交集相同
+pub(crate) fn smooth_intersect_result(
+    result1: SdfResult,
+    result2: SdfResult,
+    k: Float,
+) -> SdfResult {
+    if k <= 0.0 {
+        return intersect_result(result1, result2);
+    }
+    // h 为第一个形状的距离的权重
+    let h = (0.5 - 0.5 * (result2.sd - result1.sd) / k).clamp(0.0, 1.0);
+    let sd = result2.sd * (1.0 - h) + result1.sd * h + k * h * (1.0 - h);
+    let emissive = result2.material.emissive * h + result1.material.emissive * (1.0 - h);
+    let mut result = if h >= 0.5 { result2 } else { result1 };
+    result.sd = sd;
+    result.material.emissive = emissive;
+    result
 }
 
-impl Circle {
-    pub fn new(ox: f64, oy: f64, r: f64, emissive: f64) -> Circle {
-        Circle {
-            ox,
-            oy,
-            r,
-            emissive,
-        }
+// 从第一个形状中平滑地减去第二个形状, 即第一个距离和第二个距离的相反数的平滑最大值, 材质总是第一个形状的
+pub(crate) fn smooth_subtract_result(
+    mut result1: SdfResult,
+    result2: SdfResult,
+    k: Float,
+) -> SdfResult {
+    if k <= 0.0 {
+        return subtract_result(result1, result2);
     }
+    let h = (0.5 - 0.5 * (result1.sd + result2.sd) / k).clamp(0.0, 1.0);
+    result1.sd = result1.sd * (1.0 - h) - result2.sd * h + k * h * (1.0 - h);
+    result1
 }
 
-impl Shape for Circle {
-    // 计算 (x, y) 点离这个圆的 SDF(也就是到这个圆的边的最近距离)
-    fn sdf(&self, x: f64, y: f64) -> SdfResult {
-        let ux = x - self.ox;
-        let uy = y - self.oy;
+// 平滑最小值比两个距离中较小的一个最多小 k / 4, 所以形状最多向外扩展 k / 4
+pub(crate) fn smooth_union_bounds(
+    bounds1: Option<Aabb>,
+    bounds2: Option<Aabb>,
+    k: Float,
+) -> Option<Aabb> {
+    let bounds = union_bounds(bounds1, bounds2)?;
+    let margin = k.max(0.0) / 4.0;
+    Some(Aabb::new(
+        bounds.min_x - margin,
+        bounds.min_y - margin,
+        bounds.max_x + margin,
+        bounds.max_y + margin,
+    ))
+}
 
-        let sd = ((ux * ux + uy * uy) as f64).sqrt() - self.r as f64;
-        return SdfResult {
-            sd,
-            emissive: self.emissive,
-        };
+pub(crate) fn intersect_bounds(bounds1: Option<Aabb>, bounds2: Option<Aabb>) -> Option<Aabb> {
+    match (bounds1, bounds2) {
+        (Some(a), Some(b)) => Some(a.intersection(&b)),
+        (Some(a), None) | (None, Some(a)) => Some(a),
+        (None, None) => None,
     }
 }
 
-pub struct Plane {
-    // 用一个点和法线来确定一个平面
-    px: f64,
-    py: f64,
-    nx: f64,
-    ny: f64,
-    emissive: f64,
+pub struct UnionShape {
+    shape1: Box<dyn Shape>,
+    shape2: Box<dyn Shape>,
 }
 
-impl Plane {
-    pub fn new(px: f64, py: f64, nx: f64, ny: f64, emissive: f64) -> Plane {
-        Plane {
-            px,
-            py,
-            nx,
-            ny,
-            emissive,
+impl Shape for UnionShape {
+    fn sdf(&self, x: Float, y: Float) -> SdfResult {
+        union_result(self.shape1.sdf(x, y), self.shape2.sdf(x, y))
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        union_bounds(self.shape1.bounds(), self.shape2.bounds())
+    }
+
+    // 与 sdf 一样取离得近的形状
+    fn gradient(&self, x: Float, y: Float) -> (Float, Float) {
+        if self.shape1.sdf(x, y).sd < self.shape2.sdf(x, y).sd {
+            self.shape1.gradient(x, y)
+        } else {
+            self.shape2.gradient(x, y)
         }
     }
 }
 
-impl Shape for Plane {
-    fn sdf(&self, x: f64, y: f64) -> SdfResult {
-        return SdfResult {
-            sd: (x - self.px) * self.nx + (y - self.py) * self.ny,
-            emissive: self.emissive,
-        };
+// 多个形状的并集, 在一个循环里依次比较, 不用为很多形状嵌套很深的 UnionShape
+// 没有形状时 sdf 为 Float::MAX, 与空的 CsgTree 一样
+pub struct UnionAllShape {
+    shapes: Vec<Box<dyn Shape>>,
+}
+
+impl UnionAllShape {
+    fn nearest(&self, x: Float, y: Float) -> Option<(SdfResult, usize)> {
+        nearest_result(self.shapes.iter().map(|shape| shape.sdf(x, y)))
     }
 }
 
-pub struct Capsule {
-    // 用两个点和半径来表示胶囊
-    ax: f64,
-    ay: f64,
-    bx: f64,
-    by: f64,
-    r: f64,
-    emissive: f64,
+impl Shape for UnionAllShape {
+    fn sdf(&self, x: Float, y: Float) -> SdfResult {
+        union_all_result(self.shapes.iter().map(|shape| shape.sdf(x, y)))
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        union_all_bounds(self.shapes.iter().map(|shape| shape.bounds()))
+    }
+
+    fn gradient(&self, x: Float, y: Float) -> (Float, Float) {
+        match self.nearest(x, y) {
+            Some((_, i)) => self.shapes[i].gradient(x, y),
+            None => (0.0, 0.0),
+        }
+    }
 }
 
-impl Capsule {
-    pub fn new(ax: f64, ay: f64, bx: f64, by: f64, r: f64, emissive: f64) -> Capsule {
-        Capsule {
-            ax,
-            ay,
-            bx,
-            by,
-            r,
-            emissive,
+// 距离最小的结果和它的下标, 距离相同时与 union_result 一样取后面的
+fn nearest_result(results: impl Iterator<Item = SdfResult>) -> Option<(SdfResult, usize)> {
+    let mut nearest: Option<(SdfResult, usize)> = None;
+    for (i, result) in results.enumerate() {
+        match &nearest {
+            Some((best, _)) if best.sd < result.sd => {}
+            _ => nearest = Some((result, i)),
         }
     }
+    nearest
 }
 
-impl Shape for Capsule {
-    fn sdf(&self, x: f64, y: f64) -> SdfResult {
-        let vx = x - self.ax;
-        let vy = y - self.ay;
-        let ux = self.bx - self.ax;
-        let uy = self.by - self.ay;
-        let t = ((vx * ux + vy * uy) / (ux * ux + uy * uy))
-            .min(1.0)
-            .max(0.0);
-        let dx = vx - ux * t;
-        let dy = vy - uy * t;
-        let segment_sd = (dx * dx + dy * dy).sqrt();
-        let capsule_sd = segment_sd - self.r;
+// 多个结果的并集, 没有结果时 sdf 为 Float::MAX
+pub(crate) fn union_all_result(results: impl Iterator<Item = SdfResult>) -> SdfResult {
+    match nearest_result(results) {
+        Some((result, _)) => result,
+        None => SdfResult {
+            sd: Float::MAX,
+            material: Material::default(),
+            profile: EmissionProfile::Uniform,
+        },
+    }
+}
 
-        SdfResult {
-            sd: capsule_sd,
-            emissive: self.emissive,
+// 多个包围盒的并集, 没有包围盒时为 None
+pub(crate) fn union_all_bounds(mut bounds: impl Iterator<Item = Option<Aabb>>) -> Option<Aabb> {
+    let first = bounds.next()?;
+    bounds.fold(first, union_bounds)
+}
+
+pub struct SmoothUnionShape {
+    shape1: Box<dyn Shape>,
+    shape2: Box<dyn Shape>,
+    k: Float,
+}
+
+impl Shape for SmoothUnionShape {
+    fn sdf(&self, x: Float, y: Float) -> SdfResult {
+        smooth_union_result(self.shape1.sdf(x, y), self.shape2.sdf(x, y), self.k)
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        smooth_union_bounds(self.shape1.bounds(), self.shape2.bounds(), self.k)
+    }
+}
+
+// 平滑最大值不小于两个距离中较大的一个, 形状只会收缩, 包围盒与交集、差集相同
+pub struct SmoothIntersectShape {
+    shape1: Box<dyn Shape>,
+    shape2: Box<dyn Shape>,
+    k: Float,
+}
+
+impl Shape for SmoothIntersectShape {
+    fn sdf(&self, x: Float, y: Float) -> SdfResult {
+        smooth_intersect_result(self.shape1.sdf(x, y), self.shape2.sdf(x, y), self.k)
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        intersect_bounds(self.shape1.bounds(), self.shape2.bounds())
+    }
+}
+
+pub struct SmoothSubtractShape {
+    shape1: Box<dyn Shape>,
+    shape2: Box<dyn Shape>,
+    k: Float,
+}
+
+impl Shape for SmoothSubtractShape {
+    fn sdf(&self, x: Float, y: Float) -> SdfResult {
+        smooth_subtract_result(self.shape1.sdf(x, y), self.shape2.sdf(x, y), self.k)
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        self.shape1.bounds()
+    }
+}
+
+pub struct IntersectShape {
+    shape1: Box<dyn Shape>,
+    shape2: Box<dyn Shape>,
+}
+
+impl Shape for IntersectShape {
+    fn sdf(&self, x: Float, y: Float) -> SdfResult {
+        intersect_result(self.shape1.sdf(x, y), self.shape2.sdf(x, y))
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        intersect_bounds(self.shape1.bounds(), self.shape2.bounds())
+    }
+
+    // 与 sdf 一样取离得远的形状
+    fn gradient(&self, x: Float, y: Float) -> (Float, Float) {
+        if self.shape1.sdf(x, y).sd > self.shape2.sdf(x, y).sd {
+            self.shape1.gradient(x, y)
+        } else {
+            self.shape2.gradient(x, y)
         }
     }
 }
 
-pub struct Rect {
-    // 矩形由中心点(cx, cy), 旋转角(theta), 半长(sx, sy) 组成
-    cx: f64,
-    cy: f64,
-    theta: f64,
-    sx: f64,
-    sy: f64,
-    emissive: f64,
-    // 圆角矩形的半径
-    r: f64,
+pub struct SubtractShape {
+    shape1: Box<dyn Shape>,
+    shape2: Box<dyn Shape>,
 }
 
-impl Rect {
-    pub fn new(cx: f64, cy: f64, theta: f64, sx: f64, sy: f64, emissive: f64) -> Rect {
-        Rect {
-            cx,
-            cy,
-            theta,
-            sx,
-            sy,
-            emissive,
-            r: 0.0,
+impl Shape for SubtractShape {
+    fn sdf(&self, x: Float, y: Float) -> SdfResult {
+        subtract_result(self.shape1.sdf(x, y), self.shape2.sdf(x, y))
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        self.shape1.bounds()
+    }
+
+    // 在被减去的形状的边上, 梯度是它的梯度取反
+    fn gradient(&self, x: Float, y: Float) -> (Float, Float) {
+        if self.shape1.sdf(x, y).sd > -self.shape2.sdf(x, y).sd {
+            self.shape1.gradient(x, y)
+        } else {
+            let (gx, gy) = self.shape2.gradient(x, y);
+            (-gx, -gy)
         }
     }
 }
 
-impl Shape for Rect {
-    fn sdf(&self, x: f64, y: f64) -> SdfResult {
-        let sin_theta = self.theta.sin();
-        let cos_theta = self.theta.cos();
-        let dx = ((x - self.cx) * cos_theta + (y - self.cy) * sin_theta).abs() - self.sx;
-        let dy = ((y - self.cy) * cos_theta - (x - self.cx) * sin_theta).abs() - self.sy;
-        let ax = dx.max(0.0);
-        let ay = dy.max(0.0);
-        let sd = dx.max(dy).min(0.0) + (ax * ax + ay * ay).sqrt();
-        return SdfResult {
-            sd,
-            emissive: self.emissive,
-        };
+// 形状的补集: 原来的外部变成内部, 例如挖空的房间, 墙壁会遮挡光线
+// 补集是无界的, 没有包围盒; 边和原来的形状相同, 所以射线求交不变
+pub struct InvertShape {
+    shape: Box<dyn Shape>,
+}
+
+impl Shape for InvertShape {
+    fn sdf(&self, x: Float, y: Float) -> SdfResult {
+        let mut result = self.shape.sdf(x, y);
+        result.sd = -result.sd;
+        result
+    }
+
+    fn perimeter(&self) -> Float {
+        self.shape.perimeter()
+    }
+
+    fn gradient(&self, x: Float, y: Float) -> (Float, Float) {
+        let (gx, gy) = self.shape.gradient(x, y);
+        (-gx, -gy)
+    }
+
+    fn supports_raycast(&self) -> bool {
+        self.shape.supports_raycast()
+    }
+
+    fn raycast(&self, x: Float, y: Float, dx: Float, dy: Float) -> Option<Float> {
+        self.shape.raycast(x, y, dx, dy)
     }
 }
 
-pub struct Triangle {
-    ax: f64,
-    ay: f64,
-    bx: f64,
-    by: f64,
-    cx: f64,
-    cy: f64,
-    emissive: f64,
-    // 圆角三角形的半径
-    r: f64,
+// 形状向外扩展 r (r 为负时向内收缩), 尖角变成半径为 r 的圆角
+// sdf 只是减去 r, 所以形状内部的距离场不精确的地方(例如 CSG 的结果)扩展后也同样只是近似
+pub struct OffsetShape {
+    shape: Box<dyn Shape>,
+    r: Float,
 }
 
-impl Triangle {
-    pub fn new(ax: f64, ay: f64, bx: f64, by: f64, cx: f64, cy: f64, emissive: f64) -> Triangle {
-        Triangle {
-            ax,
-            ay,
-            bx,
-            by,
-            cx,
-            cy,
-            emissive,
-            r: 0.0,
-        }
+impl Shape for OffsetShape {
+    fn sdf(&self, x: Float, y: Float) -> SdfResult {
+        let mut result = self.shape.sdf(x, y);
+        result.sd -= self.r;
+        result
     }
 
-    fn segment_sdf(x: f64, y: f64, ax: f64, ay: f64, bx: f64, by:f64) -> f64 {
-        let vx = x - ax;
-        let vy = y - ay;
-        let ux = bx - ax;
-        let uy = by - ay;
-        let t = ((vx * ux + vy * uy) / (ux * ux + uy * uy))
-            .min(1.0)
-            .max(0.0);
-        let dx = vx - ux * t;
-        let dy = vy - uy * t;
-        return (dx * dx + dy * dy).sqrt();
+    fn bounds(&self) -> Option<Aabb> {
+        offset_bounds(self.shape.bounds(), self.r)
+    }
+
+    fn gradient(&self, x: Float, y: Float) -> (Float, Float) {
+        self.shape.gradient(x, y)
     }
 }
 
-impl Shape for Triangle {
-    fn sdf(&self, x: f64, y: f64) -> SdfResult {
-        let result1 = Triangle::segment_sdf(x, y, self.ax, self.ay, self.bx, self.by);
-        let result2 = Triangle::segment_sdf(x, y, self.bx, self.by, self.cx, self.cy);
-        let result3 = Triangle::segment_sdf(x, y, self.cx, self.cy, self.ax, self.ay);
+// 只保留形状的边, 变成宽为 2 * thickness 的空心轮廓, 像霓虹灯管
+pub struct ShellShape {
+    shape: Box<dyn Shape>,
+    thickness: Float,
+}
 
-        // 三角形的 sd 是三个线段中距离最近的那个
-        let mut sd = result1.min(result2).min(result3);
+impl Shape for ShellShape {
+    fn sdf(&self, x: Float, y: Float) -> SdfResult {
+        let mut result = self.shape.sdf(x, y);
+        result.sd = result.sd.abs() - self.thickness;
+        result
+    }
 
-        // 如果在三角形内，那么返回 -sd
-        if (self.bx - self.ax) * (y - self.ay) > (self.by - self.ay) * (x - self.ax) &&
-            (self.cx - self.bx) * (y - self.by) > (self.cy - self.by) * (x - self.bx) &&
-            (self.ax - self.cx) * (y - self.cy) > (self.ay - self.cy) * (x - self.cx) {
-            sd = -sd;
+    fn bounds(&self) -> Option<Aabb> {
+        offset_bounds(self.shape.bounds(), self.thickness)
+    }
+
+    // 在原来的形状里面梯度反向
+    fn gradient(&self, x: Float, y: Float) -> (Float, Float) {
+        let (gx, gy) = self.shape.gradient(x, y);
+        if self.shape.sdf(x, y).sd < 0.0 {
+            (-gx, -gy)
+        } else {
+            (gx, gy)
         }
+    }
+}
 
-        return SdfResult {
-            sd,
-            emissive: self.emissive
+// 向外扩展 r 之后的包围盒, 收缩时原来的包围盒仍然包围形状
+pub(crate) fn offset_bounds(bounds: Option<Aabb>, r: Float) -> Option<Aabb> {
+    let bounds = bounds?;
+    let r = r.max(0.0);
+    Some(Aabb::new(
+        bounds.min_x - r,
+        bounds.min_y - r,
+        bounds.max_x + r,
+        bounds.max_y + r,
+    ))
+}
+
+// 先放大 scale 倍, 再绕原点旋转 theta, 最后平移 (x, y) 的变换, 默认是恒等变换
+// Transformed 和 CsgNode::Transformed 都用它计算 sdf 和包围盒
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    x: Float,
+    y: Float,
+    theta: Float,
+    scale: Float,
+}
+
+impl Default for Transform {
+    fn default() -> Transform {
+        Transform {
+            x: 0.0,
+            y: 0.0,
+            theta: 0.0,
+            scale: 1.0,
+        }
+    }
+}
+
+impl Transform {
+    pub fn with_translation(mut self, x: Float, y: Float) -> Self {
+        self.x = x;
+        self.y = y;
+        self
+    }
+
+    pub fn with_rotation(mut self, theta: Float) -> Self {
+        self.theta = theta;
+        self
+    }
+
+    // scale 需要大于 0
+    pub fn with_scale(mut self, scale: Float) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    // 场景中的点变换到形状自己的坐标系
+    fn to_local(self, x: Float, y: Float) -> (Float, Float) {
+        let (sin_theta, cos_theta) = self.theta.sin_cos();
+        let (dx, dy) = (x - self.x, y - self.y);
+        (
+            (dx * cos_theta + dy * sin_theta) / self.scale,
+            (dy * cos_theta - dx * sin_theta) / self.scale,
+        )
+    }
+
+    // 形状自己的坐标系中的向量旋转到场景中
+    fn rotate(&self, x: Float, y: Float) -> (Float, Float) {
+        let (sin_theta, cos_theta) = self.theta.sin_cos();
+        (x * cos_theta - y * sin_theta, x * sin_theta + y * cos_theta)
+    }
+
+    fn to_world(self, x: Float, y: Float) -> (Float, Float) {
+        let (x, y) = self.rotate(x * self.scale, y * self.scale);
+        (x + self.x, y + self.y)
+    }
+
+    // sdf 为变换前的形状的 sdf
+    pub(crate) fn sdf(
+        &self,
+        x: Float,
+        y: Float,
+        sdf: impl Fn(Float, Float) -> SdfResult,
+    ) -> SdfResult {
+        let (lx, ly) = self.to_local(x, y);
+        let mut result = sdf(lx, ly);
+        result.sd *= self.scale;
+        result
+    }
+
+    // 变换后的包围盒的四个角的包围盒
+    pub(crate) fn bounds(&self, bounds: Option<Aabb>) -> Option<Aabb> {
+        let bounds = bounds?;
+        let corners = [
+            self.to_world(bounds.min_x, bounds.min_y),
+            self.to_world(bounds.max_x, bounds.min_y),
+            self.to_world(bounds.min_x, bounds.max_y),
+            self.to_world(bounds.max_x, bounds.max_y),
+        ];
+        Some(points_bounds(&corners))
+    }
+}
+
+// 按 Transform 变换形状, 不用重新创建其中的每个基本形状
+// 计算 sdf 时对查询点做逆变换, 均匀缩放不改变距离的比例, 所以距离乘以 scale 之后仍然精确
+pub struct Transformed {
+    shape: Box<dyn Shape>,
+    transform: Transform,
+}
+
+impl Transformed {
+    // 默认是恒等变换
+    pub fn new(shape: Box<dyn Shape>) -> Transformed {
+        Transformed {
+            shape,
+            transform: Transform::default(),
+        }
+    }
+
+    pub fn with_translation(mut self, x: Float, y: Float) -> Self {
+        self.transform = self.transform.with_translation(x, y);
+        self
+    }
+
+    pub fn with_rotation(mut self, theta: Float) -> Self {
+        self.transform = self.transform.with_rotation(theta);
+        self
+    }
+
+    // scale 需要大于 0
+    pub fn with_scale(mut self, scale: Float) -> Self {
+        self.transform = self.transform.with_scale(scale);
+        self
+    }
+}
+
+impl Shape for Transformed {
+    fn sdf(&self, x: Float, y: Float) -> SdfResult {
+        self.transform.sdf(x, y, |x, y| self.shape.sdf(x, y))
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        self.transform.bounds(self.shape.bounds())
+    }
+
+    fn area(&self) -> Float {
+        self.shape.area() * self.transform.scale * self.transform.scale
+    }
+
+    fn perimeter(&self) -> Float {
+        self.shape.perimeter() * self.transform.scale
+    }
+
+    fn centroid(&self) -> Option<(Float, Float)> {
+        let (x, y) = self.shape.centroid()?;
+        Some(self.transform.to_world(x, y))
+    }
+
+    fn gradient(&self, x: Float, y: Float) -> (Float, Float) {
+        let (lx, ly) = self.transform.to_local(x, y);
+        let (gx, gy) = self.shape.gradient(lx, ly);
+        self.transform.rotate(gx, gy)
+    }
+
+    fn supports_raycast(&self) -> bool {
+        self.shape.supports_raycast()
+    }
+
+    // 方向只旋转, 仍然是单位向量, 形状坐标系中走过的距离乘以 scale
+    fn raycast(&self, x: Float, y: Float, dx: Float, dy: Float) -> Option<Float> {
+        let (lx, ly) = self.transform.to_local(x, y);
+        let (sin_theta, cos_theta) = self.transform.theta.sin_cos();
+        let (ldx, ldy) = (
+            dx * cos_theta + dy * sin_theta,
+            dy * cos_theta - dx * sin_theta,
+        );
+        Some(self.shape.raycast(lx, ly, ldx, ldy)? * self.transform.scale)
+    }
+}
+
+// 以原点为中心, 在 x 方向放大 sx 倍, y 方向放大 sy 倍, 见 ScaledShape
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Scale {
+    sx: Float,
+    sy: Float,
+}
+
+impl Scale {
+    pub fn new(sx: Float, sy: Float) -> Scale {
+        Scale { sx, sy }
+    }
+
+    pub(crate) fn sdf(
+        &self,
+        x: Float,
+        y: Float,
+        sdf: impl Fn(Float, Float) -> SdfResult,
+    ) -> SdfResult {
+        let mut result = sdf(x / self.sx, y / self.sy);
+        result.sd *= self.sx.min(self.sy);
+        result
+    }
+
+    pub(crate) fn bounds(&self, bounds: Option<Aabb>) -> Option<Aabb> {
+        let bounds = bounds?;
+        Some(Aabb::new(
+            bounds.min_x * self.sx,
+            bounds.min_y * self.sy,
+            bounds.max_x * self.sx,
+            bounds.max_y * self.sy,
+        ))
+    }
+}
+
+// 以原点为中心, 把形状在 x 方向放大 sx 倍, y 方向放大 sy 倍, 例如把圆拉成椭圆
+// 查询点的坐标分别除以 sx 和 sy, 形状坐标系中的距离放到场景中最多缩短为 min(sx, sy) 倍,
+// 所以 sdf 只是乘以 min(sx, sy) (即除以查询点的最大缩放比例) 的近似值: 不会超过真实距离, 光线步进不会穿过边,
+// 但在拉长的方向上偏小, 两个方向的比例相差越大, 步进需要的步数越多
+pub struct ScaledShape {
+    shape: Box<dyn Shape>,
+    scale: Scale,
+}
+
+impl Shape for ScaledShape {
+    fn sdf(&self, x: Float, y: Float) -> SdfResult {
+        self.scale.sdf(x, y, |x, y| self.shape.sdf(x, y))
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        self.scale.bounds(self.shape.bounds())
+    }
+
+    fn area(&self) -> Float {
+        self.shape.area() * self.scale.sx * self.scale.sy
+    }
+
+    // sdf 在边附近偏小, 估计时会把边附近的带状区域算宽, 所以改用按梯度的长度修正到一阶精确的距离
+    fn perimeter(&self) -> Float {
+        let bounds = match self.bounds() {
+            Some(bounds) => bounds,
+            None => return Float::INFINITY,
+        };
+        let Scale { sx, sy } = self.scale;
+        let corrected = FnShape::new(
+            |x, y| {
+                let (lx, ly) = (x / sx, y / sy);
+                let (gx, gy) = self.shape.gradient(lx, ly);
+                let length = (gx / sx).hypot(gy / sy);
+                let sd = self.shape.sdf(lx, ly).sd;
+                if length > 0.0 {
+                    sd / length
+                } else {
+                    sd * sx.min(sy)
+                }
+            },
+            Material::default(),
+        );
+        estimate_measure(&corrected, &bounds).1
+    }
+
+    fn centroid(&self) -> Option<(Float, Float)> {
+        let (x, y) = self.shape.centroid()?;
+        Some((x * self.scale.sx, y * self.scale.sy))
+    }
+
+    // 缩放之后边的法线方向, 是单位向量
+    fn gradient(&self, x: Float, y: Float) -> (Float, Float) {
+        let Scale { sx, sy } = self.scale;
+        let (gx, gy) = self.shape.gradient(x / sx, y / sy);
+        let (gx, gy) = (gx / sx, gy / sy);
+        let length = gx.hypot(gy);
+        if length > 0.0 {
+            (gx / length, gy / length)
+        } else {
+            (0.0, 0.0)
+        }
+    }
+}
+
+// 关于过 (x, y)、法线为 (nx, ny) 的直线对称, 见 MirrorShape
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Mirror {
+    x: Float,
+    y: Float,
+    nx: Float,
+    ny: Float,
+}
+
+impl Mirror {
+    // 法线不需要是单位向量; 法线为 (0, 0) 时没有方向, 按 (1, 0) 处理, 即关于过 (x, y) 的竖直线对称
+    pub fn new(x: Float, y: Float, nx: Float, ny: Float) -> Mirror {
+        let length = nx.hypot(ny);
+        if length == 0.0 {
+            return Mirror {
+                x,
+                y,
+                nx: 1.0,
+                ny: 0.0,
+            };
+        }
+        Mirror {
+            x,
+            y,
+            nx: nx / length,
+            ny: ny / length,
+        }
+    }
+
+    // 点到直线的有符号距离, 在法线指向的一侧为正
+    fn side(&self, x: Float, y: Float) -> Float {
+        (x - self.x) * self.nx + (y - self.y) * self.ny
+    }
+
+    fn reflect(&self, x: Float, y: Float) -> (Float, Float) {
+        let d = self.side(x, y);
+        (x - 2.0 * d * self.nx, y - 2.0 * d * self.ny)
+    }
+
+    fn fold(&self, x: Float, y: Float) -> (Float, Float) {
+        if self.side(x, y) < 0.0 {
+            self.reflect(x, y)
+        } else {
+            (x, y)
+        }
+    }
+
+    pub(crate) fn sdf(
+        &self,
+        x: Float,
+        y: Float,
+        sdf: impl Fn(Float, Float) -> SdfResult,
+    ) -> SdfResult {
+        let (x, y) = self.fold(x, y);
+        sdf(x, y)
+    }
+
+    // 原来的包围盒和它的四个角的反射的包围盒
+    pub(crate) fn bounds(&self, bounds: Option<Aabb>) -> Option<Aabb> {
+        let bounds = bounds?;
+        let corners = [
+            (bounds.min_x, bounds.min_y),
+            (bounds.max_x, bounds.min_y),
+            (bounds.min_x, bounds.max_y),
+            (bounds.max_x, bounds.max_y),
+        ];
+        Some(corners.iter().fold(bounds, |bounds, &(x, y)| {
+            let (x, y) = self.reflect(x, y);
+            bounds.union(&Aabb::new(x, y, x, y))
+        }))
+    }
+}
+
+// 关于一条直线对称: 把另一侧的查询点反射过来, 对称的场景只需要一半的形状
+// 只保留形状在法线指向的一侧的部分, 越过直线的部分会被去掉
+pub struct MirrorShape {
+    shape: Box<dyn Shape>,
+    mirror: Mirror,
+}
+
+impl Shape for MirrorShape {
+    fn sdf(&self, x: Float, y: Float) -> SdfResult {
+        self.mirror.sdf(x, y, |x, y| self.shape.sdf(x, y))
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        self.mirror.bounds(self.shape.bounds())
+    }
+
+    // 在反射过来的一侧, 梯度也要反射回去
+    fn gradient(&self, x: Float, y: Float) -> (Float, Float) {
+        let Mirror { nx, ny, .. } = self.mirror;
+        let (fx, fy) = self.mirror.fold(x, y);
+        let (gx, gy) = self.shape.gradient(fx, fy);
+        if self.mirror.side(x, y) < 0.0 {
+            let dot = gx * nx + gy * ny;
+            (gx - 2.0 * dot * nx, gy - 2.0 * dot * ny)
+        } else {
+            (gx, gy)
+        }
+    }
+}
+
+// 在 x 和 y 方向分别以 spacing_x 和 spacing_y 为间隔无限地重复, 间隔不大于 0 的方向不重复, 见 RepeatShape
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Repeat {
+    spacing_x: Float,
+    spacing_y: Float,
+}
+
+impl Repeat {
+    pub fn new(spacing_x: Float, spacing_y: Float) -> Repeat {
+        Repeat {
+            spacing_x,
+            spacing_y,
+        }
+    }
+
+    fn fold(&self, x: Float, y: Float) -> (Float, Float) {
+        (
+            repeat_coordinate(x, self.spacing_x),
+            repeat_coordinate(y, self.spacing_y),
+        )
+    }
+
+    pub(crate) fn sdf(
+        &self,
+        x: Float,
+        y: Float,
+        sdf: impl Fn(Float, Float) -> SdfResult,
+    ) -> SdfResult {
+        let (x, y) = self.fold(x, y);
+        sdf(x, y)
+    }
+}
+
+// 无限地重复形状, 一个节点就可以画出整个网格的光源
+// 查询点按间隔取模折叠到以原点为中心的格子里, 所以形状应该以原点为中心, 并且不超出格子;
+// 超出格子的部分会被截掉, 离相邻格子的形状更近的地方距离也会偏大
+pub struct RepeatShape {
+    shape: Box<dyn Shape>,
+    repeat: Repeat,
+}
+
+// 把坐标折叠到 [-spacing / 2, spacing / 2] 内, 间隔不大于 0 时不重复
+fn repeat_coordinate(x: Float, spacing: Float) -> Float {
+    if spacing > 0.0 {
+        x - spacing * (x / spacing).round()
+    } else {
+        x
+    }
+}
+
+impl Shape for RepeatShape {
+    fn sdf(&self, x: Float, y: Float) -> SdfResult {
+        self.repeat.sdf(x, y, |x, y| self.shape.sdf(x, y))
+    }
+
+    fn gradient(&self, x: Float, y: Float) -> (Float, Float) {
+        let (x, y) = self.repeat.fold(x, y);
+        self.shape.gradient(x, y)
+    }
+}
+
+// 绕 (cx, cy) 均匀地重复 count 份, 见 PolarRepeatShape
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PolarRepeat {
+    cx: Float,
+    cy: Float,
+    count: usize,
+}
+
+impl PolarRepeat {
+    // count 至少为 1, 传入 0 时按 1 处理
+    pub fn new(cx: Float, cy: Float, count: usize) -> PolarRepeat {
+        PolarRepeat {
+            cx,
+            cy,
+            count: count.max(1),
+        }
+    }
+
+    // 折叠后的点, 以及从折叠后的点转回查询点需要旋转的角度
+    fn fold(&self, x: Float, y: Float) -> (Float, Float, Float) {
+        let (dx, dy) = (x - self.cx, y - self.cy);
+        let sector = TAU / self.count as Float;
+        let angle = dy.atan2(dx);
+        let rotation = sector * (angle / sector).round();
+        let (sin_r, cos_r) = rotation.sin_cos();
+        (
+            self.cx + dx * cos_r + dy * sin_r,
+            self.cy + dy * cos_r - dx * sin_r,
+            rotation,
+        )
+    }
+
+    pub(crate) fn sdf(
+        &self,
+        x: Float,
+        y: Float,
+        sdf: impl Fn(Float, Float) -> SdfResult,
+    ) -> SdfResult {
+        let (x, y, _) = self.fold(x, y);
+        sdf(x, y)
+    }
+
+    // 以中心为圆心, 包住原来的包围盒的圆的包围盒
+    pub(crate) fn bounds(&self, bounds: Option<Aabb>) -> Option<Aabb> {
+        let bounds = bounds?;
+        let dx = (bounds.min_x - self.cx)
+            .abs()
+            .max((bounds.max_x - self.cx).abs());
+        let dy = (bounds.min_y - self.cy)
+            .abs()
+            .max((bounds.max_y - self.cy).abs());
+        let r = dx.hypot(dy);
+        Some(Aabb::around(self.cx, self.cy, r, r))
+    }
+}
+
+// 绕一点均匀地重复形状, 像钟面上的刻度
+// 查询点的角度折叠到以 +x 方向为中心、张角为 2π / count 的扇形里, 所以形状应该放在这个扇形内,
+// 超出扇形的部分会被截掉
+pub struct PolarRepeatShape {
+    shape: Box<dyn Shape>,
+    repeat: PolarRepeat,
+}
+
+impl Shape for PolarRepeatShape {
+    fn sdf(&self, x: Float, y: Float) -> SdfResult {
+        self.repeat.sdf(x, y, |x, y| self.shape.sdf(x, y))
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        self.repeat.bounds(self.shape.bounds())
+    }
+
+    fn gradient(&self, x: Float, y: Float) -> (Float, Float) {
+        let (fx, fy, rotation) = self.repeat.fold(x, y);
+        let (gx, gy) = self.shape.gradient(fx, fy);
+        let (sin_r, cos_r) = rotation.sin_cos();
+        (gx * cos_r - gy * sin_r, gx * sin_r + gy * cos_r)
+    }
+}
+
+// 用二维值噪声扰动查询点, 见 Warped
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Warp {
+    amplitude: Float,
+    frequency: Float,
+    seed: u64,
+}
+
+impl Warp {
+    pub fn new(amplitude: Float, frequency: Float, seed: u64) -> Warp {
+        Warp {
+            amplitude,
+            frequency,
+            seed,
+        }
+    }
+
+    fn warp(&self, x: Float, y: Float) -> (Float, Float) {
+        let (u, v) = (x * self.frequency, y * self.frequency);
+        (
+            x + self.amplitude * value_noise(u, v, self.seed, 0),
+            y + self.amplitude * value_noise(u, v, self.seed, 1),
+        )
+    }
+
+    fn lipschitz(&self) -> Float {
+        1.0 + (self.amplitude * self.frequency).abs() * VALUE_NOISE_LIPSCHITZ
+    }
+
+    pub(crate) fn sdf(
+        &self,
+        x: Float,
+        y: Float,
+        sdf: impl Fn(Float, Float) -> SdfResult,
+    ) -> SdfResult {
+        let (x, y) = self.warp(x, y);
+        let mut result = sdf(x, y);
+        result.sd /= self.lipschitz();
+        result
+    }
+
+    // 每个方向最多偏移 amplitude
+    pub(crate) fn bounds(&self, bounds: Option<Aabb>) -> Option<Aabb> {
+        offset_bounds(bounds, self.amplitude.abs())
+    }
+}
+
+// 让任意形状的边变得弯曲不平, 像手绘或者有机的形状
+// 查询点 p 变成 p + amplitude * noise(p * frequency), 噪声的每个分量在 [-1, 1] 内
+// 扰动之后的 sdf 不再是距离: 它的梯度长度最多是 1 + amplitude * frequency * VALUE_NOISE_LIPSCHITZ,
+// 所以把 sdf 除以这个上限, 光线步进的步长随之缩小, 不会穿过边; 振幅和频率越大, 需要的步数越多
+pub struct Warped {
+    shape: Box<dyn Shape>,
+    warp: Warp,
+}
+
+impl Warped {
+    pub fn new(shape: Box<dyn Shape>, amplitude: Float, frequency: Float, seed: u64) -> Warped {
+        Warped {
+            shape,
+            warp: Warp::new(amplitude, frequency, seed),
+        }
+    }
+}
+
+impl Shape for Warped {
+    fn sdf(&self, x: Float, y: Float) -> SdfResult {
+        self.warp.sdf(x, y, |x, y| self.shape.sdf(x, y))
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        self.warp.bounds(self.shape.bounds())
+    }
+
+    fn gradient(&self, x: Float, y: Float) -> (Float, Float) {
+        normalized_gradient(
+            |x, y| {
+                let (x, y) = self.warp.warp(x, y);
+                self.shape.sdf(x, y).sd
+            },
+            x,
+            y,
+        )
+    }
+}
+
+// [-1, 1] 内的二维值噪声: 整数格点上的随机值用五次函数平滑插值, 函数和一阶导数连续
+// channel 区分同一个种子下互相独立的噪声
+fn value_noise(x: Float, y: Float, seed: u64, channel: u64) -> Float {
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let (ix, iy) = (x0 as i64, y0 as i64);
+    let corner = |dx: i64, dy: i64| {
+        let values = [seed, channel, (ix + dx) as u64, (iy + dy) as u64];
+        hash_unit(&values) * 2.0 - 1.0
+    };
+    let fade = |t: Float| t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+    let (u, v) = (fade(fx), fade(fy));
+    let top = corner(0, 0) * (1.0 - u) + corner(1, 0) * u;
+    let bottom = corner(0, 1) * (1.0 - u) + corner(1, 1) * u;
+    top * (1.0 - v) + bottom * v
+}
+
+// 以原点为中心的水平或竖直错切, 见 ShearShape
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Shear {
+    kx: Float,
+    ky: Float,
+}
+
+impl Shear {
+    // 水平错切, 离原点 y 远的地方向右移动 k * y
+    pub fn horizontal(k: Float) -> Shear {
+        Shear { kx: k, ky: 0.0 }
+    }
+
+    // 竖直错切, 离原点 x 远的地方向下移动 k * x
+    pub fn vertical(k: Float) -> Shear {
+        Shear { kx: 0.0, ky: k }
+    }
+
+    fn to_local(self, x: Float, y: Float) -> (Float, Float) {
+        (x - self.kx * y, y - self.ky * x)
+    }
+
+    fn to_world(self, x: Float, y: Float) -> (Float, Float) {
+        (x + self.kx * y, y + self.ky * x)
+    }
+
+    fn lipschitz(&self) -> Float {
+        let k = self.kx.abs().max(self.ky.abs());
+        (k + (k * k + 4.0).sqrt()) / 2.0
+    }
+
+    pub(crate) fn sdf(
+        &self,
+        x: Float,
+        y: Float,
+        sdf: impl Fn(Float, Float) -> SdfResult,
+    ) -> SdfResult {
+        let (x, y) = self.to_local(x, y);
+        let mut result = sdf(x, y);
+        result.sd /= self.lipschitz();
+        result
+    }
+
+    pub(crate) fn bounds(&self, bounds: Option<Aabb>) -> Option<Aabb> {
+        let bounds = bounds?;
+        let corners = [
+            self.to_world(bounds.min_x, bounds.min_y),
+            self.to_world(bounds.max_x, bounds.min_y),
+            self.to_world(bounds.min_x, bounds.max_y),
+            self.to_world(bounds.max_x, bounds.max_y),
+        ];
+        Some(points_bounds(&corners))
+    }
+}
+
+// 以原点为中心的错切: 水平错切把 (x, y) 移到 (x + kx * y, y), 竖直错切把 (x, y) 移到 (x, y + ky * x),
+// 例如把矩形变成平行四边形; kx 和 ky 只有一个不为 0
+// 逆变换的雅可比矩阵的范数是 (|k| + sqrt(k² + 4)) / 2, sdf 除以它之后不会超过真实距离
+pub struct ShearShape {
+    shape: Box<dyn Shape>,
+    shear: Shear,
+}
+
+impl Shape for ShearShape {
+    fn sdf(&self, x: Float, y: Float) -> SdfResult {
+        self.shear.sdf(x, y, |x, y| self.shape.sdf(x, y))
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        self.shear.bounds(self.shape.bounds())
+    }
+
+    // 错切不改变面积, 形心随形状一起错切
+    fn area(&self) -> Float {
+        self.shape.area()
+    }
+
+    fn centroid(&self) -> Option<(Float, Float)> {
+        let (x, y) = self.shape.centroid()?;
+        Some(self.shear.to_world(x, y))
+    }
+
+    fn gradient(&self, x: Float, y: Float) -> (Float, Float) {
+        normalized_gradient(
+            |x, y| {
+                let (x, y) = self.shear.to_local(x, y);
+                self.shape.sdf(x, y).sd
+            },
+            x,
+            y,
+        )
+    }
+}
+
+// 沿 x 轴弯曲成曲率为 k 的圆弧, 见 BendShape
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bend {
+    k: Float,
+}
+
+impl Bend {
+    pub fn new(k: Float) -> Bend {
+        Bend { k }
+    }
+
+    // 场景中的点到形状坐标系, 以及这一点处沿圆弧方向的压缩比例
+    fn to_local(self, x: Float, y: Float) -> (Float, Float, Float) {
+        if self.k == 0.0 {
+            return (x, y, 1.0);
+        }
+        let r = 1.0 / self.k;
+        // 从圆心指向查询点的向量, 以 k 的符号确定方向, 使得原点处的角度为 0
+        let sign = r.signum();
+        let (dx, dy) = (x * sign, (r - y) * sign);
+        let angle = dx.atan2(dy);
+        let radius = dx.hypot(dy);
+        let ly = r - sign * radius;
+        (angle / self.k, ly, (1.0 - self.k * ly).abs())
+    }
+
+    fn to_world(self, x: Float, y: Float) -> (Float, Float) {
+        if self.k == 0.0 {
+            return (x, y);
+        }
+        let r = 1.0 / self.k;
+        let (sin_a, cos_a) = (x * self.k).sin_cos();
+        let radius = r - y;
+        (radius * sin_a, r - radius * cos_a)
+    }
+
+    // 形状的包围盒内最大的压缩比例的倒数
+    fn band_lipschitz(&self, bounds: Option<Aabb>) -> Float {
+        match bounds {
+            Some(bounds) => {
+                let m = (self.k * bounds.min_y).max(self.k * bounds.max_y);
+                if m > 0.0 && m < 1.0 {
+                    1.0 / (1.0 - m)
+                } else {
+                    1.0
+                }
+            }
+            None => 1.0,
+        }
+    }
+
+    // bounds 为弯曲前的形状的包围盒, 用来估计形状附近最大的压缩比例
+    pub(crate) fn sdf(
+        &self,
+        x: Float,
+        y: Float,
+        bounds: Option<Aabb>,
+        sdf: impl Fn(Float, Float) -> SdfResult,
+    ) -> SdfResult {
+        let (lx, ly, stretch) = self.to_local(x, y);
+        let mut result = sdf(lx, ly);
+        let lipschitz = self.band_lipschitz(bounds).max(1.0 / stretch);
+        result.sd /= lipschitz;
+        result
+    }
+
+    // 包围盒弯曲后是一段圆环, 取圆环两端的四个角和其中经过的上下左右四个方向上的点
+    pub(crate) fn bounds(&self, bounds: Option<Aabb>) -> Option<Aabb> {
+        let bounds = bounds?;
+        if self.k == 0.0 {
+            return Some(bounds);
+        }
+        let (a0, a1) = (bounds.min_x * self.k, bounds.max_x * self.k);
+        let (a0, a1) = (a0.min(a1), a0.max(a1));
+        let mut angles = vec![a0, a1];
+        let mut quarter = (a0 / FRAC_PI_2).ceil() * FRAC_PI_2;
+        while quarter < a1 {
+            angles.push(quarter);
+            quarter += FRAC_PI_2;
+        }
+        let points: Vec<(Float, Float)> = angles
+            .iter()
+            .flat_map(|&angle| {
+                let x = angle / self.k;
+                vec![
+                    self.to_world(x, bounds.min_y),
+                    self.to_world(x, bounds.max_y),
+                ]
+            })
+            .collect();
+        Some(points_bounds(&points))
+    }
+}
+
+// 把形状沿 x 轴弯曲: x 轴变成过原点、曲率为 k 的圆弧, 圆心在 (0, 1 / k), k 为负时向上弯
+// 形状中的点 (x, y) 移到圆弧上弧长为 x 的位置, 再沿半径方向移动 y, 例如把矩形和胶囊弯成拱形
+// 形状到 x 轴的距离需要小于 1 / |k|, 沿 x 轴的长度需要小于圆周长 2π / |k|, 否则会越过圆心或者首尾重叠
+// 离圆心越近, 沿圆弧方向压缩得越厉害, sdf 除以形状的包围盒和查询点处最大的压缩比例, 在形状附近不会超过真实距离
+pub struct BendShape {
+    shape: Box<dyn Shape>,
+    bend: Bend,
+}
+
+impl Shape for BendShape {
+    fn sdf(&self, x: Float, y: Float) -> SdfResult {
+        self.bend
+            .sdf(x, y, self.shape.bounds(), |x, y| self.shape.sdf(x, y))
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        self.bend.bounds(self.shape.bounds())
+    }
+
+    fn gradient(&self, x: Float, y: Float) -> (Float, Float) {
+        normalized_gradient(
+            |x, y| {
+                let (x, y, _) = self.bend.to_local(x, y);
+                self.shape.sdf(x, y).sd
+            },
+            x,
+            y,
+        )
+    }
+}
+
+// 若干个点的包围盒
+fn points_bounds(points: &[(Float, Float)]) -> Aabb {
+    let (x, y) = points[0];
+    points
+        .iter()
+        .fold(Aabb::new(x, y, x, y), |bounds, &(x, y)| {
+            bounds.union(&Aabb::new(x, y, x, y))
+        })
+}
+
+// 用中心差分计算 sd 在 (x, y) 处的梯度方向, 变形之后距离场被缩小, 梯度长度不是 1, 所以只取方向
+fn normalized_gradient(sd: impl Fn(Float, Float) -> Float, x: Float, y: Float) -> (Float, Float) {
+    let gx = sd(x + GRADIENT_DELTA, y) - sd(x - GRADIENT_DELTA, y);
+    let gy = sd(x, y + GRADIENT_DELTA) - sd(x, y - GRADIENT_DELTA);
+    let length = gx.hypot(gy);
+    if length > 0.0 {
+        (gx / length, gy / length)
+    } else {
+        (0.0, 0.0)
+    }
+}
+
+// 只修改材质的包装形状: sdf 的结果交给 $apply 修改, 其他的方法都交给被包装的形状
+macro_rules! impl_material_wrapper {
+    ($name:ident, $apply:expr) => {
+        impl Shape for $name {
+            fn sdf(&self, x: Float, y: Float) -> SdfResult {
+                let apply: fn(&$name, &mut SdfResult) = $apply;
+                let mut result = self.shape.sdf(x, y);
+                apply(self, &mut result);
+                result
+            }
+
+            fn bounds(&self) -> Option<Aabb> {
+                self.shape.bounds()
+            }
+
+            fn area(&self) -> Float {
+                self.shape.area()
+            }
+
+            fn perimeter(&self) -> Float {
+                self.shape.perimeter()
+            }
+
+            fn centroid(&self) -> Option<(Float, Float)> {
+                self.shape.centroid()
+            }
+
+            fn gradient(&self, x: Float, y: Float) -> (Float, Float) {
+                self.shape.gradient(x, y)
+            }
+
+            fn supports_raycast(&self) -> bool {
+                self.shape.supports_raycast()
+            }
+
+            fn raycast(&self, x: Float, y: Float, dx: Float, dy: Float) -> Option<Float> {
+                self.shape.raycast(x, y, dx, dy)
+            }
+        }
+    };
+}
+
+// 给形状指定自发光的角度分布
+pub struct ProfiledShape {
+    shape: Box<dyn Shape>,
+    profile: EmissionProfile,
+}
+
+impl_material_wrapper!(ProfiledShape, |shape, result| {
+    result.profile = shape.profile.clone();
+});
+
+// 给形状指定折射率, 让它变成透明的
+pub struct RefractiveShape {
+    shape: Box<dyn Shape>,
+    eta: Color,
+}
+
+impl_material_wrapper!(RefractiveShape, |shape, result| {
+    result.material.eta = shape.eta;
+});
+
+// 给透明形状指定吸收系数, 让穿过它的光线按走过的距离衰减
+pub struct AbsorbingShape {
+    shape: Box<dyn Shape>,
+    absorption: Color,
+}
+
+impl_material_wrapper!(AbsorbingShape, |shape, result| {
+    result.material.absorption = shape.absorption;
+});
+
+// 给形状指定材质, 代替形状原来的自发光、折射率等光学性质
+pub struct Surface {
+    pub shape: Box<dyn Shape>,
+    pub material: Material,
+}
+
+impl Surface {
+    pub fn new(shape: Box<dyn Shape>, material: Material) -> Surface {
+        Surface { shape, material }
+    }
+}
+
+impl_material_wrapper!(Surface, |shape, result| {
+    result.material = shape.material;
+});
+
+// 由闭包给出 sdf 的形状, 不用为每个新的形状定义结构体和实现 Shape 就可以在场景中试验自定义的 sdf
+// 闭包返回 (x, y) 处的有符号距离, 整个形状使用同一个材质
+// 默认没有包围盒, 所以面积等性质是无穷大; 有界的形状可以用 with_bounds 指定包围盒
+pub struct FnShape<F> {
+    sdf: F,
+    material: Material,
+    bounds: Option<Aabb>,
+}
+
+impl<F> FnShape<F>
+where
+    F: Fn(Float, Float) -> Float + Send + Sync,
+{
+    pub fn new(sdf: F, material: Material) -> FnShape<F> {
+        FnShape {
+            sdf,
+            material,
+            bounds: None,
+        }
+    }
+
+    pub fn with_bounds(mut self, bounds: Aabb) -> Self {
+        self.bounds = Some(bounds);
+        self
+    }
+}
+
+impl<F> Shape for FnShape<F>
+where
+    F: Fn(Float, Float) -> Float + Send + Sync,
+{
+    fn sdf(&self, x: Float, y: Float) -> SdfResult {
+        SdfResult {
+            sd: (self.sdf)(x, y),
+            material: self.material,
+            profile: EmissionProfile::Uniform,
+        }
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        self.bounds
+    }
+}
+
+pub struct Shapes;
+
+impl Shapes {
+    pub fn union(shape1: Box<dyn Shape>, shape2: Box<dyn Shape>) -> Box<UnionShape> {
+        Box::new(UnionShape { shape1, shape2 })
+    }
+
+    pub fn union_all(shapes: Vec<Box<dyn Shape>>) -> Box<UnionAllShape> {
+        Box::new(UnionAllShape { shapes })
+    }
+
+    // k 为平滑过渡的宽度, 两个形状的距离相差 k 以上的地方与 union 相同, k 为 0 时就是 union
+    pub fn smooth_union(
+        shape1: Box<dyn Shape>,
+        shape2: Box<dyn Shape>,
+        k: Float,
+    ) -> Box<SmoothUnionShape> {
+        Box::new(SmoothUnionShape { shape1, shape2, k })
+    }
+
+    pub fn intersect(shape1: Box<dyn Shape>, shape2: Box<dyn Shape>) -> Box<IntersectShape> {
+        Box::new(IntersectShape { shape1, shape2 })
+    }
+
+    pub fn subtract(shape1: Box<dyn Shape>, shape2: Box<dyn Shape>) -> Box<SubtractShape> {
+        Box::new(SubtractShape { shape1, shape2 })
+    }
+
+    // 交集的边角变成圆角, k 的含义与 smooth_union 相同
+    pub fn smooth_intersect(
+        shape1: Box<dyn Shape>,
+        shape2: Box<dyn Shape>,
+        k: Float,
+    ) -> Box<SmoothIntersectShape> {
+        Box::new(SmoothIntersectShape { shape1, shape2, k })
+    }
+
+    // 挖去的边缘平滑地过渡, 像柔和的缺口, k 的含义与 smooth_union 相同
+    pub fn smooth_subtract(
+        shape1: Box<dyn Shape>,
+        shape2: Box<dyn Shape>,
+        k: Float,
+    ) -> Box<SmoothSubtractShape> {
+        Box::new(SmoothSubtractShape { shape1, shape2, k })
+    }
+
+    // 除了这个形状以外的所有地方, sdf 取相反数
+    pub fn invert(shape: Box<dyn Shape>) -> Box<InvertShape> {
+        Box::new(InvertShape { shape })
+    }
+
+    // r 为正时形状变大, 为负时变小, 例如给任意形状加上圆角: Shapes::offset(shape, r) 包住缩小 r 的形状
+    pub fn offset(shape: Box<dyn Shape>, r: Float) -> Box<OffsetShape> {
+        Box::new(OffsetShape { shape, r })
+    }
+
+    // 轮廓从原来的边向两侧各延伸 thickness
+    pub fn shell(shape: Box<dyn Shape>, thickness: Float) -> Box<ShellShape> {
+        Box::new(ShellShape { shape, thickness })
+    }
+
+    // sx 和 sy 需要大于 0, 距离是近似值, 见 ScaledShape; 两个方向相同时用 Transformed 可以得到精确的距离
+    pub fn scale(shape: Box<dyn Shape>, sx: Float, sy: Float) -> Box<ScaledShape> {
+        Box::new(ScaledShape {
+            shape,
+            scale: Scale::new(sx, sy),
+        })
+    }
+
+    // 关于竖直线 x = axis_x 左右对称, 保留形状在直线右边 (x >= axis_x) 的部分
+    pub fn mirror_x(shape: Box<dyn Shape>, axis_x: Float) -> Box<MirrorShape> {
+        Shapes::mirror(shape, axis_x, 0.0, 1.0, 0.0)
+    }
+
+    // 关于水平线 y = axis_y 上下对称, 保留形状在直线下面 (y >= axis_y) 的部分
+    pub fn mirror_y(shape: Box<dyn Shape>, axis_y: Float) -> Box<MirrorShape> {
+        Shapes::mirror(shape, 0.0, axis_y, 0.0, 1.0)
+    }
+
+    // 关于过 (x, y)、法线为 (nx, ny) 的直线对称, 保留形状在法线指向的一侧的部分, 法线不需要是单位向量
+    // 法线为 (0, 0) 时与 mirror_x(shape, x) 相同
+    pub fn mirror(
+        shape: Box<dyn Shape>,
+        x: Float,
+        y: Float,
+        nx: Float,
+        ny: Float,
+    ) -> Box<MirrorShape> {
+        Box::new(MirrorShape {
+            shape,
+            mirror: Mirror::new(x, y, nx, ny),
+        })
+    }
+
+    // 间隔为 0 的方向不重复, 例如只在 x 方向重复的一排光源; 重复之后的形状是无界的
+    pub fn repeat(shape: Box<dyn Shape>, spacing_x: Float, spacing_y: Float) -> Box<RepeatShape> {
+        Box::new(RepeatShape {
+            shape,
+            repeat: Repeat::new(spacing_x, spacing_y),
+        })
+    }
+
+    // 绕 (cx, cy) 重复 count 份, 形状放在中心的 +x 方向, 见 PolarRepeatShape
+    // count 至少为 1, 传入 0 时按 1 处理, 即只有原来的形状
+    pub fn repeat_polar(
+        shape: Box<dyn Shape>,
+        cx: Float,
+        cy: Float,
+        count: usize,
+    ) -> Box<PolarRepeatShape> {
+        Box::new(PolarRepeatShape {
+            shape,
+            repeat: PolarRepeat::new(cx, cy, count),
+        })
+    }
+
+    // 水平错切, 离原点 y 远的地方向右移动 k * y
+    pub fn shear_x(shape: Box<dyn Shape>, k: Float) -> Box<ShearShape> {
+        Box::new(ShearShape {
+            shape,
+            shear: Shear::horizontal(k),
+        })
+    }
+
+    // 竖直错切, 离原点 x 远的地方向下移动 k * x
+    pub fn shear_y(shape: Box<dyn Shape>, k: Float) -> Box<ShearShape> {
+        Box::new(ShearShape {
+            shape,
+            shear: Shear::vertical(k),
+        })
+    }
+
+    // 沿 x 轴弯曲成曲率为 k 的圆弧, 见 BendShape
+    pub fn bend(shape: Box<dyn Shape>, k: Float) -> Box<BendShape> {
+        Box::new(BendShape {
+            shape,
+            bend: Bend::new(k),
+        })
+    }
+
+    pub fn profiled(shape: Box<dyn Shape>, profile: EmissionProfile) -> Box<ProfiledShape> {
+        Box::new(ProfiledShape { shape, profile })
+    }
+
+    // eta 为形状的折射率(例如玻璃约为 1.5), 为 0 时形状不透明
+    pub fn refractive(shape: Box<dyn Shape>, eta: impl Into<Color>) -> Box<RefractiveShape> {
+        Box::new(RefractiveShape {
+            shape,
+            eta: eta.into(),
+        })
+    }
+
+    // 红、绿、蓝三个通道的折射率不同的透明形状, 白光穿过时分散成彩虹
+    pub fn dispersive(
+        shape: Box<dyn Shape>,
+        eta_r: Float,
+        eta_g: Float,
+        eta_b: Float,
+    ) -> Box<RefractiveShape> {
+        Shapes::refractive(shape, Color::new(eta_r, eta_g, eta_b))
+    }
+
+    pub fn absorbing(shape: Box<dyn Shape>, absorption: impl Into<Color>) -> Box<AbsorbingShape> {
+        Box::new(AbsorbingShape {
+            shape,
+            absorption: absorption.into(),
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct Circle {
+    ox: Float,
+    oy: Float,
+    r: Float,
+    emissive: Color,
+}
+
+impl Circle {
+    pub fn new(ox: Float, oy: Float, r: Float, emissive: impl Into<Color>) -> Circle {
+        Circle {
+            ox,
+            oy,
+            r,
+            emissive: emissive.into(),
+        }
+    }
+}
+
+impl Shape for Circle {
+    // 计算 (x, y) 点离这个圆的 SDF(也就是到这个圆的边的最近距离)
+    fn sdf(&self, x: Float, y: Float) -> SdfResult {
+        let ux = x - self.ox;
+        let uy = y - self.oy;
+
+        let sd = (ux * ux + uy * uy).sqrt() - self.r;
+        SdfResult {
+            sd,
+            material: Material::emissive(self.emissive),
+            profile: EmissionProfile::Uniform,
+        }
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        Some(Aabb::around(self.ox, self.oy, self.r, self.r))
+    }
+
+    fn area(&self) -> Float {
+        PI * self.r * self.r
+    }
+
+    fn perimeter(&self) -> Float {
+        TAU * self.r
+    }
+
+    fn centroid(&self) -> Option<(Float, Float)> {
+        Some((self.ox, self.oy))
+    }
+
+    // 从圆心指向 (x, y) 的单位向量, 圆心处没有确定的方向, 返回 0
+    fn gradient(&self, x: Float, y: Float) -> (Float, Float) {
+        let ux = x - self.ox;
+        let uy = y - self.oy;
+        let length = (ux * ux + uy * uy).sqrt();
+        if length == 0.0 {
+            return (0.0, 0.0);
+        }
+        (ux / length, uy / length)
+    }
+
+    fn supports_raycast(&self) -> bool {
+        true
+    }
+
+    // 解 |(x, y) + t(dx, dy) - 圆心| = r, 取不小于 0 的较小的根
+    fn raycast(&self, x: Float, y: Float, dx: Float, dy: Float) -> Option<Float> {
+        let ux = x - self.ox;
+        let uy = y - self.oy;
+        let b = ux * dx + uy * dy;
+        let c = ux * ux + uy * uy - self.r * self.r;
+        let discriminant = b * b - c;
+        if discriminant < 0.0 {
+            return Some(Float::INFINITY);
+        }
+        let root = discriminant.sqrt();
+        Some(if -b - root >= 0.0 {
+            -b - root
+        } else if -b + root >= 0.0 {
+            -b + root
+        } else {
+            Float::INFINITY
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct Plane {
+    // 用一个点和法线来确定一个平面
+    px: Float,
+    py: Float,
+    nx: Float,
+    ny: Float,
+    emissive: Color,
+}
+
+impl Plane {
+    pub fn new(px: Float, py: Float, nx: Float, ny: Float, emissive: impl Into<Color>) -> Plane {
+        Plane {
+            px,
+            py,
+            nx,
+            ny,
+            emissive: emissive.into(),
+        }
+    }
+}
+
+impl Shape for Plane {
+    fn sdf(&self, x: Float, y: Float) -> SdfResult {
+        SdfResult {
+            sd: (x - self.px) * self.nx + (y - self.py) * self.ny,
+            material: Material::emissive(self.emissive),
+            profile: EmissionProfile::Uniform,
+        }
+    }
+
+    // 平面的 sdf 是线性函数, 梯度处处等于法线
+    fn gradient(&self, _x: Float, _y: Float) -> (Float, Float) {
+        (self.nx, self.ny)
+    }
+
+    fn supports_raycast(&self) -> bool {
+        true
+    }
+
+    fn raycast(&self, x: Float, y: Float, dx: Float, dy: Float) -> Option<Float> {
+        let speed = dx * self.nx + dy * self.ny;
+        let t = -self.sdf(x, y).sd / speed;
+        Some(if t >= 0.0 { t } else { Float::INFINITY })
+    }
+}
+
+#[derive(Clone)]
+pub struct Capsule {
+    // 用两个点和半径来表示胶囊
+    ax: Float,
+    ay: Float,
+    bx: Float,
+    by: Float,
+    r: Float,
+    emissive: Color,
+}
+
+impl Capsule {
+    pub fn new(
+        ax: Float,
+        ay: Float,
+        bx: Float,
+        by: Float,
+        r: Float,
+        emissive: impl Into<Color>,
+    ) -> Capsule {
+        Capsule {
+            ax,
+            ay,
+            bx,
+            by,
+            r,
+            emissive: emissive.into(),
+        }
+    }
+}
+
+impl Shape for Capsule {
+    fn sdf(&self, x: Float, y: Float) -> SdfResult {
+        let vx = x - self.ax;
+        let vy = y - self.ay;
+        let ux = self.bx - self.ax;
+        let uy = self.by - self.ay;
+        let t = ((vx * ux + vy * uy) / (ux * ux + uy * uy)).clamp(0.0, 1.0);
+        let dx = vx - ux * t;
+        let dy = vy - uy * t;
+        let segment_sd = (dx * dx + dy * dy).sqrt();
+        let capsule_sd = segment_sd - self.r;
+
+        SdfResult {
+            sd: capsule_sd,
+            material: Material::emissive(self.emissive),
+            profile: EmissionProfile::Uniform,
+        }
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        let a = Aabb::around(self.ax, self.ay, self.r, self.r);
+        Some(a.union(&Aabb::around(self.bx, self.by, self.r, self.r)))
+    }
+
+    // 中间的矩形加上两端的两个半圆
+    fn area(&self) -> Float {
+        let length = (self.bx - self.ax).hypot(self.by - self.ay);
+        2.0 * self.r * length + PI * self.r * self.r
+    }
+
+    fn perimeter(&self) -> Float {
+        let length = (self.bx - self.ax).hypot(self.by - self.ay);
+        2.0 * length + TAU * self.r
+    }
+
+    fn centroid(&self) -> Option<(Float, Float)> {
+        Some(((self.ax + self.bx) / 2.0, (self.ay + self.by) / 2.0))
+    }
+}
+
+#[derive(Clone)]
+pub struct Rect {
+    // 矩形由中心点(cx, cy), 旋转角(theta), 半长(sx, sy) 组成
+    cx: Float,
+    cy: Float,
+    theta: Float,
+    sx: Float,
+    sy: Float,
+    emissive: Color,
+    // 圆角矩形的半径
+    r: Float,
+}
+
+impl Rect {
+    pub fn new(
+        cx: Float,
+        cy: Float,
+        theta: Float,
+        sx: Float,
+        sy: Float,
+        emissive: impl Into<Color>,
+    ) -> Rect {
+        Rect {
+            cx,
+            cy,
+            theta,
+            sx,
+            sy,
+            emissive: emissive.into(),
+            r: 0.0,
+        }
+    }
+
+    // 四个角的圆角半径为 r, 不超过较短的半长, 矩形的总尺寸不变
+    pub fn with_radius(mut self, r: Float) -> Rect {
+        self.r = r.clamp(0.0, self.sx.min(self.sy));
+        self
+    }
+}
+
+impl Shape for Rect {
+    // 有圆角时先把矩形缩小 r, 再向外扩展 r
+    fn sdf(&self, x: Float, y: Float) -> SdfResult {
+        let sin_theta = self.theta.sin();
+        let cos_theta = self.theta.cos();
+        let dx = ((x - self.cx) * cos_theta + (y - self.cy) * sin_theta).abs() - (self.sx - self.r);
+        let dy = ((y - self.cy) * cos_theta - (x - self.cx) * sin_theta).abs() - (self.sy - self.r);
+        let ax = dx.max(0.0);
+        let ay = dy.max(0.0);
+        let sd = dx.max(dy).min(0.0) + (ax * ax + ay * ay).sqrt() - self.r;
+        SdfResult {
+            sd,
+            material: Material::emissive(self.emissive),
+            profile: EmissionProfile::Uniform,
+        }
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        let (sin_theta, cos_theta) = self.theta.sin_cos();
+        let hx = (cos_theta * self.sx).abs() + (sin_theta * self.sy).abs();
+        let hy = (sin_theta * self.sx).abs() + (cos_theta * self.sy).abs();
+        Some(Aabb::around(self.cx, self.cy, hx, hy))
+    }
+
+    // 四个角各变成四分之一圆
+    fn area(&self) -> Float {
+        4.0 * self.sx * self.sy - (4.0 - PI) * self.r * self.r
+    }
+
+    fn perimeter(&self) -> Float {
+        4.0 * (self.sx + self.sy) - (8.0 - TAU) * self.r
+    }
+
+    fn centroid(&self) -> Option<(Float, Float)> {
+        Some((self.cx, self.cy))
+    }
+}
+
+#[derive(Clone)]
+pub struct Triangle {
+    ax: Float,
+    ay: Float,
+    bx: Float,
+    by: Float,
+    cx: Float,
+    cy: Float,
+    emissive: Color,
+    // 圆角三角形的半径
+    r: Float,
+}
+
+impl Triangle {
+    pub fn new(
+        ax: Float,
+        ay: Float,
+        bx: Float,
+        by: Float,
+        cx: Float,
+        cy: Float,
+        emissive: impl Into<Color>,
+    ) -> Triangle {
+        Triangle {
+            ax,
+            ay,
+            bx,
+            by,
+            cx,
+            cy,
+            emissive: emissive.into(),
+            r: 0.0,
+        }
+    }
+
+    // 三个角的圆角半径为 r, 不超过内切圆的半径, 三角形的总尺寸不变
+    pub fn with_radius(mut self, r: Float) -> Triangle {
+        self.r = r.clamp(0.0, self.inradius());
+        self
+    }
+
+    // 没有圆角时的面积和周长
+    fn sharp_measure(&self) -> (Float, Float) {
+        let area = ((self.bx - self.ax) * (self.cy - self.ay)
+            - (self.by - self.ay) * (self.cx - self.ax))
+            .abs()
+            / 2.0;
+        let perimeter = (self.bx - self.ax).hypot(self.by - self.ay)
+            + (self.cx - self.bx).hypot(self.cy - self.by)
+            + (self.ax - self.cx).hypot(self.ay - self.cy);
+        (area, perimeter)
+    }
+
+    fn inradius(&self) -> Float {
+        let (area, perimeter) = self.sharp_measure();
+        2.0 * area / perimeter
+    }
+
+    // 内心是以对边的长度为权重的顶点的平均
+    fn incenter(&self) -> (Float, Float) {
+        let la = (self.cx - self.bx).hypot(self.cy - self.by);
+        let lb = (self.ax - self.cx).hypot(self.ay - self.cy);
+        let lc = (self.bx - self.ax).hypot(self.by - self.ay);
+        let sum = la + lb + lc;
+        (
+            (la * self.ax + lb * self.bx + lc * self.cx) / sum,
+            (la * self.ay + lb * self.by + lc * self.cy) / sum,
+        )
+    }
+
+    // 向内平移三条边 r 得到的三角形与原来的三角形相似, 相似中心是内心, 比例为 (ρ - r) / ρ
+    // 返回这个三角形的顶点和比例
+    fn shrunk(&self) -> ([(Float, Float); 3], Float) {
+        let vertices = [(self.ax, self.ay), (self.bx, self.by), (self.cx, self.cy)];
+        if self.r == 0.0 {
+            return (vertices, 1.0);
+        }
+        let (ix, iy) = self.incenter();
+        let scale = 1.0 - self.r / self.inradius();
+        let shrink = |(x, y): (Float, Float)| (ix + (x - ix) * scale, iy + (y - iy) * scale);
+        (vertices.map(shrink), scale)
+    }
+}
+
+// (x, y) 到线段 ab 的距离, a 和 b 重合时是到这个点的距离
+fn segment_distance(x: Float, y: Float, ax: Float, ay: Float, bx: Float, by: Float) -> Float {
+    let vx = x - ax;
+    let vy = y - ay;
+    let ux = bx - ax;
+    let uy = by - ay;
+    let length = ux * ux + uy * uy;
+    let t = if length > 0.0 {
+        ((vx * ux + vy * uy) / length).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let dx = vx - ux * t;
+    let dy = vy - uy * t;
+    (dx * dx + dy * dy).sqrt()
+}
+
+impl Shape for Triangle {
+    fn sdf(&self, x: Float, y: Float) -> SdfResult {
+        // 有圆角时计算到缩小后的三角形的距离, 再向外扩展 r
+        let ([(ax, ay), (bx, by), (cx, cy)], _) = self.shrunk();
+        let result1 = segment_distance(x, y, ax, ay, bx, by);
+        let result2 = segment_distance(x, y, bx, by, cx, cy);
+        let result3 = segment_distance(x, y, cx, cy, ax, ay);
+
+        // 三角形的 sd 是三个线段中距离最近的那个
+        let mut sd = result1.min(result2).min(result3);
+
+        // 如果在三角形内，那么返回 -sd
+        if (bx - ax) * (y - ay) > (by - ay) * (x - ax)
+            && (cx - bx) * (y - by) > (cy - by) * (x - bx)
+            && (ax - cx) * (y - cy) > (ay - cy) * (x - cx)
+        {
+            sd = -sd;
+        }
+
+        SdfResult {
+            sd: sd - self.r,
+            material: Material::emissive(self.emissive),
+            profile: EmissionProfile::Uniform,
+        }
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        Some(Aabb::new(
+            self.ax.min(self.bx).min(self.cx),
+            self.ay.min(self.by).min(self.cy),
+            self.ax.max(self.bx).max(self.cx),
+            self.ay.max(self.by).max(self.cy),
+        ))
+    }
+
+    // 有圆角时是缩小后的三角形向外扩展 r: 加上三条边外侧的矩形和三个角上合起来的一个整圆
+    fn area(&self) -> Float {
+        let (area, perimeter) = self.sharp_measure();
+        let (_, scale) = self.shrunk();
+        area * scale * scale + self.r * perimeter * scale + PI * self.r * self.r
+    }
+
+    fn perimeter(&self) -> Float {
+        let (_, perimeter) = self.sharp_measure();
+        let (_, scale) = self.shrunk();
+        perimeter * scale + TAU * self.r
+    }
+
+    // 与面积一样分成缩小后的三角形、三条边外侧的矩形和三个角上的扇形, 按面积加权平均各部分的形心
+    fn centroid(&self) -> Option<(Float, Float)> {
+        let (vertices, scale) = self.shrunk();
+        let (area, _) = self.sharp_measure();
+        let area = area * scale * scale;
+        let mut sum_area = area;
+        let mut sum_x = area * (vertices[0].0 + vertices[1].0 + vertices[2].0) / 3.0;
+        let mut sum_y = area * (vertices[0].1 + vertices[1].1 + vertices[2].1) / 3.0;
+        if self.r > 0.0 {
+            // 缩小后的三角形与原来的相似, 边的方向和角度都用原来的三角形计算, 这样缩小成一个点时也能计算;
+            // 内心在三角形内部, 用来确定外侧的方向, 角平分线也经过内心
+            let (ix, iy) = self.incenter();
+            let original = [(self.ax, self.ay), (self.bx, self.by), (self.cx, self.cy)];
+            for i in 0..3 {
+                let (px, py) = original[i];
+                let (qx, qy) = original[(i + 1) % 3];
+                let (ox, oy) = original[(i + 2) % 3];
+                let length = (qx - px).hypot(qy - py);
+                let (mut nx, mut ny) = ((qy - py) / length, (px - qx) / length);
+                if (px - ix) * nx + (py - iy) * ny < 0.0 {
+                    nx = -nx;
+                    ny = -ny;
+                }
+                let (sx, sy) = vertices[i];
+                let (tx, ty) = vertices[(i + 1) % 3];
+                let rect = length * scale * self.r;
+                sum_area += rect;
+                sum_x += rect * ((sx + tx) / 2.0 + nx * self.r / 2.0);
+                sum_y += rect * ((sy + ty) / 2.0 + ny * self.r / 2.0);
+
+                // 角上的扇形的圆心角等于外角, 形心在角平分线上, 到顶点的距离为 4r sin(α/2) / 3α
+                let (ux, uy) = (px - ox, py - oy);
+                let (vx, vy) = (qx - px, qy - py);
+                let angle = (ux * vy - uy * vx).abs().atan2(ux * vx + uy * vy);
+                let (bx, by) = (px - ix, py - iy);
+                let b = bx.hypot(by);
+                let sector = self.r * self.r * angle / 2.0;
+                let distance = 4.0 * self.r * (angle / 2.0).sin() / (3.0 * angle);
+                sum_area += sector;
+                sum_x += sector * (sx + bx / b * distance);
+                sum_y += sector * (sy + by / b * distance);
+            }
+        }
+        Some((sum_x / sum_area, sum_y / sum_area))
+    }
+}
+
+// 圆环: 以半径为 r 的圆为中线, 宽度为 thickness
+#[derive(Clone)]
+pub struct Ring {
+    cx: Float,
+    cy: Float,
+    r: Float,
+    thickness: Float,
+    emissive: Color,
+}
+
+impl Ring {
+    pub fn new(
+        cx: Float,
+        cy: Float,
+        r: Float,
+        thickness: Float,
+        emissive: impl Into<Color>,
+    ) -> Ring {
+        Ring {
+            cx,
+            cy,
+            r,
+            thickness,
+            emissive: emissive.into(),
+        }
+    }
+
+    // 外圆和内圆的半径, thickness 大于 2r 时中间没有洞, 内圆半径为 0
+    fn radii(&self) -> (Float, Float) {
+        let half = self.thickness / 2.0;
+        (self.r + half, (self.r - half).max(0.0))
+    }
+}
+
+impl Shape for Ring {
+    fn sdf(&self, x: Float, y: Float) -> SdfResult {
+        let length = (x - self.cx).hypot(y - self.cy);
+        SdfResult {
+            sd: (length - self.r).abs() - self.thickness / 2.0,
+            material: Material::emissive(self.emissive),
+            profile: EmissionProfile::Uniform,
+        }
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        let (outer, _) = self.radii();
+        Some(Aabb::around(self.cx, self.cy, outer, outer))
+    }
+
+    fn area(&self) -> Float {
+        let (outer, inner) = self.radii();
+        PI * (outer * outer - inner * inner)
+    }
+
+    fn perimeter(&self) -> Float {
+        let (outer, inner) = self.radii();
+        TAU * (outer + inner)
+    }
+
+    fn centroid(&self) -> Option<(Float, Float)> {
+        Some((self.cx, self.cy))
+    }
+
+    // 在中线外指向外面, 在中线内指向圆心
+    fn gradient(&self, x: Float, y: Float) -> (Float, Float) {
+        let (gx, gy) = Circle::new(self.cx, self.cy, self.r, 0.0).gradient(x, y);
+        let length = (x - self.cx).hypot(y - self.cy);
+        if length < self.r {
+            (-gx, -gy)
+        } else {
+            (gx, gy)
+        }
+    }
+
+    fn supports_raycast(&self) -> bool {
+        true
+    }
+
+    // 先穿过外圆和内圆中的哪一个, 就是第一次穿过圆环的边
+    fn raycast(&self, x: Float, y: Float, dx: Float, dy: Float) -> Option<Float> {
+        let (outer, inner) = self.radii();
+        let t = Circle::new(self.cx, self.cy, outer, 0.0).raycast(x, y, dx, dy)?;
+        if inner == 0.0 {
+            return Some(t);
+        }
+        let inner = Circle::new(self.cx, self.cy, inner, 0.0).raycast(x, y, dx, dy)?;
+        Some(t.min(inner))
+    }
+}
+
+// 圆弧: 半径为 r 的圆上以 theta 方向为中间, 向两侧各张开 aperture 的一段, 描边的宽度为 thickness
+// aperture 在 0 到 π 之间, 为 π 时是完整的圆环
+#[derive(Clone)]
+pub struct Arc {
+    cx: Float,
+    cy: Float,
+    r: Float,
+    theta: Float,
+    aperture: Float,
+    thickness: Float,
+    emissive: Color,
+}
+
+impl Arc {
+    pub fn new(
+        cx: Float,
+        cy: Float,
+        r: Float,
+        theta: Float,
+        aperture: Float,
+        thickness: Float,
+        emissive: impl Into<Color>,
+    ) -> Arc {
+        Arc {
+            cx,
+            cy,
+            r,
+            theta,
+            aperture,
+            thickness,
+            emissive: emissive.into(),
+        }
+    }
+}
+
+impl Shape for Arc {
+    // 旋转到圆弧的中间方向为 y 轴, 关于 y 轴对称地只考虑 x ≥ 0 的一侧:
+    // 在端点的外侧时是到端点的距离, 否则是到圆的距离
+    fn sdf(&self, x: Float, y: Float) -> SdfResult {
+        let (sin_theta, cos_theta) = self.theta.sin_cos();
+        let (ux, uy) = (x - self.cx, y - self.cy);
+        let px = (ux * sin_theta - uy * cos_theta).abs();
+        let py = ux * cos_theta + uy * sin_theta;
+        let (sin_aperture, cos_aperture) = self.aperture.sin_cos();
+        let distance = if cos_aperture * px > sin_aperture * py {
+            (px - sin_aperture * self.r).hypot(py - cos_aperture * self.r)
+        } else {
+            (px.hypot(py) - self.r).abs()
+        };
+        SdfResult {
+            sd: distance - self.thickness / 2.0,
+            material: Material::emissive(self.emissive),
+            profile: EmissionProfile::Uniform,
+        }
+    }
+
+    // 使用整个圆环的包围盒
+    fn bounds(&self) -> Option<Aabb> {
+        let extent = self.r + self.thickness / 2.0;
+        Some(Aabb::around(self.cx, self.cy, extent, extent))
+    }
+}
+
+// 扇形: 半径为 r 的圆中从 start 方向逆时针转到 end 方向的部分
+#[derive(Clone)]
+pub struct Pie {
+    cx: Float,
+    cy: Float,
+    r: Float,
+    // 中间的方向和向两侧张开的角度(0 到 π)
+    theta: Float,
+    aperture: Float,
+    emissive: Color,
+}
+
+impl Pie {
+    // end 小于 start 时转过 2π, 例如从 -π/4 到 π/4 与从 7π/4 到 π/4 相同
+    pub fn new(
+        cx: Float,
+        cy: Float,
+        r: Float,
+        start: Float,
+        end: Float,
+        emissive: impl Into<Color>,
+    ) -> Pie {
+        let sweep = (end - start).rem_euclid(TAU);
+        let sweep = if sweep == 0.0 && end != start {
+            TAU
+        } else {
+            sweep
+        };
+        Pie {
+            cx,
+            cy,
+            r,
+            theta: start + sweep / 2.0,
+            aperture: sweep / 2.0,
+            emissive: emissive.into(),
+        }
+    }
+}
+
+impl Shape for Pie {
+    // 与 Arc 一样旋转到中间方向为 y 轴并只考虑 x ≥ 0 的一侧,
+    // 取到圆的距离和到边上的半径的距离中较大的一个, 在扇形的角度外时后者为正
+    fn sdf(&self, x: Float, y: Float) -> SdfResult {
+        let (sin_theta, cos_theta) = self.theta.sin_cos();
+        let (ux, uy) = (x - self.cx, y - self.cy);
+        let px = (ux * sin_theta - uy * cos_theta).abs();
+        let py = ux * cos_theta + uy * sin_theta;
+        let (sin_aperture, cos_aperture) = self.aperture.sin_cos();
+        let circle = px.hypot(py) - self.r;
+        let t = (px * sin_aperture + py * cos_aperture).clamp(0.0, self.r);
+        let edge = (px - sin_aperture * t).hypot(py - cos_aperture * t);
+        let edge = if cos_aperture * px > sin_aperture * py {
+            edge
+        } else {
+            -edge
+        };
+        SdfResult {
+            sd: circle.max(edge),
+            material: Material::emissive(self.emissive),
+            profile: EmissionProfile::Uniform,
+        }
+    }
+
+    // 使用整个圆的包围盒
+    fn bounds(&self) -> Option<Aabb> {
+        Some(Aabb::around(self.cx, self.cy, self.r, self.r))
+    }
+
+    fn area(&self) -> Float {
+        self.aperture * self.r * self.r
+    }
+
+    // 圆弧加上两条半径, 张角为 2π 时两条半径重合在内部, 不算边
+    fn perimeter(&self) -> Float {
+        if self.aperture >= PI {
+            TAU * self.r
+        } else {
+            2.0 * self.aperture * self.r + 2.0 * self.r
+        }
+    }
+
+    // 在中间方向上离圆心 2r·sin(a) / 3a 处
+    fn centroid(&self) -> Option<(Float, Float)> {
+        if self.aperture == 0.0 {
+            return None;
+        }
+        let distance = 2.0 * self.r * self.aperture.sin() / (3.0 * self.aperture);
+        let (sin_theta, cos_theta) = self.theta.sin_cos();
+        Some((
+            self.cx + distance * cos_theta,
+            self.cy + distance * sin_theta,
+        ))
+    }
+}
+
+// 沿二次贝塞尔曲线描边, 宽度为 thickness, 两端是半圆
+#[derive(Clone)]
+pub struct Bezier2 {
+    p0: (Float, Float),
+    p1: (Float, Float),
+    p2: (Float, Float),
+    thickness: Float,
+    emissive: Color,
+}
+
+impl Bezier2 {
+    pub fn new(
+        p0: (Float, Float),
+        p1: (Float, Float),
+        p2: (Float, Float),
+        thickness: Float,
+        emissive: impl Into<Color>,
+    ) -> Bezier2 {
+        Bezier2 {
+            p0,
+            p1,
+            p2,
+            thickness,
+            emissive: emissive.into(),
+        }
+    }
+
+    // 曲线上参数为 t 的点
+    pub(crate) fn point(&self, t: Float) -> (Float, Float) {
+        let ((x0, y0), (x1, y1), (x2, y2)) = (self.p0, self.p1, self.p2);
+        let s = 1.0 - t;
+        (
+            s * s * x0 + 2.0 * s * t * x1 + t * t * x2,
+            s * s * y0 + 2.0 * s * t * y1 + t * t * y2,
+        )
+    }
+
+    // (x, y) 到曲线的距离
+    // 曲线上最近的点满足 (B(t) - p)·B'(t) = 0, 这是关于 t 的三次方程, 用卡尔达诺公式求根,
+    // 把根限制在 [0, 1] 内后取最近的一个
+    pub(crate) fn distance(&self, x: Float, y: Float) -> Float {
+        let ((x0, y0), (x1, y1), (x2, y2)) = (self.p0, self.p1, self.p2);
+        let (ax, ay) = (x1 - x0, y1 - y0);
+        let (bx, by) = (x0 - 2.0 * x1 + x2, y0 - 2.0 * y1 + y2);
+        let (dx, dy) = (x0 - x, y0 - y);
+        let bb = bx * bx + by * by;
+        // 三个控制点等间距地排在一条直线上, 曲线就是线段
+        if bb == 0.0 {
+            return segment_distance(x, y, x0, y0, x2, y2);
+        }
+
+        // 化为 t³ + 3·kx·t² + 3·ky·t + kz = 0
+        let kx = (ax * bx + ay * by) / bb;
+        let ky = (2.0 * (ax * ax + ay * ay) + dx * bx + dy * by) / (3.0 * bb);
+        let kz = (dx * ax + dy * ay) / bb;
+        let p = ky - kx * kx;
+        let q = kx * (2.0 * kx * kx - 3.0 * ky) + kz;
+        let h = q * q + 4.0 * p * p * p;
+        let point = |t: Float| {
+            let t = t.clamp(0.0, 1.0);
+            let ex = dx + (2.0 * ax + bx * t) * t;
+            let ey = dy + (2.0 * ay + by * t) * t;
+            ex * ex + ey * ey
+        };
+        let squared = if h >= 0.0 {
+            // 只有一个实根
+            let h = h.sqrt();
+            let u = ((h - q) / 2.0).cbrt();
+            let v = ((-h - q) / 2.0).cbrt();
+            point(u + v - kx)
+        } else {
+            // 三个实根, 中间的根对应距离的极大值, 不需要考虑
+            let z = (-p).sqrt();
+            let angle = (q / (2.0 * p * z)).clamp(-1.0, 1.0).acos() / 3.0;
+            let (m, n) = (angle.cos(), angle.sin() * (3.0 as Float).sqrt());
+            point(2.0 * m * z - kx).min(point((-n - m) * z - kx))
+        };
+        squared.sqrt()
+    }
+}
+
+impl Shape for Bezier2 {
+    fn sdf(&self, x: Float, y: Float) -> SdfResult {
+        SdfResult {
+            sd: self.distance(x, y) - self.thickness / 2.0,
+            material: Material::emissive(self.emissive),
+            profile: EmissionProfile::Uniform,
+        }
+    }
+
+    // 曲线在三个控制点组成的三角形内
+    fn bounds(&self) -> Option<Aabb> {
+        let ((x0, y0), (x1, y1), (x2, y2)) = (self.p0, self.p1, self.p2);
+        let half = self.thickness / 2.0;
+        Some(Aabb::new(
+            x0.min(x1).min(x2) - half,
+            y0.min(y1).min(y2) - half,
+            x0.max(x1).max(x2) + half,
+            y0.max(y1).max(y2) + half,
+        ))
+    }
+}
+
+// 沿三次贝塞尔曲线描边, 宽度为 thickness, 两端是半圆
+// 三次曲线的最近点没有简单的解析解, 创建时把曲线自适应地细分为若干段二次曲线, 计算到其中最近的一段的距离
+#[derive(Clone)]
+pub struct Bezier3 {
+    points: [(Float, Float); 4],
+    pieces: Vec<Bezier2>,
+    thickness: Float,
+    emissive: Color,
+}
+
+impl Bezier3 {
+    pub fn new(
+        p0: (Float, Float),
+        p1: (Float, Float),
+        p2: (Float, Float),
+        p3: (Float, Float),
+        thickness: Float,
+        emissive: impl Into<Color>,
+    ) -> Bezier3 {
+        let points = [p0, p1, p2, p3];
+        let mut pieces = vec![];
+        Bezier3::flatten(points, 0, &mut pieces);
+        Bezier3 {
+            points,
+            pieces,
+            thickness,
+            emissive: emissive.into(),
+        }
+    }
+
+    // 近似的二次曲线的段数
+    pub fn pieces(&self) -> usize {
+        self.pieces.len()
+    }
+
+    // 用一段二次曲线近似 [p0, p1, p2, p3], 偏差太大时在 t = 1/2 处分成两半分别近似
+    // 两条曲线的端点相同, 中间的控制点取 (3(p1 + p2) - p0 - p3) / 4 时,
+    // 偏差不超过 √3/36·|p3 - 3p2 + 3p1 - p0|
+    pub(crate) fn flatten(points: [(Float, Float); 4], depth: usize, pieces: &mut Vec<Bezier2>) {
+        let [p0, p1, p2, p3] = points;
+        let ex = p3.0 - 3.0 * p2.0 + 3.0 * p1.0 - p0.0;
+        let ey = p3.1 - 3.0 * p2.1 + 3.0 * p1.1 - p0.1;
+        let error = (3.0 as Float).sqrt() / 36.0 * ex.hypot(ey);
+        if error <= FLATTEN_TOLERANCE || depth >= FLATTEN_DEPTH {
+            let control = (
+                (3.0 * (p1.0 + p2.0) - p0.0 - p3.0) / 4.0,
+                (3.0 * (p1.1 + p2.1) - p0.1 - p3.1) / 4.0,
+            );
+            pieces.push(Bezier2::new(p0, control, p3, 0.0, Color::BLACK));
+            return;
+        }
+
+        // de Casteljau 算法
+        let middle = |a: (Float, Float), b: (Float, Float)| ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0);
+        let (q0, q1, q2) = (middle(p0, p1), middle(p1, p2), middle(p2, p3));
+        let (r0, r1) = (middle(q0, q1), middle(q1, q2));
+        let s = middle(r0, r1);
+        Bezier3::flatten([p0, q0, r0, s], depth + 1, pieces);
+        Bezier3::flatten([s, r1, q2, p3], depth + 1, pieces);
+    }
+}
+
+// (x, y) 到若干段二次曲线中最近的一段的距离, 包围盒离 (x, y) 比目前最近的距离还远的段不需要计算
+pub(crate) fn pieces_distance(pieces: &[Bezier2], x: Float, y: Float) -> Float {
+    let mut distance = Float::INFINITY;
+    for piece in pieces {
+        if let Some(bounds) = piece.bounds() {
+            if bounds.distance(x, y) >= distance {
+                continue;
+            }
+        }
+        distance = distance.min(piece.distance(x, y));
+    }
+    distance
+}
+
+// 把首尾相接的若干段二次曲线细分为折线, 弦与曲线的偏差不超过 FLATTEN_TOLERANCE, 不包含最后一段的终点
+// 二次曲线分成 n 段时弦与曲线的偏差为 |p0 - 2p1 + p2| / 4n²
+pub(crate) fn pieces_outline(pieces: &[Bezier2]) -> Vec<(Float, Float)> {
+    let mut outline = vec![];
+    for piece in pieces {
+        let (x0, y0) = piece.p0;
+        let (x1, y1) = piece.p1;
+        let (x2, y2) = piece.p2;
+        let deviation = (x0 - 2.0 * x1 + x2).hypot(y0 - 2.0 * y1 + y2) / 4.0;
+        let n = ((deviation / FLATTEN_TOLERANCE).sqrt().ceil() as usize).max(1);
+        outline.extend((0..n).map(|k| piece.point(k as Float / n as Float)));
+    }
+    outline
+}
+
+impl Shape for Bezier3 {
+    fn sdf(&self, x: Float, y: Float) -> SdfResult {
+        let distance = pieces_distance(&self.pieces, x, y);
+        SdfResult {
+            sd: distance - self.thickness / 2.0,
+            material: Material::emissive(self.emissive),
+            profile: EmissionProfile::Uniform,
+        }
+    }
+
+    // 曲线在四个控制点的凸包内
+    fn bounds(&self) -> Option<Aabb> {
+        let half = self.thickness / 2.0;
+        let bounds = self.points.iter().fold(
+            Aabb::around(self.points[0].0, self.points[0].1, half, half),
+            |bounds, &(x, y)| bounds.union(&Aabb::around(x, y, half, half)),
+        );
+        Some(bounds)
+    }
+}
+
+// 经过所有控制点的闭合 Catmull-Rom 样条围成的区域
+// 每两个相邻控制点之间的一段转换为三次贝塞尔曲线, 再像 Bezier3 一样细分为若干段二次曲线, 距离是到最近的一段的距离
+// 符号由细分后的折线的环绕数决定(非零规则), 只有离边界不到 FLATTEN_TOLERANCE 的点符号可能不对
+#[derive(Clone)]
+pub struct Spline {
+    points: Vec<(Float, Float)>,
+    pieces: Vec<Bezier2>,
+    outline: Vec<(Float, Float)>,
+    emissive: Color,
+}
+
+impl Spline {
+    // 一般需要至少 3 个控制点; 更少的控制点围不成区域, 形状退化为 1 个点或者 2 个点之间的线段,
+    // sdf 是到它们的距离, 没有内部; 没有控制点时是空的, sdf 处处为无穷大
+    pub fn new(points: &[(Float, Float)], emissive: impl Into<Color>) -> Spline {
+        let len = points.len();
+        let mut pieces = vec![];
+        // 退化的形状不需要细分, 在 sdf 中直接计算距离
+        let segments = if len >= 3 { len } else { 0 };
+        for i in 0..segments {
+            let p0 = points[(i + len - 1) % len];
+            let (p1, p2) = (points[i], points[(i + 1) % len]);
+            let p3 = points[(i + 2) % len];
+            // 切线取 (p2 - p0) / 2 和 (p3 - p1) / 2, 对应的贝塞尔控制点离端点三分之一的切线长
+            let c1 = (p1.0 + (p2.0 - p0.0) / 6.0, p1.1 + (p2.1 - p0.1) / 6.0);
+            let c2 = (p2.0 - (p3.0 - p1.0) / 6.0, p2.1 - (p3.1 - p1.1) / 6.0);
+            Bezier3::flatten([p1, c1, c2, p2], 0, &mut pieces);
+        }
+
+        Spline {
+            points: points.to_vec(),
+            outline: pieces_outline(&pieces),
+            pieces,
+            emissive: emissive.into(),
+        }
+    }
+
+    // 近似的二次曲线的段数
+    pub fn pieces(&self) -> usize {
+        self.pieces.len()
+    }
+}
+
+impl Shape for Spline {
+    fn sdf(&self, x: Float, y: Float) -> SdfResult {
+        let sd = match self.points[..] {
+            [] => Float::INFINITY,
+            [(ax, ay)] => segment_distance(x, y, ax, ay, ax, ay),
+            [(ax, ay), (bx, by)] => segment_distance(x, y, ax, ay, bx, by),
+            _ => {
+                let distance = pieces_distance(&self.pieces, x, y);
+                let inside = winding_number(&self.outline, x, y) != 0;
+                if inside {
+                    -distance
+                } else {
+                    distance
+                }
+            }
+        };
+        SdfResult {
+            sd,
+            material: Material::emissive(self.emissive),
+            profile: EmissionProfile::Uniform,
+        }
+    }
+
+    // 每一段三次曲线在它的四个控制点的凸包内, 中间的两个控制点与端点的距离不超过相邻控制点间距的六分之一
+    fn bounds(&self) -> Option<Aabb> {
+        let bounds = self
+            .pieces
+            .iter()
+            .filter_map(|piece| piece.bounds())
+            .fold(None, |bounds: Option<Aabb>, piece| {
+                Some(bounds.map_or(piece, |bounds| bounds.union(&piece)))
+            });
+        // 退化为点或者线段时是控制点的包围盒
+        bounds.or_else(|| {
+            let (x, y) = *self.points.first()?;
+            let bounds = Aabb::new(x, y, x, y);
+            Some(self.points.iter().fold(bounds, |bounds, &(x, y)| {
+                bounds.union(&Aabb::new(x, y, x, y))
+            }))
+        })
+    }
+}
+
+// 心形: 由两个圆弧和两条与对称轴成 45 度的线段围成, 尖朝下(y 增大的方向)
+// (cx, cy) 是包围盒的中心, 心形的高度为 size, 需要发光以外的材质时用 Surface 包装
+#[derive(Clone)]
+pub struct Heart {
+    cx: Float,
+    cy: Float,
+    size: Float,
+    emissive: Color,
+}
+
+// 高度为 1 的心形: 尖在原点, 两个圆弧的圆心在 (±a, 3a), 半径为 √2·a, 最高点的高度 3a + √2·a 为 1
+// 圆弧与线段在 x + y = 4a 处相切, 两个圆弧在对称轴上的 (0, 4a) 处相交
+fn unit_heart() -> (Float, Float) {
+    let a = 1.0 / (3.0 + SQRT_2);
+    (a, SQRT_2 * a)
+}
+
+impl Heart {
+    pub fn new(cx: Float, cy: Float, size: Float, emissive: impl Into<Color>) -> Heart {
+        Heart {
+            cx,
+            cy,
+            size,
+            emissive: emissive.into(),
+        }
+    }
+}
+
+impl Shape for Heart {
+    // 变换到高度为 1、尖朝下在原点、y 轴向上的坐标系, 关于 y 轴对称只考虑 x ≥ 0 的一侧
+    fn sdf(&self, x: Float, y: Float) -> SdfResult {
+        let (a, r) = unit_heart();
+        let px = ((x - self.cx) / self.size).abs();
+        let py = (self.cy - y) / self.size + 0.5;
+        // 边界由线段 (0, 0)-(2a, 2a) 和圆弧组成, 取到两者的距离中较小的
+        let t = ((px + py) / 2.0).clamp(0.0, 2.0 * a);
+        let to_line = (px - t).hypot(py - t);
+        let to_center = (px - a).hypot(py - 3.0 * a);
+        let to_arc = if px + py >= 4.0 * a {
+            (to_center - r).abs()
+        } else {
+            // 最近的点在圆弧之外时, 是圆弧的两个端点之一: 切点或者对称轴上的凹点
+            let to_tangent = (px - 2.0 * a).hypot(py - 2.0 * a);
+            let to_cusp = px.hypot(py - 4.0 * a);
+            to_tangent.min(to_cusp)
+        };
+        let distance = to_line.min(to_arc);
+        let inside = if px + py > 4.0 * a {
+            to_center < r
+        } else {
+            px < py
+        };
+        let distance = if inside { -distance } else { distance };
+        SdfResult {
+            sd: distance * self.size,
+            material: Material::emissive(self.emissive),
+            profile: EmissionProfile::Uniform,
+        }
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        let (a, r) = unit_heart();
+        Some(Aabb::around(
+            self.cx,
+            self.cy,
+            (a + r) * self.size,
+            self.size / 2.0,
+        ))
+    }
+}
+
+// 十字: 以 (cx, cy) 为中心, 旋转角为 theta 的两根互相垂直的横条,
+// 半长分别为 sx 和 sy, 宽度都为 thickness
+#[derive(Clone)]
+pub struct Cross {
+    cx: Float,
+    cy: Float,
+    theta: Float,
+    sx: Float,
+    sy: Float,
+    thickness: Float,
+    emissive: Color,
+    // 四根横条末端的圆角半径, 不超过 thickness 的一半
+    r: Float,
+}
+
+impl Cross {
+    pub fn new(
+        cx: Float,
+        cy: Float,
+        theta: Float,
+        sx: Float,
+        sy: Float,
+        thickness: Float,
+        emissive: impl Into<Color>,
+    ) -> Cross {
+        Cross {
+            cx,
+            cy,
+            theta,
+            sx,
+            sy,
+            thickness,
+            emissive: emissive.into(),
+            r: 0.0,
+        }
+    }
+
+    // 末端的圆角半径为 r, 十字的总尺寸不变
+    pub fn with_radius(mut self, r: Float) -> Cross {
+        self.r = r.clamp(0.0, self.thickness / 2.0);
+        self
+    }
+}
+
+impl Shape for Cross {
+    // 关于两根横条对称, 只考虑第一象限: 十字在第一象限内的边界是一条折线,
+    // 最近的边界点总是在同一个象限内, 所以 sdf 就是到这条折线的距离
+    // 有圆角时先把十字缩小 r, 再向外扩展 r
+    fn sdf(&self, x: Float, y: Float) -> SdfResult {
+        let (sin_theta, cos_theta) = self.theta.sin_cos();
+        let px = ((x - self.cx) * cos_theta + (y - self.cy) * sin_theta).abs();
+        let py = ((y - self.cy) * cos_theta - (x - self.cx) * sin_theta).abs();
+        let h = self.thickness / 2.0 - self.r;
+        let (a, b) = (self.sx - self.r, self.sy - self.r);
+        let distance = segment_distance(px, py, a, 0.0, a, h)
+            .min(segment_distance(px, py, a, h, h, h))
+            .min(segment_distance(px, py, h, h, h, b))
+            .min(segment_distance(px, py, h, b, 0.0, b));
+        let inside = (px < a && py < h) || (px < h && py < b);
+        let sd = if inside { -distance } else { distance };
+        SdfResult {
+            sd: sd - self.r,
+            material: Material::emissive(self.emissive),
+            profile: EmissionProfile::Uniform,
+        }
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        let h = self.thickness / 2.0;
+        let horizontal = Rect::new(self.cx, self.cy, self.theta, self.sx, h, 0.0);
+        let vertical = Rect::new(self.cx, self.cy, self.theta, h, self.sy, 0.0);
+        union_bounds(horizontal.bounds(), vertical.bounds())
+    }
+
+    // 两根横条的面积减去重叠的部分, 八个凸角各变成四分之一圆
+    fn area(&self) -> Float {
+        let t = self.thickness;
+        2.0 * t * (self.sx + self.sy) - t * t - (8.0 - 2.0 * PI) * self.r * self.r
+    }
+
+    fn perimeter(&self) -> Float {
+        4.0 * (self.sx + self.sy) - (16.0 - 4.0 * PI) * self.r
+    }
+
+    fn centroid(&self) -> Option<(Float, Float)> {
+        Some((self.cx, self.cy))
+    }
+}
+
+// 菱形: 中心点(cx, cy), 旋转角(theta), 两条对角线的半长(sx, sy)
+#[derive(Clone)]
+pub struct Rhombus {
+    cx: Float,
+    cy: Float,
+    theta: Float,
+    sx: Float,
+    sy: Float,
+    emissive: Color,
+}
+
+impl Rhombus {
+    pub fn new(
+        cx: Float,
+        cy: Float,
+        theta: Float,
+        sx: Float,
+        sy: Float,
+        emissive: impl Into<Color>,
+    ) -> Rhombus {
+        Rhombus {
+            cx,
+            cy,
+            theta,
+            sx,
+            sy,
+            emissive: emissive.into(),
+        }
+    }
+}
+
+impl Shape for Rhombus {
+    // 关于两条对角线对称, 只考虑第一象限内从 (sx, 0) 到 (0, sy) 的边
+    // 点在这条边上的投影的参数 h 从 -1 到 1, 限制在边内之后求距离, 在边的内侧时为负
+    fn sdf(&self, x: Float, y: Float) -> SdfResult {
+        let (sin_theta, cos_theta) = self.theta.sin_cos();
+        let px = ((x - self.cx) * cos_theta + (y - self.cy) * sin_theta).abs();
+        let py = ((y - self.cy) * cos_theta - (x - self.cx) * sin_theta).abs();
+        let (bx, by) = (self.sx, self.sy);
+        let projection = (bx - 2.0 * px) * bx - (by - 2.0 * py) * by;
+        let h = (projection / (bx * bx + by * by)).clamp(-1.0, 1.0);
+        let distance = (px - 0.5 * bx * (1.0 - h)).hypot(py - 0.5 * by * (1.0 + h));
+        let sd = if px * by + py * bx < bx * by {
+            -distance
+        } else {
+            distance
+        };
+        SdfResult {
+            sd,
+            material: Material::emissive(self.emissive),
+            profile: EmissionProfile::Uniform,
+        }
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        let (sin_theta, cos_theta) = self.theta.sin_cos();
+        let hx = (cos_theta * self.sx).abs().max((sin_theta * self.sy).abs());
+        let hy = (sin_theta * self.sx).abs().max((cos_theta * self.sy).abs());
+        Some(Aabb::around(self.cx, self.cy, hx, hy))
+    }
+
+    fn area(&self) -> Float {
+        2.0 * self.sx * self.sy
+    }
+
+    fn perimeter(&self) -> Float {
+        4.0 * self.sx.hypot(self.sy)
+    }
+
+    fn centroid(&self) -> Option<(Float, Float)> {
+        Some((self.cx, self.cy))
+    }
+}
+
+// 等腰梯形: 中心点(cx, cy), 旋转角(theta), 半高(h)
+// 旋转前 y = -h 的底边的半宽为 s1, y = h 的底边的半宽为 s2
+#[derive(Clone)]
+pub struct Trapezoid {
+    cx: Float,
+    cy: Float,
+    theta: Float,
+    s1: Float,
+    s2: Float,
+    h: Float,
+    emissive: Color,
+}
+
+impl Trapezoid {
+    pub fn new(
+        cx: Float,
+        cy: Float,
+        theta: Float,
+        s1: Float,
+        s2: Float,
+        h: Float,
+        emissive: impl Into<Color>,
+    ) -> Trapezoid {
+        Trapezoid {
+            cx,
+            cy,
+            theta,
+            s1,
+            s2,
+            h,
+            emissive: emissive.into(),
+        }
+    }
+
+    // 旋转前的局部坐标 (u, v) 在场景中的位置
+    fn to_world(&self, u: Float, v: Float) -> (Float, Float) {
+        let (sin_theta, cos_theta) = self.theta.sin_cos();
+        (
+            self.cx + u * cos_theta - v * sin_theta,
+            self.cy + u * sin_theta + v * cos_theta,
+        )
+    }
+}
+
+impl Shape for Trapezoid {
+    // 关于 y 轴对称, 只考虑 x ≥ 0 的一侧: 分别求到两条底边和到腰的距离, 取较小的一个
+    // 在两条底边之间并且在腰的内侧时为负
+    fn sdf(&self, x: Float, y: Float) -> SdfResult {
+        let (sin_theta, cos_theta) = self.theta.sin_cos();
+        let px = ((x - self.cx) * cos_theta + (y - self.cy) * sin_theta).abs();
+        let py = (y - self.cy) * cos_theta - (x - self.cx) * sin_theta;
+        let width = if py < 0.0 { self.s1 } else { self.s2 };
+        let (ax, ay) = (px - px.min(width), py.abs() - self.h);
+        // 腰从 (s2, h) 到 (s1, -h)
+        let (kx, ky) = (self.s2 - self.s1, 2.0 * self.h);
+        let t = (((self.s2 - px) * kx + (self.h - py) * ky) / (kx * kx + ky * ky)).clamp(0.0, 1.0);
+        let (bx, by) = (px - self.s2 + kx * t, py - self.h + ky * t);
+        let distance = ax.hypot(ay).min(bx.hypot(by));
+        let sd = if bx < 0.0 && ay < 0.0 {
+            -distance
+        } else {
+            distance
+        };
+        SdfResult {
+            sd,
+            material: Material::emissive(self.emissive),
+            profile: EmissionProfile::Uniform,
+        }
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        let corners = [
+            self.to_world(-self.s1, -self.h),
+            self.to_world(self.s1, -self.h),
+            self.to_world(-self.s2, self.h),
+            self.to_world(self.s2, self.h),
+        ];
+        let (x, y) = corners[0];
+        let bounds = Aabb::new(x, y, x, y);
+        Some(corners.iter().fold(bounds, |bounds, &(x, y)| {
+            bounds.union(&Aabb::new(x, y, x, y))
+        }))
+    }
+
+    fn area(&self) -> Float {
+        2.0 * self.h * (self.s1 + self.s2)
+    }
+
+    fn perimeter(&self) -> Float {
+        2.0 * (self.s1 + self.s2) + 2.0 * (self.s2 - self.s1).hypot(2.0 * self.h)
+    }
+
+    // 在对称轴上, 离两条底边的距离之比为 (s1 + 2·s2) : (2·s1 + s2)
+    fn centroid(&self) -> Option<(Float, Float)> {
+        let sum = self.s1 + self.s2;
+        if sum == 0.0 {
+            return None;
+        }
+        Some(self.to_world(0.0, self.h * (self.s2 - self.s1) / (3.0 * sum)))
+    }
+}
+
+// 沿抛物线的一段描边, 宽度为 thickness, 两端是半圆, 加上反射材质就是抛物面反射镜
+// 旋转前顶点在 (cx, cy), 抛物线为 y = x² / 4f, 向 y 轴正方向开口, 焦点在 (cx, cy + f), x 的范围为 [-w, w]
+#[derive(Clone)]
+pub struct Parabola {
+    cx: Float,
+    cy: Float,
+    theta: Float,
+    f: Float,
+    w: Float,
+    thickness: Float,
+    emissive: Color,
+}
+
+impl Parabola {
+    pub fn new(
+        cx: Float,
+        cy: Float,
+        theta: Float,
+        f: Float,
+        w: Float,
+        thickness: Float,
+        emissive: impl Into<Color>,
+    ) -> Parabola {
+        Parabola {
+            cx,
+            cy,
+            theta,
+            f,
+            w,
+            thickness,
+            emissive: emissive.into(),
+        }
+    }
+
+    // 焦点在场景中的位置, 从焦点发出的光被抛物线反射后平行于对称轴
+    pub fn focus(&self) -> (Float, Float) {
+        self.to_world(0.0, self.f)
+    }
+
+    fn to_world(&self, u: Float, v: Float) -> (Float, Float) {
+        let (sin_theta, cos_theta) = self.theta.sin_cos();
+        (
+            self.cx + u * cos_theta - v * sin_theta,
+            self.cy + u * sin_theta + v * cos_theta,
+        )
+    }
+}
+
+impl Shape for Parabola {
+    // 关于对称轴对称, 只考虑 x ≥ 0 的一侧, 记 k = 1 / 4f
+    // 曲线上最近的点 (t, k·t²) 满足 2k²·t³ + (1 - 2k·y)·t - x = 0, x ≥ 0 时最近的点是最大的实根,
+    // 用卡尔达诺公式求出后限制在 [0, w] 内
+    fn sdf(&self, x: Float, y: Float) -> SdfResult {
+        let (sin_theta, cos_theta) = self.theta.sin_cos();
+        let px = ((x - self.cx) * cos_theta + (y - self.cy) * sin_theta).abs();
+        let py = (y - self.cy) * cos_theta - (x - self.cx) * sin_theta;
+        let k = 1.0 / (4.0 * self.f);
+        // 化为 t³ + a·t + b = 0
+        let a = (1.0 - 2.0 * k * py) / (2.0 * k * k);
+        let b = -px / (2.0 * k * k);
+        let discriminant = b * b / 4.0 + a * a * a / 27.0;
+        let t = if discriminant >= 0.0 {
+            let root = discriminant.sqrt();
+            (-b / 2.0 + root).cbrt() + (-b / 2.0 - root).cbrt()
+        } else {
+            let m = (-a / 3.0).sqrt();
+            let angle = (-b / (2.0 * m * m * m)).clamp(-1.0, 1.0).acos() / 3.0;
+            2.0 * m * angle.cos()
+        };
+        let t = t.clamp(0.0, self.w);
+        let distance = (px - t).hypot(py - k * t * t);
+        SdfResult {
+            sd: distance - self.thickness / 2.0,
+            material: Material::emissive(self.emissive),
+            profile: EmissionProfile::Uniform,
+        }
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        let depth = self.w * self.w / (4.0 * self.f);
+        let corners = [
+            self.to_world(-self.w, 0.0),
+            self.to_world(self.w, 0.0),
+            self.to_world(-self.w, depth),
+            self.to_world(self.w, depth),
+        ];
+        let half = self.thickness / 2.0;
+        let (x, y) = corners[0];
+        let bounds = Aabb::around(x, y, half, half);
+        Some(corners.iter().fold(bounds, |bounds, &(x, y)| {
+            bounds.union(&Aabb::around(x, y, half, half))
+        }))
+    }
+}
+
+// 透镜(双凸): 两个半径为 r 的圆的交集, 旋转前两个圆心在 (cx ± d, cy), 0 ≤ d < r
+// 透镜沿 x 轴的厚度为 2(r - d), 两个尖在 (cx, cy ± √(r² - d²))
+#[derive(Clone)]
+pub struct Vesica {
+    cx: Float,
+    cy: Float,
+    theta: Float,
+    r: Float,
+    d: Float,
+    emissive: Color,
+}
+
+impl Vesica {
+    pub fn new(
+        cx: Float,
+        cy: Float,
+        theta: Float,
+        r: Float,
+        d: Float,
+        emissive: impl Into<Color>,
+    ) -> Vesica {
+        Vesica {
+            cx,
+            cy,
+            theta,
+            r,
+            d,
+            emissive: emissive.into(),
+        }
+    }
+
+    // 尖到中心的距离
+    fn half_height(&self) -> Float {
+        (self.r * self.r - self.d * self.d).max(0.0).sqrt()
+    }
+
+    // 每段圆弧对应的圆心角的一半
+    fn half_angle(&self) -> Float {
+        (self.d / self.r).clamp(-1.0, 1.0).acos()
+    }
+}
+
+impl Shape for Vesica {
+    // 关于两条对称轴对称, 只考虑第一象限: 在尖与右边圆弧的圆心 (-d, 0) 的连线的延长线外侧时,
+    // 最近的是尖, 否则最近的在圆弧上
+    fn sdf(&self, x: Float, y: Float) -> SdfResult {
+        let (sin_theta, cos_theta) = self.theta.sin_cos();
+        let px = ((x - self.cx) * cos_theta + (y - self.cy) * sin_theta).abs();
+        let py = ((y - self.cy) * cos_theta - (x - self.cx) * sin_theta).abs();
+        let b = self.half_height();
+        let sd = if (py - b) * self.d > px * b {
+            px.hypot(py - b)
+        } else {
+            (px + self.d).hypot(py) - self.r
+        };
+        SdfResult {
+            sd,
+            material: Material::emissive(self.emissive),
+            profile: EmissionProfile::Uniform,
+        }
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        Rect::new(
+            self.cx,
+            self.cy,
+            self.theta,
+            self.r - self.d,
+            self.half_height(),
+            0.0,
+        )
+        .bounds()
+    }
+
+    // 两个弓形, 每个是扇形减去三角形
+    fn area(&self) -> Float {
+        2.0 * (self.r * self.r * self.half_angle() - self.d * self.half_height())
+    }
+
+    fn perimeter(&self) -> Float {
+        4.0 * self.r * self.half_angle()
+    }
+
+    fn centroid(&self) -> Option<(Float, Float)> {
+        Some((self.cx, self.cy))
+    }
+
+    fn supports_raycast(&self) -> bool {
+        true
+    }
+
+    // 透镜是凸的: 光线在两个圆内的区间的交集就是在透镜内的区间
+    fn raycast(&self, x: Float, y: Float, dx: Float, dy: Float) -> Option<Float> {
+        let (sin_theta, cos_theta) = self.theta.sin_cos();
+        let (ux, uy) = (self.d * cos_theta, self.d * sin_theta);
+        let mut enter = Float::NEG_INFINITY;
+        let mut exit = Float::INFINITY;
+        for &(ox, oy) in &[(self.cx - ux, self.cy - uy), (self.cx + ux, self.cy + uy)] {
+            let (vx, vy) = (x - ox, y - oy);
+            let b = vx * dx + vy * dy;
+            let c = vx * vx + vy * vy - self.r * self.r;
+            let discriminant = b * b - c;
+            if discriminant < 0.0 {
+                return Some(Float::INFINITY);
+            }
+            let root = discriminant.sqrt();
+            enter = enter.max(-b - root);
+            exit = exit.min(-b + root);
+        }
+        Some(if enter > exit {
+            Float::INFINITY
+        } else if enter >= 0.0 {
+            enter
+        } else if exit >= 0.0 {
+            exit
+        } else {
+            Float::INFINITY
+        })
+    }
+}
+
+// 月牙: 圆心在 (cx, cy)、半径为 ra 的圆减去沿 theta 方向偏移 d、半径为 rb 的圆
+#[derive(Clone)]
+pub struct Crescent {
+    cx: Float,
+    cy: Float,
+    theta: Float,
+    ra: Float,
+    rb: Float,
+    d: Float,
+    emissive: Color,
+}
+
+impl Crescent {
+    pub fn new(
+        cx: Float,
+        cy: Float,
+        theta: Float,
+        ra: Float,
+        rb: Float,
+        d: Float,
+        emissive: impl Into<Color>,
+    ) -> Crescent {
+        Crescent {
+            cx,
+            cy,
+            theta,
+            ra,
+            rb,
+            d,
+            emissive: emissive.into(),
+        }
+    }
+
+    // 两个圆的交点所对的、在对方圆内的圆弧的圆心角的一半, 分别在大圆和被减去的圆上
+    // 两个圆没有交点时, 一个圆在另一个圆内为 π, 否则为 0
+    fn half_angles(&self) -> (Float, Float) {
+        let (ra, rb, d) = (self.ra, self.rb, self.d);
+        if d >= ra + rb {
+            return (0.0, 0.0);
+        }
+        if d <= (ra - rb).abs() {
+            return if ra > rb { (0.0, PI) } else { (PI, 0.0) };
+        }
+        let alpha = ((d * d + ra * ra - rb * rb) / (2.0 * d * ra))
+            .clamp(-1.0, 1.0)
+            .acos();
+        let beta = ((d * d + rb * rb - ra * ra) / (2.0 * d * rb))
+            .clamp(-1.0, 1.0)
+            .acos();
+        (alpha, beta)
+    }
+}
+
+impl Shape for Crescent {
+    // 关于两个圆心的连线对称, 只考虑一侧: 两个圆的交点 (a, b) 是月牙的尖,
+    // 在尖附近的楔形区域内最近的是尖, 否则就是圆的差集的 sdf
+    fn sdf(&self, x: Float, y: Float) -> SdfResult {
+        let (sin_theta, cos_theta) = self.theta.sin_cos();
+        let px = (x - self.cx) * cos_theta + (y - self.cy) * sin_theta;
+        let py = ((y - self.cy) * cos_theta - (x - self.cx) * sin_theta).abs();
+        let (ra, rb, d) = (self.ra, self.rb, self.d);
+        // 同心时没有尖, 是两个同心圆的差集
+        if d == 0.0 {
+            let r = px.hypot(py);
+            return SdfResult {
+                sd: (r - ra).max(rb - r),
+                material: Material::emissive(self.emissive),
+                profile: EmissionProfile::Uniform,
+            };
+        }
+        let a = (ra * ra - rb * rb + d * d) / (2.0 * d);
+        let b = (ra * ra - a * a).max(0.0).sqrt();
+        let sd = if d * (px * b - py * a) > d * d * (b - py).max(0.0) {
+            (px - a).hypot(py - b)
+        } else {
+            (px.hypot(py) - ra).max(rb - (px - d).hypot(py))
+        };
+        SdfResult {
+            sd,
+            material: Material::emissive(self.emissive),
+            profile: EmissionProfile::Uniform,
+        }
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        Some(Aabb::around(self.cx, self.cy, self.ra, self.ra))
+    }
+
+    // 大圆的面积减去两个圆重叠的部分, 重叠部分是两个弓形
+    fn area(&self) -> Float {
+        let (alpha, beta) = self.half_angles();
+        let segment = |r: Float, angle: Float| r * r * (angle - angle.sin() * angle.cos());
+        PI * self.ra * self.ra - segment(self.ra, alpha) - segment(self.rb, beta)
+    }
+
+    // 大圆在被减去的圆外的圆弧, 加上被减去的圆在大圆内的圆弧
+    fn perimeter(&self) -> Float {
+        let (alpha, beta) = self.half_angles();
+        if alpha == PI {
+            return 0.0;
+        }
+        2.0 * self.ra * (PI - alpha) + 2.0 * self.rb * beta
+    }
+}
+
+// 超椭圆: 旋转前满足 |x / sx|^n + |y / sy|^n ≤ 1 的区域, 中心为 (cx, cy), 旋转角为 theta
+// n = 2 时是椭圆, n 在 4 左右时是圆角方形(squircle), n 越大越接近矩形, n < 1 时是四角星
+#[derive(Clone)]
+pub struct Superellipse {
+    cx: Float,
+    cy: Float,
+    theta: Float,
+    sx: Float,
+    sy: Float,
+    n: Float,
+    emissive: Color,
+}
+
+impl Superellipse {
+    pub fn new(
+        cx: Float,
+        cy: Float,
+        theta: Float,
+        sx: Float,
+        sy: Float,
+        n: Float,
+        emissive: impl Into<Color>,
+    ) -> Superellipse {
+        Superellipse {
+            cx,
+            cy,
+            theta,
+            sx,
+            sy,
+            n,
+            emissive: emissive.into(),
+        }
+    }
+
+    // 第一象限内 (px, py) 到边界的距离
+    // 以对角线 x / sx = y / sy 为界把边界分成两段, 靠近 x 轴的一段写成 x = sx·f(y / sy), 另一段对称,
+    // 其中 f(v) = (1 - v^n)^(1 / n), v ∈ [0, 2^(-1 / n)], 这样 n 很大时参数也不会挤在端点附近
+    fn distance(&self, px: Float, py: Float) -> Float {
+        Self::nearest(self.sx, self.sy, self.n, px, py)
+            .min(Self::nearest(self.sy, self.sx, self.n, py, px))
+    }
+
+    // (px, py) 到曲线 (a·f(v), b·v) 的最近距离
+    // 先等间隔取点找到最近的一段, 再在这一段内用牛顿法求 (C(v) - p)·C'(v) = 0 的根,
+    // 牛顿法的一步超出这一段或者导数不存在(n < 1 时的尖角处)时退回二分
+    fn nearest(a: Float, b: Float, n: Float, px: Float, py: Float) -> Float {
+        let end = (0.5 as Float).powf(1.0 / n);
+        let curve = |v: Float| {
+            let w = (1.0 - v.powf(n)).max(0.0);
+            let f = w.powf(1.0 / n);
+            let df = -v.powf(n - 1.0) * w.powf(1.0 / n - 1.0);
+            let ddf = (1.0 - n) * v.powf(n - 2.0) * w.powf(1.0 / n - 2.0);
+            (a * f - px, b * v - py, a * df, a * ddf)
+        };
+        let distance = |v: Float| {
+            let (ex, ey, _, _) = curve(v);
+            ex.hypot(ey)
+        };
+
+        let step = end / SUPERELLIPSE_SAMPLES as Float;
+        let (mut best, mut nearest) = (0, Float::INFINITY);
+        for i in 0..=SUPERELLIPSE_SAMPLES {
+            let d = distance(i as Float * step);
+            if d < nearest {
+                best = i;
+                nearest = d;
+            }
+        }
+
+        let mut low = best.saturating_sub(1) as Float * step;
+        let mut high = (best + 1).min(SUPERELLIPSE_SAMPLES) as Float * step;
+        let mut v = best as Float * step;
+        for _ in 0..SUPERELLIPSE_ITERATIONS {
+            let (ex, ey, dx, ddx) = curve(v);
+            let g = ex * dx + ey * b;
+            let slope = dx * dx + b * b + ex * ddx;
+            if g > 0.0 {
+                high = v;
+            } else {
+                low = v;
+            }
+            let next = v - g / slope;
+            v = if next > low && next < high {
+                next
+            } else {
+                (low + high) / 2.0
+            };
+            nearest = nearest.min(distance(v));
+        }
+        nearest
+    }
+}
+
+impl Shape for Superellipse {
+    // 距离是近似值: n ≥ 0.8 时与精确距离相差不到尺寸的 1e-6,
+    // n 更小时边界在四个角上是尖的, 牛顿法在尖角附近收敛很慢, n = 0.5 时误差可达尺寸的 2%
+    fn sdf(&self, x: Float, y: Float) -> SdfResult {
+        let (sin_theta, cos_theta) = self.theta.sin_cos();
+        let px = ((x - self.cx) * cos_theta + (y - self.cy) * sin_theta).abs();
+        let py = ((y - self.cy) * cos_theta - (x - self.cx) * sin_theta).abs();
+        let distance = self.distance(px, py);
+        let inside = (px / self.sx).powf(self.n) + (py / self.sy).powf(self.n) < 1.0;
+        SdfResult {
+            sd: if inside { -distance } else { distance },
+            material: Material::emissive(self.emissive),
+            profile: EmissionProfile::Uniform,
+        }
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        Rect::new(self.cx, self.cy, self.theta, self.sx, self.sy, 0.0).bounds()
+    }
+
+    fn centroid(&self) -> Option<(Float, Float)> {
+        Some((self.cx, self.cy))
+    }
+}
+
+// 齿轮: 中心点(cx, cy), 旋转角(theta), 齿数(teeth), 齿根圆半径(inner), 齿顶圆半径(outer)
+// 旋转前第一个齿朝向 x 轴正方向, 齿的两侧是直线, 齿顶是齿顶圆的一段圆弧
+// 齿形由 with_profile 指定, 默认齿根处占齿距的一半, 齿顶处占 0.3
+#[derive(Clone)]
+pub struct Gear {
+    cx: Float,
+    cy: Float,
+    theta: Float,
+    teeth: usize,
+    inner: Float,
+    outer: Float,
+    // 以齿的中心线为 y 轴的局部坐标中的一个齿, 从齿根圆内一直延伸到齿顶圆外
+    tooth: Trapezoid,
+    emissive: Color,
+}
+
+impl Gear {
+    pub fn new(
+        cx: Float,
+        cy: Float,
+        theta: Float,
+        teeth: usize,
+        inner: Float,
+        outer: Float,
+        emissive: impl Into<Color>,
+    ) -> Gear {
+        let teeth = teeth.max(1);
+        Gear {
+            cx,
+            cy,
+            theta,
+            teeth,
+            inner,
+            outer,
+            tooth: Self::tooth(teeth, inner, outer, 0.5, 0.3),
+            emissive: emissive.into(),
+        }
+    }
+
+    // 齿根圆和齿顶圆上齿的宽度分别占齿距的 root 和 tip (都按弦长计算, 取值在 0 到 1 之间)
+    // root > tip 时齿向外收窄, root = tip 时接近方齿
+    pub fn with_profile(mut self, root: Float, tip: Float) -> Self {
+        self.tooth = Self::tooth(self.teeth, self.inner, self.outer, root, tip);
+        self
+    }
+
+    pub fn teeth(&self) -> usize {
+        self.teeth
+    }
+
+    // 齿的底边是齿根圆的一条弦, 两端在齿根圆上, 这样齿和齿根圆之间没有缝隙
+    fn tooth(teeth: usize, inner: Float, outer: Float, root: Float, tip: Float) -> Trapezoid {
+        let pitch = PI / teeth as Float;
+        let s1 = inner * (pitch * root.clamp(0.0, 1.0)).sin();
+        let s2 = outer * (pitch * tip.clamp(0.0, 1.0)).sin();
+        let base = (inner * inner - s1 * s1).max(0.0).sqrt();
+        let h = (outer - base) / 2.0;
+        Trapezoid::new(0.0, base + h, 0.0, s1, s2, h, 0.0)
+    }
+}
+
+impl Shape for Gear {
+    // 把查询点的角度折叠到离它最近的齿所在的扇区, 再按齿的中心线对称折到一侧,
+    // 这时只需要考虑这个齿和另一侧相邻的齿: 齿根圆并上 (齿与齿顶圆的交集)
+    // 并集在外部是精确距离, 齿顶圆的交集在齿顶两角的外侧只是距离的下界, 与 CSG 的交集一样
+    fn sdf(&self, x: Float, y: Float) -> SdfResult {
+        let (dx, dy) = (x - self.cx, y - self.cy);
+        let r = dx.hypot(dy);
+        let sector = TAU / self.teeth as Float;
+        let angle = dy.atan2(dx) - self.theta;
+        let angle = (angle - sector * (angle / sector).round()).abs();
+        let tooth = |angle: Float| {
+            let (sin, cos) = angle.sin_cos();
+            self.tooth.sdf(r * sin, r * cos).sd
+        };
+        let teeth = if self.teeth > 1 {
+            tooth(angle).min(tooth(sector - angle))
+        } else {
+            tooth(angle)
+        };
+        SdfResult {
+            sd: (r - self.inner).min(teeth.max(r - self.outer)),
+            material: Material::emissive(self.emissive),
+            profile: EmissionProfile::Uniform,
+        }
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        Circle::new(self.cx, self.cy, self.outer, 0.0).bounds()
+    }
+
+    fn centroid(&self) -> Option<(Float, Float)> {
+        if self.teeth > 1 {
+            Some((self.cx, self.cy))
+        } else {
+            let bounds = self.bounds()?;
+            estimate_measure(self, &bounds).2
+        }
+    }
+}
+
+// 六角星: 中心点(cx, cy), 旋转角(theta), 六个角到中心的距离(r)
+// 旋转前有一个角朝向 y 轴正方向, 相当于两个外接圆半径为 r 的正三角形的并集, 但重叠的部分只算一次
+#[derive(Clone)]
+pub struct Hexagram {
+    cx: Float,
+    cy: Float,
+    theta: Float,
+    r: Float,
+    emissive: Color,
+}
+
+impl Hexagram {
+    pub fn new(
+        cx: Float,
+        cy: Float,
+        theta: Float,
+        r: Float,
+        emissive: impl Into<Color>,
+    ) -> Hexagram {
+        Hexagram {
+            cx,
+            cy,
+            theta,
+            r,
+            emissive: emissive.into(),
+        }
+    }
+}
+
+impl Shape for Hexagram {
+    // 按 x 轴、y 轴和两条 60° 的直线对称折叠后, 只需要求到一条边 y = r / 2, x ∈ [r / 2√3, r√3 / 2] 的距离
+    fn sdf(&self, x: Float, y: Float) -> SdfResult {
+        let (sin_theta, cos_theta) = self.theta.sin_cos();
+        let mut px = ((x - self.cx) * cos_theta + (y - self.cy) * sin_theta).abs();
+        let mut py = ((y - self.cy) * cos_theta - (x - self.cx) * sin_theta).abs();
+        let sqrt_3 = (3.0 as Float).sqrt();
+        let (kx, ky) = (-0.5, sqrt_3 / 2.0);
+        let d = 2.0 * (kx * px + ky * py).min(0.0);
+        px -= d * kx;
+        py -= d * ky;
+        let d = 2.0 * (ky * px + kx * py).min(0.0);
+        px -= d * ky;
+        py -= d * kx;
+        let h = self.r / 2.0;
+        px -= px.clamp(h / sqrt_3, h * sqrt_3);
+        py -= h;
+        let distance = px.hypot(py);
+        SdfResult {
+            sd: if py < 0.0 { -distance } else { distance },
+            material: Material::emissive(self.emissive),
+            profile: EmissionProfile::Uniform,
+        }
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        let (x, y) = (self.cx, self.cy);
+        Some((0..6).fold(Aabb::new(x, y, x, y), |bounds, i| {
+            let angle = self.theta + FRAC_PI_2 + i as Float * PI / 3.0;
+            let (x, y) = (
+                self.cx + self.r * angle.cos(),
+                self.cy + self.r * angle.sin(),
+            );
+            bounds.union(&Aabb::new(x, y, x, y))
+        }))
+    }
+
+    // 由 12 个边长为 r / √3 的正三角形组成
+    fn area(&self) -> Float {
+        (3.0 as Float).sqrt() * self.r * self.r
+    }
+
+    fn perimeter(&self) -> Float {
+        4.0 * (3.0 as Float).sqrt() * self.r
+    }
+
+    fn centroid(&self) -> Option<(Float, Float)> {
+        Some((self.cx, self.cy))
+    }
+}
+
+// 蛋形: 旋转前下半部分是以 (cx, cy) 为圆心、半径为 ra 的半圆, 上半部分收窄, 旋转角为 theta
+// 先取半径为 r = ra - rb 的半圆, 上面接两段半径为 2r、圆心在 (cx ∓ r, cy) 的圆弧, 交于尖端 (cx, cy + √3·r),
+// 再整体向外扩展 rb, 所以 rb 越大尖端越圆, rb = ra 时是圆
+#[derive(Clone)]
+pub struct Egg {
+    cx: Float,
+    cy: Float,
+    theta: Float,
+    ra: Float,
+    rb: Float,
+    emissive: Color,
+}
+
+impl Egg {
+    pub fn new(
+        cx: Float,
+        cy: Float,
+        theta: Float,
+        ra: Float,
+        rb: Float,
+        emissive: impl Into<Color>,
+    ) -> Egg {
+        Egg {
+            cx,
+            cy,
+            theta,
+            ra,
+            rb: rb.clamp(0.0, ra),
+            emissive: emissive.into(),
+        }
+    }
+
+    // 旋转前的局部坐标 (u, v) 在场景中的位置
+    fn to_world(&self, u: Float, v: Float) -> (Float, Float) {
+        let (sin_theta, cos_theta) = self.theta.sin_cos();
+        (
+            self.cx + u * cos_theta - v * sin_theta,
+            self.cy + u * sin_theta + v * cos_theta,
+        )
+    }
+}
+
+impl Shape for Egg {
+    // 关于 y 轴对称, 只考虑 x ≥ 0 的一侧: 下半部分是到半圆的距离,
+    // 在尖端的法线锥里是到尖端的距离, 否则是到圆心在 (-r, 0) 的大圆弧的距离, 最后减去 rb
+    fn sdf(&self, x: Float, y: Float) -> SdfResult {
+        let (sin_theta, cos_theta) = self.theta.sin_cos();
+        let px = ((x - self.cx) * cos_theta + (y - self.cy) * sin_theta).abs();
+        let py = (y - self.cy) * cos_theta - (x - self.cx) * sin_theta;
+        let k = (3.0 as Float).sqrt();
+        let r = self.ra - self.rb;
+        let distance = if py < 0.0 {
+            px.hypot(py) - r
+        } else if k * (px + r) < py {
+            px.hypot(py - k * r)
+        } else {
+            (px + r).hypot(py) - 2.0 * r
+        };
+        SdfResult {
+            sd: distance - self.rb,
+            material: Material::emissive(self.emissive),
+            profile: EmissionProfile::Uniform,
+        }
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        let top = (3.0 as Float).sqrt() * (self.ra - self.rb) + self.rb;
+        let corners = [
+            self.to_world(-self.ra, -self.ra),
+            self.to_world(self.ra, -self.ra),
+            self.to_world(-self.ra, top),
+            self.to_world(self.ra, top),
+        ];
+        let (x, y) = corners[0];
+        let bounds = Aabb::new(x, y, x, y);
+        Some(corners.iter().fold(bounds, |bounds, &(x, y)| {
+            bounds.union(&Aabb::new(x, y, x, y))
+        }))
+    }
+
+    // 扩展前的凸集面积为 (11π/6 - √3)·r², 周长为 7π·r/3, 扩展 rb 后面积增加 P·rb + π·rb², 周长增加 2π·rb
+    fn area(&self) -> Float {
+        let r = self.ra - self.rb;
+        let perimeter = 7.0 * PI * r / 3.0;
+        (11.0 * PI / 6.0 - (3.0 as Float).sqrt()) * r * r
+            + perimeter * self.rb
+            + PI * self.rb * self.rb
+    }
+
+    fn perimeter(&self) -> Float {
+        7.0 * PI * (self.ra - self.rb) / 3.0 + TAU * self.rb
+    }
+}
+
+// 由一条或多条闭合轮廓组成的多边形, 每条轮廓的最后一个顶点连回第一个顶点
+// 距离总是到最近的边的距离, 点是否在多边形内由填充规则决定
+#[derive(Clone)]
+pub struct Polygon {
+    contours: Vec<Vec<(Float, Float)>>,
+    fill_rule: FillRule,
+    // 由 new 创建的凸多边形, 面积、周长和形心有精确的公式
+    convex: bool,
+    emissive: Color,
+}
+
+impl Polygon {
+    // 凸多边形, 顶点按顺时针或者逆时针排列都可以, 至少需要 3 个顶点
+    pub fn new(points: &[(Float, Float)], emissive: impl Into<Color>) -> Polygon {
+        Polygon {
+            contours: vec![points.to_vec()],
+            fill_rule: FillRule::NonZero,
+            convex: true,
+            emissive: emissive.into(),
+        }
+    }
+
+    // 任意的多边形: 轮廓可以是凹的、自相交的, 也可以互相嵌套(例如带洞的字形)
+    pub fn with_contours(
+        contours: &[Vec<(Float, Float)>],
+        fill_rule: FillRule,
+        emissive: impl Into<Color>,
+    ) -> Polygon {
+        Polygon {
+            contours: contours.to_vec(),
+            fill_rule,
+            convex: false,
+            emissive: emissive.into(),
+        }
+    }
+
+    pub fn contours(&self) -> &[Vec<(Float, Float)>] {
+        &self.contours
+    }
+
+    pub fn fill_rule(&self) -> FillRule {
+        self.fill_rule
+    }
+
+    // 依次经过各条轮廓的各条边 (a, b)
+    fn edges(&self) -> impl Iterator<Item = ((Float, Float), (Float, Float))> + '_ {
+        self.contours.iter().flat_map(|points| {
+            let len = points.len();
+            (0..len).map(move |i| (points[i], points[(i + 1) % len]))
+        })
+    }
+
+    // 按顶点排列方向带符号的面积, 逆时针(y 轴向上时)为正
+    fn signed_area(&self) -> Float {
+        self.edges()
+            .map(|((ax, ay), (bx, by))| ax * by - bx * ay)
+            .sum::<Float>()
+            / 2.0
+    }
+}
+
+impl Shape for Polygon {
+    // 到最近的边的距离, 凸多边形按非零规则判断内外, 与顶点的排列方向无关
+    fn sdf(&self, x: Float, y: Float) -> SdfResult {
+        let distance = self
+            .edges()
+            .map(|((ax, ay), (bx, by))| segment_distance(x, y, ax, ay, bx, by))
+            .fold(Float::INFINITY, Float::min);
+        let sd = if self.fill_rule.contains(&self.contours, x, y) {
+            -distance
+        } else {
+            distance
+        };
+        SdfResult {
+            sd,
+            material: Material::emissive(self.emissive),
+            profile: EmissionProfile::Uniform,
+        }
+    }
+
+    // 没有顶点时 sdf 处处为无穷大, 也没有包围盒
+    fn bounds(&self) -> Option<Aabb> {
+        let mut points = self.contours.iter().flatten();
+        let &(x, y) = points.next()?;
+        let bounds = Aabb::new(x, y, x, y);
+        Some(points.fold(bounds, |bounds, &(x, y)| {
+            bounds.union(&Aabb::new(x, y, x, y))
+        }))
+    }
+
+    // 自相交和嵌套的轮廓中有的部分被重复计算, 有的是洞, 只有凸多边形使用精确的公式
+    fn area(&self) -> Float {
+        match self.bounds() {
+            Some(_) if self.convex => self.signed_area().abs(),
+            Some(bounds) => estimate_measure(self, &bounds).0,
+            None => 0.0,
+        }
+    }
+
+    fn perimeter(&self) -> Float {
+        match self.bounds() {
+            Some(_) if self.convex => self
+                .edges()
+                .map(|((ax, ay), (bx, by))| (bx - ax).hypot(by - ay))
+                .sum(),
+            Some(bounds) => estimate_measure(self, &bounds).1,
+            None => 0.0,
+        }
+    }
+
+    // 把多边形分成以原点为公共顶点的三角形, 按带符号的面积对三角形的形心加权平均
+    fn centroid(&self) -> Option<(Float, Float)> {
+        if !self.convex {
+            return estimate_measure(self, &self.bounds()?).2;
+        }
+        let area = self.signed_area();
+        if area == 0.0 {
+            return None;
+        }
+        let (mut sum_x, mut sum_y) = (0.0, 0.0);
+        for ((ax, ay), (bx, by)) in self.edges() {
+            let cross = ax * by - bx * ay;
+            sum_x += (ax + bx) * cross;
+            sum_y += (ay + by) * cross;
+        }
+        Some((sum_x / (6.0 * area), sum_y / (6.0 * area)))
+    }
+
+    fn supports_raycast(&self) -> bool {
+        self.convex || self.fill_rule != FillRule::NonZero
+    }
+
+    // 与每条边求交, 取不小于 0 的最近的交点
+    // 非零规则下自相交或者嵌套的轮廓可能有在内部的边, 穿过它时并没有离开多边形, 只能用光线步进
+    fn raycast(&self, x: Float, y: Float, dx: Float, dy: Float) -> Option<Float> {
+        if !self.supports_raycast() {
+            return None;
+        }
+        let mut nearest = Float::INFINITY;
+        for ((ax, ay), (bx, by)) in self.edges() {
+            let (ex, ey) = (bx - ax, by - ay);
+            let denominator = dx * ey - dy * ex;
+            if denominator == 0.0 {
+                continue;
+            }
+            let (vx, vy) = (ax - x, ay - y);
+            let t = (vx * ey - vy * ex) / denominator;
+            let s = (vx * dy - vy * dx) / denominator;
+            if t >= 0.0 && (0.0..=1.0).contains(&s) {
+                nearest = nearest.min(t);
+            }
+        }
+        Some(nearest)
+    }
+}
+
+#[derive(Clone)]
+pub struct Ellipse {
+    // 椭圆由中心点(cx, cy), 两个半轴长(rx, ry)和旋转角(theta)组成, 半轴长都要大于 0
+    cx: Float,
+    cy: Float,
+    rx: Float,
+    ry: Float,
+    theta: Float,
+    emissive: Color,
+}
+
+impl Ellipse {
+    pub fn new(
+        cx: Float,
+        cy: Float,
+        rx: Float,
+        ry: Float,
+        theta: Float,
+        emissive: impl Into<Color>,
+    ) -> Ellipse {
+        Ellipse {
+            cx,
+            cy,
+            rx,
+            ry,
+            theta,
+            emissive: emissive.into(),
+        }
+    }
+
+    // (x, y) 在椭圆自己的坐标系(中心为原点, 半轴与坐标轴对齐)中的坐标
+    fn to_local(&self, x: Float, y: Float) -> (Float, Float) {
+        let (sin_theta, cos_theta) = self.theta.sin_cos();
+        (
+            (x - self.cx) * cos_theta + (y - self.cy) * sin_theta,
+            (y - self.cy) * cos_theta - (x - self.cx) * sin_theta,
+        )
+    }
+
+    // 在椭圆自己的坐标系中, 椭圆上离 (px, py) 最近的点 (a·tx, b·ty), 返回 (tx, ty)
+    // 对称地只在第一象限计算, 每次迭代用最近点附近的曲率圆代替椭圆修正 (tx, ty), 几次迭代就足够精确
+    fn nearest(&self, px: Float, py: Float) -> (Float, Float) {
+        let (a, b) = (self.rx, self.ry);
+        let (px, py) = (px.abs(), py.abs());
+        let (mut tx, mut ty) = (FRAC_1_SQRT_2, FRAC_1_SQRT_2);
+        for _ in 0..ELLIPSE_ITERATIONS {
+            // 曲率中心 (ex, ey)
+            let ex = (a * a - b * b) * tx * tx * tx / a;
+            let ey = (b * b - a * a) * ty * ty * ty / b;
+            let r = (a * tx - ex).hypot(b * ty - ey);
+            let q = (px - ex).hypot(py - ey);
+            if q == 0.0 {
+                break;
+            }
+            tx = (((px - ex) * r / q + ex) / a).clamp(0.0, 1.0);
+            ty = (((py - ey) * r / q + ey) / b).clamp(0.0, 1.0);
+            let length = tx.hypot(ty);
+            tx /= length;
+            ty /= length;
+        }
+        (tx, ty)
+    }
+}
+
+impl Shape for Ellipse {
+    // 到椭圆上最近点的距离, 点在椭圆内时为负
+    fn sdf(&self, x: Float, y: Float) -> SdfResult {
+        let (px, py) = self.to_local(x, y);
+        let (tx, ty) = self.nearest(px, py);
+        let distance = (px.abs() - self.rx * tx).hypot(py.abs() - self.ry * ty);
+        let (u, v) = (px / self.rx, py / self.ry);
+        let sd = if u * u + v * v < 1.0 {
+            -distance
+        } else {
+            distance
+        };
+        SdfResult {
+            sd,
+            material: Material::emissive(self.emissive),
+            profile: EmissionProfile::Uniform,
+        }
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        let (sin_theta, cos_theta) = self.theta.sin_cos();
+        let hx = (self.rx * cos_theta).hypot(self.ry * sin_theta);
+        let hy = (self.rx * sin_theta).hypot(self.ry * cos_theta);
+        Some(Aabb::around(self.cx, self.cy, hx, hy))
+    }
+
+    fn area(&self) -> Float {
+        PI * self.rx * self.ry
+    }
+
+    // 椭圆的周长没有初等的表达式, 使用 Ramanujan 的第二个近似公式, 相对误差不超过 1e-4
+    fn perimeter(&self) -> Float {
+        let (a, b) = (self.rx, self.ry);
+        let h = ((a - b) / (a + b)).powi(2);
+        PI * (a + b) * (1.0 + 3.0 * h / (10.0 + (4.0 - 3.0 * h).sqrt()))
+    }
+
+    fn centroid(&self) -> Option<(Float, Float)> {
+        Some((self.cx, self.cy))
+    }
+
+    // 最近点处的外法线, 椭圆内外的点都一样
+    fn gradient(&self, x: Float, y: Float) -> (Float, Float) {
+        let (px, py) = self.to_local(x, y);
+        let (tx, ty) = self.nearest(px, py);
+        let nx = (tx / self.rx).copysign(px);
+        let ny = (ty / self.ry).copysign(py);
+        let length = nx.hypot(ny);
+        let (nx, ny) = (nx / length, ny / length);
+        let (sin_theta, cos_theta) = self.theta.sin_cos();
+        (
+            nx * cos_theta - ny * sin_theta,
+            nx * sin_theta + ny * cos_theta,
+        )
+    }
+
+    fn supports_raycast(&self) -> bool {
+        true
+    }
+
+    // 把椭圆缩放成单位圆之后解二次方程, 缩放不改变光线上的参数 t
+    fn raycast(&self, x: Float, y: Float, dx: Float, dy: Float) -> Option<Float> {
+        let (px, py) = self.to_local(x, y);
+        let (sin_theta, cos_theta) = self.theta.sin_cos();
+        let (ox, oy) = (px / self.rx, py / self.ry);
+        let vx = (dx * cos_theta + dy * sin_theta) / self.rx;
+        let vy = (dy * cos_theta - dx * sin_theta) / self.ry;
+        let a = vx * vx + vy * vy;
+        let b = ox * vx + oy * vy;
+        let c = ox * ox + oy * oy - 1.0;
+        let discriminant = b * b - a * c;
+        if discriminant < 0.0 {
+            return Some(Float::INFINITY);
+        }
+        let root = discriminant.sqrt();
+        Some(if -b - root >= 0.0 {
+            (-b - root) / a
+        } else if -b + root >= 0.0 {
+            (-b + root) / a
+        } else {
+            Float::INFINITY
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fill_rule() {
+        // 五角星: 中间的五边形被绕了两圈
+        let star: Vec<(Float, Float)> = (0..5)
+            .map(|i| {
+                let angle = crate::float::consts::TAU * (i * 2) as Float / 5.0;
+                (angle.cos(), angle.sin())
+            })
+            .collect();
+        let star = vec![star];
+        assert!(FillRule::NonZero.contains(&star, 0.0, 0.0));
+        assert!(!FillRule::EvenOdd.contains(&star, 0.0, 0.0));
+        assert!(FillRule::EvenOdd.contains(&star, 0.8, 0.0));
+        assert!(!FillRule::NonZero.contains(&star, 2.0, 0.0));
+
+        // 两个方向相同的嵌套正方形: 偶奇规则下里面的正方形是洞
+        let square = |r: Float| vec![(-r, -r), (r, -r), (r, r), (-r, r)];
+        let nested = vec![square(2.0), square(1.0)];
+        assert!(FillRule::NonZero.contains(&nested, 0.0, 0.0));
+        assert!(!FillRule::EvenOdd.contains(&nested, 0.0, 0.0));
+        assert!(FillRule::EvenOdd.contains(&nested, 1.5, 0.0));
+    }
+
+    #[test]
+    fn measure() {
+        let close = |a: Float, b: Float, tolerance: Float| (a - b).abs() <= b.abs() * tolerance;
+
+        let rect = Rect::new(0.0, 0.0, 0.5, 3.0, 2.0, 0.0);
+        assert_eq!(rect.area(), 24.0);
+        assert_eq!(rect.perimeter(), 20.0);
+        // 数值估计与精确值一致
+        let bounds = rect.bounds().unwrap();
+        let (area, perimeter, centroid) = estimate_measure(&rect, &bounds);
+        assert!(close(area, 24.0, 1e-3));
+        assert!(close(perimeter, 20.0, 1e-2));
+        let (cx, cy) = centroid.unwrap();
+        assert!(cx.abs() < 1e-3 && cy.abs() < 1e-3);
+
+        // 两个相同的圆相减得到月牙, 面积为圆的面积减去两圆重叠的面积
+        let moon = Shapes::subtract(
+            Box::new(Circle::new(0.0, 0.0, 2.0, 0.0)),
+            Box::new(Circle::new(2.0, 0.0, 2.0, 0.0)),
+        );
+        let lens = 8.0 * (0.5 as Float).acos() - (12 as Float).sqrt();
+        assert!(close(moon.area(), 4.0 * PI - lens, 1e-3));
+        assert!(moon.centroid().unwrap().0 < 0.0);
+
+        let capsule = Capsule::new(0.0, 0.0, 4.0, 0.0, 1.0, 0.0);
+        assert!(close(
+            Shapes::union(Box::new(capsule), Box::new(Circle::new(0.0, 0.0, 0.5, 0.0))).area(),
+            8.0 + PI,
+            1e-3
+        ));
+        assert_eq!(Plane::new(0.0, 0.0, 0.0, 1.0, 0.0).area(), Float::INFINITY);
+        assert_eq!(Plane::new(0.0, 0.0, 0.0, 1.0, 0.0).centroid(), None);
+    }
+
+    #[test]
+    fn rounded_rect() {
+        let rect = Rect::new(1.0, 2.0, 0.0, 3.0, 2.0, 1.0).with_radius(1.0);
+        // 边的中点不变, 角上是圆弧
+        assert_eq!(rect.sdf(4.0, 2.0).sd, 0.0);
+        assert_eq!(rect.sdf(1.0, 2.0).sd, -2.0);
+        assert!((rect.sdf(4.0, 4.0).sd - (SQRT_2 - 1.0)).abs() < 1e-6);
+        assert!((rect.sdf(6.0, 7.0).sd - 4.0).abs() < 1e-6);
+        assert!((rect.area() - (24.0 - 4.0 + PI)).abs() < 1e-6);
+        let bounds = rect.bounds().unwrap();
+        let (area, perimeter, _) = estimate_measure(&rect, &bounds);
+        assert!((area / rect.area() - 1.0).abs() < 1e-3);
+        assert!((perimeter / rect.perimeter() - 1.0).abs() < 1e-2);
+
+        // 半径不超过较短的半长, 此时短边变成半圆
+        let pill = Rect::new(0.0, 0.0, 0.0, 3.0, 1.0, 1.0).with_radius(5.0);
+        let capsule = Capsule::new(-2.0, 0.0, 2.0, 0.0, 1.0, 1.0);
+        for &(x, y) in &[(4.0, 1.0), (0.0, 0.5), (-3.0, -3.0)] {
+            assert!((pill.sdf(x, y).sd - capsule.sdf(x, y).sd).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn rounded_triangle() {
+        // 3-4-5 直角三角形的内切圆半径为 1, 内心在 (1, 1)
+        let sharp = Triangle::new(0.0, 0.0, 4.0, 0.0, 0.0, 3.0, 1.0);
+        let triangle = sharp.clone().with_radius(0.5);
+        // 边上离角较远的点不变, 角上是圆弧
+        assert!(triangle.sdf(2.0, 0.0).sd.abs() < 1e-6);
+        assert!((triangle.sdf(1.0, 1.0).sd - sharp.sdf(1.0, 1.0).sd).abs() < 1e-6);
+        assert!(triangle.sdf(0.0, 0.0).sd > 0.0);
+        assert!((triangle.sdf(-1.0, -1.0).sd - (1.5 * SQRT_2 - 0.5)).abs() < 1e-6);
+        let bounds = triangle.bounds().unwrap();
+        let (area, _, centroid) = estimate_measure(&triangle, &bounds);
+        assert!((area / triangle.area() - 1.0).abs() < 1e-3);
+        // 圆角让形心离开尖角三角形的形心 (4/3, 1)
+        let (cx, cy) = triangle.centroid().unwrap();
+        let (ex, ey) = centroid.unwrap();
+        assert!((cx - ex).abs() < 1e-3 && (cy - ey).abs() < 1e-3);
+        assert!((cx - 4.0 / 3.0).abs() > 1e-2);
+        assert_eq!(sharp.centroid(), Some((4.0 / 3.0, 1.0)));
+        // 缩小后的三角形的周长为 6, 加上三个角上合起来的一个整圆
+        assert!((triangle.perimeter() - (6.0 + PI)).abs() < 1e-6);
+        assert!(triangle.area() < sharp.area());
+
+        // 半径不超过内切圆的半径, 此时就是内切圆
+        let circle = sharp.with_radius(2.0);
+        assert!((circle.sdf(3.0, 1.0).sd - 1.0).abs() < 1e-6);
+        assert!((circle.area() - PI).abs() < 1e-6);
+        assert!((circle.perimeter() - TAU).abs() < 1e-6);
+        let (cx, cy) = circle.centroid().unwrap();
+        assert!((cx - 1.0).abs() < 1e-6 && (cy - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn gradient() {
+        // 使用 f32 时中心差分的舍入误差较大
+        let tolerance = if cfg!(feature = "f32") { 1e-3 } else { 1e-6 };
+        let close = |(ax, ay): (Float, Float), (bx, by): (Float, Float)| {
+            (ax - bx).abs() < tolerance && (ay - by).abs() < tolerance
+        };
+
+        // 精确的梯度与中心差分的估计一致
+        let circle = Circle::new(1.0, 2.0, 3.0, 0.0);
+        assert!(close(circle.gradient(4.0, 6.0), (0.6, 0.8)));
+        let estimate = estimate_gradient(&circle, 4.0, 6.0);
+        assert!(close(circle.gradient(4.0, 6.0), estimate));
+        assert_eq!(circle.gradient(1.0, 2.0), (0.0, 0.0));
+        let plane = Plane::new(0.0, 0.0, 0.6, 0.8, 0.0);
+        let estimate = estimate_gradient(&plane, 5.0, -3.0);
+        assert!(close(plane.gradient(5.0, -3.0), estimate));
+
+        // 月牙被挖掉的一侧, 梯度指向被减去的圆的圆心
+        let moon = Shapes::subtract(
+            Box::new(Circle::new(0.0, 0.0, 2.0, 0.0)),
+            Box::new(Circle::new(2.0, 0.0, 2.0, 0.0)),
+        );
+        assert!(close(moon.gradient(0.5, 0.0), (1.0, 0.0)));
+        assert!(close(moon.gradient(-1.5, 0.0), (-1.0, 0.0)));
+    }
+
+    #[test]
+    fn surface() {
+        // 材质代替了形状原来的自发光
+        let glass = Material::glass(1.5);
+        let surface = Surface::new(Box::new(Circle::new(0.0, 0.0, 1.0, 2.0)), glass);
+        assert_eq!(surface.sdf(0.0, 0.0).material, glass);
+        assert_eq!(surface.sdf(0.0, 0.0).sd, -1.0);
+        let circle = Circle::new(0.0, 0.0, 1.0, 2.0);
+        assert_eq!(circle.sdf(0.0, 0.0).material, Material::emissive(2.0));
+    }
+
+    #[test]
+    fn fn_shape() {
+        let (ox, oy, r) = (1.0, 2.0, 3.0);
+        let shape = FnShape::new(
+            move |x: Float, y: Float| (x - ox).hypot(y - oy) - r,
+            Material::emissive(2.0),
+        );
+        let circle = Circle::new(1.0, 2.0, 3.0, 2.0);
+        for &(x, y) in &[(0.0, 0.0), (1.0, 2.0), (5.0, -1.0)] {
+            let result = shape.sdf(x, y);
+            assert!((result.sd - circle.sdf(x, y).sd).abs() < 1e-9);
+            assert_eq!(result.material, Material::emissive(2.0));
+        }
+        assert_eq!(shape.bounds(), None);
+        assert_eq!(shape.area(), Float::INFINITY);
+
+        // 指定包围盒后可以估计面积, 也可以和其他形状组合
+        let shape = shape.with_bounds(circle.bounds().unwrap());
+        assert!((shape.area() / circle.area() - 1.0).abs() < 1e-3);
+        let union = Shapes::union(Box::new(shape), Box::new(Circle::new(6.0, 2.0, 1.0, 1.0)));
+        assert!((union.sdf(6.0, 2.0).sd + 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn smooth_union() {
+        let shape = Shapes::smooth_union(
+            Box::new(Circle::new(0.0, 0.0, 2.0, 1.0)),
+            Box::new(Circle::new(5.0, 0.0, 2.0, 3.0)),
+            2.0,
+        );
+        let union = Shapes::union(
+            Box::new(Circle::new(0.0, 0.0, 2.0, 1.0)),
+            Box::new(Circle::new(5.0, 0.0, 2.0, 3.0)),
+        );
+        // 离接缝远的地方与并集相同
+        for &(x, y) in &[(-3.0, 0.0), (0.0, 0.0), (8.0, 1.0), (5.0, -2.5)] {
+            let (result, expected) = (shape.sdf(x, y), union.sdf(x, y));
+            assert_eq!(result.sd, expected.sd);
+            assert_eq!(result.material, expected.material);
+        }
+        // 接缝中间两个距离相同, 距离减小 k / 4, 自发光取平均
+        let result = shape.sdf(2.5, 0.0);
+        assert!((result.sd - (0.5 - 0.5)).abs() < 1e-9);
+        assert_eq!(result.material.emissive, Color::gray(2.0));
+        // 两个圆之间原来的空隙被填上了一部分
+        assert!(union.sdf(2.5, 0.0).sd > 0.0 && shape.sdf(2.5, 0.0).sd <= 0.0);
+        let bounds = shape.bounds().unwrap();
+        assert_eq!(bounds, Aabb::new(-2.5, -2.5, 7.5, 2.5));
+
+        // k 为 0 时就是并集
+        let sharp = Shapes::smooth_union(
+            Box::new(Circle::new(0.0, 0.0, 2.0, 1.0)),
+            Box::new(Circle::new(5.0, 0.0, 2.0, 3.0)),
+            0.0,
+        );
+        assert_eq!(sharp.sdf(2.5, 0.0).sd, union.sdf(2.5, 0.0).sd);
+    }
+
+    #[test]
+    fn smooth_intersect_and_subtract() {
+        // 矩形的范围是 x ∈ [0, 4], y ∈ [-1, 1], 与圆的边交于 (√3, ±1)
+        let circle = || Box::new(Circle::new(0.0, 0.0, 2.0, 1.0));
+        let rect = || Box::new(Rect::new(2.0, 0.0, 0.0, 2.0, 1.0, 3.0));
+        let intersect = Shapes::intersect(circle(), rect());
+        let smooth = Shapes::smooth_intersect(circle(), rect(), 1.0);
+        // 两个距离相差 k 以上的地方与交集相同
+        for &(x, y) in &[(3.0, 0.0), (-3.0, 0.0), (0.5, 0.0)] {
+            let (result, expected) = (smooth.sdf(x, y), intersect.sdf(x, y));
+            assert_eq!(result.sd, expected.sd);
+            assert_eq!(result.material, expected.material);
+        }
+        // 两条边的交点处距离增加 k / 4, 自发光取平均, 尖角变成圆角
+        let result = smooth.sdf((3.0 as Float).sqrt(), 1.0);
+        assert!((result.sd - 0.25).abs() < 1e-9);
+        assert!((result.material.emissive.r - 2.0).abs() < 1e-9);
+        assert_eq!(smooth.bounds(), intersect.bounds());
+
+        let subtract = Shapes::subtract(circle(), rect());
+        let soft = Shapes::smooth_subtract(circle(), rect(), 1.0);
+        for &(x, y) in &[(-1.5, 0.0), (-3.0, 0.0), (2.0, 0.0)] {
+            assert_eq!(soft.sdf(x, y).sd, subtract.sdf(x, y).sd);
+        }
+        // (0, 1.5) 离圆和矩形的边都是 0.5, 差集为 -0.5, 平滑后的边缘离它更近
+        assert!((subtract.sdf(0.0, 1.5).sd + 0.5).abs() < 1e-9);
+        assert!((soft.sdf(0.0, 1.5).sd + 0.25).abs() < 1e-9);
+        assert_eq!(soft.sdf(0.0, 1.5).material, Material::emissive(1.0));
+        assert_eq!(soft.bounds(), circle().bounds());
+
+        // k 为 0 时与交集、差集相同
+        let sharp = Shapes::smooth_intersect(circle(), rect(), 0.0);
+        assert_eq!(sharp.sdf(0.0, 1.0).sd, intersect.sdf(0.0, 1.0).sd);
+        let sharp = Shapes::smooth_subtract(circle(), rect(), 0.0);
+        assert_eq!(sharp.sdf(0.0, 1.5).sd, subtract.sdf(0.0, 1.5).sd);
+    }
+
+    #[test]
+    fn union_all() {
+        let circles = || -> Vec<Box<dyn Shape>> {
+            (0..5)
+                .map(|i| {
+                    Box::new(Circle::new(i as Float * 3.0, 0.0, 1.0, i as Float)) as Box<dyn Shape>
+                })
+                .collect()
+        };
+        let flat = Shapes::union_all(circles());
+        let nested = circles()
+            .into_iter()
+            .reduce(|a, b| Shapes::union(a, b))
+            .unwrap();
+        for i in 0..100 {
+            let (x, y) = (i as Float * 0.15 - 1.0, (i * 7 % 100) as Float * 0.05 - 2.5);
+            let (r1, r2) = (flat.sdf(x, y), nested.sdf(x, y));
+            assert_eq!(r1.sd, r2.sd);
+            assert_eq!(r1.material, r2.material);
+            assert_eq!(flat.gradient(x, y), nested.gradient(x, y));
+        }
+        assert_eq!(flat.bounds(), nested.bounds());
+        assert_eq!(flat.bounds(), Some(Aabb::new(-1.0, -1.0, 13.0, 1.0)));
+
+        let empty = Shapes::union_all(vec![]);
+        assert_eq!(empty.sdf(0.0, 0.0).sd, Float::MAX);
+        assert_eq!(empty.bounds(), None);
+    }
+
+    #[test]
+    fn invert() {
+        let shape = Shapes::invert(Box::new(Circle::new(0.0, 0.0, 10.0, 1.0)));
+        assert_eq!(shape.sdf(0.0, 0.0).sd, 10.0);
+        assert_eq!(shape.sdf(20.0, 0.0).sd, -10.0);
+        assert_eq!(shape.sdf(20.0, 0.0).material.emissive, Color::gray(1.0));
+        assert_eq!(shape.gradient(0.0, 5.0), (0.0, -1.0));
+        assert_eq!(shape.bounds(), None);
+        assert_eq!(shape.area(), Float::INFINITY);
+        assert!((shape.perimeter() - TAU * 10.0).abs() < 1e-6);
+        assert_eq!(shape.raycast(0.0, 0.0, 1.0, 0.0), Some(10.0));
+
+        // 房间: 挖空的圆盘里面是空的, 外面是墙
+        let room = Shapes::intersect(
+            Shapes::invert(Box::new(Circle::new(0.0, 0.0, 10.0, 0.0))),
+            Box::new(Circle::new(0.0, 0.0, 12.0, 0.0)),
+        );
+        assert!(room.sdf(0.0, 0.0).sd > 0.0);
+        assert!(room.sdf(11.0, 0.0).sd < 0.0);
+        assert!(room.sdf(13.0, 0.0).sd > 0.0);
+    }
+
+    #[test]
+    fn offset() {
+        let rect = || Box::new(Rect::new(0.0, 0.0, 0.0, 4.0, 2.0, 1.0));
+        let grown = Shapes::offset(rect(), 1.0);
+        let tolerance = if cfg!(feature = "f32") { 1e-3 } else { 1e-6 };
+        assert_eq!(grown.sdf(5.0, 0.0).sd, 0.0);
+        // 角上变成圆角
+        assert!((grown.sdf(5.0, 3.0).sd - (SQRT_2 - 1.0)).abs() < tolerance);
+        assert_eq!(grown.bounds(), Some(Aabb::new(-5.0, -3.0, 5.0, 3.0)));
+        let (gx, gy) = grown.gradient(6.0, 0.0);
+        assert!((gx - 1.0).abs() < tolerance && gy.abs() < tolerance);
+        assert_eq!(grown.sdf(0.0, 0.0).material.emissive, Color::gray(1.0));
+        let expected = 8.0 * 4.0 + 2.0 * (8.0 + 4.0) + PI;
+        assert!((grown.area() / expected - 1.0).abs() < 1e-2);
+
+        let shrunk = Shapes::offset(rect(), -1.0);
+        assert_eq!(shrunk.sdf(3.0, 0.0).sd, 0.0);
+        assert_eq!(shrunk.bounds(), rect().bounds());
+        assert!((shrunk.area() / (6.0 * 2.0) - 1.0).abs() < 1e-2);
+    }
+
+    #[test]
+    fn shell() {
+        let shape = Shapes::shell(Box::new(Circle::new(0.0, 0.0, 10.0, 1.0)), 1.0);
+        assert_eq!(shape.sdf(0.0, 0.0).sd, 9.0);
+        assert_eq!(shape.sdf(10.0, 0.0).sd, -1.0);
+        assert_eq!(shape.sdf(0.0, 12.0).sd, 1.0);
+        assert_eq!(shape.sdf(0.0, 0.0).material.emissive, Color::gray(1.0));
+        assert_eq!(shape.gradient(0.0, 5.0), (0.0, -1.0));
+        assert_eq!(shape.gradient(0.0, 15.0), (0.0, 1.0));
+        assert_eq!(shape.bounds(), Some(Aabb::new(-11.0, -11.0, 11.0, 11.0)));
+        let expected = PI * (11.0 * 11.0 - 9.0 * 9.0);
+        assert!((shape.area() / expected - 1.0).abs() < 1e-2);
+    }
+
+    #[test]
+    fn transformed() {
+        let tolerance = if cfg!(feature = "f32") { 1e-4 } else { 1e-9 };
+        // 宽 4 高 2 的矩形放大 2 倍, 旋转 90 度, 移到 (10, 20), 变成宽 4 高 8 的矩形
+        let shape = Transformed::new(Box::new(Rect::new(0.0, 0.0, 0.0, 2.0, 1.0, 1.0)))
+            .with_scale(2.0)
+            .with_rotation(FRAC_PI_2)
+            .with_translation(10.0, 20.0);
+        let expected = Rect::new(10.0, 20.0, 0.0, 2.0, 4.0, 1.0);
+        for i in 0..100 {
+            let (x, y) = (i as Float * 0.2, 10.0 + (i * 7 % 100) as Float * 0.2);
+            assert!((shape.sdf(x, y).sd - expected.sdf(x, y).sd).abs() < tolerance);
+            let (gx, gy) = shape.gradient(x, y);
+            let (ex, ey) = expected.gradient(x, y);
+            assert!((gx - ex).abs() < 1e-3 && (gy - ey).abs() < 1e-3);
+        }
+        let bounds = shape.bounds().unwrap();
+        let expected_bounds = expected.bounds().unwrap();
+        assert!((bounds.min_x - expected_bounds.min_x).abs() < tolerance);
+        assert!((bounds.max_y - expected_bounds.max_y).abs() < tolerance);
+        assert!((shape.area() - 32.0).abs() < 1e-3);
+        assert!((shape.perimeter() - 24.0).abs() < 1e-3);
+        let (cx, cy) = shape.centroid().unwrap();
+        assert!((cx - 10.0).abs() < 1e-3 && (cy - 20.0).abs() < 1e-3);
+        assert_eq!(shape.sdf(10.0, 20.0).material.emissive, Color::gray(1.0));
+
+        let circle = Transformed::new(Box::new(Circle::new(1.0, 0.0, 1.0, 0.0)))
+            .with_scale(3.0)
+            .with_translation(5.0, 0.0);
+        let t = circle.raycast(0.0, 0.0, 1.0, 0.0).unwrap();
+        assert!((t - 5.0).abs() < tolerance);
+        assert!((circle.sdf(8.0, 0.0).sd + 3.0).abs() < tolerance);
+    }
+
+    #[test]
+    fn scaled() {
+        // 单位圆拉成半轴为 3 和 1 的椭圆
+        let shape = Shapes::scale(Box::new(Circle::new(0.0, 0.0, 1.0, 1.0)), 3.0, 1.0);
+        let ellipse = Ellipse::new(0.0, 0.0, 3.0, 1.0, 0.0, 1.0);
+        for i in 0..100 {
+            let (x, y) = (i as Float * 0.1 - 5.0, (i * 7 % 100) as Float * 0.05 - 2.5);
+            let (sd, expected) = (shape.sdf(x, y).sd, ellipse.sdf(x, y).sd);
+            // 符号相同, 绝对值不超过真实距离
+            assert_eq!(sd < 0.0, expected < 0.0);
+            assert!(sd.abs() <= expected.abs() + 1e-4);
+        }
+        assert!(shape.sdf(3.0, 0.0).sd.abs() < 1e-6);
+        assert!((shape.sdf(0.0, 2.0).sd - 1.0).abs() < 1e-6);
+        // 在边上梯度是椭圆的法线
+        let (x, y) = (3.0 * FRAC_1_SQRT_2, FRAC_1_SQRT_2);
+        let ((gx, gy), (ex, ey)) = (shape.gradient(x, y), ellipse.gradient(x, y));
+        assert!((gx - ex).abs() < 1e-3 && (gy - ey).abs() < 1e-3);
+        assert_eq!(shape.bounds(), Some(Aabb::new(-3.0, -1.0, 3.0, 1.0)));
+        assert!((shape.area() - 3.0 * PI).abs() < 1e-6);
+        assert!((shape.perimeter() / ellipse.perimeter() - 1.0).abs() < 1e-2);
+        assert_eq!(shape.centroid(), Some((0.0, 0.0)));
+    }
+
+    #[test]
+    fn mirror() {
+        let tolerance = if cfg!(feature = "f32") { 1e-4 } else { 1e-9 };
+        let circle = || Box::new(Circle::new(3.0, 1.0, 1.0, 1.0));
+        let shape = Shapes::mirror_x(circle(), 1.0);
+        let mirrored = Circle::new(-1.0, 1.0, 1.0, 1.0);
+        for i in 0..100 {
+            let (x, y) = (i as Float * 0.1 - 5.0, (i * 7 % 100) as Float * 0.06 - 2.0);
+            let expected = circle().sdf(x, y).sd.min(mirrored.sdf(x, y).sd);
+            assert!((shape.sdf(x, y).sd - expected).abs() < tolerance);
+        }
+        assert_eq!(shape.gradient(-3.0, 1.0), (-1.0, 0.0));
+        assert_eq!(shape.gradient(5.0, 1.0), (1.0, 0.0));
+        assert_eq!(shape.bounds(), Some(Aabb::new(-2.0, 0.0, 4.0, 2.0)));
+        assert_eq!(shape.sdf(-1.0, 1.0).material.emissive, Color::gray(1.0));
+
+        let shape = Shapes::mirror_y(circle(), 0.0);
+        assert!((shape.sdf(3.0, -1.0).sd + 1.0).abs() < tolerance);
+        // 在直线另一侧的形状被去掉
+        assert!(Shapes::mirror_y(circle(), 3.0).sdf(3.0, 1.0).sd > 0.0);
+
+        // 关于直线 y = x 对称, 保留直线右下方的部分
+        let shape = Shapes::mirror(circle(), 0.0, 0.0, 1.0, -1.0);
+        assert!((shape.sdf(1.0, 3.0).sd + 1.0).abs() < tolerance);
+        assert!((shape.sdf(3.0, 1.0).sd + 1.0).abs() < tolerance);
+        let (gx, gy) = shape.gradient(1.0, 5.0);
+        assert!(gx.abs() < 1e-3 && (gy - 1.0).abs() < 1e-3);
+
+        // 法线为 (0, 0) 时关于竖直线对称
+        let shape = Shapes::mirror(circle(), 1.0, 5.0, 0.0, 0.0);
+        let expected = Shapes::mirror_x(circle(), 1.0);
+        for &(x, y) in &[(-1.0, 1.0), (3.0, 1.0), (0.0, -2.0)] {
+            assert_eq!(shape.sdf(x, y).sd, expected.sdf(x, y).sd);
+        }
+        assert_eq!(shape.bounds(), expected.bounds());
+    }
+
+    #[test]
+    fn repeat() {
+        let tolerance = if cfg!(feature = "f32") { 1e-4 } else { 1e-9 };
+        let shape = Shapes::repeat(Box::new(Circle::new(0.0, 0.0, 1.0, 1.0)), 10.0, 5.0);
+        assert!((shape.sdf(30.0, -15.0).sd + 1.0).abs() < tolerance);
+        assert!((shape.sdf(-102.0, 5.0).sd - 1.0).abs() < tolerance);
+        // 两个格子中间离两边的圆一样远
+        assert!((shape.sdf(5.0, 0.0).sd - 4.0).abs() < tolerance);
+        assert!((shape.sdf(0.0, 2.5).sd - 1.5).abs() < tolerance);
+        let (gx, gy) = shape.gradient(48.0, 0.0);
+        assert!((gx + 1.0).abs() < tolerance && gy.abs() < tolerance);
+        assert_eq!(shape.bounds(), None);
+        assert_eq!(shape.sdf(10.0, 10.0).material.emissive, Color::gray(1.0));
+
+        // 只在 x 方向重复
+        let row = Shapes::repeat(Box::new(Circle::new(0.0, 0.0, 1.0, 1.0)), 10.0, 0.0);
+        assert!((row.sdf(20.0, 5.0).sd - 4.0).abs() < tolerance);
+    }
+
+    #[test]
+    fn repeat_polar() {
+        let tolerance = if cfg!(feature = "f32") { 1e-4 } else { 1e-9 };
+        // 以 (10, 10) 为中心, 半径 5 的圆上的 12 个小圆, 像钟面
+        let shape =
+            Shapes::repeat_polar(Box::new(Circle::new(15.0, 10.0, 1.0, 1.0)), 10.0, 10.0, 12);
+        let copies: Vec<Circle> = (0..12)
+            .map(|i| {
+                let angle = i as Float * TAU / 12.0;
+                Circle::new(10.0 + 5.0 * angle.cos(), 10.0 + 5.0 * angle.sin(), 1.0, 1.0)
+            })
+            .collect();
+        for i in 0..100 {
+            let (x, y) = (i as Float * 0.2, (i * 7 % 100) as Float * 0.2);
+            let expected = copies
+                .iter()
+                .map(|circle| circle.sdf(x, y).sd)
+                .fold(Float::INFINITY, Float::min);
+            assert!((shape.sdf(x, y).sd - expected).abs() < tolerance);
+        }
+        // 12 点方向的小圆
+        assert!((shape.sdf(10.0, 5.0).sd + 1.0).abs() < tolerance);
+        let (gx, gy) = shape.gradient(10.0, 3.0);
+        assert!(gx.abs() < tolerance && (gy + 1.0).abs() < tolerance);
+        let bounds = shape.bounds().unwrap();
+        assert!((bounds.max_x - 10.0 - (36.0 as Float + 1.0).sqrt()).abs() < tolerance);
+        assert!((shape.area() / (12.0 * PI) - 1.0).abs() < 1e-2);
+
+        // 0 份按 1 份处理, 与原来的形状相同
+        let circle = Circle::new(15.0, 10.0, 1.0, 1.0);
+        let shape = Shapes::repeat_polar(Box::new(circle.clone()), 10.0, 10.0, 0);
+        for &(x, y) in &[(15.0, 10.0), (12.0, 10.0), (16.0, 12.0)] {
+            assert!((shape.sdf(x, y).sd - circle.sdf(x, y).sd).abs() < tolerance);
+        }
+    }
+
+    #[test]
+    fn value_noise_is_smooth_and_bounded() {
+        let mut previous = value_noise(0.0, 0.3, 7, 0);
+        for i in 1..1000 {
+            let x = i as Float * 0.01;
+            let noise = value_noise(x, 0.3, 7, 0);
+            assert!((-1.0..=1.0).contains(&noise));
+            // 导数不超过 2 * 15 / 8
+            assert!((noise - previous).abs() <= 0.01 * 3.75 + 1e-6);
+            previous = noise;
+        }
+        assert_eq!(value_noise(2.0, 3.0, 7, 0), value_noise(2.0, 3.0, 7, 0));
+        assert_ne!(value_noise(2.5, 3.5, 7, 0), value_noise(2.5, 3.5, 7, 1));
+        assert_ne!(value_noise(2.5, 3.5, 7, 0), value_noise(2.5, 3.5, 8, 0));
+    }
+
+    #[test]
+    fn warped() {
+        let shape = Warped::new(Box::new(Circle::new(0.0, 0.0, 10.0, 1.0)), 1.0, 0.2, 3);
+        let bounds = shape.bounds().unwrap();
+        assert_eq!(bounds, Aabb::new(-11.0, -11.0, 11.0, 11.0));
+        // 扰动后的边在原来的边附近
+        assert!(shape.sdf(0.0, 0.0).sd < 0.0);
+        assert!(shape.sdf(12.0, 0.0).sd > 0.0);
+        // 离边的距离的估计不超过真实距离: 从 p 沿任何方向走 sd 都不会穿过边
+        for i in 0..40 {
+            let angle = i as Float * TAU / 40.0;
+            let (x, y) = (15.0 * angle.cos(), 15.0 * angle.sin());
+            let sd = shape.sdf(x, y).sd;
+            assert!(sd > 0.0);
+            for j in 0..40 {
+                let direction = j as Float * TAU / 40.0;
+                let (qx, qy) = (x + sd * direction.cos(), y + sd * direction.sin());
+                assert!(shape.sdf(qx, qy).sd >= 0.0);
+            }
+        }
+        let (gx, gy) = shape.gradient(14.0, 0.0);
+        assert!((gx.hypot(gy) - 1.0).abs() < 1e-3);
+        assert!(gx > 0.5);
+        assert_eq!(shape.sdf(0.0, 0.0).material.emissive, Color::gray(1.0));
+        // 没有扰动时与原来的形状相同
+        let plain = Warped::new(Box::new(Circle::new(0.0, 0.0, 10.0, 1.0)), 0.0, 0.2, 3);
+        assert_eq!(plain.sdf(3.0, 4.0).sd, -5.0);
+    }
+
+    // 从 p 沿任何方向走 |sd| 都不会穿过边
+    fn assert_conservative(shape: &dyn Shape, x: Float, y: Float) {
+        let tolerance = if cfg!(feature = "f32") { 1e-5 } else { 1e-9 };
+        let sd = shape.sdf(x, y).sd;
+        for i in 0..32 {
+            let angle = i as Float * TAU / 32.0;
+            let (qx, qy) = (x + sd.abs() * angle.cos(), y + sd.abs() * angle.sin());
+            assert!(shape.sdf(qx, qy).sd * sd >= -tolerance);
+        }
+    }
+
+    #[test]
+    fn shear() {
+        let tolerance = if cfg!(feature = "f32") { 1e-4 } else { 1e-9 };
+        // 宽 4 高 2 的矩形变成平行四边形, 顶点 (-3, -1), (1, -1), (3, 1), (-1, 1)
+        let rect = || Box::new(Rect::new(0.0, 0.0, 0.0, 2.0, 1.0, 1.0));
+        let shape = Shapes::shear_x(rect(), 1.0);
+        let expected = Polygon::new(&[(-3.0, -1.0), (1.0, -1.0), (3.0, 1.0), (-1.0, 1.0)], 1.0);
+        for i in 0..100 {
+            let (x, y) = (i as Float * 0.1 - 5.0, (i * 7 % 100) as Float * 0.05 - 2.5);
+            let (sd, exact) = (shape.sdf(x, y).sd, expected.sdf(x, y).sd);
+            assert_eq!(sd < 0.0, exact < 0.0);
+            assert!(sd.abs() <= exact.abs() + tolerance);
+            assert_conservative(shape.as_ref(), x, y);
+        }
+        assert!(shape.sdf(3.0, 1.0).sd.abs() < tolerance);
+        let bounds = shape.bounds().unwrap();
+        assert!((bounds.min_x + 3.0).abs() < tolerance && (bounds.max_x - 3.0).abs() < tolerance);
+        assert!((shape.area() - 8.0).abs() < 1e-6);
+        // 在边上梯度是平行四边形的法线
+        let (gx, gy) = shape.gradient(1.0, 1.0);
+        assert!(gx.abs() < 1e-3 && (gy - 1.0).abs() < 1e-3);
+        let (gx, gy) = shape.gradient(2.0, 0.0);
+        assert!((gx - FRAC_1_SQRT_2).abs() < 1e-3 && (gy + FRAC_1_SQRT_2).abs() < 1e-3);
+
+        let shape = Shapes::shear_y(rect(), 0.5);
+        assert!(shape.sdf(2.0, 2.0).sd.abs() < tolerance);
+        assert!(shape.sdf(2.0, -1.0).sd > 0.0);
+    }
+
+    #[test]
+    fn bend() {
+        let tolerance = if cfg!(feature = "f32") { 1e-4 } else { 1e-6 };
+        // 长 2π 宽 2 的矩形弯成半径 4 的圆弧上的一段圆环, 圆心在 (0, 4)
+        let rect = || Box::new(Rect::new(0.0, 0.0, 0.0, PI, 1.0, 1.0));
+        let shape = Shapes::bend(rect(), 0.25);
+        let ring = Ring::new(0.0, 4.0, 4.0, 2.0, 1.0);
+        // 圆环的下半部分中间
+        assert!(
+            (shape.sdf(0.0, 0.0).sd + 1.0 / shape.bend.band_lipschitz(shape.shape.bounds())).abs()
+                < tolerance
+        );
+        for i in 0..100 {
+            let (x, y) = (i as Float * 0.06 - 3.0, (i * 7 % 100) as Float * 0.03 - 1.5);
+            let (sd, exact) = (shape.sdf(x, y).sd, ring.sdf(x, y).sd);
+            // 在矩形弯成的扇形范围内与圆环相同
+            if (x / (4.0 - y)).atan().abs() < FRAC_PI_2 / 2.0 - 0.2 {
+                assert_eq!(sd < 0.0, exact < 0.0);
+                assert!(sd.abs() <= exact.abs() + tolerance);
+            }
+            assert_conservative(shape.as_ref(), x, y);
+        }
+        // 矩形的右端弯到圆心右下方 45 度的方向
+        let (x, y) = (4.0 * FRAC_1_SQRT_2, 4.0 - 4.0 * FRAC_1_SQRT_2);
+        assert!(shape.sdf(x, y).sd.abs() < tolerance);
+        let bounds = shape.bounds().unwrap();
+        assert!((bounds.max_x - 5.0 * FRAC_1_SQRT_2).abs() < tolerance);
+        assert!((bounds.max_y - (4.0 - 3.0 * FRAC_1_SQRT_2)).abs() < tolerance);
+        assert!((bounds.min_y + 1.0).abs() < tolerance);
+        let (gx, gy) = shape.gradient(0.0, -2.0);
+        assert!(gx.abs() < 1e-3 && (gy + 1.0).abs() < 1e-3);
+
+        // 向上弯与向下弯上下对称
+        let up = Shapes::bend(rect(), -0.25);
+        assert!((up.sdf(1.0, -0.5).sd - shape.sdf(1.0, 0.5).sd).abs() < tolerance);
+        // k 为 0 时不弯曲
+        let straight = Shapes::bend(rect(), 0.0);
+        assert_eq!(straight.sdf(0.0, 3.0).sd, rect().sdf(0.0, 3.0).sd);
+    }
+
+    #[test]
+    fn raycast() {
+        // 从外面射入, 从里面射出, 错过和背对着圆
+        let circle = Circle::new(5.0, 0.0, 2.0, 1.0);
+        assert_eq!(circle.raycast(0.0, 0.0, 1.0, 0.0), Some(3.0));
+        assert_eq!(circle.raycast(5.0, 0.0, 1.0, 0.0), Some(2.0));
+        assert_eq!(circle.raycast(0.0, 3.0, 1.0, 0.0), Some(Float::INFINITY));
+        assert_eq!(circle.raycast(0.0, 0.0, -1.0, 0.0), Some(Float::INFINITY));
+
+        let plane = Plane::new(0.0, 2.0, 0.0, -1.0, 1.0);
+        assert_eq!(plane.raycast(1.0, 0.0, 0.0, 1.0), Some(2.0));
+        assert_eq!(plane.raycast(1.0, 0.0, 1.0, 0.0), Some(Float::INFINITY));
+        assert_eq!(plane.raycast(1.0, 0.0, 0.0, -1.0), Some(Float::INFINITY));
+
+        let glass = Shapes::refractive(Box::new(circle.clone()), 1.5);
+        assert_eq!(glass.raycast(0.0, 0.0, 1.0, 0.0), Some(3.0));
+        assert!(glass.supports_raycast());
+        let union = Shapes::union(Box::new(Circle::new(0.0, 0.0, 1.0, 1.0)), glass);
+        assert_eq!(union.raycast(0.0, 0.0, 1.0, 0.0), None);
+        assert!(!union.supports_raycast());
+
+        // 错过的光线也说明支持求交
+        assert!(circle.supports_raycast() && plane.supports_raycast());
+        let concave = Polygon::with_contours(
+            &[vec![(0.0, 0.0), (4.0, 0.0), (2.0, 1.0), (2.0, 4.0)]],
+            FillRule::NonZero,
+            1.0,
+        );
+        assert!(!concave.supports_raycast());
+        assert_eq!(concave.raycast(-1.0, 0.5, 1.0, 0.0), None);
+        let square = Polygon::new(&[(0.0, 0.0), (4.0, 0.0), (4.0, 4.0), (0.0, 4.0)], 1.0);
+        assert!(square.supports_raycast());
+        assert_eq!(square.raycast(-1.0, 10.0, 1.0, 0.0), Some(Float::INFINITY));
+    }
+
+    #[test]
+    fn ellipse() {
+        // 与在椭圆上密集取点得到的最近距离比较
+        let ellipse = Ellipse::new(1.0, -2.0, 5.0, 2.0, 0.4, 1.0);
+        let boundary: Vec<(Float, Float)> = (0..20000)
+            .map(|i| {
+                let angle = TAU * i as Float / 20000.0;
+                let (x, y) = (5.0 * angle.cos(), 2.0 * angle.sin());
+                let (sin, cos) = (0.4 as Float).sin_cos();
+                (1.0 + x * cos - y * sin, -2.0 + x * sin + y * cos)
+            })
+            .collect();
+        let tolerance = if cfg!(feature = "f32") { 1e-3 } else { 1e-4 };
+        let points = [(8.0, 1.0), (1.0, -2.0), (2.0, -1.5), (-6.0, -7.0)];
+        for &(x, y) in &points {
+            let expected = boundary
+                .iter()
+                .map(|&(bx, by)| (x - bx).hypot(y - by))
+                .fold(Float::INFINITY, Float::min);
+            let sd = ellipse.sdf(x, y).sd;
+            assert!((sd.abs() - expected).abs() < tolerance, "{}", sd);
+        }
+        assert!(ellipse.sdf(1.0, -2.0).sd < 0.0);
+        assert!(ellipse.sdf(8.0, 1.0).sd > 0.0);
+
+        // 半轴相等时与圆相同
+        let round = Ellipse::new(0.0, 0.0, 2.0, 2.0, 1.0, 1.0);
+        let circle = Circle::new(0.0, 0.0, 2.0, 1.0);
+        assert!((round.sdf(3.0, 1.0).sd - circle.sdf(3.0, 1.0).sd).abs() < 1e-5);
+
+        // 精确值与数值估计一致
+        let bounds = ellipse.bounds().unwrap();
+        let (area, perimeter, _) = estimate_measure(&ellipse, &bounds);
+        assert!((area / ellipse.area() - 1.0).abs() < 1e-3);
+        assert!((perimeter / ellipse.perimeter() - 1.0).abs() < 1e-2);
+        let gradient = estimate_gradient(&ellipse, 6.0, 1.0);
+        let (gx, gy) = ellipse.gradient(6.0, 1.0);
+        assert!((gx - gradient.0).abs() < 1e-3 && (gy - gradient.1).abs() < 1e-3);
+
+        // 沿长轴射入
+        let flat = Ellipse::new(0.0, 0.0, 4.0, 1.0, 0.0, 1.0);
+        assert_eq!(flat.raycast(-6.0, 0.0, 1.0, 0.0), Some(2.0));
+        assert_eq!(flat.raycast(0.0, 0.0, 0.0, 1.0), Some(1.0));
+        assert_eq!(flat.raycast(0.0, 2.0, 1.0, 0.0), Some(Float::INFINITY));
+    }
+
+    #[test]
+    fn ring() {
+        let ring = Ring::new(1.0, 1.0, 3.0, 1.0, 1.0);
+        assert_eq!(ring.sdf(1.0, 1.0).sd, 2.5);
+        assert_eq!(ring.sdf(4.0, 1.0).sd, -0.5);
+        assert_eq!(ring.sdf(1.0, 6.0).sd, 1.5);
+        assert!((ring.area() - 6.0 * PI).abs() < 1e-6);
+        assert_eq!(ring.perimeter(), 6.0 * TAU);
+        assert_eq!(ring.bounds(), Some(Aabb::around(1.0, 1.0, 3.5, 3.5)));
+        let bounds = ring.bounds().unwrap();
+        let (area, perimeter, _) = estimate_measure(&ring, &bounds);
+        assert!((area / ring.area() - 1.0).abs() < 1e-3);
+        assert!((perimeter / ring.perimeter() - 1.0).abs() < 1e-2);
+        let estimate = estimate_gradient(&ring, 2.0, 1.0);
+        assert_eq!(ring.gradient(2.0, 1.0), (-1.0, 0.0));
+        assert!((estimate.0 + 1.0).abs() < 1e-3 && estimate.1.abs() < 1e-3);
+
+        // 从中间的洞里射出时先穿过内圆, 从外面射入时先穿过外圆
+        assert_eq!(ring.raycast(1.0, 1.0, 1.0, 0.0), Some(2.5));
+        assert_eq!(ring.raycast(-9.0, 1.0, 1.0, 0.0), Some(6.5));
+        assert_eq!(ring.raycast(4.0, 1.0, 1.0, 0.0), Some(0.5));
+        assert_eq!(ring.raycast(1.0, 9.0, 1.0, 0.0), Some(Float::INFINITY));
+
+        // 宽度超过直径时没有洞
+        let disk = Ring::new(0.0, 0.0, 1.0, 4.0, 1.0);
+        assert!((disk.area() - 9.0 * PI).abs() < 1e-6);
+        assert_eq!(disk.raycast(0.0, 0.0, 0.0, 1.0), Some(3.0));
+    }
+
+    #[test]
+    fn arc() {
+        // 以 y 轴正方向为中间, 向两侧各张开 45 度的四分之一圆弧
+        let arc = Arc::new(0.0, 0.0, 2.0, FRAC_PI_2, PI / 4.0, 0.5, 1.0);
+        assert!((arc.sdf(0.0, 2.0).sd + 0.25).abs() < 1e-6);
+        assert!((arc.sdf(0.0, 3.0).sd - 0.75).abs() < 1e-6);
+        assert!((arc.sdf(0.0, 0.0).sd - 1.75).abs() < 1e-6);
+        // 圆弧之外是到端点的距离, 与两侧对称
+        let end = (2.0 as Float).sqrt();
+        assert!((arc.sdf(end, -end).sd - (2.0 * end - 0.25)).abs() < 1e-6);
+        assert!((arc.sdf(-end, -end).sd - arc.sdf(end, -end).sd).abs() < 1e-6);
+        assert!((arc.sdf(end, end).sd + 0.25).abs() < 1e-6);
+
+        // 与胶囊一样, 面积是中线的长度乘以宽度再加上两端的半圆
+        let bounds = arc.bounds().unwrap();
+        let expected = PI * 0.5 + PI * 0.0625;
+        assert!((estimate_measure(&arc, &bounds).0 / expected - 1.0).abs() < 1e-3);
+
+        // 张角为 π 时与圆环相同
+        let full = Arc::new(1.0, 2.0, 3.0, 0.7, PI, 1.0, 1.0);
+        let ring = Ring::new(1.0, 2.0, 3.0, 1.0, 1.0);
+        for &(x, y) in &[(0.0, 0.0), (4.0, 2.0), (-3.0, 5.0)] {
+            assert!((full.sdf(x, y).sd - ring.sdf(x, y).sd).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn pie() {
+        // 右半圆: 从 -π/2 到 π/2, 与减去左半平面的圆相同
+        let pie = Pie::new(1.0, 1.0, 2.0, -FRAC_PI_2, FRAC_PI_2, 1.0);
+        let half = Shapes::intersect(
+            Box::new(Circle::new(1.0, 1.0, 2.0, 1.0)),
+            Box::new(Plane::new(1.0, 1.0, -1.0, 0.0, 1.0)),
+        );
+        for &(x, y) in &[(2.0, 1.5), (0.0, 1.0), (5.0, 1.0), (1.5, 5.0)] {
+            assert!((pie.sdf(x, y).sd - half.sdf(x, y).sd).abs() < 1e-6);
+        }
+        // 左侧远处离最近的是半径的端点
+        assert!((pie.sdf(-2.0, 4.0).sd - (3.0 as Float).hypot(1.0)).abs() < 1e-6);
+        assert!((pie.area() - 2.0 * PI).abs() < 1e-6);
+        assert!((pie.perimeter() - (2.0 * PI + 4.0)).abs() < 1e-6);
+        let (cx, cy) = pie.centroid().unwrap();
+        assert!((cx - (1.0 + 8.0 / (3.0 * PI))).abs() < 1e-6 && (cy - 1.0).abs() < 1e-6);
+
+        // 跨过 0 度的四分之一圆
+        let quarter = Pie::new(0.0, 0.0, 3.0, 7.0 * PI / 4.0, PI / 4.0, 1.0);
+        assert!(quarter.sdf(2.0, 0.0).sd < 0.0 && quarter.sdf(0.0, 2.0).sd > 0.0);
+        assert!((quarter.sdf(1.0, 2.0).sd - (0.5 as Float).sqrt()).abs() < 1e-6);
+
+        // 精确值与数值估计一致, 估计周长时斜着 45 度的边误差较大, 所以换一个角度
+        let quarter = Pie::new(0.0, 0.0, 3.0, 0.3, 0.3 + FRAC_PI_2, 1.0);
+        let bounds = quarter.bounds().unwrap();
+        let (area, perimeter, centroid) = estimate_measure(&quarter, &bounds);
+        assert!((area / quarter.area() - 1.0).abs() < 1e-3);
+        assert!((perimeter / quarter.perimeter() - 1.0).abs() < 1e-2);
+        let (cx, cy) = quarter.centroid().unwrap();
+        let estimate = centroid.unwrap();
+        assert!((cx - estimate.0).abs() < 1e-2 && (cy - estimate.1).abs() < 1e-2);
+    }
+
+    #[test]
+    fn bezier2() {
+        // 与在曲线上密集取点得到的最近距离比较
+        let (p0, p1, p2) = ((0.0, 0.0), (4.0, 6.0), (8.0, -1.0));
+        let curve = Bezier2::new(p0, p1, p2, 1.0, 1.0);
+        let samples: Vec<(Float, Float)> = (0..=20000)
+            .map(|i| {
+                let t = i as Float / 20000.0;
+                let s = 1.0 - t;
+                (
+                    s * s * p0.0 + 2.0 * s * t * p1.0 + t * t * p2.0,
+                    s * s * p0.1 + 2.0 * s * t * p1.1 + t * t * p2.1,
+                )
+            })
+            .collect();
+        let tolerance = if cfg!(feature = "f32") { 1e-3 } else { 1e-4 };
+        let points = [(4.0, 3.0), (4.0, 1.0), (-2.0, -1.0), (4.0, 10.0)];
+        for &(x, y) in &points {
+            let expected = samples
+                .iter()
+                .map(|&(sx, sy)| (x - sx).hypot(y - sy))
+                .fold(Float::INFINITY, Float::min);
+            let sd = curve.sdf(x, y).sd;
+            assert!((sd + 0.5 - expected).abs() < tolerance, "{}", sd);
+        }
+        let bounds = curve.bounds().unwrap();
+        assert_eq!(bounds, Aabb::new(-0.5, -1.5, 8.5, 6.5));
+
+        // 控制点在一条直线上时与胶囊相同
+        let line = Bezier2::new((0.0, 0.0), (1.0, 1.0), (2.0, 2.0), 1.0, 1.0);
+        let capsule = Capsule::new(0.0, 0.0, 2.0, 2.0, 0.5, 1.0);
+        for &(x, y) in &[(0.0, 2.0), (3.0, 3.0), (1.0, 1.0)] {
+            assert!((line.sdf(x, y).sd - capsule.sdf(x, y).sd).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn bezier3() {
+        // 与在曲线上密集取点得到的最近距离比较
+        let (p0, p1, p2, p3) = ((0.0, 0.0), (2.0, 8.0), (8.0, -6.0), (10.0, 2.0));
+        let curve = Bezier3::new(p0, p1, p2, p3, 1.0, 1.0);
+        assert!(curve.pieces() > 1 && curve.pieces() < 64);
+        let samples: Vec<(Float, Float)> = (0..=20000)
+            .map(|i| {
+                let t = i as Float / 20000.0;
+                let s = 1.0 - t;
+                let (a, b, c, d) = (s * s * s, 3.0 * s * s * t, 3.0 * s * t * t, t * t * t);
+                (
+                    a * p0.0 + b * p1.0 + c * p2.0 + d * p3.0,
+                    a * p0.1 + b * p1.1 + c * p2.1 + d * p3.1,
+                )
+            })
+            .collect();
+        let tolerance = if cfg!(feature = "f32") { 3e-3 } else { 2e-3 };
+        let points = [(5.0, 0.0), (2.0, 3.0), (-1.0, -1.0), (12.0, 2.0)];
+        for &(x, y) in &points {
+            let expected = samples
+                .iter()
+                .map(|&(sx, sy)| (x - sx).hypot(y - sy))
+                .fold(Float::INFINITY, Float::min);
+            let sd = curve.sdf(x, y).sd;
+            assert!((sd + 0.5 - expected).abs() < tolerance, "{}", sd);
+        }
+        assert_eq!(curve.bounds(), Some(Aabb::new(-0.5, -6.5, 10.5, 8.5)));
+
+        // 可以精确表示为二次曲线的三次曲线只需要一段
+        let quadratic = Bezier2::new((0.0, 0.0), (3.0, 6.0), (6.0, 0.0), 1.0, 1.0);
+        let elevated = Bezier3::new((0.0, 0.0), (2.0, 4.0), (4.0, 4.0), (6.0, 0.0), 1.0, 1.0);
+        assert_eq!(elevated.pieces(), 1);
+        for &(x, y) in &[(3.0, 1.0), (3.0, 5.0), (7.0, -1.0)] {
+            assert!((elevated.sdf(x, y).sd - quadratic.sdf(x, y).sd).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn heart() {
+        let heart = Heart::new(10.0, 20.0, 8.0, 1.0);
+        let bounds = heart.bounds().unwrap();
+        assert_eq!((bounds.min_y, bounds.max_y), (16.0, 24.0));
+        // 尖在下面, 顶上的两个圆弧和包围盒的上边相切, 中间是凹进去的
+        assert!(heart.sdf(10.0, 24.0).sd.abs() < 1e-6);
+        assert!(heart.sdf(10.0, 20.0).sd < 0.0);
+        assert!(heart.sdf(10.0, 16.5).sd > 0.0);
+        // 左右两边最宽处在圆弧的圆心的高度
+        let a = 1.0 / (3.0 + SQRT_2);
+        assert!(heart.sdf(bounds.min_x, 24.0 - 24.0 * a).sd.abs() < 1e-6);
+        assert_eq!(heart.sdf(7.0, 19.0).sd, heart.sdf(13.0, 19.0).sd);
+
+        // 与在边界上密集取点得到的最近距离比较, 包括线段内侧靠近圆弧的点
+        let (a, r) = unit_heart();
+        let samples: Vec<(Float, Float)> = (0..=20000)
+            .flat_map(|i| {
+                let v = i as Float / 20000.0;
+                let angle = -PI / 4.0 + PI * v;
+                vec![
+                    (2.0 * a * v, 2.0 * a * v),
+                    (a + r * angle.cos(), 3.0 * a + r * angle.sin()),
+                ]
+            })
+            .collect();
+        let unit = Heart::new(0.0, 0.5, 1.0, 1.0);
+        for &(x, y) in &[
+            (a, 2.9 * a),
+            (0.5 * a, 2.0 * a),
+            (1.8 * a, 2.1 * a),
+            (0.2, 0.6),
+        ] {
+            let expected = samples
+                .iter()
+                .map(|&(sx, sy)| (x - sx).hypot(y - sy))
+                .fold(Float::INFINITY, Float::min);
+            let sd = unit.sdf(x, 1.0 - y).sd;
+            assert!((sd.abs() - expected).abs() < 1e-4, "{} {} {}", x, y, sd);
+        }
+        assert!(unit.sdf(a, 1.0 - 2.9 * a).sd < 0.0);
+
+        // 精确的 sdf 的梯度长度处处为 1
+        let points = [(10.0, 30.0), (4.0, 17.0), (10.0, 17.0), (11.0, 10.0)];
+        for &(x, y) in &points {
+            let (gx, gy) = heart.gradient(x, y);
+            assert!((gx.hypot(gy) - 1.0).abs() < 1e-3, "{} {}", x, y);
+        }
+    }
+
+    #[test]
+    fn cross() {
+        // 外面和没有重叠的横条里与两个矩形的并集相同, 中心处是到最近的凹角的距离
+        let cross = Cross::new(1.0, 2.0, 0.3, 4.0, 3.0, 2.0, 1.0);
+        let union = Shapes::union(
+            Box::new(Rect::new(1.0, 2.0, 0.3, 4.0, 1.0, 1.0)),
+            Box::new(Rect::new(1.0, 2.0, 0.3, 1.0, 3.0, 1.0)),
+        );
+        let (sin, cos) = (0.3 as Float).sin_cos();
+        let at = |u: Float, v: Float| (1.0 + u * cos - v * sin, 2.0 + u * sin + v * cos);
+        for &(u, v) in &[(5.0, 0.5), (2.0, 2.0), (-3.5, 0.2), (0.0, -4.0), (3.0, 0.0)] {
+            let (x, y) = at(u, v);
+            assert!((cross.sdf(x, y).sd - union.sdf(x, y).sd).abs() < 1e-6);
+        }
+        let (x, y) = at(0.0, 0.0);
+        assert!((cross.sdf(x, y).sd + SQRT_2).abs() < 1e-6);
+        assert_eq!(cross.area(), 4.0 * 7.0 - 4.0);
+        assert_eq!(cross.perimeter(), 28.0);
+
+        // 圆角: 末端的角变圆, 总尺寸不变
+        let rounded = Cross::new(0.0, 0.0, 0.0, 4.0, 3.0, 2.0, 1.0).with_radius(0.5);
+        assert!(rounded.sdf(4.0, 0.0).sd.abs() < 1e-6);
+        let corner = 0.5 * SQRT_2 - 0.5;
+        assert!((rounded.sdf(4.0, 1.0).sd - corner).abs() < 1e-6);
+        let bounds = rounded.bounds().unwrap();
+        assert_eq!(bounds, Aabb::new(-4.0, -3.0, 4.0, 3.0));
+        let (area, perimeter, _) = estimate_measure(&rounded, &bounds);
+        assert!((area / rounded.area() - 1.0).abs() < 1e-3);
+        assert!((perimeter / rounded.perimeter() - 1.0).abs() < 1e-2);
+    }
+
+    #[test]
+    fn rhombus() {
+        // 与四个顶点组成的凸多边形相同
+        let rhombus = Rhombus::new(1.0, 2.0, 0.4, 3.0, 2.0, 1.0);
+        let (sin, cos) = (0.4 as Float).sin_cos();
+        let at = |u: Float, v: Float| (1.0 + u * cos - v * sin, 2.0 + u * sin + v * cos);
+        let polygon = Polygon::new(
+            &[at(3.0, 0.0), at(0.0, 2.0), at(-3.0, 0.0), at(0.0, -2.0)],
+            1.0,
+        );
+        let points = [(1.0, 2.0), (4.0, 4.0), (-3.0, 1.0), (2.0, -1.0), (1.5, 2.5)];
+        for &(x, y) in &points {
+            assert!((rhombus.sdf(x, y).sd - polygon.sdf(x, y).sd).abs() < 1e-6);
+        }
+        let bounds = rhombus.bounds().unwrap();
+        let expected = polygon.bounds().unwrap();
+        assert!((bounds.min_x - expected.min_x).abs() < 1e-6);
+        assert!((bounds.max_y - expected.max_y).abs() < 1e-6);
+        assert!((rhombus.area() - polygon.area()).abs() < 1e-6);
+        assert!((rhombus.perimeter() - polygon.perimeter()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn trapezoid() {
+        // 与四个顶点组成的凸多边形相同
+        let trapezoid = Trapezoid::new(1.0, 2.0, 0.5, 3.0, 1.0, 2.0, 1.0);
+        let corners = [(-3.0, -2.0), (3.0, -2.0), (1.0, 2.0), (-1.0, 2.0)];
+        let corners: Vec<(Float, Float)> = corners
+            .iter()
+            .map(|&(u, v)| trapezoid.to_world(u, v))
+            .collect();
+        let polygon = Polygon::new(&corners, 1.0);
+        let tolerance = if cfg!(feature = "f32") { 1e-4 } else { 1e-6 };
+        let points = [(1.0, 2.0), (4.0, 4.0), (-3.0, 1.0), (2.0, -1.0), (1.5, 4.5)];
+        for &(x, y) in &points {
+            assert!((trapezoid.sdf(x, y).sd - polygon.sdf(x, y).sd).abs() < tolerance);
+        }
+        assert_eq!(trapezoid.bounds(), polygon.bounds());
+        assert!((trapezoid.area() - polygon.area()).abs() < tolerance);
+        assert!((trapezoid.perimeter() - polygon.perimeter()).abs() < tolerance);
+        let (cx, cy) = trapezoid.centroid().unwrap();
+        let (px, py) = polygon.centroid().unwrap();
+        assert!((cx - px).abs() < tolerance && (cy - py).abs() < tolerance);
+    }
+
+    #[test]
+    fn parabola() {
+        // 与在曲线上密集取点得到的最近距离比较
+        let parabola = Parabola::new(1.0, 2.0, 0.3, 1.5, 4.0, 0.5, 1.0);
+        let samples: Vec<(Float, Float)> = (0..=20000)
+            .map(|i| {
+                let t = -4.0 + 8.0 * i as Float / 20000.0;
+                parabola.to_world(t, t * t / 6.0)
+            })
+            .collect();
+        let points = [(1.0, 2.0), (1.0, 5.0), (6.0, 0.0), (-4.0, 6.0), (0.0, 9.0)];
+        for &(x, y) in &points {
+            let expected = samples
+                .iter()
+                .map(|&(sx, sy)| (x - sx).hypot(y - sy))
+                .fold(Float::INFINITY, Float::min);
+            let sd = parabola.sdf(x, y).sd;
+            assert!((sd + 0.25 - expected).abs() < 1e-3, "{} {}", x, y);
+        }
+        let bounds = parabola.bounds().unwrap();
+        for &(x, y) in &samples {
+            assert!(bounds.distance(x, y) == 0.0);
+        }
+
+        // 焦点到抛物线上各点的距离等于这一点到准线的距离
+        let (fx, fy) = parabola.focus();
+        let (sin, cos) = (0.3 as Float).sin_cos();
+        for &(x, y) in samples.iter().step_by(1000) {
+            let directrix = (y - 2.0) * cos - (x - 1.0) * sin + 1.5;
+            assert!(((x - fx).hypot(y - fy) - directrix).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn vesica() {
+        // 与两个圆的交集相同
+        let vesica = Vesica::new(1.0, 2.0, 0.6, 5.0, 3.0, 1.0);
+        let (sin, cos) = (0.6 as Float).sin_cos();
+        let intersection = Shapes::intersect(
+            Box::new(Circle::new(1.0 - 3.0 * cos, 2.0 - 3.0 * sin, 5.0, 1.0)),
+            Box::new(Circle::new(1.0 + 3.0 * cos, 2.0 + 3.0 * sin, 5.0, 1.0)),
+        );
+        let tolerance = if cfg!(feature = "f32") { 1e-4 } else { 1e-6 };
+        for &(x, y) in &[(1.0, 2.0), (2.0, 3.0), (4.0, 2.0), (-1.0, -1.0)] {
+            assert!((vesica.sdf(x, y).sd - intersection.sdf(x, y).sd).abs() < tolerance);
+        }
+        // 尖的外侧是到尖的距离, 而不是交集的 sdf 给出的下限
+        let (x, y) = (1.0 - 6.0 * sin, 2.0 + 6.0 * cos);
+        assert!((vesica.sdf(x, y).sd - 2.0).abs() < tolerance);
+        assert!(vesica.sdf(x, y).sd > intersection.sdf(x, y).sd + 0.1);
+
+        let bounds = vesica.bounds().unwrap();
+        let (area, perimeter, _) = estimate_measure(&vesica, &bounds);
+        assert!((area / vesica.area() - 1.0).abs() < 1e-3);
+        assert!((perimeter / vesica.perimeter() - 1.0).abs() < 1e-2);
+
+        // 沿光轴射入和射出
+        let lens = Vesica::new(0.0, 0.0, 0.0, 5.0, 3.0, 1.0);
+        assert_eq!(lens.raycast(-5.0, 0.0, 1.0, 0.0), Some(3.0));
+        assert_eq!(lens.raycast(0.0, 0.0, 1.0, 0.0), Some(2.0));
+        assert_eq!(lens.raycast(0.0, 5.0, 1.0, 0.0), Some(Float::INFINITY));
+        assert_eq!(lens.raycast(0.0, -6.0, 0.0, 1.0), Some(2.0));
+    }
+
+    #[test]
+    fn crescent() {
+        // 在尖的附近以外与两个圆的差集相同
+        let crescent = Crescent::new(1.0, 2.0, 0.4, 4.0, 3.0, 2.0, 1.0);
+        let (sin, cos) = (0.4 as Float).sin_cos();
+        let moon = Shapes::subtract(
+            Box::new(Circle::new(1.0, 2.0, 4.0, 1.0)),
+            Box::new(Circle::new(1.0 + 2.0 * cos, 2.0 + 2.0 * sin, 3.0, 1.0)),
+        );
+        let tolerance = if cfg!(feature = "f32") { 1e-4 } else { 1e-6 };
+        for &(x, y) in &[(1.0, 2.0), (-2.0, 2.0), (-4.0, 0.0), (-6.0, 3.0)] {
+            assert!((crescent.sdf(x, y).sd - moon.sdf(x, y).sd).abs() < tolerance);
+        }
+        // 两个圆的交点在 (a, ±b), 尖的外侧是到尖的距离
+        let (a, b) = (11.0 / 4.0, (16.0 - 121.0 / 16.0 as Float).sqrt());
+        let at = |u: Float, v: Float| (1.0 + u * cos - v * sin, 2.0 + u * sin + v * cos);
+        let (tx, ty) = at(a, -b);
+        let expected = (6.0 - tx).hypot(3.0 - ty);
+        assert!((crescent.sdf(6.0, 3.0).sd - expected).abs() < tolerance);
+        assert!(crescent.sdf(6.0, 3.0).sd > moon.sdf(6.0, 3.0).sd + 1.0);
+
+        let bounds = crescent.bounds().unwrap();
+        let (area, perimeter, _) = estimate_measure(&crescent, &bounds);
+        assert!((area / crescent.area() - 1.0).abs() < 1e-3);
+        assert!((perimeter / crescent.perimeter() - 1.0).abs() < 1e-2);
+
+        // 被减去的圆在里面时是圆环, 在外面时是完整的圆
+        let ring = Crescent::new(0.0, 0.0, 0.0, 4.0, 1.0, 1.0, 1.0);
+        assert!((ring.area() - 15.0 * PI).abs() < 1e-6);
+        assert!((ring.perimeter() - 10.0 * PI).abs() < 1e-6);
+        let full = Crescent::new(0.0, 0.0, 0.0, 4.0, 1.0, 6.0, 1.0);
+        assert!((full.area() - 16.0 * PI).abs() < 1e-6);
+        assert_eq!(Crescent::new(0.0, 0.0, 0.0, 1.0, 4.0, 1.0, 1.0).area(), 0.0);
+        // 同心时是圆环, 两个圆一样大时是空的
+        let concentric = Crescent::new(1.0, 2.0, 0.3, 4.0, 1.0, 0.0, 1.0);
+        assert_eq!(concentric.sdf(1.0, 2.0).sd, 1.0);
+        assert!((concentric.sdf(4.0, 2.0).sd + 1.0).abs() < 1e-6);
+        assert!((concentric.sdf(7.0, 2.0).sd - 2.0).abs() < 1e-6);
+        assert!((concentric.area() - 15.0 * PI).abs() < 1e-4);
+        assert!((concentric.perimeter() - 10.0 * PI).abs() < 1e-4);
+        let empty = Crescent::new(0.0, 0.0, 0.0, 2.0, 2.0, 0.0, 1.0);
+        assert!(empty.sdf(0.5, 0.0).sd > 0.0);
+        assert_eq!(empty.area(), 0.0);
+        assert_eq!(empty.perimeter(), 0.0);
+    }
+
+    #[test]
+    fn superellipse() {
+        // 与在边界上密集取点得到的最近距离比较
+        let tolerance = if cfg!(feature = "f32") { 1e-4 } else { 1e-5 };
+        for &(n, tolerance) in &[
+            (0.8, tolerance),
+            (1.0, tolerance),
+            (1.5, tolerance),
+            (2.0, tolerance),
+            (4.0, tolerance),
+            (10.0, tolerance),
+            (0.5, 0.06),
+        ] {
+            let shape = Superellipse::new(0.0, 0.0, 0.0, 3.0, 2.0, n, 1.0);
+            let f = |v: Float| (1.0 - v.powf(n)).max(0.0).powf(1.0 / n);
+            let samples: Vec<(Float, Float)> = (0..=20000)
+                .flat_map(|i| {
+                    let v = i as Float / 20000.0;
+                    vec![(3.0 * v, 2.0 * f(v)), (3.0 * f(v), 2.0 * v)]
+                })
+                .collect();
+            let mut worst: Float = 0.0;
+            for i in 0..20 {
+                for j in 0..20 {
+                    let (x, y) = (i as Float * 0.2, j as Float * 0.15);
+                    let expected = samples
+                        .iter()
+                        .map(|&(sx, sy)| (x - sx).hypot(y - sy))
+                        .fold(Float::INFINITY, Float::min);
+                    worst = worst.max((shape.sdf(x, y).sd.abs() - expected).abs());
+                }
+            }
+            assert!(worst < tolerance, "{} {}", n, worst);
+        }
+
+        // n = 2 时是椭圆, 旋转后对称
+        let ellipse = Ellipse::new(1.0, 2.0, 3.0, 2.0, 0.5, 1.0);
+        let shape = Superellipse::new(1.0, 2.0, 0.5, 3.0, 2.0, 2.0, 1.0);
+        for &(x, y) in &[(1.0, 2.0), (5.0, 3.0), (0.0, 0.0), (2.0, 3.5)] {
+            assert!((shape.sdf(x, y).sd - ellipse.sdf(x, y).sd).abs() < 1e-4);
+        }
+        assert!(shape.sdf(1.0, 2.0).sd < 0.0);
+        assert!(shape.sdf(5.0, 5.0).sd > 0.0);
+    }
+
+    #[test]
+    fn gear() {
+        // 折叠后的结果与对所有齿取最小值相同
+        let gear = Gear::new(1.0, 2.0, 0.3, 7, 3.0, 4.0, 1.0).with_profile(0.6, 0.4);
+        let sector = TAU / 7.0;
+        let tolerance = if cfg!(feature = "f32") { 1e-4 } else { 1e-9 };
+        for i in 0..40 {
+            for j in 0..40 {
+                let (x, y) = (
+                    1.0 + (i as Float - 20.0) * 0.27,
+                    2.0 + (j as Float - 20.0) * 0.27,
+                );
+                let (dx, dy) = (x - 1.0, y - 2.0);
+                let r = dx.hypot(dy);
+                let teeth = (0..7)
+                    .map(|k| {
+                        let (sin, cos) = (dy.atan2(dx) - 0.3 - k as Float * sector).sin_cos();
+                        gear.tooth.sdf(r * sin, r * cos).sd
+                    })
+                    .fold(Float::INFINITY, Float::min);
+                let expected = (r - 3.0).min(teeth.max(r - 4.0));
+                assert!((gear.sdf(x, y).sd - expected).abs() < tolerance);
+            }
+        }
+
+        // 齿顶外侧和两齿之间
+        let (sin, cos) = (0.3 as Float).sin_cos();
+        assert!((gear.sdf(1.0 + 5.0 * cos, 2.0 + 5.0 * sin).sd - 1.0).abs() < tolerance);
+        let (sin, cos) = (0.3 + sector / 2.0).sin_cos();
+        assert!((gear.sdf(1.0 + 3.2 * cos, 2.0 + 3.2 * sin).sd - 0.2).abs() < 1e-2);
+        assert!((gear.sdf(1.0, 2.0).sd + 3.0).abs() < tolerance);
+        let (gx, gy) = estimate_gradient(&gear, 1.0 + 3.5 * cos, 2.0 + 3.5 * sin);
+        assert!((gx.hypot(gy) - 1.0).abs() < 1e-3);
+
+        let bounds = gear.bounds().unwrap();
+        let (area, _, centroid) = estimate_measure(&gear, &bounds);
+        assert!(area > 9.0 * PI && area < 16.0 * PI);
+        let (x, y) = centroid.unwrap();
+        assert!((x - 1.0).abs() < 1e-2 && (y - 2.0).abs() < 1e-2);
+        assert_eq!(gear.centroid(), Some((1.0, 2.0)));
+    }
+
+    #[test]
+    fn hexagram() {
+        // 在外部与两个正三角形的并集相同
+        let hexagram = Hexagram::new(1.0, 2.0, 0.3, 2.0, 1.0);
+        let vertex = |angle: Float| {
+            let angle = 0.3 + FRAC_PI_2 + angle;
+            (1.0 + 2.0 * angle.cos(), 2.0 + 2.0 * angle.sin())
+        };
+        let triangle = |offset: Float| -> Box<dyn Shape> {
+            let (a, b, c) = (
+                vertex(offset),
+                vertex(offset + TAU / 3.0),
+                vertex(offset + 2.0 * TAU / 3.0),
+            );
+            Box::new(Triangle::new(a.0, a.1, b.0, b.1, c.0, c.1, 1.0))
+        };
+        let union = Shapes::union(triangle(0.0), triangle(PI));
+        let tolerance = if cfg!(feature = "f32") { 1e-4 } else { 1e-9 };
+        for i in 0..30 {
+            for j in 0..30 {
+                let (x, y) = (-2.0 + i as Float * 0.2, -1.0 + j as Float * 0.2);
+                let expected = union.sdf(x, y).sd;
+                let sd = hexagram.sdf(x, y).sd;
+                if expected > 0.0 {
+                    assert!((sd - expected).abs() < tolerance);
+                } else {
+                    assert!(sd <= expected + tolerance);
+                }
+            }
+        }
+        // 中心到内凹的角的距离是 2 / √3
+        assert!((hexagram.sdf(1.0, 2.0).sd + 2.0 / (3.0 as Float).sqrt()).abs() < tolerance);
+
+        let bounds = hexagram.bounds().unwrap();
+        let (area, perimeter, _) = estimate_measure(&hexagram, &bounds);
+        assert!((area / hexagram.area() - 1.0).abs() < 1e-3);
+        assert!((perimeter / hexagram.perimeter() - 1.0).abs() < 3e-2);
+    }
+
+    #[test]
+    fn egg() {
+        // 与在扩展前的边界上密集取点得到的距离比较
+        let egg = Egg::new(1.0, 2.0, 0.3, 2.0, 0.5, 1.0);
+        let r: Float = 1.5;
+        let mut samples = Vec::new();
+        for i in 0..=4000 {
+            let t = PI * i as Float / 4000.0;
+            samples.push((r * t.cos(), -r * t.sin()));
+            let t = PI / 3.0 * i as Float / 4000.0;
+            samples.push((2.0 * r * t.cos() - r, 2.0 * r * t.sin()));
+            samples.push((r - 2.0 * r * t.cos(), 2.0 * r * t.sin()));
+        }
+        let (sin, cos) = (0.3 as Float).sin_cos();
+        for i in 0..20 {
+            for j in 0..20 {
+                let (u, v) = (-3.0 + i as Float * 0.31, -3.0 + j as Float * 0.37);
+                let (x, y) = (1.0 + u * cos - v * sin, 2.0 + u * sin + v * cos);
+                let nearest = samples
+                    .iter()
+                    .map(|&(su, sv)| (u - su).hypot(v - sv))
+                    .fold(Float::INFINITY, Float::min);
+                let sd = egg.sdf(x, y).sd + 0.5;
+                assert!((sd.abs() - nearest).abs() < 2e-3);
+            }
+        }
+        assert!(egg.sdf(1.0, 2.0).sd < 0.0);
+        let (tx, ty) = (
+            1.0 - (3.0 as Float).sqrt() * r * sin,
+            2.0 + (3.0 as Float).sqrt() * r * cos,
+        );
+        assert!((egg.sdf(tx, ty).sd + 0.5).abs() < 1e-4);
+
+        let bounds = egg.bounds().unwrap();
+        let (area, perimeter, _) = estimate_measure(&egg, &bounds);
+        assert!((area / egg.area() - 1.0).abs() < 1e-3);
+        assert!((perimeter / egg.perimeter() - 1.0).abs() < 1e-2);
+
+        // rb = ra 时是圆
+        let circle = Egg::new(0.0, 0.0, 0.0, 2.0, 2.0, 1.0);
+        assert!((circle.sdf(3.0, 1.0).sd - (10.0 as Float).sqrt() + 2.0).abs() < 1e-6);
+        assert!((circle.area() - 4.0 * PI).abs() < 1e-6);
+    }
+
+    #[test]
+    fn spline() {
+        // 与直接按 Catmull-Rom 公式在曲线上密集取点得到的距离比较
+        let points = [(0.0, 0.0), (3.0, -1.0), (4.0, 2.0), (2.0, 1.5), (0.5, 3.0)];
+        let spline = Spline::new(&points, 1.0);
+        let mut samples = vec![];
+        for i in 0..5 {
+            let (p0, p1) = (points[(i + 4) % 5], points[i]);
+            let (p2, p3) = (points[(i + 1) % 5], points[(i + 2) % 5]);
+            for k in 0..2000 {
+                let t = k as Float / 2000.0;
+                let (t2, t3) = (t * t, t * t * t);
+                let at = |a: Float, b: Float, c: Float, d: Float| {
+                    0.5 * (2.0 * b
+                        + (c - a) * t
+                        + (2.0 * a - 5.0 * b + 4.0 * c - d) * t2
+                        + (3.0 * b - a - 3.0 * c + d) * t3)
+                };
+                samples.push((at(p0.0, p1.0, p2.0, p3.0), at(p0.1, p1.1, p2.1, p3.1)));
+            }
+        }
+        for i in 0..25 {
+            for j in 0..25 {
+                let (x, y) = (-1.0 + i as Float * 0.25, -2.0 + j as Float * 0.25);
+                let nearest = samples
+                    .iter()
+                    .map(|&(sx, sy)| (x - sx).hypot(y - sy))
+                    .fold(Float::INFINITY, Float::min);
+                let sd = spline.sdf(x, y).sd;
+                assert!((sd.abs() - nearest).abs() < 3e-3, "{} {}", sd, nearest);
+                if nearest > 1e-2 {
+                    let inside = winding_number(&samples, x, y) != 0;
+                    assert_eq!(sd < 0.0, inside);
+                }
+            }
+        }
+        // 曲线经过所有控制点
+        for &(x, y) in &points {
+            assert!(spline.sdf(x, y).sd.abs() < 1e-6);
+        }
+
+        let bounds = spline.bounds().unwrap();
+        for &(x, y) in &samples {
+            assert!(bounds.distance(x, y) == 0.0);
+        }
+        assert!(spline.pieces() >= 5);
+
+        // 少于 3 个控制点时退化为点或者线段, 没有内部
+        let empty = Spline::new(&[], 1.0);
+        assert_eq!(empty.sdf(0.0, 0.0).sd, Float::INFINITY);
+        assert_eq!(empty.bounds(), None);
+        let point = Spline::new(&[(1.0, 2.0)], 1.0);
+        assert_eq!(point.sdf(1.0, 2.0).sd, 0.0);
+        assert_eq!(point.sdf(4.0, 6.0).sd, 5.0);
+        assert_eq!(point.bounds(), Some(Aabb::new(1.0, 2.0, 1.0, 2.0)));
+        let segment = Spline::new(&[(0.0, 0.0), (4.0, 0.0)], 1.0);
+        assert_eq!(segment.sdf(2.0, 0.0).sd, 0.0);
+        assert_eq!(segment.sdf(2.0, -3.0).sd, 3.0);
+        assert_eq!(segment.sdf(7.0, 4.0).sd, 5.0);
+        assert_eq!(segment.bounds(), Some(Aabb::new(0.0, 0.0, 4.0, 0.0)));
+        assert_eq!(segment.area(), 0.0);
+    }
+
+    #[test]
+    fn polygon() {
+        // 顶点顺序相反的两个三角形结果相同, 与 Triangle 一致
+        let points = [(0.0, 0.0), (4.0, 0.0), (0.0, 3.0)];
+        let reversed: Vec<(Float, Float)> = points.iter().rev().copied().collect();
+        let triangle = Triangle::new(0.0, 0.0, 4.0, 0.0, 0.0, 3.0, 1.0);
+        for polygon in &[Polygon::new(&points, 1.0), Polygon::new(&reversed, 1.0)] {
+            for &(x, y) in &[(1.0, 1.0), (3.0, 3.0), (-1.0, -1.0), (0.5, 2.0)] {
+                assert!((polygon.sdf(x, y).sd - triangle.sdf(x, y).sd).abs() < 1e-6);
+            }
+            assert_eq!(polygon.area(), 6.0);
+            assert_eq!(polygon.perimeter(), 12.0);
+            let (cx, cy) = polygon.centroid().unwrap();
+            assert!((cx - 4.0 / 3.0).abs() < 1e-6 && (cy - 1.0).abs() < 1e-6);
+            assert_eq!(polygon.bounds(), Some(Aabb::new(0.0, 0.0, 4.0, 3.0)));
+        }
+
+        // 正六边形: 内切圆的半径等于中心到边的距离
+        let hexagon: Vec<(Float, Float)> = (0..6)
+            .map(|i| {
+                let angle = TAU * i as Float / 6.0;
+                (2.0 * angle.cos(), 2.0 * angle.sin())
+            })
+            .collect();
+        let hexagon = Polygon::new(&hexagon, 1.0);
+        assert_eq!(hexagon.fill_rule(), FillRule::NonZero);
+        let apothem = (3.0 as Float).sqrt();
+        assert!((hexagon.sdf(0.0, 0.0).sd + apothem).abs() < 1e-6);
+        assert!((hexagon.sdf(0.0, 3.0).sd - (3.0 - apothem)).abs() < 1e-6);
+        assert!((hexagon.raycast(0.0, -5.0, 0.0, 1.0).unwrap() - (5.0 - apothem)).abs() < 1e-6);
+        assert!((hexagon.raycast(0.0, 0.0, 1.0, 0.0).unwrap() - 2.0).abs() < 1e-6);
+        assert_eq!(hexagon.raycast(0.0, 3.0, 1.0, 0.0), Some(Float::INFINITY));
+        let bounds = hexagon.bounds().unwrap();
+        let (area, perimeter, _) = estimate_measure(&hexagon, &bounds);
+        assert!((area / hexagon.area() - 1.0).abs() < 1e-3);
+        assert!((perimeter / hexagon.perimeter() - 1.0).abs() < 1e-2);
+    }
+
+    #[test]
+    fn general_polygon() {
+        // 凹的 L 形: 缺口里的点在外面, 距离是到最近的边的距离
+        let l_shape = vec![vec![
+            (0.0, 0.0),
+            (4.0, 0.0),
+            (4.0, 1.0),
+            (1.0, 1.0),
+            (1.0, 4.0),
+            (0.0, 4.0),
+        ]];
+        let polygon = Polygon::with_contours(&l_shape, FillRule::NonZero, 1.0);
+        assert_eq!(polygon.sdf(3.0, 3.0).sd, 2.0);
+        assert_eq!(polygon.sdf(0.5, 3.0).sd, -0.5);
+        assert!((polygon.area() / 7.0 - 1.0).abs() < 1e-3);
+        assert!((polygon.perimeter() / 16.0 - 1.0).abs() < 1e-2);
+        let (cx, cy) = polygon.centroid().unwrap();
+        assert!((cx - 9.5 / 7.0).abs() < 1e-2 && (cy - 9.5 / 7.0).abs() < 1e-2);
+
+        // 五角星: 非零规则下中间的五边形在里面, 偶奇规则下是洞
+        let star: Vec<(Float, Float)> = (0..5)
+            .map(|i| {
+                let angle = TAU * (i * 2) as Float / 5.0;
+                (2.0 * angle.cos(), 2.0 * angle.sin())
+            })
+            .collect();
+        let star = vec![star];
+        let nonzero = Polygon::with_contours(&star, FillRule::NonZero, 1.0);
+        let evenodd = Polygon::with_contours(&star, FillRule::EvenOdd, 1.0);
+        assert!(nonzero.sdf(0.0, 0.0).sd < 0.0);
+        assert_eq!(evenodd.sdf(0.0, 0.0).sd, -nonzero.sdf(0.0, 0.0).sd);
+        assert!(nonzero.area() > evenodd.area());
+        // 非零规则下有在内部的边, 不能解析求交
+        assert_eq!(nonzero.raycast(-5.0, 0.1, 1.0, 0.0), None);
+        let hit = evenodd.raycast(-5.0, 0.0, 1.0, 0.0).unwrap();
+        assert!((evenodd.sdf(-5.0 + hit, 0.0).sd).abs() < 1e-6);
+
+        // 带洞的正方形
+        let square = |r: Float| vec![(-r, -r), (r, -r), (r, r), (-r, r)];
+        let frame = Polygon::with_contours(&[square(2.0), square(1.0)], FillRule::EvenOdd, 1.0);
+        assert_eq!(frame.sdf(0.0, 0.0).sd, 1.0);
+        assert_eq!(frame.sdf(1.5, 0.0).sd, -0.5);
+        assert_eq!(frame.raycast(0.0, 0.0, 1.0, 0.0), Some(1.0));
+        assert!((frame.area() / 12.0 - 1.0).abs() < 1e-3);
+    }
+
+    // 用 Shape 默认的中心差分计算梯度
+    fn estimate_gradient<S: Shape>(shape: &S, x: Float, y: Float) -> (Float, Float) {
+        struct Estimated<'a, S>(&'a S);
+        impl<S: Shape> Shape for Estimated<'_, S> {
+            fn sdf(&self, x: Float, y: Float) -> SdfResult {
+                self.0.sdf(x, y)
+            }
         }
+        Estimated(shape).gradient(x, y)
     }
 }
@@ -0,0 +1,512 @@
+// 从 SVG 路径数据(<path> 的 d 属性)创建形状, 可以直接使用现成的矢量图形
+// 直线、二次和三次贝塞尔曲线、椭圆弧都转换为二次贝塞尔曲线, 距离是到最近的一段的距离,
+// 内外由细分后的轮廓按填充规则判断; 与 SVG 的填充一样, 没有闭合的子路径会自动连回起点
+
+use crate::color::Color;
+use crate::float::consts::{FRAC_PI_2, PI, TAU};
+use crate::float::Float;
+use crate::material::Material;
+use crate::shape::{
+    pieces_distance, pieces_outline, Aabb, Bezier2, Bezier3, EmissionProfile, FillRule, SdfResult,
+    Shape,
+};
+use std::io::{Error, ErrorKind, Result};
+
+type Point = (Float, Float);
+
+#[derive(Clone)]
+pub struct SvgPathShape {
+    pieces: Vec<Bezier2>,
+    // 每个子路径细分后的轮廓
+    contours: Vec<Vec<Point>>,
+    fill_rule: FillRule,
+    emissive: Color,
+}
+
+impl SvgPathShape {
+    // 支持 M L H V C S Q T A Z 命令和它们的相对坐标形式, 默认按非零规则填充, 不发光
+    pub fn parse(data: &str) -> Result<SvgPathShape> {
+        let mut parser = Parser {
+            data: data.as_bytes(),
+            pos: 0,
+        };
+        let mut path = PathBuilder::default();
+        let mut previous: Option<u8> = None;
+        loop {
+            parser.skip_separators();
+            if parser.pos >= parser.data.len() {
+                break;
+            }
+            let command = match parser.command() {
+                Some(command) => command,
+                // 省略命令字母时重复上一个命令, moveto 之后的坐标是 lineto
+                None => match previous {
+                    Some(b'M') => b'L',
+                    Some(b'm') => b'l',
+                    Some(command) if !command.eq_ignore_ascii_case(&b'Z') => command,
+                    _ => return Err(parser.error("expected a command")),
+                },
+            };
+            if previous.is_none() && !command.eq_ignore_ascii_case(&b'M') {
+                return Err(parser.error("path must start with a moveto"));
+            }
+
+            let (x, y) = if command.is_ascii_lowercase() {
+                path.current
+            } else {
+                (0.0, 0.0)
+            };
+            match command.to_ascii_uppercase() {
+                b'M' => {
+                    let (px, py) = parser.point()?;
+                    path.move_to((x + px, y + py));
+                }
+                b'L' => {
+                    let (px, py) = parser.point()?;
+                    path.line_to((x + px, y + py));
+                }
+                b'H' => {
+                    let px = parser.number()?;
+                    path.line_to((x + px, path.current.1));
+                }
+                b'V' => {
+                    let py = parser.number()?;
+                    path.line_to((path.current.0, y + py));
+                }
+                b'C' => {
+                    let (ax, ay) = parser.point()?;
+                    let (bx, by) = parser.point()?;
+                    let (px, py) = parser.point()?;
+                    path.cubic_to((x + ax, y + ay), (x + bx, y + by), (x + px, y + py));
+                }
+                b'S' => {
+                    // 第一个控制点是上一个三次曲线的第二个控制点关于当前点的对称点
+                    let first = match previous.map(|c| c.to_ascii_uppercase()) {
+                        Some(b'C') | Some(b'S') => path.reflect(),
+                        _ => path.current,
+                    };
+                    let (bx, by) = parser.point()?;
+                    let (px, py) = parser.point()?;
+                    path.cubic_to(first, (x + bx, y + by), (x + px, y + py));
+                }
+                b'Q' => {
+                    let (ax, ay) = parser.point()?;
+                    let (px, py) = parser.point()?;
+                    path.quad_to((x + ax, y + ay), (x + px, y + py));
+                }
+                b'T' => {
+                    let control = match previous.map(|c| c.to_ascii_uppercase()) {
+                        Some(b'Q') | Some(b'T') => path.reflect(),
+                        _ => path.current,
+                    };
+                    let (px, py) = parser.point()?;
+                    path.quad_to(control, (x + px, y + py));
+                }
+                b'A' => {
+                    let rx = parser.number()?;
+                    let ry = parser.number()?;
+                    let rotation = parser.number()?;
+                    let large = parser.flag()?;
+                    let sweep = parser.flag()?;
+                    let (px, py) = parser.point()?;
+                    path.arc_to(rx, ry, rotation, large, sweep, (x + px, y + py));
+                }
+                b'Z' => path.close(),
+                _ => return Err(parser.error("unknown command")),
+            }
+            previous = Some(command);
+        }
+        path.finish_subpath();
+
+        Ok(SvgPathShape {
+            pieces: path.pieces,
+            contours: path.contours,
+            fill_rule: FillRule::NonZero,
+            emissive: Color::BLACK,
+        })
+    }
+
+    // 对应 SVG 的 fill-rule 属性, 例如带洞的图标通常使用 EvenOdd
+    pub fn with_fill_rule(mut self, fill_rule: FillRule) -> Self {
+        self.fill_rule = fill_rule;
+        self
+    }
+
+    pub fn with_emissive(mut self, emissive: impl Into<Color>) -> Self {
+        self.emissive = emissive.into();
+        self
+    }
+
+    // 转换后的二次曲线的段数
+    pub fn pieces(&self) -> usize {
+        self.pieces.len()
+    }
+}
+
+impl Shape for SvgPathShape {
+    fn sdf(&self, x: Float, y: Float) -> SdfResult {
+        let distance = pieces_distance(&self.pieces, x, y);
+        let sd = if self.fill_rule.contains(&self.contours, x, y) {
+            -distance
+        } else {
+            distance
+        };
+        SdfResult {
+            sd,
+            material: Material::emissive(self.emissive),
+            profile: EmissionProfile::Uniform,
+        }
+    }
+
+    // 空路径没有包围盒
+    fn bounds(&self) -> Option<Aabb> {
+        self.pieces
+            .iter()
+            .filter_map(|piece| piece.bounds())
+            .fold(None, |bounds: Option<Aabb>, piece| {
+                Some(bounds.map_or(piece, |bounds| bounds.union(&piece)))
+            })
+    }
+}
+
+struct Parser<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> Error {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("invalid svg path at {}: {}", self.pos, message),
+        )
+    }
+
+    fn skip_separators(&mut self) {
+        while self.pos < self.data.len()
+            && (self.data[self.pos].is_ascii_whitespace() || self.data[self.pos] == b',')
+        {
+            self.pos += 1;
+        }
+    }
+
+    fn command(&mut self) -> Option<u8> {
+        self.skip_separators();
+        let c = *self.data.get(self.pos)?;
+        // e 和 E 只会出现在数字的指数部分
+        if c.is_ascii_alphabetic() && c != b'e' && c != b'E' {
+            self.pos += 1;
+            Some(c)
+        } else {
+            None
+        }
+    }
+
+    // 数字之间可以没有分隔符, 例如 "10-5.5.5" 是 10、-5.5 和 0.5
+    fn number(&mut self) -> Result<Float> {
+        self.skip_separators();
+        let start = self.pos;
+        let digits = |parser: &mut Self| {
+            let start = parser.pos;
+            while parser.pos < parser.data.len() && parser.data[parser.pos].is_ascii_digit() {
+                parser.pos += 1;
+            }
+            parser.pos > start
+        };
+        if matches!(self.data.get(self.pos), Some(b'+') | Some(b'-')) {
+            self.pos += 1;
+        }
+        let mut valid = digits(self);
+        if self.data.get(self.pos) == Some(&b'.') {
+            self.pos += 1;
+            valid |= digits(self);
+        }
+        if valid && matches!(self.data.get(self.pos), Some(b'e') | Some(b'E')) {
+            let mantissa = self.pos;
+            self.pos += 1;
+            if matches!(self.data.get(self.pos), Some(b'+') | Some(b'-')) {
+                self.pos += 1;
+            }
+            if !digits(self) {
+                self.pos = mantissa;
+            }
+        }
+        if !valid {
+            self.pos = start;
+            return Err(self.error("expected a number"));
+        }
+        let text = std::str::from_utf8(&self.data[start..self.pos]).unwrap();
+        text.parse().map_err(|_| self.error("expected a number"))
+    }
+
+    fn point(&mut self) -> Result<Point> {
+        Ok((self.number()?, self.number()?))
+    }
+
+    // 椭圆弧的标志只有一个字符, 后面可以直接跟下一个数字
+    fn flag(&mut self) -> Result<bool> {
+        self.skip_separators();
+        match self.data.get(self.pos) {
+            Some(b'0') => {
+                self.pos += 1;
+                Ok(false)
+            }
+            Some(b'1') => {
+                self.pos += 1;
+                Ok(true)
+            }
+            _ => Err(self.error("expected a flag")),
+        }
+    }
+}
+
+// 也用于把字形的轮廓转换为曲线
+#[derive(Default)]
+pub(crate) struct PathBuilder {
+    pub(crate) pieces: Vec<Bezier2>,
+    pub(crate) contours: Vec<Vec<Point>>,
+    // 当前子路径的起点, 以及它的第一段在 pieces 中的下标
+    start: Point,
+    first: usize,
+    current: Point,
+    // 上一段曲线最后一个控制点, 用于 S 和 T 命令
+    control: Point,
+}
+
+impl PathBuilder {
+    pub(crate) fn move_to(&mut self, to: Point) {
+        self.finish_subpath();
+        self.start = to;
+        self.current = to;
+        self.control = to;
+    }
+
+    // 直线是中间的控制点在中点的二次曲线
+    pub(crate) fn line_to(&mut self, to: Point) {
+        let from = self.current;
+        if from != to {
+            let middle = ((from.0 + to.0) / 2.0, (from.1 + to.1) / 2.0);
+            self.pieces.push(Bezier2::new(from, middle, to, 0.0, 0.0));
+        }
+        self.current = to;
+        self.control = to;
+    }
+
+    pub(crate) fn quad_to(&mut self, control: Point, to: Point) {
+        self.pieces
+            .push(Bezier2::new(self.current, control, to, 0.0, 0.0));
+        self.current = to;
+        self.control = control;
+    }
+
+    pub(crate) fn cubic_to(&mut self, first: Point, second: Point, to: Point) {
+        Bezier3::flatten([self.current, first, second, to], 0, &mut self.pieces);
+        self.current = to;
+        self.control = second;
+    }
+
+    fn reflect(&self) -> Point {
+        (
+            2.0 * self.current.0 - self.control.0,
+            2.0 * self.current.1 - self.control.1,
+        )
+    }
+
+    // 按 SVG 规范附录中的方法把端点形式转换为圆心形式, 半径不够时等比例放大,
+    // 再把弧分成不超过 90° 的几段, 每段用三次曲线近似
+    fn arc_to(
+        &mut self,
+        rx: Float,
+        ry: Float,
+        rotation: Float,
+        large: bool,
+        sweep: bool,
+        to: Point,
+    ) {
+        let from = self.current;
+        if from == to {
+            return;
+        }
+        let (mut rx, mut ry) = (rx.abs(), ry.abs());
+        if rx == 0.0 || ry == 0.0 {
+            self.line_to(to);
+            return;
+        }
+
+        let (sin, cos) = (rotation * PI / 180.0).sin_cos();
+        let (dx, dy) = ((from.0 - to.0) / 2.0, (from.1 - to.1) / 2.0);
+        let (x1, y1) = (cos * dx + sin * dy, -sin * dx + cos * dy);
+        let lambda = (x1 * x1) / (rx * rx) + (y1 * y1) / (ry * ry);
+        if lambda > 1.0 {
+            rx *= lambda.sqrt();
+            ry *= lambda.sqrt();
+        }
+        let numerator = rx * rx * ry * ry - rx * rx * y1 * y1 - ry * ry * x1 * x1;
+        let denominator = rx * rx * y1 * y1 + ry * ry * x1 * x1;
+        let mut k = (numerator / denominator).max(0.0).sqrt();
+        if large == sweep {
+            k = -k;
+        }
+        let (cx1, cy1) = (k * rx * y1 / ry, -k * ry * x1 / rx);
+        let center = (
+            cos * cx1 - sin * cy1 + (from.0 + to.0) / 2.0,
+            sin * cx1 + cos * cy1 + (from.1 + to.1) / 2.0,
+        );
+
+        let angle = |ux: Float, uy: Float, vx: Float, vy: Float| {
+            (ux * vy - uy * vx).atan2(ux * vx + uy * vy)
+        };
+        let (ux, uy) = ((x1 - cx1) / rx, (y1 - cy1) / ry);
+        let (vx, vy) = ((-x1 - cx1) / rx, (-y1 - cy1) / ry);
+        let start = angle(1.0, 0.0, ux, uy);
+        let mut delta = angle(ux, uy, vx, vy);
+        if !sweep && delta > 0.0 {
+            delta -= TAU;
+        } else if sweep && delta < 0.0 {
+            delta += TAU;
+        }
+
+        // 椭圆上角度为 t 的点和切线方向
+        let at = |t: Float| {
+            let (s, c) = t.sin_cos();
+            let point = (
+                center.0 + cos * rx * c - sin * ry * s,
+                center.1 + sin * rx * c + cos * ry * s,
+            );
+            let tangent = (-cos * rx * s - sin * ry * c, -sin * rx * s + cos * ry * c);
+            (point, tangent)
+        };
+        let segments = (delta.abs() / FRAC_PI_2).ceil().max(1.0) as usize;
+        let step = delta / segments as Float;
+        let k = 4.0 / 3.0 * (step / 4.0).tan();
+        for i in 0..segments {
+            let (p0, t0) = at(start + step * i as Float);
+            let (mut p3, t3) = at(start + step * (i + 1) as Float);
+            if i + 1 == segments {
+                p3 = to;
+            }
+            let p1 = (p0.0 + k * t0.0, p0.1 + k * t0.1);
+            let p2 = (p3.0 - k * t3.0, p3.1 - k * t3.1);
+            self.cubic_to(p1, p2, p3);
+        }
+        self.control = to;
+    }
+
+    pub(crate) fn close(&mut self) {
+        self.finish_subpath();
+        self.current = self.start;
+        self.control = self.start;
+    }
+
+    // 结束当前子路径, 没有闭合时连回起点
+    pub(crate) fn finish_subpath(&mut self) {
+        if self.pieces.len() > self.first {
+            let start = self.start;
+            self.line_to(start);
+            self.contours
+                .push(pieces_outline(&self.pieces[self.first..]));
+        }
+        self.first = self.pieces.len();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shape::{Circle, Rect};
+
+    #[test]
+    fn square() {
+        let tolerance = if cfg!(feature = "f32") { 1e-4 } else { 1e-9 };
+        let rect = Rect::new(5.0, 5.0, 0.0, 5.0, 5.0, 1.0);
+        let absolute = SvgPathShape::parse("M 0 0 L 10 0 L 10 10 L 0 10 Z").unwrap();
+        // 相对坐标、水平和竖直线、省略分隔符和自动闭合
+        let relative = SvgPathShape::parse("m0,0h10v10h-10").unwrap();
+        let implicit = SvgPathShape::parse("M0 0 10 0 10 10 0 10z").unwrap();
+        for i in 0..20 {
+            for j in 0..20 {
+                let (x, y) = (-2.0 + i as Float * 0.75, -3.0 + j as Float * 0.8);
+                let expected = rect.sdf(x, y).sd;
+                for shape in &[&absolute, &relative, &implicit] {
+                    assert!((shape.sdf(x, y).sd - expected).abs() < tolerance);
+                }
+            }
+        }
+        assert_eq!(absolute.pieces(), 4);
+        assert_eq!(relative.bounds(), Some(Aabb::new(0.0, 0.0, 10.0, 10.0)));
+    }
+
+    #[test]
+    fn arcs() {
+        // 两段半圆弧, 标志之间没有分隔符
+        let shape = SvgPathShape::parse("M 0 5 A 5 5 0 0 1 10 5 a5 5 0 01-10 0 Z").unwrap();
+        let circle = Circle::new(5.0, 5.0, 5.0, 1.0);
+        for i in 0..20 {
+            for j in 0..20 {
+                let (x, y) = (-2.0 + i as Float * 0.75, -3.0 + j as Float * 0.8);
+                assert!((shape.sdf(x, y).sd - circle.sdf(x, y).sd).abs() < 3e-3);
+            }
+        }
+        // 半径不够时放大到刚好连接两个端点
+        let small = SvgPathShape::parse("M 0 5 A 1 1 0 0 1 10 5 Z").unwrap();
+        assert!((small.sdf(5.0, 0.0).sd).abs() < 3e-3);
+        assert!(small.sdf(5.0, 4.0).sd < 0.0);
+        assert!(small.sdf(5.0, 6.0).sd > 0.0);
+    }
+
+    #[test]
+    fn curves() {
+        let tolerance = if cfg!(feature = "f32") { 1e-4 } else { 1e-9 };
+        let quad = SvgPathShape::parse("M 0 0 Q 5 10 10 0 T 20 0 Z").unwrap();
+        let first = Bezier2::new((0.0, 0.0), (5.0, 10.0), (10.0, 0.0), 0.0, 0.0);
+        // T 的控制点是上一个控制点的对称点 (15, -10)
+        let second = Bezier2::new((10.0, 0.0), (15.0, -10.0), (20.0, 0.0), 0.0, 0.0);
+        for &(x, y) in &[(5.0, 8.0), (15.0, -8.0), (3.0, 3.5)] {
+            let expected = first.distance(x, y).min(second.distance(x, y));
+            assert!((quad.sdf(x, y).sd.abs() - expected).abs() < tolerance);
+        }
+        assert!(quad.sdf(5.0, 2.0).sd < 0.0);
+        assert!(quad.sdf(15.0, -2.0).sd < 0.0);
+
+        let cubic = SvgPathShape::parse("M0 0C0 10 10 10 10 0S20-10 20 0").unwrap();
+        let first = Bezier3::new((0.0, 0.0), (0.0, 10.0), (10.0, 10.0), (10.0, 0.0), 0.0, 0.0);
+        let second = Bezier3::new(
+            (10.0, 0.0),
+            (10.0, -10.0),
+            (20.0, -10.0),
+            (20.0, 0.0),
+            0.0,
+            0.0,
+        );
+        for &(x, y) in &[(5.0, 9.0), (15.0, -9.0), (5.0, 12.0)] {
+            let expected = first.sdf(x, y).sd.min(second.sdf(x, y).sd);
+            assert!((cubic.sdf(x, y).sd.abs() - expected).abs() < tolerance);
+        }
+    }
+
+    #[test]
+    fn fill_rule() {
+        let tolerance = if cfg!(feature = "f32") { 1e-4 } else { 1e-9 };
+        let data = "M0 0H10V10H0Z M2 2H8V8H2Z";
+        let nonzero = SvgPathShape::parse(data).unwrap();
+        let evenodd = SvgPathShape::parse(data)
+            .unwrap()
+            .with_fill_rule(FillRule::EvenOdd);
+        assert!((nonzero.sdf(5.0, 5.0).sd + 3.0).abs() < tolerance);
+        assert!((evenodd.sdf(5.0, 5.0).sd - 3.0).abs() < tolerance);
+        assert!(evenodd.sdf(1.0, 5.0).sd < 0.0);
+    }
+
+    #[test]
+    fn errors() {
+        assert!(SvgPathShape::parse("L 1 2").is_err());
+        assert!(SvgPathShape::parse("M 1").is_err());
+        assert!(SvgPathShape::parse("M 0 0 X 1 1").is_err());
+        assert!(SvgPathShape::parse("M 0 0 A 1 1 0 2 0 1 1").is_err());
+        assert!(SvgPathShape::parse("M 0 0 L 1 1 Z 3").is_err());
+        let empty = SvgPathShape::parse("").unwrap();
+        assert_eq!(empty.bounds(), None);
+        let compact = SvgPathShape::parse("M0 0L10-5.5.5e1 0").unwrap();
+        assert_eq!(compact.bounds(), Some(Aabb::new(0.0, -5.5, 10.0, 0.0)));
+    }
+}
@@ -0,0 +1,216 @@
+// 文字形状: 用 ttf-parser 读取 TrueType / OpenType 字体, 把字形的轮廓转换为二次贝塞尔曲线,
+// 与 SvgPathShape 一样计算到最近的一段的距离, 按非零规则判断内外, 可以用来渲染发光的文字
+
+use crate::color::Color;
+use crate::float::Float;
+use crate::material::Material;
+use crate::shape::{pieces_distance, Aabb, Bezier2, EmissionProfile, FillRule, SdfResult, Shape};
+use crate::svg::PathBuilder;
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+use ttf_parser::{Face, OutlineBuilder};
+
+// 字体文件的内容, 创建时检查能否解析
+#[derive(Clone)]
+pub struct Font {
+    data: Vec<u8>,
+}
+
+impl Font {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Font> {
+        Font::from_bytes(fs::read(path)?)
+    }
+
+    pub fn from_bytes(data: Vec<u8>) -> Result<Font> {
+        Face::parse(&data, 0).map_err(|e| Error::new(ErrorKind::InvalidData, e.to_string()))?;
+        Ok(Font { data })
+    }
+
+    fn face(&self) -> Face<'_> {
+        Face::parse(&self.data, 0).expect("font data is checked when loaded")
+    }
+}
+
+#[derive(Clone)]
+pub struct TextShape {
+    pieces: Vec<Bezier2>,
+    contours: Vec<Vec<(Float, Float)>>,
+    emissive: Color,
+}
+
+impl TextShape {
+    // 第一行文字的基线从 (x, y) 开始向右排列, size 为字号(一个 em 在场景中的长度)
+    // 字形的 y 轴向上, 场景的 y 轴向下, 转换时上下翻转; 换行符移动到下一行, 字体中没有的字符画成空白
+    // 只按每个字形的前进宽度排列, 不处理字距调整和连字, 默认不发光
+    pub fn new(font: &Font, text: &str, size: Float, x: Float, y: Float) -> TextShape {
+        let face = font.face();
+        let scale = size / face.units_per_em() as Float;
+        let line_height = (face.ascender() as Float - face.descender() as Float
+            + face.line_gap() as Float)
+            * scale;
+
+        let mut outline = GlyphOutline {
+            path: PathBuilder::default(),
+            x,
+            y,
+            scale,
+        };
+        for (row, line) in text.lines().enumerate() {
+            outline.x = x;
+            outline.y = y + row as Float * line_height;
+            for c in line.chars() {
+                let glyph = match face.glyph_index(c) {
+                    Some(glyph) => glyph,
+                    None => continue,
+                };
+                face.outline_glyph(glyph, &mut outline);
+                outline.path.finish_subpath();
+                outline.x += face.glyph_hor_advance(glyph).unwrap_or(0) as Float * scale;
+            }
+        }
+
+        TextShape {
+            pieces: outline.path.pieces,
+            contours: outline.path.contours,
+            emissive: Color::BLACK,
+        }
+    }
+
+    pub fn with_emissive(mut self, emissive: impl Into<Color>) -> Self {
+        self.emissive = emissive.into();
+        self
+    }
+}
+
+impl Shape for TextShape {
+    fn sdf(&self, x: Float, y: Float) -> SdfResult {
+        let distance = pieces_distance(&self.pieces, x, y);
+        let sd = if FillRule::NonZero.contains(&self.contours, x, y) {
+            -distance
+        } else {
+            distance
+        };
+        SdfResult {
+            sd,
+            material: Material::emissive(self.emissive),
+            profile: EmissionProfile::Uniform,
+        }
+    }
+
+    // 没有可见字形时没有包围盒
+    fn bounds(&self) -> Option<Aabb> {
+        self.pieces
+            .iter()
+            .filter_map(|piece| piece.bounds())
+            .fold(None, |bounds: Option<Aabb>, piece| {
+                Some(bounds.map_or(piece, |bounds| bounds.union(&piece)))
+            })
+    }
+}
+
+// 把一个字形的轮廓从字体单位变换到场景中, 原点在 (x, y)
+struct GlyphOutline {
+    path: PathBuilder,
+    x: Float,
+    y: Float,
+    scale: Float,
+}
+
+impl GlyphOutline {
+    // 使用 f32 时 Float::from 是同一类型的转换
+    #[allow(clippy::useless_conversion)]
+    fn point(&self, x: f32, y: f32) -> (Float, Float) {
+        (
+            self.x + Float::from(x) * self.scale,
+            self.y - Float::from(y) * self.scale,
+        )
+    }
+}
+
+impl OutlineBuilder for GlyphOutline {
+    fn move_to(&mut self, x: f32, y: f32) {
+        let to = self.point(x, y);
+        self.path.move_to(to);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let to = self.point(x, y);
+        self.path.line_to(to);
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let (control, to) = (self.point(x1, y1), self.point(x, y));
+        self.path.quad_to(control, to);
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let (first, second, to) = (self.point(x1, y1), self.point(x2, y2), self.point(x, y));
+        self.path.cubic_to(first, second, to);
+    }
+
+    fn close(&mut self) {
+        self.path.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 测试使用系统中的 DejaVu Sans 字体, 没有安装时跳过
+    fn dejavu() -> Option<Font> {
+        Font::from_file("/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf").ok()
+    }
+
+    #[test]
+    fn glyphs() {
+        let font = match dejavu() {
+            Some(font) => font,
+            None => return,
+        };
+        // "I" 是一个竖直的矩形, 基线在 y = 100, 顶部在基线上方约 0.73 em
+        let shape = TextShape::new(&font, "I", 100.0, 10.0, 100.0).with_emissive(1.0);
+        let bounds = shape.bounds().unwrap();
+        assert!((bounds.max_y - 100.0).abs() < 1e-3);
+        assert!(bounds.min_y > 20.0 && bounds.min_y < 35.0);
+        let middle = (bounds.min_x + bounds.max_x) / 2.0;
+        let half = (bounds.max_x - bounds.min_x) / 2.0;
+        assert!((shape.sdf(middle, 60.0).sd + half).abs() < 1e-3);
+        assert!((shape.sdf(bounds.max_x + 5.0, 60.0).sd - 5.0).abs() < 1e-3);
+        assert_eq!(shape.sdf(middle, 60.0).material.emissive, Color::gray(1.0));
+
+        // "O" 中间的洞在外部
+        let shape = TextShape::new(&font, "O", 100.0, 0.0, 100.0);
+        let bounds = shape.bounds().unwrap();
+        let (cx, cy) = (
+            (bounds.min_x + bounds.max_x) / 2.0,
+            (bounds.min_y + bounds.max_y) / 2.0,
+        );
+        assert!(shape.sdf(cx, cy).sd > 10.0);
+        assert!(shape.sdf(bounds.min_x + 3.0, cy).sd < 0.0);
+    }
+
+    #[test]
+    fn layout() {
+        let font = match dejavu() {
+            Some(font) => font,
+            None => return,
+        };
+        let one = TextShape::new(&font, "ab", 50.0, 0.0, 50.0)
+            .bounds()
+            .unwrap();
+        let two = TextShape::new(&font, "abab", 50.0, 0.0, 50.0)
+            .bounds()
+            .unwrap();
+        assert!(two.max_x > one.max_x * 1.8);
+        let lines = TextShape::new(&font, "ab\nab", 50.0, 0.0, 50.0)
+            .bounds()
+            .unwrap();
+        assert!((lines.max_x - one.max_x).abs() < 1e-6);
+        assert!(lines.max_y > one.max_y + 40.0);
+        // 空格和字体中没有的字符不产生轮廓
+        assert_eq!(TextShape::new(&font, " ", 50.0, 0.0, 0.0).bounds(), None);
+        assert!(Font::from_bytes(vec![0; 16]).is_err());
+    }
+}